@@ -0,0 +1,562 @@
+/// Periodic saving of the running simulation, so that a crash loses at
+/// most a few minutes of it. Saves go round a few numbered files in turn,
+/// every file is written under a temporary name first and then renamed,
+/// so an interrupted save never spoils the previous ones.
+/// Between full saves only the cells born and died since the previous
+/// save are appended to a journal next to the last full save, a line per
+/// save, and the journal is replayed on restore. The cells are the changed
+/// cells of the boards replaced since the previous save, see
+/// `track_changes`, so a journaled save doesn't go through the whole board.
+/// Once the journal holds enough saves or grows bigger than the full save,
+/// or the board is replaced as a whole, the next save is a full one again.
+/// A damaged journal line stops the replay, the save is restored as of the
+/// line before it.
+/// Every full save is numbered one more than the latest one found, and
+/// only the latest full save gets journaled, so the highest number tells
+/// the save to restore; times of the files may be equal for saves in quick
+/// succession.
+/// The files are removed on clean exit, thus any autosave found on
+/// startup is left by a crashed session.
+///
+
+use ::board::{Board, Coord};
+use ::io::container::Container;
+use ::io::invalid_data;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const FILE_PREFIX: &'static str = "life-rs-autosave-";
+const TEMP_SUFFIX: &'static str = ".tmp";
+const JOURNAL_SUFFIX: &'static str = ".journal";
+// meta and sections which change every generation and aren't journaled
+const GENERATION_KEY: &'static str = "generation";
+const HISTORY_SECTION: &'static str = "history";
+// number of the full save, counted across sessions
+const SEQUENCE_KEY: &'static str = "autosave_sequence";
+
+
+// the last full save and the saves journaled after it
+struct Journal {
+    slot: usize,
+    // cells changed since the previous save, cells changed twice are back as they were
+    changes: HashSet<Coord>,
+    meta: BTreeMap<String, String>,
+    sections: BTreeMap<String, String>,
+
+    entries: usize,
+    size: u64,
+    snapshot_size: u64,
+}
+
+impl Journal {
+
+    fn new(slot: usize, container: &Container, board: &Board, snapshot_size: u64) -> Journal {
+        let (meta, sections) = Journal::get_state(container);
+
+        Journal {
+            slot: slot,
+            // changes the board has so far are in the full save already
+            changes: board.changed_cells().clone(),
+            meta: meta,
+            sections: sections,
+
+            entries: 0,
+            size: 0,
+            snapshot_size: snapshot_size,
+        }
+    }
+
+    // everything but the cells, generation and history, which a journal entry can't change
+    fn get_state(container: &Container) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+        let mut meta = container.meta.clone();
+        meta.remove(GENERATION_KEY);
+        meta.remove(SEQUENCE_KEY);
+        let mut sections = container.sections.clone();
+        sections.remove(HISTORY_SECTION);
+        (meta, sections)
+    }
+
+    fn can_append(&self, container: &Container, limit: usize) -> bool {
+        let (meta, sections) = Journal::get_state(container);
+        self.entries < limit && self.size < self.snapshot_size && meta == self.meta && sections == self.sections
+    }
+
+    fn toggle(&mut self, changed: &HashSet<Coord>) {
+        for coord in changed {
+            if !self.changes.remove(coord) {
+                self.changes.insert(*coord);
+            }
+        }
+    }
+
+}
+
+// a journal line: generation, then born cells as +col,row and died ones as -col,row
+fn format_entry(generation: &str, births: &[&Coord], deaths: &[&Coord]) -> String {
+    let mut line = generation.to_string();
+    for &(sign, cells) in [('+', births), ('-', deaths)].iter() {
+        for cell in cells {
+            line.push_str(&format!(" {}{},{}", sign, cell.col, cell.row));
+        }
+    }
+    line.push('\n');
+    line
+}
+
+// cells are changed only if the whole line is well-formed
+fn parse_entry(line: &str, cells: &mut HashSet<Coord>) -> io::Result<usize> {
+    let mut parts = line.split_whitespace();
+    let mut changes = Vec::new();
+
+    let generation = match parts.next().and_then(|generation| generation.parse().ok()) {
+        Some(generation) => generation,
+        None => return invalid_data("malformed generation in the autosave journal")
+    };
+
+    for part in parts {
+        // the sign may be any character in a damaged file, not just one byte long
+        let sign = match part.chars().next() {
+            Some(sign) => sign,
+            None => return invalid_data("malformed cell in the autosave journal")
+        };
+
+        let coords: Result<Vec<i64>, _> = part[sign.len_utf8()..].split(',').map(|v| v.parse()).collect();
+        let cell = match coords {
+            Ok(ref coords) if coords.len() == 2 => Coord { col: coords[0], row: coords[1] },
+            _ => return invalid_data("malformed cell in the autosave journal")
+        };
+
+        match sign {
+            '+' | '-' => changes.push((sign == '+', cell)),
+            _ => return invalid_data("malformed cell in the autosave journal")
+        }
+    }
+
+    for (is_born, cell) in changes {
+        if is_born {
+            cells.insert(cell);
+        } else {
+            cells.remove(&cell);
+        }
+    }
+
+    Ok(generation)
+}
+
+
+pub struct Autosave {
+    dir: PathBuf,
+    slots: usize,
+
+    // 0 disables the corresponding trigger
+    generations: usize,
+    seconds: f64,
+
+    // gzipped saves are recognized on load by their contents
+    compressed: bool,
+
+    // saves journaled between full ones, 0 makes every save a full one
+    journal_limit: usize,
+    journal: Option<Journal>,
+
+    next_slot: usize,
+    // number of the last full save, looked up in the files before the first one
+    sequence: Option<u64>,
+    last_generation: usize,
+    last_time: Option<f64>,
+}
+
+impl Autosave {
+
+    pub fn new(dir: &Path, slots: usize, generations: usize, seconds: f64) -> Self {
+        Autosave {
+            dir: dir.to_path_buf(),
+            slots: slots.max(1),
+
+            generations: generations,
+            seconds: seconds,
+
+            compressed: false,
+
+            journal_limit: 0,
+            journal: None,
+
+            next_slot: 0,
+            sequence: None,
+            last_generation: 0,
+            last_time: None,
+        }
+    }
+
+    pub fn set_compressed(&mut self, compressed: bool) {
+        self.compressed = compressed;
+    }
+
+    pub fn set_journal_limit(&mut self, journal_limit: usize) {
+        self.journal_limit = journal_limit;
+    }
+
+    pub fn get_path(&self, slot: usize) -> PathBuf {
+        self.dir.join(format!("{}{}", FILE_PREFIX, slot))
+    }
+
+    fn get_journal_path(path: &Path) -> PathBuf {
+        let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        path.with_file_name(format!("{}{}", name, JOURNAL_SUFFIX))
+    }
+
+    /// Checks whether enough generations or seconds passed since the last save,
+    /// the first call only starts the countdown
+    pub fn is_due(&mut self, iteration: usize, now: f64) -> bool {
+        let last_time = match self.last_time {
+            Some(time) => time,
+            None => {
+                self.last_generation = iteration;
+                self.last_time = Some(now);
+                return false;
+            }
+        };
+
+        // board has been reset since the last save
+        let passed = if iteration >= self.last_generation { iteration - self.last_generation } else { iteration };
+
+        (self.generations > 0 && passed >= self.generations) ||
+            (self.seconds > 0.0 && now - last_time >= self.seconds)
+    }
+
+    /// Takes the changed cells of the board about to be replaced by the
+    /// next generation, the next journal entry is made of them
+    pub fn track_changes(&mut self, changed: &HashSet<Coord>) {
+        if let Some(ref mut journal) = self.journal {
+            journal.toggle(changed);
+        }
+    }
+
+    /// The board was replaced as a whole, e.g. by a loaded pattern, and
+    /// its changed cells say nothing of the last save, the next save is a full one
+    pub fn forget_changes(&mut self) {
+        self.journal = None;
+    }
+
+    /// Saves the given container, with the cells of the board, in full,
+    /// the container gets the number of the save.
+    /// Returns the path of the save to restore from
+    pub fn save(&mut self, container: &mut Container, board: &Board, iteration: usize, now: f64) -> io::Result<PathBuf> {
+        let path = self.save_snapshot(container, board)?;
+
+        self.last_generation = iteration;
+        self.last_time = Some(now);
+
+        Ok(path)
+    }
+
+    /// Appends the cells changed on the board since the last save to the
+    /// journal of the last full save, the container has everything else,
+    /// see `Engine::to_container_meta`. Returns the path of the full save
+    /// to restore from, `None` if a full save is due instead
+    pub fn save_changes(&mut self, container: &Container, board: &Board, iteration: usize, now: f64) -> io::Result<Option<PathBuf>> {
+        let path = self.append_journal(container, board)?;
+
+        if path.is_some() {
+            self.last_generation = iteration;
+            self.last_time = Some(now);
+        }
+
+        Ok(path)
+    }
+
+    fn save_snapshot(&mut self, container: &mut Container, board: &Board) -> io::Result<PathBuf> {
+        let slot = self.next_slot;
+        let path = self.get_path(slot);
+        let temp_path = path.with_file_name(format!("{}{}{}", FILE_PREFIX, slot, TEMP_SUFFIX));
+
+        // saves left by a crashed session are older than any of this one
+        let sequence = match self.sequence {
+            Some(sequence) => sequence + 1,
+            None => self.find_latest_sequence().map_or(0, |(sequence, _)| sequence + 1)
+        };
+        container.set_meta(SEQUENCE_KEY, sequence);
+
+        container.save(&temp_path, self.compressed)?;
+        self.sequence = Some(sequence);
+
+        // journal of the replaced save would be replayed over the new one
+        if let Err(err) = fs::remove_file(Autosave::get_journal_path(&path)) {
+            if err.kind() != io::ErrorKind::NotFound {
+                return Err(err);
+            }
+        }
+        fs::rename(&temp_path, &path)?;
+
+        self.next_slot = (self.next_slot + 1) % self.slots;
+        self.journal = if self.journal_limit > 0 {
+            Some(Journal::new(slot, container, board, fs::metadata(&path)?.len()))
+        } else {
+            None
+        };
+
+        Ok(path)
+    }
+
+    fn append_journal(&mut self, container: &Container, board: &Board) -> io::Result<Option<PathBuf>> {
+        let limit = self.journal_limit;
+        let path = match self.journal {
+            Some(ref journal) if journal.can_append(container, limit) => self.get_path(journal.slot),
+            _ => return Ok(None)
+        };
+
+        let journal = self.journal.as_mut().expect("journal was just checked");
+
+        // changes of the current board are tracked only once it is replaced
+        let line = {
+            let (births, deaths): (Vec<&Coord>, Vec<&Coord>) = journal.changes.symmetric_difference(board.changed_cells())
+                .partition(|cell| board.is_alive(cell.col, cell.row));
+            format_entry(container.get_meta(GENERATION_KEY).unwrap_or("0"), &births, &deaths)
+        };
+
+        // the whole line is written at once, a line cut short by a crash is skipped on restore
+        let mut file = OpenOptions::new().create(true).append(true).open(Autosave::get_journal_path(&path))?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        journal.changes = board.changed_cells().clone();
+        journal.entries += 1;
+        journal.size += line.len() as u64;
+
+        Ok(Some(path))
+    }
+
+    /// Loads a full save and replays its journal over it, up to the first damaged line
+    pub fn restore(path: &Path) -> io::Result<Container> {
+        let mut container = Container::load(path)?;
+
+        let mut contents = String::new();
+        match File::open(Autosave::get_journal_path(path)) {
+            Ok(mut file) => { file.read_to_string(&mut contents)?; }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(container),
+            Err(err) => return Err(err)
+        }
+
+        let complete = match contents.rfind('\n') {
+            Some(pos) => &contents[..pos + 1],
+            None => return Ok(container)
+        };
+
+        let mut cells: HashSet<Coord> = container.cells.drain(..).collect();
+        // the lines after a damaged one change cells it was supposed to change first
+        for line in complete.lines() {
+            match parse_entry(line, &mut cells) {
+                Ok(generation) => container.set_meta(GENERATION_KEY, generation),
+                Err(_) => break
+            }
+        }
+
+        container.cells = cells.into_iter().collect();
+        container.cells.sort();
+
+        Ok(container)
+    }
+
+    /// The most recently written autosave
+    pub fn find_latest(&self) -> Option<PathBuf> {
+        self.find_latest_sequence().map(|(_, path)| path)
+    }
+
+    // saves without a number, e.g. damaged ones, are the oldest
+    fn find_latest_sequence(&self) -> Option<(u64, PathBuf)> {
+        let mut res: Option<(u64, PathBuf)> = None;
+
+        for slot in 0..self.slots {
+            let path = self.get_path(slot);
+            if !path.exists() {
+                continue;
+            }
+
+            let sequence = Container::load_meta(&path).ok()
+                .and_then(|container| container.get_meta(SEQUENCE_KEY).and_then(|value| value.parse().ok()))
+                .unwrap_or(0);
+
+            if res.as_ref().map_or(true, |&(latest, _)| sequence > latest) {
+                res = Some((sequence, path));
+            }
+        }
+
+        res
+    }
+
+    pub fn clear(&self) {
+        for slot in 0..self.slots {
+            // missing files are fine
+            let path = self.get_path(slot);
+            let _ = fs::remove_file(Autosave::get_journal_path(&path));
+            let _ = fs::remove_file(path);
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+use std::env;
+#[cfg(test)]
+use ::board::hashed::new as new_hashed;
+
+#[test]
+fn test_autosave_due() {
+    let mut autosave = Autosave::new(Path::new("."), 3, 100, 60.0);
+
+    assert!(!autosave.is_due(10, 1000.0));
+    assert!(!autosave.is_due(109, 1059.0));
+    assert!(autosave.is_due(110, 1000.0));
+    assert!(autosave.is_due(20, 1060.0));
+
+    // generations are counted from zero after reset
+    let mut reset = Autosave::new(Path::new("."), 3, 100, 60.0);
+    assert!(!reset.is_due(500, 1000.0));
+    assert!(!reset.is_due(50, 1001.0));
+    assert!(reset.is_due(120, 1001.0));
+
+    let mut by_time = Autosave::new(Path::new("."), 3, 0, 60.0);
+    assert!(!by_time.is_due(0, 1000.0));
+    assert!(!by_time.is_due(1000000, 1001.0));
+    assert!(by_time.is_due(1000001, 1061.0));
+}
+
+#[test]
+fn test_autosave_rotation() {
+    let dir = env::temp_dir().join("life-rs-autosave-test");
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut autosave = Autosave::new(&dir, 2, 1, 0.0);
+    autosave.clear();
+    assert_eq!(autosave.find_latest(), None);
+
+    for iteration in 1..4 {
+        let mut container = Container::new();
+        container.set_meta("iteration", iteration);
+        autosave.save(&mut container, &Board::new(new_hashed(), None, None), iteration, iteration as f64).unwrap();
+    }
+
+    // the third save took the place of the first one
+    let load = |slot| Container::load(&autosave.get_path(slot)).unwrap();
+    assert_eq!(load(0).get_meta("iteration"), Some("3"));
+    assert_eq!(load(1).get_meta("iteration"), Some("2"));
+    assert!(!dir.join(format!("{}0{}", FILE_PREFIX, TEMP_SUFFIX)).exists());
+    // the saves are told apart by their numbers, not by the time of the files
+    assert_eq!(autosave.find_latest(), Some(autosave.get_path(0)));
+
+    // the next session goes on numbering from the saves left by the last one
+    let mut next = Autosave::new(&dir, 2, 1, 0.0);
+    next.next_slot = 1;
+    next.save(&mut Container::new(), &Board::new(new_hashed(), None, None), 1, 1.0).unwrap();
+    assert_eq!(next.find_latest(), Some(next.get_path(1)));
+
+    autosave.clear();
+    assert_eq!(autosave.find_latest(), None);
+}
+
+#[cfg(test)]
+fn next_board(autosave: &mut Autosave, board: &Board, cells: &[(i64, i64)]) -> Board<'static> {
+    autosave.track_changes(board.changed_cells());
+
+    let mut next = Board::new(new_hashed(), None, None);
+    for &(col, row) in cells {
+        next.born_at(col, row);
+    }
+    let changed = next.get_changes_from(board);
+    next.set_changed_cells(changed);
+    next
+}
+
+// as the engine does it, a full save only when the journal can't take the changes
+#[cfg(test)]
+fn save_board(autosave: &mut Autosave, board: &Board, generation: usize, rule: &str) -> PathBuf {
+    let mut container = Container::new();
+    container.set_meta("generation", generation);
+    container.set_meta("rule", rule);
+    if let Some(path) = autosave.save_changes(&container, board, generation, generation as f64).unwrap() {
+        return path;
+    }
+
+    container.cells = board.get_occupied().iter().cloned().collect();
+    autosave.save(&mut container, board, generation, generation as f64).unwrap()
+}
+
+#[test]
+fn test_autosave_journal() {
+    let dir = env::temp_dir().join("life-rs-autosave-journal-test");
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut autosave = Autosave::new(&dir, 2, 1, 0.0);
+    autosave.set_journal_limit(2);
+    autosave.clear();
+
+    let board = next_board(&mut autosave, &Board::new(new_hashed(), None, None), &[(0, 0), (1, 0)]);
+    let path = save_board(&mut autosave, &board, 1, "B3/S23");
+    let mut board = next_board(&mut autosave, &board, &[(1, 0), (-5, 7)]);
+    assert_eq!(save_board(&mut autosave, &board, 2, "B3/S23"), path);
+
+    // cells edited on the current board are journaled too
+    board.born_at(2, 2);
+    assert_eq!(save_board(&mut autosave, &board, 3, "B3/S23"), path);
+
+    let restored = Autosave::restore(&autosave.find_latest().unwrap()).unwrap();
+    assert_eq!(restored.get_meta("generation"), Some("3"));
+    assert_eq!(restored.cells, vec![Coord { col: -5, row: 7 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 2 }]);
+
+    // a line cut short by a crash is skipped
+    let journal_path = Autosave::get_journal_path(&path);
+    OpenOptions::new().append(true).open(&journal_path).unwrap().write_all(b"4 +9,9").unwrap();
+    assert_eq!(Autosave::restore(&path).unwrap().get_meta("generation"), Some("3"));
+
+    // so are a damaged line and the ones after it, the lines before it are still replayed
+    OpenOptions::new().append(true).open(&journal_path).unwrap().write_all(b"x\n5 +8,8\n").unwrap();
+    let restored = Autosave::restore(&path).unwrap();
+    assert_eq!(restored.get_meta("generation"), Some("3"));
+    assert_eq!(restored.cells.len(), 3);
+
+    // journal is full, the next save is a full one in the next slot
+    let board = next_board(&mut autosave, &board, &[(3, 3)]);
+    let full = save_board(&mut autosave, &board, 4, "B3/S23");
+    assert!(full != path);
+    assert!(!Autosave::get_journal_path(&full).exists());
+    assert_eq!(Autosave::restore(&full).unwrap().cells, vec![Coord { col: 3, row: 3 }]);
+
+    // so is a save of a changed rule
+    let board = next_board(&mut autosave, &board, &[]);
+    assert_eq!(save_board(&mut autosave, &board, 5, "B3/S23"), full);
+    let board = next_board(&mut autosave, &board, &[(0, 0)]);
+    assert_eq!(save_board(&mut autosave, &board, 6, "B36/S23"), path);
+    assert!(!Autosave::get_journal_path(&path).exists());
+    assert_eq!(Autosave::restore(&autosave.find_latest().unwrap()).unwrap().get_meta("rule"), Some("B36/S23"));
+
+    // and a save of a board replaced as a whole
+    autosave.forget_changes();
+    assert_eq!(save_board(&mut autosave, &board, 7, "B36/S23"), full);
+
+    autosave.clear();
+    assert_eq!(autosave.find_latest(), None);
+    assert!(!Autosave::get_journal_path(&full).exists());
+}
+
+#[test]
+fn test_journal_entry() {
+    let mut cells = HashSet::new();
+    assert_eq!(parse_entry("7 +1,2 +3,-4 -1,2", &mut cells).unwrap(), 7);
+    assert_eq!(cells.into_iter().collect::<Vec<_>>(), vec![Coord { col: 3, row: -4 }]);
+
+    // damaged lines are errors, whatever characters they have
+    for line in ["7 \u{e9}1,2", "7 \u{e9}", "7 +1,x,2", "7 +1", "x +1,2", "7 *1,2"].iter() {
+        assert!(parse_entry(line, &mut HashSet::new()).is_err());
+    }
+
+    // and change no cells
+    let mut cells = HashSet::new();
+    assert!(parse_entry("8 +1,1 +2,x", &mut cells).is_err());
+    assert!(cells.is_empty());
+}
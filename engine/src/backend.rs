@@ -0,0 +1,275 @@
+/// Backends of the engine, each one a kind of board storage along with
+/// the way generations of it are computed. The engine keeps one of them
+/// as a trait object, so it can be changed at runtime, e.g. by a command
+/// line option or the `engine.backend` setting, by name:
+/// ```
+/// # use engine::backend;
+/// # use engine::engine::Engine;
+/// let mut engine = Engine::new(None, None);
+/// engine.set_backend(backend::find("sparse").unwrap());
+///
+/// assert_eq!(engine.get_backend_name(), "sparse");
+/// assert!(backend::find("paged:0").is_none());
+/// ```
+///
+/// The engine steps boards, reads and edits their cells, population and
+/// bounds through the backend which made them. By default all but the
+/// step are left to the `Board`, see `BoardInternal` for what new storage
+/// has to provide. The default backend, `auto`, moves the cells between
+/// a hash map and vectors as the pattern gets sparse or dense, backends
+/// chosen by name stay as they are.
+///
+
+use ::board::{Board, Cell, Rect};
+use ::board::hashed::new as new_hashed;
+use ::board::vect::new as new_vect;
+use ::board::bits::new as new_bits;
+use ::board::paged::new as new_paged;
+use ::board::sparse::new as new_sparse;
+use ::rule::Rule;
+
+
+// live share of the bounding box above which vectors beat hash maps
+const DENSE: f64 = 0.03;
+
+
+pub trait EngineBackend {
+
+    /// Name the backend is found by, see `find`
+    fn get_name(&self) -> String;
+
+    /// Kind of board the backend makes now, the name unless the backend
+    /// switches between boards on its own
+    fn get_board_name(&self) -> String {
+        self.get_name()
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a>;
+
+    /// Next generation of a board made by the backend along with the
+    /// numbers of cells born and died, None leaves it to the engine to
+    /// compute it cell by cell; the board may be left empty otherwise
+    fn step<'a>(&self, _board: &mut Board<'a>, _rule: &Rule) -> Option<(Board<'a>, usize, usize)> {
+        None
+    }
+
+    /// Number of live cells of a board made by the backend
+    fn get_population(&self, board: &Board) -> usize {
+        board.get_population()
+    }
+
+    fn get_cell(&self, board: &Board, col: i64, row: i64) -> Cell {
+        board.get_cell(col, row)
+    }
+
+    /// Makes the cell live with the given generation or kills it
+    fn set_cell(&self, board: &mut Board, col: i64, row: i64, cell: Cell) {
+        match cell {
+            Cell::Occupied { gen } => board.born_at_gen(col, row, gen),
+            Cell::Empty => board.kill_at(col, row)
+        }
+    }
+
+    /// Region the cells of a board made by the backend can be in
+    fn get_bounds(&self, board: &Board) -> Rect {
+        board.get_bounds()
+    }
+
+    /// Backend the engine switches to on its own at the given density of
+    /// the pattern, backends chosen by the user are kept whatever it is
+    fn get_switch(&self, _density: f64) -> Option<Box<EngineBackend>> {
+        None
+    }
+
+    /// Chosen by the user for the way it keeps big patterns, which isn't
+    /// replaced by the dense grid of the kernel
+    fn is_chosen(&self) -> bool {
+        true
+    }
+
+}
+
+/// Cells in a hash map while the pattern is sparse and in vectors once
+/// it gets dense, the default
+pub struct Auto {
+    dense: bool,
+}
+
+/// Cells in a hash map, good for sparse patterns
+pub struct Hashed;
+
+/// Cells in vectors growing in both directions, good for dense patterns
+pub struct SymVec;
+
+pub struct BitPacked;
+
+/// Keeps at most the given number of chunks in memory, the live cells
+/// are still indexed in memory by `Board`
+pub struct Paged(pub usize);
+
+pub struct Sparse;
+
+impl Auto {
+
+    pub fn new() -> Self {
+        Auto { dense: false }
+    }
+
+}
+
+impl Default for Auto {
+    fn default() -> Self {
+        Auto::new()
+    }
+}
+
+impl EngineBackend for Auto {
+
+    fn get_name(&self) -> String {
+        "auto".to_string()
+    }
+
+    fn get_board_name(&self) -> String {
+        if self.dense { SymVec.get_name() } else { Hashed.get_name() }
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
+        if self.dense { SymVec.new_board(cols, rows) } else { Hashed.new_board(cols, rows) }
+    }
+
+    fn get_switch(&self, density: f64) -> Option<Box<EngineBackend>> {
+        if (density >= DENSE) != self.dense {
+            Some(Box::new(Auto { dense: !self.dense }))
+        } else {
+            None
+        }
+    }
+
+    fn is_chosen(&self) -> bool {
+        false
+    }
+
+}
+
+impl EngineBackend for Hashed {
+
+    fn get_name(&self) -> String {
+        "hashed".to_string()
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
+        Board::new(new_hashed(), cols, rows)
+    }
+
+}
+
+impl EngineBackend for SymVec {
+
+    fn get_name(&self) -> String {
+        "symvec".to_string()
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
+        Board::new(new_vect(), cols, rows)
+    }
+
+}
+
+impl EngineBackend for BitPacked {
+
+    fn get_name(&self) -> String {
+        "bits".to_string()
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
+        Board::new(new_bits(), cols, rows)
+    }
+
+    fn step<'a>(&self, board: &mut Board<'a>, rule: &Rule) -> Option<(Board<'a>, usize, usize)> {
+        board.next_generation(rule)
+    }
+
+}
+
+impl EngineBackend for Paged {
+
+    fn get_name(&self) -> String {
+        format!("paged:{}", self.0)
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
+        Board::new(new_paged(self.0), cols, rows)
+    }
+
+}
+
+impl EngineBackend for Sparse {
+
+    fn get_name(&self) -> String {
+        "sparse".to_string()
+    }
+
+    fn new_board<'a>(&self, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
+        Board::new(new_sparse(), cols, rows)
+    }
+
+}
+
+/// Backend by its name: `auto`, `hashed`, `symvec`, `bits`, `sparse`
+/// or `paged:CHUNKS`
+pub fn find(name: &str) -> Option<Box<EngineBackend>> {
+    let name = name.trim();
+
+    if name.starts_with("paged:") {
+        return match name["paged:".len()..].parse::<usize>() {
+            Ok(max_chunks) if max_chunks > 0 => Some(Box::new(Paged(max_chunks))),
+            _ => None
+        };
+    }
+
+    match name {
+        "auto" => Some(Box::new(Auto::new())),
+        "hashed" => Some(Box::new(Hashed)),
+        "symvec" => Some(Box::new(SymVec)),
+        "bits" => Some(Box::new(BitPacked)),
+        "sparse" => Some(Box::new(Sparse)),
+        _ => None
+    }
+}
+
+
+#[test]
+fn test_find_backend() {
+    for name in ["auto", "hashed", "symvec", "bits", "sparse", "paged:64"].iter() {
+        assert_eq!(find(name).unwrap().get_name(), name.to_string());
+    }
+    assert!(find("hashlife").is_none() && find("paged").is_none() && find("paged:x").is_none());
+
+    // only the default backend switches boards, the chosen ones are kept
+    let dense = Auto::new().get_switch(0.5).unwrap();
+    assert_eq!((dense.get_name(), dense.get_board_name()), ("auto".to_string(), "symvec".to_string()));
+    assert!(Auto::new().get_switch(0.01).is_none() && dense.get_switch(0.5).is_none());
+    assert_eq!(dense.get_switch(0.01).unwrap().get_board_name(), "hashed");
+    assert!(Hashed.get_switch(0.5).is_none() && SymVec.get_switch(0.01).is_none());
+    assert!(Hashed.is_chosen() && SymVec.is_chosen() && !Auto::new().is_chosen());
+    assert!(BitPacked.get_switch(0.01).is_none() && Sparse.get_switch(0.5).is_none());
+}
+
+#[test]
+fn test_backend_cells() {
+    for name in ["auto", "hashed", "symvec", "bits", "sparse"].iter() {
+        let backend = find(name).unwrap();
+        let mut board = backend.new_board(Some(10), Some(10));
+
+        backend.set_cell(&mut board, 1, 2, Cell::Occupied { gen: 3 });
+        backend.set_cell(&mut board, 2, 2, Cell::Occupied { gen: 1 });
+        backend.set_cell(&mut board, 2, 2, Cell::Empty);
+        // bounded boards wrap around
+        backend.set_cell(&mut board, 11, 12, Cell::Occupied { gen: 1 });
+
+        assert_eq!(backend.get_population(&board), 1, "{}", name);
+        assert_eq!(backend.get_cell(&board, 1, 2), Cell::Occupied { gen: 3 });
+        assert_eq!(backend.get_cell(&board, 2, 2), Cell::Empty);
+        assert_eq!(backend.get_bounds(&board), Rect::new(-5, -5, 4, 4));
+    }
+}
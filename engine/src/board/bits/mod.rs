@@ -0,0 +1,488 @@
+// Bit-packed board: every row of the allocated region is kept as 64-cell
+// words, so the next generation is computed a word at a time by adding up
+// neighbour rows shifted by one bit. Only live cells have their ages kept,
+// as the generation they were born at, so cells which survive a generation
+// aren't touched at all. Regions needing more than MAX_AREA cells aren't
+// allocated, the cells which would need them are refused. Built with the
+// `simd` feature, rows are counted four words at a time with AVX2 if the
+// processor has it
+use ::board::{BoardInternal, Cell, CellIterType, Coord, Rect};
+use ::rule::Rule;
+
+use std::collections::HashMap;
+use std::iter;
+use std::mem;
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+
+const WORD: usize = 64;
+// the least number of cells the region grows by when a cell outside it is allocated
+const GROW: i64 = 32;
+// most cells of the region, 512 MB of bits
+const MAX_AREA: u64 = 1 << 32;
+
+
+pub struct BitBased {
+    left: i64,
+    top: i64,
+    width: usize,
+    height: usize,
+
+    // words in a row, bit j of word w is the cell in column w * WORD + j
+    words: usize,
+    bits: Vec<u64>,
+
+    // generations the board went through and the ones live cells were
+    // born at, the age of a cell is the difference of the two
+    generation: i64,
+    births: HashMap<Coord, i64>,
+}
+
+// positions of set bits, the lowest first
+struct Ones(u64);
+
+impl Iterator for Ones {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let pos = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(pos)
+    }
+}
+
+// counts of up to 15 neighbours, one bit of the count per word
+#[derive(Default)]
+struct Counter {
+    planes: [u64; 4],
+}
+
+impl Counter {
+
+    #[inline]
+    fn add(&mut self, mut carry: u64) {
+        for plane in self.planes.iter_mut() {
+            let next = *plane & carry;
+            *plane ^= carry;
+            carry = next;
+        }
+    }
+
+    #[inline]
+    fn equals(&self, n: usize) -> u64 {
+        self.planes.iter().enumerate()
+            .fold(!0, |res, (bit, &plane)| res & if n & (1 << bit) != 0 { plane } else { !plane })
+    }
+
+}
+
+impl BitBased {
+
+    fn with_region(left: i64, top: i64, width: usize, height: usize) -> BitBased {
+        let words = (width + WORD - 1) / WORD;
+
+        BitBased {
+            left: left,
+            top: top,
+            width: width,
+            height: height,
+
+            words: words,
+            bits: vec![0; words * height],
+
+            generation: 0,
+            births: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    fn contains(&self, col: i64, row: i64) -> bool {
+        // regions may reach the edges of i64, the distances always fit into u64
+        col >= self.left && (col.wrapping_sub(self.left) as u64) < self.width as u64 &&
+            row >= self.top && (row.wrapping_sub(self.top) as u64) < self.height as u64
+    }
+
+    #[inline]
+    fn get_pos(&self, col: i64, row: i64) -> (usize, usize) {
+        (col.wrapping_sub(self.left) as usize, row.wrapping_sub(self.top) as usize)
+    }
+
+    #[inline]
+    fn get_bit(&self, x: usize, y: usize) -> bool {
+        self.bits[y * self.words + x / WORD] & (1 << (x % WORD)) != 0
+    }
+
+    #[inline]
+    fn get_gen(&self, col: i64, row: i64) -> usize {
+        let birth = self.births.get(&Coord { col: col, row: row }).cloned().unwrap_or(self.generation);
+        (self.generation - birth + 1) as usize
+    }
+
+    #[inline]
+    fn set_bit(&mut self, x: usize, y: usize, value: bool) {
+        let (word, mask) = (y * self.words + x / WORD, 1 << (x % WORD));
+        if value {
+            self.bits[word] |= mask;
+        } else {
+            self.bits[word] &= !mask;
+        }
+    }
+
+    // positions of live cells inside the region
+    fn live_cells<'a>(&'a self) -> Box<Iterator<Item=(usize, usize)> + 'a> {
+        Box::new((0..self.height).flat_map(move |y| {
+            (0..self.words).flat_map(move |w| {
+                Ones(self.bits[y * self.words + w]).map(move |bit| (w * WORD + bit, y))
+            })
+        }))
+    }
+
+    fn get_bounding_box(&self) -> Option<Rect> {
+        self.live_cells().fold(None, |res: Option<Rect>, (x, y)| {
+            let (col, row) = (self.left + x as i64, self.top + y as i64);
+            Some(match res {
+                Some(rect) => Rect::new(rect.left.min(col), rect.top.min(row),
+                                        rect.right.max(col), rect.bottom.max(row)),
+                None => Rect::new(col, row, col, row)
+            })
+        })
+    }
+
+    // bits of the columns from `first` to `last` of a word
+    fn get_mask(word: usize, first: usize, last: usize) -> u64 {
+        (0..WORD)
+            .filter(|&bit| word * WORD + bit >= first && word * WORD + bit <= last)
+            .fold(0, |mask, bit| mask | 1 << bit)
+    }
+
+    // words of a row for the vector code
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn get_row(&self, y: usize) -> &[u64] {
+        &self.bits[y * self.words..(y + 1) * self.words]
+    }
+
+    // next states of the words of a row, which has rows above and below
+    fn next_row(&self, y: usize, counts: &[(usize, bool, bool)], out: &mut [u64]) {
+        // words from the second one up to this one are done by the vector code
+        let end = self.next_row_simd(y, counts, out);
+
+        for w in iter::once(0).chain(end..self.words) {
+            out[w] = self.next_word(w, y, counts);
+        }
+    }
+
+    #[inline]
+    fn next_word(&self, w: usize, y: usize, counts: &[(usize, bool, bool)]) -> u64 {
+        let counter = self.count_neighbours(w, y);
+        let alive = self.bits[y * self.words + w];
+
+        let mut res = 0;
+        for &(n, born, survives) in counts {
+            res |= counter.equals(n) & (if born { !alive } else { 0 } | if survives { alive } else { 0 });
+        }
+        res
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    fn next_row_simd(&self, y: usize, counts: &[(usize, bool, bool)], out: &mut [u64]) -> usize {
+        if !::simd::is_enabled() || !is_x86_feature_detected!("avx2") {
+            return 1;
+        }
+
+        unsafe { simd::next_row([self.get_row(y - 1), self.get_row(y), self.get_row(y + 1)], counts, out) }
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    fn next_row_simd(&self, _y: usize, _counts: &[(usize, bool, bool)], _out: &mut [u64]) -> usize {
+        1
+    }
+
+    // live neighbours of every cell of a word
+    #[inline]
+    fn count_neighbours(&self, x_word: usize, y: usize) -> Counter {
+        let mut counter = Counter::default();
+
+        for row in y - 1..y + 2 {
+            let word = |w: usize| self.bits[row * self.words + w];
+
+            let cur = word(x_word);
+            let prev = if x_word > 0 { word(x_word - 1) } else { 0 };
+            let next = if x_word + 1 < self.words { word(x_word + 1) } else { 0 };
+
+            // cells to the west and to the east moved into the place of the cell
+            counter.add(cur << 1 | prev >> (WORD - 1));
+            counter.add(cur >> 1 | next << (WORD - 1));
+            if row != y {
+                counter.add(cur);
+            }
+        }
+
+        counter
+    }
+
+}
+
+impl BoardInternal for BitBased {
+
+    fn get_cell(&self, col: i64, row: i64) -> Option<Cell> {
+        if !self.contains(col, row) {
+            return None;
+        }
+
+        let (x, y) = self.get_pos(col, row);
+        Some(if self.get_bit(x, y) { Cell::Occupied { gen: self.get_gen(col, row) } } else { Cell::Empty })
+    }
+
+    fn set_cell(&mut self, col: i64, row: i64, val: Cell) {
+        self.ensure_cell(col, row);
+        if !self.contains(col, row) {
+            return;
+        }
+
+        let (x, y) = self.get_pos(col, row);
+        match val {
+            Cell::Occupied { gen } => {
+                self.births.insert(Coord { col: col, row: row }, self.generation + 1 - gen as i64);
+                self.set_bit(x, y, true);
+            }
+            Cell::Empty => self.rm_cell(col, row)
+        }
+    }
+
+    fn ensure_cell(&mut self, col: i64, row: i64) {
+        if self.contains(col, row) {
+            return;
+        }
+
+        // region is reallocated with room to grow in the direction it grows
+        let (grow_cols, grow_rows) = (GROW.max(self.width as i64 / 2), GROW.max(self.height as i64 / 2));
+
+        let rect = if self.width == 0 {
+            Rect::new(col.saturating_sub(GROW), row.saturating_sub(GROW), col.saturating_add(GROW), row.saturating_add(GROW))
+        } else {
+            let (right, bottom) = (self.left + self.width as i64 - 1, self.top + self.height as i64 - 1);
+            Rect::new(if col < self.left { col.saturating_sub(grow_cols) } else { self.left },
+                      if row < self.top { row.saturating_sub(grow_rows) } else { self.top },
+                      if col > right { col.saturating_add(grow_cols) } else { right },
+                      if row > bottom { row.saturating_add(grow_rows) } else { bottom })
+        };
+
+        // cells too far apart are refused, see `is_allocated`
+        if (rect.get_width() as u64).saturating_mul(rect.get_height() as u64) > MAX_AREA {
+            return;
+        }
+
+        let mut res = BitBased::with_region(rect.left, rect.top, rect.get_width(), rect.get_height());
+        for (x, y) in self.live_cells() {
+            let (x, y) = res.get_pos(self.left + x as i64, self.top + y as i64);
+            res.set_bit(x, y, true);
+        }
+        res.generation = self.generation;
+        res.births = mem::replace(&mut self.births, HashMap::new());
+
+        *self = res;
+    }
+
+    fn is_allocated(&self, col: i64, row: i64) -> bool {
+        self.contains(col, row)
+    }
+
+    fn rm_cell(&mut self, col: i64, row: i64) {
+        if self.contains(col, row) {
+            let (x, y) = self.get_pos(col, row);
+            self.births.remove(&Coord { col: col, row: row });
+            self.set_bit(x, y, false);
+        }
+    }
+
+    fn get_iter<'a>(&'a self) -> Box<Iterator<Item=CellIterType> + 'a> {
+        // empty cells are never needed by the stepping, so only live ones are given
+        Box::new(self.live_cells().map(move |(x, y)| {
+            let (col, row) = (self.left + x as i64, self.top + y as i64);
+            (col, row, Cell::Occupied { gen: self.get_gen(col, row) })
+        }))
+    }
+
+    fn clear(&mut self) {
+        *self = BitBased::with_region(0, 0, 0, 0);
+    }
+
+    fn next_generation(&mut self, rule: &Rule, bounds: Option<&Rect>) -> Option<(Box<BoardInternal>, Vec<Coord>)> {
+        // region of the next generation, cells are born at most one cell away from live ones
+        let rect = match (bounds, self.get_bounding_box()) {
+            (Some(bounds), _) => *bounds,
+            (None, Some(rect)) => Rect::new(rect.left - 1, rect.top - 1, rect.right + 1, rect.bottom + 1),
+            (None, None) => return Some((new(), Vec::new()))
+        };
+
+        // the region with a border of one cell, which on bounded boards
+        // holds copies of the cells on the opposite edges
+        let mut cur = BitBased::with_region(rect.left - 1, rect.top - 1, rect.get_width() + 2, rect.get_height() + 2);
+        let (width, height) = (rect.get_width() as i64, rect.get_height() as i64);
+
+        let wrap = |pos: i64, first: i64, last: i64, size: i64| -> [i64; 3] {
+            match bounds {
+                Some(_) => [pos, if pos == first { pos + size } else { pos }, if pos == last { pos - size } else { pos }],
+                None => [pos; 3]
+            }
+        };
+
+        for (x, y) in self.live_cells() {
+            let (col, row) = (self.left + x as i64, self.top + y as i64);
+
+            for &col in wrap(col, rect.left, rect.right, width).iter() {
+                for &row in wrap(row, rect.top, rect.bottom, height).iter() {
+                    let (x, y) = cur.get_pos(col, row);
+                    cur.set_bit(x, y, true);
+                }
+            }
+        }
+
+        let mut next = BitBased::with_region(cur.left, cur.top, cur.width, cur.height);
+        let mut changed = Vec::new();
+
+        // the border isn't a part of the next generation
+        let masks: Vec<u64> = (0..cur.words).map(|w| BitBased::get_mask(w, 1, cur.width - 2)).collect();
+
+        // numbers of neighbours cells are born or survive with
+        let counts: Vec<(usize, bool, bool)> = (0..9)
+            .map(|n| (n, rule.is_born(n), rule.survives(n)))
+            .filter(|&(_, born, survives)| born || survives)
+            .collect();
+        let mut row = vec![0; cur.words];
+
+        for y in 1..cur.height - 1 {
+            cur.next_row(y, &counts, &mut row);
+
+            for w in 0..cur.words {
+                let alive = cur.bits[y * cur.words + w] & masks[w];
+                let res = row[w] & masks[w];
+
+                for bit in Ones(res ^ alive) {
+                    changed.push(Coord { col: cur.left + (w * WORD + bit) as i64, row: cur.top + y as i64 });
+                }
+                next.bits[y * next.words + w] = res;
+            }
+        }
+
+        // survivors get one generation older by the generation going on,
+        // only the cells born or died change their births
+        next.generation = self.generation + 1;
+        next.births = mem::replace(&mut self.births, HashMap::new());
+        for coord in changed.iter() {
+            if next.births.remove(coord).is_none() {
+                next.births.insert(*coord, next.generation);
+            }
+        }
+
+        Some((Box::new(next), changed))
+    }
+
+}
+
+pub fn new() -> Box<BoardInternal> {
+    Box::new(BitBased::with_region(0, 0, 0, 0))
+}
+
+
+#[cfg(test)]
+use ::board::{Board, BoardError, CombineMode};
+#[cfg(test)]
+use ::board::hashed::new as new_hashed;
+#[cfg(test)]
+use ::engine::Engine;
+#[cfg(test)]
+use ::soup::SoupSearch;
+
+#[test]
+fn test_bits_board() {
+    let mut board = Board::new(new(), None, None);
+
+    // region grows to any side
+    for &(col, row) in [(0, 0), (100, -3), (-70, 200), (64, 63)].iter() {
+        board.born_at(col, row);
+    }
+    board.kill_at(100, -3);
+
+    assert_eq!(board.get_population(), 3);
+    assert_eq!(board.get_bounding_box(), Some(Rect::new(-70, 0, 64, 200)));
+    assert_eq!(board.into_iter().filter(|cell| cell.is_alive).count(), 3);
+    assert_eq!(board.get_cell(64, 63), Cell::Occupied { gen: 1 });
+
+    board.trim();
+    assert_eq!(board.copy_region(&Rect::new(0, 0, 64, 63)), vec![Coord { col: 0, row: 0 }, Coord { col: 64, row: 63 }]);
+
+    // a cell too far away for one region is refused instead of
+    // allocating all the cells in between
+    board.born_at(i64::max_value() - 10, 0);
+    assert!(!board.is_alive(i64::max_value() - 10, 0));
+    assert_eq!(board.get_population(), 3);
+    assert_eq!(board.take_error(), Some(BoardError::TooFar { col: i64::max_value() - 10, row: 0 }));
+    assert_eq!(board.take_error(), None);
+}
+
+#[test]
+fn test_bits_next_generation() {
+    // the same soups stepped word by word and cell by cell
+    for &(rule, bounded) in [("B3/S23", false), ("B36/S23", true), ("B2/S", false), ("B3/S012345678", true)].iter() {
+        let size = if bounded { Some(20) } else { None };
+        let mut engines: Vec<Engine> = (0..2).map(|_| Engine::new(size, size)).collect();
+
+        for engine in engines.iter_mut() {
+            engine.set_rule(rule.parse().unwrap());
+            SoupSearch::new(5, Rect::new(-8, -8, 9, 9), 0.4).seed_board(engine);
+        }
+        engines[0].set_bit_packed(true);
+
+        assert!(engines[0].get_board().get_population() > 0);
+
+        // only the bit-packed board steps itself, its cells are moved
+        // over to the next generation
+        let mut boards = vec![Board::new(new(), size, size), Board::new(new_hashed(), size, size)];
+        for board in boards.iter_mut() {
+            board.merge(engines[0].get_board(), Coord { col: 0, row: 0 }, CombineMode::Or);
+        }
+        assert!(boards[0].next_generation(&engines[0].get_rule()).is_some());
+        assert!(boards[0].get_occupied().is_empty() && boards[0].get_population() == 0);
+        assert!(boards[1].next_generation(&engines[1].get_rule()).is_none());
+        assert_eq!(boards[1].get_population(), engines[0].get_board().get_population());
+
+        for _ in 0..30 {
+            let prev = engines[0].get_board().get_occupied().clone();
+            for engine in engines.iter_mut() {
+                engine.one_iteration();
+            }
+
+            let board = engines[0].get_board();
+            assert_eq!(board.changed_cells(), &board.get_occupied().symmetric_difference(&prev).cloned().collect());
+
+            let cells: Vec<Vec<(Coord, usize)>> = engines.iter().map(|engine| {
+                let mut cells: Vec<(Coord, usize)> = engine.get_board().into_iter()
+                    .filter(|cell| cell.is_alive)
+                    .map(|cell| (cell.coord, cell.gen))
+                    .collect();
+                cells.sort();
+                cells
+            }).collect();
+
+            assert_eq!(cells[0], cells[1]);
+            assert_eq!(engines[0].get_board().get_population(), engines[1].get_board().get_population());
+        }
+
+        // neighbours of a stepped board are counted once it's edited
+        let board = engines[0].get_board_mut();
+        let coord = *board.get_occupied().iter().next().unwrap();
+        board.kill_at(coord.col, coord.row);
+        board.count_neighbours();
+        for &Coord { col, row } in board.get_occupied().iter() {
+            let count = board.get_vicinity(col, row).into_iter().filter(|&alive| alive).count();
+            assert_eq!(board.get_neighbour_count(col, row), count);
+        }
+    }
+}
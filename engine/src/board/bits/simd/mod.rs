@@ -0,0 +1,106 @@
+// AVX2 stepping of the bit-packed board: four words of a row, 256 cells,
+// are counted at once by the same bit-sliced adder as the scalar code,
+// the words on both ends of the row are left to it
+use std::arch::x86_64::*;
+
+
+const LANES: usize = 4;
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn load(row: &[u64], w: usize) -> __m256i {
+    _mm256_loadu_si256(row[w..w + LANES].as_ptr() as *const __m256i)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn add(planes: &mut [__m256i; 4], mut carry: __m256i) {
+    for plane in planes.iter_mut() {
+        let next = _mm256_and_si256(*plane, carry);
+        *plane = _mm256_xor_si256(*plane, carry);
+        carry = next;
+    }
+}
+
+/// Same as `BitBased::next_row` for the words from the second one on which
+/// fill the lanes and have a word after them, returns the first word left
+#[target_feature(enable = "avx2")]
+pub unsafe fn next_row(rows: [&[u64]; 3], counts: &[(usize, bool, bool)], out: &mut [u64]) -> usize {
+    let words = out.len();
+    let ones = _mm256_set1_epi64x(-1);
+
+    let mut w = 1;
+    while w + LANES < words {
+        let mut planes = [_mm256_setzero_si256(); 4];
+
+        for (i, row) in rows.iter().enumerate() {
+            let (prev, cur, next) = (load(row, w - 1), load(row, w), load(row, w + 1));
+
+            // cells to the west and to the east moved into the place of the cell
+            add(&mut planes, _mm256_or_si256(_mm256_slli_epi64(cur, 1), _mm256_srli_epi64(prev, 63)));
+            add(&mut planes, _mm256_or_si256(_mm256_srli_epi64(cur, 1), _mm256_slli_epi64(next, 63)));
+            if i != 1 {
+                add(&mut planes, cur);
+            }
+        }
+
+        let alive = load(rows[1], w);
+        let mut res = _mm256_setzero_si256();
+
+        for &(n, born, survives) in counts {
+            let mut count = ones;
+            for (bit, &plane) in planes.iter().enumerate() {
+                count = if n & (1 << bit) != 0 { _mm256_and_si256(count, plane) } else { _mm256_andnot_si256(plane, count) };
+            }
+
+            let states = match (born, survives) {
+                (true, true) => ones,
+                (true, false) => _mm256_andnot_si256(alive, ones),
+                _ => alive
+            };
+            res = _mm256_or_si256(res, _mm256_and_si256(count, states));
+        }
+
+        _mm256_storeu_si256(out[w..w + LANES].as_mut_ptr() as *mut __m256i, res);
+        w += LANES;
+    }
+
+    w
+}
+
+
+#[cfg(test)]
+use super::BitBased;
+#[cfg(test)]
+use ::rule::Rule;
+
+#[test]
+fn test_simd_next_row() {
+    if !is_x86_feature_detected!("avx2") {
+        return;
+    }
+
+    // rows of two passes of the lanes and a tail
+    let mut board = BitBased::with_region(0, 0, 64 * 11, 3);
+    let mut state = 1u64;
+    for word in board.bits.iter_mut() {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        *word = state ^ (state << 17);
+    }
+
+    for rule in ["B3/S23", "B36/S125", "B2/S"].iter() {
+        let rule: Rule = rule.parse().unwrap();
+        let counts: Vec<(usize, bool, bool)> = (0..9)
+            .map(|n| (n, rule.is_born(n), rule.survives(n)))
+            .filter(|&(_, born, survives)| born || survives)
+            .collect();
+
+        let mut vector = vec![0; board.words];
+        let end = unsafe { next_row([board.get_row(0), board.get_row(1), board.get_row(2)], &counts, &mut vector) };
+        assert_eq!(end, 9);
+
+        for (w, &word) in vector.iter().enumerate().take(end).skip(1) {
+            assert_eq!(word, board.next_word(w, 1, &counts), "word {} of {}", w, rule);
+        }
+    }
+}
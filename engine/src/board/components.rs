@@ -0,0 +1,132 @@
+// Connected groups of live cells, touching each other by a side or a
+// corner, found by union-find over the live cells only, so the cost
+// doesn't depend on the empty space between objects
+use std::collections::{HashMap, HashSet};
+
+use ::board::{Coord, Rect};
+
+
+// half of the neighbourhood, the other half finds the cell from the
+// other side of every pair
+const FORWARD: [(i64, i64); 4] = [(1, 0), (1, 1), (0, 1), (-1, 1)];
+
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Component {
+    pub id: usize,
+    pub size: usize,
+    pub bounds: Rect,
+}
+
+/// Components numbered in the order of their smallest cell
+#[derive(Debug, Default)]
+pub struct Components {
+    components: Vec<Component>,
+    ids: HashMap<Coord, usize>,
+}
+
+impl Components {
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    pub fn get_components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Id of the component the live cell belongs to
+    pub fn get_id(&self, coord: &Coord) -> Option<usize> {
+        self.ids.get(coord).cloned()
+    }
+
+    pub fn get_component_of(&self, coord: &Coord) -> Option<&Component> {
+        self.get_id(coord).map(|id| &self.components[id])
+    }
+
+    pub fn get_cells(&self, id: usize) -> Vec<Coord> {
+        let mut cells: Vec<Coord> = self.ids.iter()
+            .filter(|&(_, &cell_id)| cell_id == id)
+            .map(|(&coord, _)| coord)
+            .collect();
+        cells.sort();
+        cells
+    }
+
+}
+
+fn find_root(parents: &mut [usize], mut idx: usize) -> usize {
+    // every node on the way is pointed at its grandparent
+    while parents[idx] != idx {
+        parents[idx] = parents[parents[idx]];
+        idx = parents[idx];
+    }
+    idx
+}
+
+fn union(parents: &mut [usize], sizes: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find_root(parents, a), find_root(parents, b));
+    if a == b {
+        return;
+    }
+
+    // smaller trees go under bigger ones
+    let (big, small) = if sizes[a] >= sizes[b] { (a, b) } else { (b, a) };
+    parents[small] = big;
+    sizes[big] += sizes[small];
+}
+
+/// Groups the live cells, `wrap` maps coordinates of neighbours on
+/// bounded boards back onto the board
+pub fn find<F>(occupied: &HashSet<Coord>, wrap: F) -> Components
+    where F: Fn(i64, i64) -> (i64, i64) {
+
+    let mut cells: Vec<Coord> = occupied.iter().cloned().collect();
+    cells.sort();
+
+    let indices: HashMap<Coord, usize> = cells.iter().enumerate().map(|(idx, &coord)| (coord, idx)).collect();
+    let mut parents: Vec<usize> = (0..cells.len()).collect();
+    let mut sizes = vec![1; cells.len()];
+
+    for (idx, coord) in cells.iter().enumerate() {
+        for &(dc, dr) in FORWARD.iter() {
+            let (col, row) = wrap(coord.col + dc, coord.row + dr);
+            if let Some(&other) = indices.get(&Coord { col: col, row: row }) {
+                union(&mut parents, &mut sizes, idx, other);
+            }
+        }
+    }
+
+    // cells are sorted, so components get ids in the order of their smallest cells
+    let mut res = Components::default();
+    let mut root_ids: HashMap<usize, usize> = HashMap::new();
+
+    for (idx, &coord) in cells.iter().enumerate() {
+        let root = find_root(&mut parents, idx);
+        let next_id = res.components.len();
+        let id = *root_ids.entry(root).or_insert(next_id);
+
+        if id == next_id {
+            res.components.push(Component { id: id, size: 0, bounds: Rect::new(coord.col, coord.row, coord.col, coord.row) });
+        }
+
+        let component = &mut res.components[id];
+        component.size += 1;
+        component.bounds = Rect {
+            left: component.bounds.left.min(coord.col),
+            top: component.bounds.top.min(coord.row),
+            right: component.bounds.right.max(coord.col),
+            bottom: component.bounds.bottom.max(coord.row),
+        };
+
+        res.ids.insert(coord, id);
+    }
+
+    res
+}
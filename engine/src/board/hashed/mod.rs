@@ -1,14 +1,14 @@
 use std::collections::HashMap;
 use std::collections::hash_map::Iter;
 
-use ::board::{BoardInternal, Cell, CellIterType};
+use ::board::{BoardInternal, Cell, CellIterType, Rect};
 
 pub struct HashBased {
-    cells: HashMap<(isize, isize), Cell>
+    cells: HashMap<(i64, i64), Cell>
 }
 
 pub struct CellsIterator<'a> {
-    iter: Iter<'a, (isize, isize), Cell>
+    iter: Iter<'a, (i64, i64), Cell>
 }
 
 impl<'a> Iterator for CellsIterator<'a> {
@@ -38,21 +38,21 @@ impl<'a> IntoIterator for &'a HashBased {
 
 impl BoardInternal for HashBased {
 
-    fn get_cell(&self, col: isize, row: isize) -> Option<&Cell> {
-        self.cells.get(&(col, row))
+    fn get_cell(&self, col: i64, row: i64) -> Option<Cell> {
+        self.cells.get(&(col, row)).cloned()
     }
 
-    fn set_cell(&mut self, col: isize, row: isize, val: Cell) {
+    fn set_cell(&mut self, col: i64, row: i64, val: Cell) {
         self.cells.insert((col, row), val);
     }
 
-    fn ensure_cell(&mut self, col: isize, row: isize) {
+    fn ensure_cell(&mut self, col: i64, row: i64) {
         if self.get_cell(col, row) == None {
             self.set_cell(col, row, Cell::Empty);
         }
     }
 
-    fn rm_cell(&mut self, col: isize, row: isize) {
+    fn rm_cell(&mut self, col: i64, row: i64) {
         self.cells.remove(&(col, row));
     }
 
@@ -60,6 +60,52 @@ impl BoardInternal for HashBased {
         Box::new(IntoIterator::into_iter(self))
     }
 
+    fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    fn shrink(&mut self) {
+        let is_live = |cells: &HashMap<(i64, i64), Cell>, col: i64, row: i64| {
+            match cells.get(&(col, row)) {
+                Some(&Cell::Occupied { .. }) => true,
+                _ => false
+            }
+        };
+
+        let unused: Vec<(i64, i64)> = self.cells.iter()
+            .filter(|&(&(col, row), cell)| {
+                *cell == Cell::Empty &&
+                    (-1..2).all(|dr| (-1..2).all(|dc| !is_live(&self.cells, col + dc, row + dr)))
+            })
+            .map(|(&coord, _)| coord)
+            .collect();
+
+        for coord in unused {
+            self.cells.remove(&coord);
+        }
+        self.cells.shrink_to_fit();
+    }
+
+    fn population_in(&self, rect: &Rect) -> usize {
+        // for small regions it is cheaper to look up every cell of
+        // the region in the hash table than to scan the whole table
+        if rect.get_area() < self.cells.len() {
+            let mut cnt = 0;
+            for row in rect.rows() {
+                for col in rect.cols() {
+                    if let Some(&Cell::Occupied { .. }) = self.cells.get(&(col, row)) {
+                        cnt += 1;
+                    }
+                }
+            }
+            cnt
+        } else {
+            self.cells.iter()
+                .filter(|&(&(col, row), cell)| *cell != Cell::Empty && rect.contains(col, row))
+                .count()
+        }
+    }
+
 }
 
 pub fn new() -> Box<BoardInternal> {
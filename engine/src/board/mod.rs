@@ -3,19 +3,48 @@
 ///
 /// Example usage:
 /// ```
-/// let mut my_board = Board::new(Some(30), Some(30));
+/// # use engine::board::Board;
+/// # use engine::board::hashed::new as new_hashed;
+/// let mut my_board = Board::new(new_hashed(), Some(30), Some(30));
 /// my_board.born_at(20, 20);
 /// ```
 ///
 
 pub mod vect;
 pub mod hashed;
-
-
-#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub mod quadtree;
+pub mod bits;
+pub mod paged;
+pub mod sparse;
+mod components;
+
+pub use self::components::{Component, Components};
+
+use std::io;
+use std::error::Error;
+use std::cmp;
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+use std::collections::{HashMap, HashSet};
+use std::iter::Chain;
+use std::ops::Range;
+use std::option;
+use ::io::plaintext;
+use ::rule::Rule;
+use ::io::plaintext::PlaintextFile;
+use ::pattern::Pattern;
+
+#[cfg(test)]
+use self::hashed::new as new_hashed;
+#[cfg(test)]
+use self::vect::new as new_vect;
+
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
 pub struct Coord {
-    pub col: isize,
-    pub row: isize,
+    pub col: i64,
+    pub row: i64,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -25,6 +54,7 @@ pub enum Cell {
     Occupied { gen: usize }
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct CellDesc {
     pub coord: Coord,
     pub gen: usize,
@@ -32,21 +62,283 @@ pub struct CellDesc {
     pub new_line: bool,
 }
 
-pub type CellIterType = (isize, isize, Cell);
+/// Columns or rows from the first to the last one, the last one included
+pub type Inclusive = Chain<Range<i64>, option::IntoIter<i64>>;
+
+/// Same as `first..last + 1`, which overflows when `last` is `i64::MAX`
+pub fn inclusive(first: i64, last: i64) -> Inclusive {
+    (first..last).chain(if first <= last { Some(last) } else { None })
+}
+
+/// Rectangular region of a board, all the bounds are inclusive; sizes
+/// of regions wider than `usize` can hold are saturated
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Rect {
+    pub left: i64,
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+}
+
+impl Rect {
+
+    pub fn new(left: i64, top: i64, right: i64, bottom: i64) -> Self {
+        // corners may come in any order, e.g. from mouse selection
+        Rect {
+            left: left.min(right),
+            top: top.min(bottom),
+            right: left.max(right),
+            bottom: top.max(bottom),
+        }
+    }
+
+    #[inline]
+    pub fn get_width(&self) -> usize {
+        Rect::get_side(self.left, self.right)
+    }
+
+    #[inline]
+    pub fn get_height(&self) -> usize {
+        Rect::get_side(self.top, self.bottom)
+    }
+
+    #[inline]
+    pub fn get_area(&self) -> usize {
+        self.get_width().saturating_mul(self.get_height())
+    }
+
+    #[inline]
+    fn get_side(first: i64, last: i64) -> usize {
+        // the difference of bounds always fits into u64, unlike i64
+        let side = (last.wrapping_sub(first) as u64).saturating_add(1);
+        cmp::min(side, usize::max_value() as u64) as usize
+    }
+
+    pub fn cols(&self) -> Inclusive {
+        inclusive(self.left, self.right)
+    }
+
+    pub fn rows(&self) -> Inclusive {
+        inclusive(self.top, self.bottom)
+    }
+
+    #[inline]
+    pub fn contains(&self, col: i64, row: i64) -> bool {
+        col >= self.left && col <= self.right && row >= self.top && row <= self.bottom
+    }
+
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right.min(other.right);
+        let bottom = self.bottom.min(other.bottom);
+
+        if left > right || top > bottom {
+            None
+        } else {
+            Some(Rect { left: left, top: top, right: right, bottom: bottom })
+        }
+    }
+}
+
+/// How pasted cells are combined with the cells already on the board
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CombineMode {
+    // add live cells, existing ones are kept
+    Or,
+    // toggle cells
+    Xor,
+    // clear the pasted region first
+    Overwrite,
+}
+
+impl CombineMode {
+    pub fn next(&self) -> CombineMode {
+        match *self {
+            CombineMode::Or => CombineMode::Xor,
+            CombineMode::Xor => CombineMode::Overwrite,
+            CombineMode::Overwrite => CombineMode::Or,
+        }
+    }
+}
+
+/// What edits of a bounded board do with cells outside of its bounds,
+/// generations always wrap around
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EdgePolicy {
+    // cells go to the opposite edge
+    Wrap,
+    // cells outside are left out, the rest are placed
+    Clip,
+    // the whole edit fails with `BoardError::OutOfBounds`
+    Reject,
+}
+
+impl Default for EdgePolicy {
+    fn default() -> Self {
+        EdgePolicy::Wrap
+    }
+}
+
+impl FromStr for EdgePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(EdgePolicy::Wrap),
+            "clip" => Ok(EdgePolicy::Clip),
+            "reject" => Ok(EdgePolicy::Reject),
+            _ => Err(format!("unknown edge policy '{}'", s))
+        }
+    }
+}
+
+/// Reasons why cells can't be placed on a board
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BoardError {
+    // region is wider or taller than the bounded board, its cells would
+    // wrap around onto each other
+    TooLarge { width: usize, height: usize },
+    // edited cell is outside of the bounded board
+    OutOfBounds { col: i64, row: i64 },
+    // paged board couldn't write its chunks to disk, they stay in memory
+    PagingFailed,
+    // paged out chunk with the given top left cell couldn't be read back
+    PageLost { col: i64, row: i64 },
+    // cell is too far from the others for the backend to keep them all,
+    // it is left out
+    TooFar { col: i64, row: i64 },
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BoardError::TooLarge { width, height } =>
+                write!(f, "pattern of {}x{} cells doesn't fit the board", width, height),
+            BoardError::OutOfBounds { col, row } =>
+                write!(f, "cell {}:{} is outside of the board", col, row),
+            BoardError::PagingFailed =>
+                write!(f, "board chunks can't be written to disk, they are kept in memory"),
+            BoardError::PageLost { col, row } =>
+                write!(f, "board chunk at {}:{} couldn't be read back from disk, its cells are lost", col, row),
+            BoardError::TooFar { col, row } =>
+                write!(f, "cell {}:{} is too far from the other cells for the board, it is left out", col, row),
+        }
+    }
+}
+
+impl Error for BoardError {
+    fn description(&self) -> &str {
+        match *self {
+            BoardError::TooLarge { .. } => "pattern doesn't fit the board",
+            BoardError::OutOfBounds { .. } => "cell is outside of the board",
+            BoardError::PagingFailed => "board chunks can't be written to disk",
+            BoardError::PageLost { .. } => "board chunk couldn't be read back from disk",
+            BoardError::TooFar { .. } => "cell is too far from the other cells for the board",
+        }
+    }
+}
+
+pub type CellIterType = (i64, i64, Cell);
 
 pub trait BoardInternal {
-    fn get_cell(&self, col: isize, row: isize) -> Option<&Cell>;
-    fn set_cell(&mut self, col: isize, row: isize, val: Cell);
-    fn ensure_cell(&mut self, col: isize, row: isize);
-    fn rm_cell(&mut self, col: isize, row: isize);
+    fn get_cell(&self, col: i64, row: i64) -> Option<Cell>;
+    fn set_cell(&mut self, col: i64, row: i64, val: Cell);
+    fn ensure_cell(&mut self, col: i64, row: i64);
+    fn rm_cell(&mut self, col: i64, row: i64);
 
     fn get_iter<'a>(&'a self) -> Box<Iterator<Item=CellIterType> + 'a>;
+
+    // drop all the cells including allocated empty ones
+    fn clear(&mut self);
+
+    // next generation of an outer totalistic rule along with the cells
+    // born or died, for backends which compute it better than cell by
+    // cell, bounded boards wrap around the given bounds; the backend may
+    // hand its cells over to the next generation
+    fn next_generation(&mut self, _rule: &Rule, _bounds: Option<&Rect>) -> Option<(Box<BoardInternal>, Vec<Coord>)> {
+        None
+    }
+
+    // whether the cell can be kept after `ensure_cell`, backends with
+    // a limited region refuse cells too far from the others
+    fn is_allocated(&self, _col: i64, _row: i64) -> bool {
+        true
+    }
+
+    // hint that cells of the region are going to be needed soon
+    fn prefetch(&self, _rect: &Rect) {
+    }
+
+    // drop allocated empty cells which are not next to live ones,
+    // backends which can't do it cheaply leave it as it is
+    fn shrink(&mut self) {
+    }
+
+    // storage errors since the last call one at a time, for backends
+    // which keep cells outside of memory
+    fn take_error(&self) -> Option<BoardError> {
+        None
+    }
+
+    fn population_in(&self, rect: &Rect) -> usize {
+        // generic implementation, backends should override it
+        // with something smarter than a full scan
+        self.get_iter()
+            .filter(|&(col, row, cell)| cell != Cell::Empty && rect.contains(col, row))
+            .count()
+    }
+}
+
+/// Cells as the tools and the UI see them, live or dead, whatever keeps
+/// them; `Board` keeps them for the engine, code which only edits and
+/// looks up cells, e.g. `search`, takes any `LifeBoard`
+pub trait LifeBoard {
+
+    fn born_at(&mut self, col: i64, row: i64);
+
+    fn kill_at(&mut self, col: i64, row: i64);
+
+    fn is_alive(&self, col: i64, row: i64) -> bool;
+
+    /// Live cells in no particular order
+    fn get_live_cells<'b>(&'b self) -> Box<Iterator<Item=Coord> + 'b>;
+
+    /// Region the cells can be in, the whole plane for infinite boards
+    fn get_bounds(&self) -> Rect;
+
+    /// Smallest region containing all live cells
+    fn get_bounding_box(&self) -> Option<Rect> {
+        self.get_live_cells().fold(None, |res, coord| Some(match res {
+            Some(rect) => Rect {
+                left: cmp::min(rect.left, coord.col),
+                top: cmp::min(rect.top, coord.row),
+                right: cmp::max(rect.right, coord.col),
+                bottom: cmp::max(rect.bottom, coord.row),
+            },
+            None => Rect::new(coord.col, coord.row, coord.col, coord.row)
+        }))
+    }
+
 }
 
 pub struct Board<'a> {
     cells: Box<BoardInternal + 'a>,
 
     population: usize,
+    // live cells only, backends also keep empty cells around them
+    occupied: HashSet<Coord>,
+    // numbers of live neighbours, kept up to date on every birth and
+    // death; cells without live neighbours are left out
+    neighbours: HashMap<Coord, u8>,
+    // boards stepped by their backends don't need the numbers of
+    // neighbours, they are counted the first time they are asked for
+    counted: bool,
+    // cells born or died since the board was made, boards of new
+    // generations get the births and deaths which led to them
+    changed: HashSet<Coord>,
+    // the last cell the backend had no room for, see `take_error`
+    refused: Option<BoardError>,
 
     rows: Option<usize>,
     cols: Option<usize>,
@@ -54,24 +346,19 @@ pub struct Board<'a> {
 }
 
 #[inline]
-fn cycle(x: isize, min_val: isize, max_val: isize) -> isize {
-    // TODO: add description
+fn cycle(x: i64, min_val: i64, max_val: i64) -> i64 {
+    // wrap the coordinate into [min_val, max_val), so that cells
+    // far from the board land where their neighbours do
 
     let cnt = max_val - min_val;
 
     assert!(cnt > 0);
 
-    if x < min_val {
-        max_val - (min_val - x) % (cnt + 1)
-    } else if x > max_val - 1 {
-        min_val + (x - max_val) % cnt
-    } else {
-        x
-    }
+    min_val + ((x - min_val) % cnt + cnt) % cnt
 }
 
 #[inline]
-fn bound_coordinate(left: isize, right: isize, coord: isize) -> isize {
+fn bound_coordinate(left: i64, right: i64, coord: i64) -> i64 {
     if coord < left || coord >= right {
         cycle(coord, left, right)
     } else { coord }
@@ -84,6 +371,11 @@ impl<'a> Board<'a> {
 
             cells: cells,
             population: 0,
+            occupied: HashSet::new(),
+            neighbours: HashMap::new(),
+            counted: true,
+            changed: HashSet::new(),
+            refused: None,
 
             cols: width,
             rows: height,
@@ -91,10 +383,55 @@ impl<'a> Board<'a> {
         }
     }
 
+    /// Board with cells of a plaintext `.cells` pattern, the top
+    /// left corner of the pattern goes to (0, 0)
+    pub fn from_plaintext(cells: Box<BoardInternal>, data: &str) -> io::Result<Board<'a>> {
+        let pattern = plaintext::read(&mut data.as_bytes())?;
+
+        let mut board = Board::new(cells, None, None);
+        board.paste(&pattern.cells, 0, 0, CombineMode::Or);
+
+        Ok(board)
+    }
+
+    /// Live cells in plaintext `.cells` format, the pattern is
+    /// cut to its bounding box
+    pub fn to_plaintext(&self) -> String {
+        self.to_string(None)
+    }
+
+    /// Live cells of the region, or of the whole board, one line per row
+    /// with `O` for live cells and `.` for dead ones; empty rows and
+    /// columns around the live cells are left out
+    pub fn to_string(&self, rect: Option<&Rect>) -> String {
+        let mut cells = match rect.cloned().or(self.get_bounding_box()) {
+            Some(rect) => self.copy_region(&rect),
+            None => Vec::new()
+        };
+
+        // region may be larger than the cells in it
+        let left = cells.iter().map(|c| c.col).min().unwrap_or(0);
+        let top = cells.iter().map(|c| c.row).min().unwrap_or(0);
+        for cell in cells.iter_mut() {
+            cell.col -= left;
+            cell.row -= top;
+        }
+
+        let pattern = PlaintextFile {
+            cells: cells,
+            ..PlaintextFile::default()
+        };
+
+        let mut res: Vec<u8> = Vec::new();
+        plaintext::write(&pattern, &mut res).expect("writing to memory never fails");
+        String::from_utf8(res).expect("plaintext pattern is always valid UTF-8")
+    }
+
     #[inline]
-    fn constrain_board(&self, col: isize, row: isize) -> (isize, isize) {
+    pub fn constrain_board(&self, col: i64, row: i64) -> (i64, i64) {
 
-        // ensure cell coordinates lie inside limits
+        // ensure cell coordinates lie inside limits, bounded
+        // boards wrap around
 
         let mut new_col = col;
         let mut new_row = row;
@@ -110,7 +447,7 @@ impl<'a> Board<'a> {
                 left = (cols - 1) / 2;
                 right = left + 1
             }
-            new_col = bound_coordinate(-(left as isize), right as isize, col);
+            new_col = bound_coordinate(-(left as i64), right as i64, col);
         }
 
         if let Some(rows) = self.rows {
@@ -121,18 +458,68 @@ impl<'a> Board<'a> {
                 left = (rows - 1) / 2;
                 right = left + 1
             }
-            new_row = bound_coordinate(-(left as isize), right as isize, row)
+            new_row = bound_coordinate(-(left as i64), right as i64, row)
         }
 
         (new_col, new_row)
     }
 
-    fn ensure_cell(&mut self, col: isize, row: isize) {
+    fn ensure_cell(&mut self, col: i64, row: i64) {
         let (col, row) = self.constrain_board(col, row);
         self.cells.ensure_cell(col, row);
     }
 
-    pub fn born_at_gen(&mut self, col: isize, row: isize, gen: usize) {
+    fn update_neighbours(&mut self, col: i64, row: i64, born: bool) {
+        if !self.counted {
+            return;
+        }
+
+        for &(dc, dr) in [(-1, 0), (-1, -1), (0, -1), (1, -1),
+                          (1, 0), (1, 1), (0, 1), (-1, 1)].iter() {
+            let (col, row) = self.constrain_board(col + dc, row + dr);
+            let coord = Coord { col: col, row: row };
+
+            if born {
+                *self.neighbours.entry(coord).or_insert(0) += 1;
+            } else {
+                let left = match self.neighbours.get_mut(&coord) {
+                    Some(count) => {
+                        *count -= 1;
+                        *count
+                    }
+                    None => continue
+                };
+                if left == 0 {
+                    self.neighbours.remove(&coord);
+                }
+            }
+        }
+    }
+
+    // a cell born and killed again is back to where it was
+    fn toggle_changed(&mut self, coord: Coord) {
+        if !self.changed.remove(&coord) {
+            self.changed.insert(coord);
+        }
+    }
+
+    /// Counts live neighbours of the cells if they aren't counted yet,
+    /// e.g. after a generation computed by the backend, see `get_neighbourhood`
+    pub fn count_neighbours(&mut self) {
+        if self.counted {
+            return;
+        }
+
+        let occupied: Vec<Coord> = self.occupied.iter().cloned().collect();
+
+        self.neighbours.clear();
+        self.counted = true;
+        for Coord { col, row } in occupied {
+            self.update_neighbours(col, row, true);
+        }
+    }
+
+    pub fn born_at_gen(&mut self, col: i64, row: i64, gen: usize) {
         if !self.is_alive(col, row) {
 
             self.ensure_cell(col, row);
@@ -151,56 +538,337 @@ impl<'a> Board<'a> {
             self.ensure_cell(col - 1, row + 1);
 
             let (col, row) = self.constrain_board(col, row);
+            if !self.cells.is_allocated(col, row) {
+                self.refused = Some(BoardError::TooFar { col: col, row: row });
+                return;
+            }
+
             self.population += 1;
+            self.occupied.insert(Coord { col: col, row: row });
             self.cells.set_cell(col, row, Cell::Occupied { gen: gen });
+            self.update_neighbours(col, row, true);
+            self.toggle_changed(Coord { col: col, row: row });
         }
     }
 
-    pub fn born_at(&mut self, col: isize, row: isize) {
+    pub fn born_at(&mut self, col: i64, row: i64) {
         self.born_at_gen(col, row, 1);
     }
 
     #[inline]
-    pub fn kill_at(&mut self, col: isize, row: isize) {
+    pub fn kill_at(&mut self, col: i64, row: i64) {
         let (col, row) = self.constrain_board(col, row);
+        // killing a dead cell changes nothing
+        if self.forget_cell(col, row) {
+            self.cells.rm_cell(col, row);
+        }
+    }
+
+    // drops the live cell from the bookkeeping, not from the backend
+    fn forget_cell(&mut self, col: i64, row: i64) -> bool {
+        if !self.occupied.remove(&Coord { col: col, row: row }) {
+            return false;
+        }
+
         self.population -= 1;
-        self.cells.rm_cell(col, row);
+        self.update_neighbours(col, row, false);
+        self.toggle_changed(Coord { col: col, row: row });
+        true
     }
 
     #[inline]
-    pub fn is_alive(&self, col: isize, row: isize) -> bool {
+    pub fn is_alive(&self, col: i64, row: i64) -> bool {
         self.get_cell(col, row) != Cell::Empty
     }
 
+    /// Checks that a region of the given size fits the bounded dimensions
+    pub fn check_fits(&self, width: usize, height: usize) -> Result<(), BoardError> {
+        if self.cols.map_or(false, |cols| width > cols) || self.rows.map_or(false, |rows| height > rows) {
+            return Err(BoardError::TooLarge { width: width, height: height });
+        }
+        Ok(())
+    }
+
     pub fn is_infinite(&self) -> bool {
         // returns true if board is infinite at at least one direction
         // and false otherwise
         self.cols.is_none() || self.rows.is_none()
     }
 
-    pub fn get_cell(&self, col: isize, row: isize) -> Cell {
+    pub fn get_cell(&self, col: i64, row: i64) -> Cell {
         // if cell is not yet initialized it is considered as free
         let (col, row) = self.constrain_board(col, row);
 
         match self.cells.get_cell(col, row) {
-            Some(x) => {
-                match x {
-                   &Cell::Occupied {gen} => *x,
-                   _ => Cell::Empty,
+            Some(cell) => cell,
+            None => Cell::Empty
+        }
+    }
+
+    pub fn get_bounds(&self) -> Rect {
+        // region of cells which are addressable without wrapping around,
+        // infinite dimensions span the whole i64 range
+        fn dim_bounds(size: Option<usize>) -> (i64, i64) {
+            match size {
+                Some(size) => {
+                    let left = -(size as i64 / 2);
+                    (left, left + size as i64 - 1)
                 }
+                None => (i64::min_value(), i64::max_value())
             }
-            None => Cell::Empty
         }
+
+        let (left, right) = dim_bounds(self.cols);
+        let (top, bottom) = dim_bounds(self.rows);
+
+        Rect { left: left, top: top, right: right, bottom: bottom }
+    }
+
+    pub fn population_in(&self, rect: &Rect) -> usize {
+        // count live cells inside the given region
+        match rect.intersect(&self.get_bounds()) {
+            Some(rect) => self.cells.population_in(&rect),
+            None => 0
+        }
+    }
+
+    fn get_live_in(&self, rect: &Rect) -> Vec<Coord> {
+        // live cells of the region, small regions are looked up cell
+        // by cell, big ones are found among the live cells, so neither
+        // takes longer than the region or the population, and empty
+        // cells allocated by the backend aren't visited at all
+        let side = |first: i64, last: i64| last.checked_sub(first).and_then(|len| len.checked_add(1));
+        let area = side(rect.left, rect.right).and_then(|width| side(rect.top, rect.bottom).and_then(|height| width.checked_mul(height)));
+
+        if area.map_or(false, |area| area < self.occupied.len() as i64) {
+            let mut res = Vec::new();
+            for row in rect.rows() {
+                for col in rect.cols() {
+                    if self.occupied.contains(&Coord { col: col, row: row }) {
+                        res.push(Coord { col: col, row: row });
+                    }
+                }
+            }
+            res
+        } else {
+            self.occupied.iter().filter(|coord| rect.contains(coord.col, coord.row)).cloned().collect()
+        }
+    }
+
+    pub fn crop(&mut self, rect: &Rect) {
+        // kill everything outside the given region
+        let outside: Vec<Coord> = self.occupied.iter()
+            .filter(|coord| !rect.contains(coord.col, coord.row))
+            .cloned()
+            .collect();
+
+        for Coord { col, row } in outside {
+            self.kill_at(col, row);
+        }
+    }
+
+    pub fn copy_region(&self, rect: &Rect) -> Vec<Coord> {
+        // live cells of the region, coordinates are relative
+        // to the top left corner of the region
+        let mut res: Vec<Coord> = self.get_live_in(rect).into_iter()
+            .map(|coord| Coord { col: coord.col - rect.left, row: coord.row - rect.top })
+            .collect();
+
+        res.sort();
+        res
+    }
+
+    pub fn paste(&mut self, cells: &[Coord], col: i64, row: i64, mode: CombineMode) {
+        // place cells given relative to (col, row) onto the board
+
+        if cells.is_empty() {
+            return;
+        }
+
+        if mode == CombineMode::Overwrite {
+            let right = cells.iter().map(|c| c.col).max().unwrap_or(0);
+            let bottom = cells.iter().map(|c| c.row).max().unwrap_or(0);
+            self.clear_region(&Rect::new(col, row, col + right, row + bottom));
+        }
+
+        for cell in cells {
+            let (col, row) = (col + cell.col, row + cell.row);
+
+            if mode == CombineMode::Xor && self.is_alive(col, row) {
+                self.kill_at(col, row);
+            } else {
+                self.born_at(col, row);
+            }
+        }
+    }
+
+    /// Whether an edit of the cell is placed by the policy or left out
+    pub fn check_edit(&self, col: i64, row: i64, policy: EdgePolicy) -> Result<bool, BoardError> {
+        if policy == EdgePolicy::Wrap || self.get_bounds().contains(col, row) {
+            Ok(true)
+        } else if policy == EdgePolicy::Clip {
+            Ok(false)
+        } else {
+            Err(BoardError::OutOfBounds { col: col, row: row })
+        }
+    }
+
+    /// Same as `born_at`, but cells outside of a bounded board are
+    /// handled by the policy, for edits made by the user
+    pub fn try_born_at(&mut self, col: i64, row: i64, policy: EdgePolicy) -> Result<(), BoardError> {
+        if self.check_edit(col, row, policy)? {
+            self.born_at(col, row);
+        }
+        Ok(())
+    }
+
+    pub fn try_kill_at(&mut self, col: i64, row: i64, policy: EdgePolicy) -> Result<(), BoardError> {
+        if self.check_edit(col, row, policy)? && self.is_alive(col, row) {
+            self.kill_at(col, row);
+        }
+        Ok(())
+    }
+
+    /// Same as `paste` with cells outside of a bounded board handled by
+    /// the policy, a rejected paste leaves the board intact
+    pub fn try_paste(&mut self, cells: &[Coord], col: i64, row: i64, mode: CombineMode,
+                     policy: EdgePolicy) -> Result<(), BoardError> {
+        if policy == EdgePolicy::Wrap {
+            self.paste(cells, col, row, mode);
+            return Ok(());
+        }
+
+        let mut placed = Vec::with_capacity(cells.len());
+        for cell in cells {
+            if self.check_edit(col + cell.col, row + cell.row, policy)? {
+                placed.push(*cell);
+            }
+        }
+
+        // the clipped part of the region is cleared too
+        if mode == CombineMode::Overwrite && !cells.is_empty() {
+            let right = cells.iter().map(|c| c.col).max().unwrap_or(0);
+            let bottom = cells.iter().map(|c| c.row).max().unwrap_or(0);
+            if let Some(rect) = Rect::new(col, row, col + right, row + bottom).intersect(&self.get_bounds()) {
+                self.clear_region(&rect);
+            }
+        }
+
+        let mode = if mode == CombineMode::Overwrite { CombineMode::Or } else { mode };
+        self.paste(&placed, col, row, mode);
+        Ok(())
+    }
+
+    /// Composites live cells of another board onto this one shifted
+    /// by the given offset, merged cells keep their generations
+    pub fn merge(&mut self, other: &Board, offset: Coord, mode: CombineMode) {
+        if mode == CombineMode::Overwrite {
+            if let Some(rect) = other.get_bounding_box() {
+                self.clear_region(&Rect::new(rect.left + offset.col, rect.top + offset.row,
+                                             rect.right + offset.col, rect.bottom + offset.row));
+            }
+        }
+
+        let cells: Vec<(Coord, usize)> = other.occupied.iter()
+            .map(|coord| (Coord { col: coord.col + offset.col, row: coord.row + offset.row },
+                          other.get_cell_gen(coord.col, coord.row)))
+            .collect();
+
+        for (Coord { col, row }, gen) in cells {
+            if !self.is_alive(col, row) {
+                self.born_at_gen(col, row, gen);
+            } else if mode == CombineMode::Xor {
+                self.kill_at(col, row);
+            }
+        }
+    }
+
+    pub fn get_paste_conflicts(&self, cells: &[Coord], col: i64, row: i64,
+                               mode: CombineMode) -> Vec<Coord> {
+        // existing live cells which would be affected by paste
+
+        let mut res: Vec<Coord> = cells.iter()
+            .map(|cell| Coord { col: col + cell.col, row: row + cell.row })
+            .filter(|cell| self.is_alive(cell.col, cell.row))
+            .collect();
+
+        if mode == CombineMode::Overwrite && !cells.is_empty() {
+            // everything inside the pasted region is going to be replaced
+            let right = cells.iter().map(|c| c.col).max().unwrap_or(0);
+            let bottom = cells.iter().map(|c| c.row).max().unwrap_or(0);
+            let rect = Rect::new(col, row, col + right, row + bottom);
+
+            res = self.get_live_in(&rect);
+        }
+
+        res.sort();
+        res
     }
 
-    pub fn get_cell_gen(&self, col: isize, row: isize) -> usize {
+    pub fn clear_region(&mut self, rect: &Rect) {
+        // kill everything inside the given region
+        for Coord { col, row } in self.get_live_in(rect) {
+            self.kill_at(col, row);
+        }
+    }
+
+    pub fn get_bounding_box(&self) -> Option<Rect> {
+        // smallest region containing all live cells
+        let mut res: Option<Rect> = None;
+
+        for coord in self.occupied.iter() {
+            res = Some(match res {
+                Some(rect) => Rect {
+                    left: rect.left.min(coord.col),
+                    top: rect.top.min(coord.row),
+                    right: rect.right.max(coord.col),
+                    bottom: rect.bottom.max(coord.row),
+                },
+                None => Rect::new(coord.col, coord.row, coord.col, coord.row)
+            });
+        }
+
+        res
+    }
+
+    pub fn trim(&mut self) {
+        // shrink allocated area to live cells bounding box by
+        // reallocating all live cells from scratch
+        let live: Vec<(Coord, usize)> = self.into_iter()
+            .filter(|cell| cell.is_alive)
+            .map(|cell| (cell.coord, cell.gen))
+            .collect();
+
+        // the same cells are alive afterwards
+        let changed = mem::replace(&mut self.changed, HashSet::new());
+
+        self.cells.clear();
+        self.population = 0;
+        self.occupied.clear();
+        self.neighbours.clear();
+
+        for (Coord { col, row }, gen) in live {
+            self.born_at_gen(col, row, gen);
+        }
+
+        self.changed = changed;
+    }
+
+    /// Frees memory of empty cells the board has grown by and no longer
+    /// needs, e.g. the rows a glider flew through; unlike `trim` the
+    /// cells near live ones stay allocated and nothing is reallocated
+    pub fn shrink(&mut self) {
+        self.cells.shrink();
+    }
+
+    pub fn get_cell_gen(&self, col: i64, row: i64) -> usize {
         match self.get_cell(col, row) {
             Cell::Occupied { gen } => gen,
             Cell::Empty => 0
         }
     }
 
-    pub fn get_vicinity(&self, col: isize, row: isize) -> Vec<bool> {
+    pub fn get_vicinity(&self, col: i64, row: i64) -> Vec<bool> {
         // get contents of 8 neighbours of a given cell
 
         let neighbours = vec![
@@ -217,6 +885,127 @@ impl<'a> Board<'a> {
         neighbours
     }
 
+    /// Number of live neighbours of the cell, bounded boards wrap around
+    #[inline]
+    pub fn get_neighbour_count(&self, col: i64, row: i64) -> usize {
+        if !self.counted {
+            return self.get_vicinity(col, row).into_iter().filter(|&alive| alive).count();
+        }
+
+        let (col, row) = self.constrain_board(col, row);
+        self.neighbours.get(&Coord { col: col, row: row }).map_or(0, |&count| count as usize)
+    }
+
+    /// Cells having live neighbours, the only dead cells which can be
+    /// born in the next generation; boards stepped by their backends
+    /// need `count_neighbours` first
+    #[inline]
+    pub fn get_neighbourhood(&self) -> &HashMap<Coord, u8> {
+        debug_assert!(self.counted, "neighbours of the board aren't counted");
+        &self.neighbours
+    }
+
+    /// Cells born or died since the board was made, for a board of
+    /// a new generation, the births and deaths which led to it
+    #[inline]
+    pub fn changed_cells(&self) -> &HashSet<Coord> {
+        &self.changed
+    }
+
+    pub fn set_changed_cells(&mut self, changed: HashSet<Coord>) {
+        self.changed = changed;
+    }
+
+    /// Cells which are alive on only one of the boards
+    pub fn get_changes_from(&self, prev: &Board) -> HashSet<Coord> {
+        self.occupied.symmetric_difference(&prev.occupied).cloned().collect()
+    }
+
+    /// Next generation computed by the backend if it can, e.g. `bits`,
+    /// with the numbers of cells born and died; the live cells are moved
+    /// over to it, this board is left empty then
+    pub fn next_generation(&mut self, rule: &Rule) -> Option<(Board<'a>, usize, usize)> {
+        let bounds = match (self.cols, self.rows) {
+            (None, None) => None,
+            (Some(_), Some(_)) => Some(self.get_bounds()),
+            _ => return None
+        };
+
+        let (cells, changed) = match self.cells.next_generation(rule, bounds.as_ref()) {
+            Some(res) => res,
+            None => return None
+        };
+
+        // only the cells born or died are looked at, the neighbours
+        // are left uncounted until they are needed
+        let mut board = Board::new(cells, self.cols, self.rows);
+        board.occupied = mem::replace(&mut self.occupied, HashSet::new());
+        board.counted = false;
+        board.refused = self.refused.take();
+
+        self.cells.clear();
+        self.population = 0;
+        self.neighbours.clear();
+        self.changed.clear();
+
+        let mut births = 0;
+        for coord in changed.iter() {
+            if !board.occupied.remove(coord) {
+                board.occupied.insert(*coord);
+                births += 1;
+            }
+        }
+        let deaths = changed.len() - births;
+
+        board.population = board.occupied.len();
+        board.changed = changed.into_iter().collect();
+
+        Some((board, births, deaths))
+    }
+
+    /// Storage error of the backend since the last call, e.g. a cell the
+    /// bit-packed one had no room for; cells a paged one reports lost are
+    /// gone from the backend and the board forgets them
+    pub fn take_error(&mut self) -> Option<BoardError> {
+        if let Some(err) = self.refused.take() {
+            return Some(err);
+        }
+
+        let err = self.cells.take_error();
+
+        if let Some(BoardError::PageLost { col, row }) = err {
+            let chunk = Rect::new(col, row, col + paged::CHUNK - 1, row + paged::CHUNK - 1);
+            for Coord { col, row } in self.get_live_in(&chunk) {
+                self.forget_cell(col, row);
+            }
+        }
+
+        err
+    }
+
+    /// Lets a paged backend read the cells of the region in advance,
+    /// e.g. the ones the camera is heading to
+    pub fn prefetch(&self, rect: &Rect) {
+        if let Some(rect) = rect.intersect(&self.get_bounds()) {
+            self.cells.prefetch(&rect);
+        }
+    }
+
+    /// Live cells, unlike iterating over the board this doesn't
+    /// go through the empty cells allocated by the backend
+    #[inline]
+    pub fn get_occupied(&self) -> &HashSet<Coord> {
+        &self.occupied
+    }
+
+    /// Groups of live cells touching each other by a side or a corner,
+    /// with their sizes and bounding boxes, e.g. the separate objects of
+    /// a census; on bounded boards objects connect across the edges,
+    /// their bounding boxes are taken in board coordinates
+    pub fn connected_components(&self) -> Components {
+        components::find(&self.occupied, |col, row| self.constrain_board(col, row))
+    }
+
     #[inline]
     pub fn get_cols(&self) -> Option<usize> {
         self.cols
@@ -231,6 +1020,69 @@ impl<'a> Board<'a> {
     pub fn get_population(&self) -> usize {
         self.population
     }
+
+    /// Shift which moves the live cells of the board onto those of the
+    /// other one, if they are the same shape, e.g. a spaceship a period
+    /// later; equal boards are shifted by (0, 0)
+    pub fn get_translation(&self, other: &Board) -> Option<Coord> {
+        if self.population != other.population {
+            return None;
+        }
+
+        let offset = match (self.get_bounding_box(), other.get_bounding_box()) {
+            (Some(rect), Some(other_rect)) => Coord { col: other_rect.left - rect.left, row: other_rect.top - rect.top },
+            (None, None) => return Some(Coord { col: 0, row: 0 }),
+            _ => return None
+        };
+
+        let shifted = self.occupied.iter()
+            .all(|coord| other.occupied.contains(&Coord { col: coord.col + offset.col, row: coord.row + offset.row }));
+
+        if shifted { Some(offset) } else { None }
+    }
+
+    /// Checks that every cell of the pattern is alive when its top left
+    /// corner is put at the offset, cells around it may be alive too,
+    /// see `search` for exact matches
+    pub fn contains_pattern_at(&self, pattern: &Pattern, offset: Coord) -> bool {
+        pattern.get_cells().iter().all(|coord| self.is_alive(coord.col + offset.col, coord.row + offset.row))
+    }
+}
+
+/// Boards are equal when the same cells are alive, whatever the
+/// backends, bounds or ages of the cells
+impl<'a, 'b> PartialEq<Board<'b>> for Board<'a> {
+    fn eq(&self, other: &Board<'b>) -> bool {
+        self.occupied == other.occupied
+    }
+}
+
+impl<'a> LifeBoard for Board<'a> {
+
+    fn born_at(&mut self, col: i64, row: i64) {
+        Board::born_at(self, col, row);
+    }
+
+    fn kill_at(&mut self, col: i64, row: i64) {
+        Board::kill_at(self, col, row);
+    }
+
+    fn is_alive(&self, col: i64, row: i64) -> bool {
+        Board::is_alive(self, col, row)
+    }
+
+    fn get_live_cells<'b>(&'b self) -> Box<Iterator<Item=Coord> + 'b> {
+        Box::new(self.occupied.iter().cloned())
+    }
+
+    fn get_bounds(&self) -> Rect {
+        Board::get_bounds(self)
+    }
+
+    fn get_bounding_box(&self) -> Option<Rect> {
+        Board::get_bounding_box(self)
+    }
+
 }
 
 impl<'a> IntoIterator for &'a Board<'a> {
@@ -280,6 +1132,34 @@ impl<'a> Iterator for BoardIntoIterator<'a> {
 }
 
 
+#[test]
+fn test_rect_limits() {
+    use std::i64;
+
+    let whole = Rect::new(i64::MIN, i64::MIN, i64::MAX, i64::MAX);
+    assert_eq!(whole.get_width(), usize::max_value());
+    assert_eq!(whole.get_area(), usize::max_value());
+    assert_eq!(Rect::new(0, 0, 2, 0).get_area(), 3);
+
+    let edge = Rect::new(i64::MAX - 1, i64::MIN, i64::MAX, i64::MIN);
+    assert_eq!(edge.cols().collect::<Vec<_>>(), vec![i64::MAX - 1, i64::MAX]);
+    assert_eq!(edge.rows().collect::<Vec<_>>(), vec![i64::MIN]);
+    assert_eq!(inclusive(1, 0).count(), 0);
+
+    // regions reaching the edges of the board are cleared and copied,
+    // cells themselves need room for their neighbours
+    let mut board = Board::new(new_hashed(), None, None);
+    board.born_at(i64::MAX - 1, i64::MIN + 1);
+    board.born_at(i64::MAX - 2, i64::MIN + 1);
+    board.born_at(0, 0);
+
+    let corner = Rect::new(i64::MAX - 2, i64::MIN, i64::MAX, i64::MIN + 1);
+    assert_eq!(board.copy_region(&corner).len(), 2);
+
+    board.clear_region(&corner);
+    assert_eq!(board.get_population(), 1);
+}
+
 #[test]
 fn test_board_ok() {
     let mut my_board = Board::new(new_hashed(), Some(10), Some(10));
@@ -371,6 +1251,265 @@ fn test_cycle() {
     assert_eq!(cycle(-6, -5, -4), -5);
 }
 
+#[test]
+fn test_population_in() {
+    for cells in vec![new_hashed(), new_vect()] {
+        let mut my_board = Board::new(cells, None, None);
+
+        my_board.born_at(0, 0);
+        my_board.born_at(1, 1);
+        my_board.born_at(-3, 2);
+        my_board.born_at(10, -10);
+
+        assert_eq!(my_board.population_in(&Rect::new(0, 0, 1, 1)), 2);
+        assert_eq!(my_board.population_in(&Rect::new(1, 2, -3, 0)), 3);
+        assert_eq!(my_board.population_in(&Rect::new(-100, -100, 100, 100)), 4);
+        assert_eq!(my_board.population_in(&Rect::new(2, 2, 5, 5)), 0);
+    }
+}
+
+#[test]
+fn test_crop() {
+    let mut my_board = Board::new(new_hashed(), None, None);
+
+    my_board.born_at(0, 0);
+    my_board.born_at(2, 3);
+    my_board.born_at(-1, 0);
+    my_board.born_at(5, 5);
+
+    my_board.crop(&Rect::new(0, 0, 4, 4));
+
+    assert_eq!(my_board.get_population(), 2);
+    assert!(my_board.is_alive(0, 0));
+    assert!(my_board.is_alive(2, 3));
+    assert!(!my_board.is_alive(-1, 0));
+    assert!(!my_board.is_alive(5, 5));
+}
+
+#[test]
+fn test_bounding_box_and_trim() {
+    for cells in vec![new_hashed(), new_vect()] {
+        let mut my_board = Board::new(cells, None, None);
+
+        assert_eq!(my_board.get_bounding_box(), None);
+
+        my_board.born_at(-2, 1);
+        my_board.born_at(3, 4);
+        my_board.born_at(100, 100);
+        my_board.kill_at(100, 100);
+
+        assert_eq!(my_board.get_bounding_box(), Some(Rect::new(-2, 1, 3, 4)));
+
+        my_board.trim();
+
+        // live cells and their neighbours only
+        assert_eq!(my_board.into_iter().filter(|c| c.coord.col > 10).count(), 0);
+        assert_eq!(my_board.get_population(), 2);
+        assert_eq!(my_board.get_cell(-2, 1), Cell::Occupied { gen: 1 });
+        assert_eq!(my_board.get_bounding_box(), Some(Rect::new(-2, 1, 3, 4)));
+    }
+}
+
+#[test]
+fn test_shrink() {
+    for cells in vec![new_hashed(), new_vect()] {
+        let mut my_board = Board::new(cells, None, None);
+
+        for &(col, row) in [(-50, -40), (0, 0), (1, 0), (2, 0), (60, 70), (-3, 80)].iter() {
+            my_board.born_at(col, row);
+        }
+        for &(col, row) in [(-50, -40), (60, 70), (-3, 80)].iter() {
+            my_board.kill_at(col, row);
+        }
+
+        my_board.shrink();
+
+        // the blinker and its neighbours are left
+        let allocated: Vec<CellDesc> = my_board.into_iter().collect();
+        assert!(allocated.iter().all(|c| c.coord.col.abs() <= 3 && c.coord.row.abs() <= 1));
+        assert_eq!(allocated.iter().filter(|c| c.is_alive).count(), 3);
+        assert_eq!(my_board.get_population(), 3);
+        assert_eq!(my_board.get_neighbour_count(1, 1), 3);
+
+        // and the board grows again
+        my_board.born_at(-20, 30);
+        assert_eq!(my_board.get_cell(-20, 30), Cell::Occupied { gen: 1 });
+        assert_eq!(my_board.get_bounding_box(), Some(Rect::new(-20, 0, 2, 30)));
+    }
+}
+
+#[test]
+fn test_connected_components() {
+    let mut board = Board::new(new_hashed(), None, None);
+    assert!(board.connected_components().is_empty());
+
+    // a block, a glider touching nothing and a diagonal line
+    let cells = [(0, 0), (1, 0), (0, 1), (1, 1),
+                 (11, 10), (12, 11), (10, 12), (11, 12), (12, 12),
+                 (-5, 5), (-4, 6), (-3, 7)];
+    for &(col, row) in cells.iter() {
+        board.born_at(col, row);
+    }
+
+    let components = board.connected_components();
+    assert_eq!(components.len(), 3);
+    assert_eq!(components.get_components()[0], Component { id: 0, size: 3, bounds: Rect::new(-5, 5, -3, 7) });
+    assert_eq!(components.get_components()[1], Component { id: 1, size: 4, bounds: Rect::new(0, 0, 1, 1) });
+    assert_eq!(components.get_components()[2], Component { id: 2, size: 5, bounds: Rect::new(10, 10, 12, 12) });
+
+    assert_eq!(components.get_id(&Coord { col: 12, row: 11 }), Some(2));
+    assert_eq!(components.get_id(&Coord { col: 5, row: 5 }), None);
+    assert_eq!(components.get_cells(0), vec![Coord { col: -5, row: 5 }, Coord { col: -4, row: 6 }, Coord { col: -3, row: 7 }]);
+
+    // a cell joining two of them
+    board.born_at(2, 2);
+    board.born_at(3, 3);
+    let components = board.connected_components();
+    assert_eq!(components.len(), 3);
+    assert_eq!(components.get_component_of(&Coord { col: 3, row: 3 }).unwrap().size, 6);
+
+    // cells on opposite edges of a bounded board touch
+    let mut bounded = Board::new(new_hashed(), Some(10), Some(10));
+    bounded.born_at(-5, 0);
+    bounded.born_at(4, 0);
+    bounded.born_at(0, 0);
+    let components = bounded.connected_components();
+    assert_eq!(components.len(), 2);
+    assert_eq!(components.get_components()[0].bounds, Rect::new(-5, 0, 4, 0));
+}
+
+#[test]
+fn test_copy_region() {
+    let mut my_board = Board::new(new_hashed(), None, None);
+
+    my_board.born_at(1, 1);
+    my_board.born_at(2, 3);
+    my_board.born_at(10, 10);
+
+    assert_eq!(my_board.copy_region(&Rect::new(1, 1, 5, 5)),
+               vec![Coord { col: 0, row: 0 }, Coord { col: 1, row: 2 }]);
+}
+
+#[test]
+fn test_paste_modes() {
+    let pattern = vec![Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 }];
+
+    let new_board = || {
+        let mut my_board = Board::new(new_hashed(), None, None);
+        my_board.born_at(10, 10);
+        my_board.born_at(11, 11);
+        my_board
+    };
+
+    let mut or_board = new_board();
+    or_board.paste(&pattern, 10, 10, CombineMode::Or);
+    assert_eq!(or_board.get_population(), 4);
+    assert!(or_board.is_alive(11, 11));
+
+    let mut xor_board = new_board();
+    xor_board.paste(&pattern, 10, 10, CombineMode::Xor);
+    assert_eq!(xor_board.get_population(), 3);
+    assert!(!xor_board.is_alive(10, 10));
+    assert!(xor_board.is_alive(11, 10));
+
+    let mut overwrite_board = new_board();
+    overwrite_board.paste(&pattern, 10, 10, CombineMode::Overwrite);
+    assert_eq!(overwrite_board.get_population(), 3);
+    assert!(!overwrite_board.is_alive(11, 11));
+    assert!(overwrite_board.is_alive(12, 11));
+}
+
+#[test]
+fn test_merge_modes() {
+    let mut layer = Board::new(new_hashed(), None, None);
+    layer.born_at(0, 0);
+    layer.born_at_gen(1, 0, 7);
+    layer.born_at(2, 1);
+
+    let new_board = || {
+        let mut my_board = Board::new(new_vect(), None, None);
+        my_board.born_at_gen(10, 10, 3);
+        my_board.born_at(11, 11);
+        my_board.born_at(20, 20);
+        my_board
+    };
+    let offset = Coord { col: 10, row: 10 };
+
+    // overlapping cell (10, 10) stays alive with its own generation
+    let mut or_board = new_board();
+    or_board.merge(&layer, offset, CombineMode::Or);
+    assert_eq!(or_board.get_population(), 5);
+    assert_eq!(or_board.get_cell(10, 10), Cell::Occupied { gen: 3 });
+    assert_eq!(or_board.get_cell(11, 10), Cell::Occupied { gen: 7 });
+    assert!(or_board.is_alive(11, 11) && or_board.is_alive(12, 11));
+
+    // overlapping cell dies
+    let mut xor_board = new_board();
+    xor_board.merge(&layer, offset, CombineMode::Xor);
+    assert_eq!(xor_board.get_population(), 4);
+    assert!(!xor_board.is_alive(10, 10));
+    assert!(xor_board.is_alive(11, 10) && xor_board.is_alive(11, 11));
+
+    // everything inside the bounding box of the layer is replaced
+    let mut overwrite_board = new_board();
+    overwrite_board.merge(&layer, offset, CombineMode::Overwrite);
+    assert_eq!(overwrite_board.get_population(), 4);
+    assert_eq!(overwrite_board.get_cell(10, 10), Cell::Occupied { gen: 1 });
+    assert!(!overwrite_board.is_alive(11, 11));
+    assert!(overwrite_board.is_alive(20, 20));
+
+    // merging an empty board changes nothing
+    let mut same_board = new_board();
+    same_board.merge(&Board::new(new_hashed(), None, None), offset, CombineMode::Overwrite);
+    assert_eq!(same_board.get_population(), 3);
+}
+
+#[test]
+fn test_paste_conflicts() {
+    let pattern = vec![Coord { col: 0, row: 0 }, Coord { col: 2, row: 1 }];
+
+    let mut my_board = Board::new(new_hashed(), None, None);
+    my_board.born_at(10, 10);
+    my_board.born_at(11, 11);
+
+    assert_eq!(my_board.get_paste_conflicts(&pattern, 10, 10, CombineMode::Or),
+               vec![Coord { col: 10, row: 10 }]);
+    assert_eq!(my_board.get_paste_conflicts(&pattern, 10, 10, CombineMode::Overwrite),
+               vec![Coord { col: 10, row: 10 }, Coord { col: 11, row: 11 }]);
+    assert!(my_board.get_paste_conflicts(&pattern, 20, 20, CombineMode::Xor).is_empty());
+}
+
+#[test]
+fn test_population_in_bounded() {
+    let mut my_board = Board::new(new_hashed(), Some(10), Some(10));
+
+    my_board.born_at(-5, -5);
+    my_board.born_at(4, 4);
+
+    assert_eq!(my_board.get_bounds(), Rect::new(-5, -5, 4, 4));
+    assert_eq!(my_board.population_in(&Rect::new(-1000, -1000, 1000, 1000)), 2);
+    assert_eq!(my_board.population_in(&Rect::new(5, 5, 1000, 1000)), 0);
+}
+
+#[test]
+fn test_occupied() {
+    for cells in vec![new_hashed(), new_vect()] {
+        let mut my_board = Board::new(cells, Some(10), Some(10));
+
+        // wrapped coordinates are kept, empty neighbours aren't
+        my_board.born_at(1, 1);
+        my_board.born_at(5, 2);
+        assert_eq!(my_board.get_occupied().len(), 2);
+        assert!(my_board.get_occupied().contains(&Coord { col: -5, row: 2 }));
+
+        my_board.kill_at(-5, 2);
+        assert_eq!(my_board.get_occupied().iter().collect::<Vec<_>>(), vec![&Coord { col: 1, row: 1 }]);
+
+        my_board.trim();
+        assert_eq!(my_board.get_occupied().len(), 1);
+    }
+}
+
 #[test]
 fn test_restricted_board() {
     let mut my_board = Board::new(new_hashed(), Some(10), Some(10));
@@ -381,3 +1520,221 @@ fn test_restricted_board() {
     my_board.born_at(0, -7);
     assert_eq!(my_board.is_alive(0, 3), true);
 }
+
+#[test]
+fn test_large_coordinates() {
+    // far beyond 32 bit range
+    let far = 1i64 << 40;
+
+    let mut board = Board::new(new_hashed(), None, None);
+    board.paste(&[Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 }, Coord { col: 0, row: 2 },
+                  Coord { col: 1, row: 2 }, Coord { col: 2, row: 2 }], far, -far, CombineMode::Or);
+
+    assert_eq!(board.get_population(), 5);
+    assert_eq!(board.get_bounding_box(), Some(Rect::new(far, -far, far + 2, -far + 2)));
+    assert_eq!(board.population_in(&Rect::new(far, -far, far + 2, -far + 2)), 5);
+}
+
+#[test]
+fn test_plaintext() {
+    let board = Board::from_plaintext(new_hashed(), "!Name: Glider\n.O\n..O\nOOO\n").unwrap();
+
+    assert_eq!(board.get_population(), 5);
+    assert!(board.is_alive(1, 0));
+    assert!(board.is_alive(2, 2));
+
+    assert_eq!(board.to_plaintext(), ".O\n..O\nOOO\n");
+
+    let copy = Board::from_plaintext(new_vect(), &board.to_plaintext()).unwrap();
+    assert_eq!(copy.copy_region(&Rect::new(0, 0, 2, 2)), board.copy_region(&Rect::new(0, 0, 2, 2)));
+
+    assert!(Board::from_plaintext(new_hashed(), "x = 1\n").is_err());
+}
+
+#[test]
+fn test_board_to_string() {
+    let mut board = Board::from_plaintext(new_hashed(), ".O\n..O\nOOO\n").unwrap();
+    board.born_at(10, 10);
+
+    assert_eq!(board.to_string(None), ".O\n..O\nOOO\n.\n.\n.\n.\n.\n.\n.\n..........O\n");
+
+    // trimmed to the live cells inside of the region
+    assert_eq!(board.to_string(Some(&Rect::new(-5, -5, 5, 1))), "O\n.O\n");
+    assert_eq!(board.to_string(Some(&Rect::new(1, 1, 20, 20))), ".O\nOO\n.\n.\n.\n.\n.\n.\n.\n.........O\n");
+    assert_eq!(board.to_string(Some(&Rect::new(5, 5, 8, 8))), "");
+}
+
+#[test]
+fn test_edge_policy() {
+    let cells = vec![Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }];
+    let live = |board: &Board| -> Vec<Coord> {
+        let mut res: Vec<Coord> = board.into_iter().filter(|cell| cell.is_alive).map(|cell| cell.coord).collect();
+        res.sort();
+        res
+    };
+
+    // bounds of a 10x10 board are -5..4
+    let mut board = Board::new(new_hashed(), Some(10), Some(10));
+    board.try_paste(&cells, 3, 0, CombineMode::Or, EdgePolicy::Wrap).unwrap();
+    assert_eq!(live(&board), vec![Coord { col: -5, row: 0 }, Coord { col: 3, row: 0 }, Coord { col: 4, row: 0 }]);
+
+    let mut board = Board::new(new_hashed(), Some(10), Some(10));
+    board.try_paste(&cells, 3, 0, CombineMode::Or, EdgePolicy::Clip).unwrap();
+    assert_eq!(live(&board), vec![Coord { col: 3, row: 0 }, Coord { col: 4, row: 0 }]);
+    board.try_born_at(7, 7, EdgePolicy::Clip).unwrap();
+    board.try_kill_at(-6, 0, EdgePolicy::Clip).unwrap();
+    assert_eq!(board.get_population(), 2);
+
+    assert_eq!(board.try_paste(&cells, 3, 1, CombineMode::Or, EdgePolicy::Reject),
+               Err(BoardError::OutOfBounds { col: 5, row: 1 }));
+    assert_eq!(board.try_born_at(0, -6, EdgePolicy::Reject), Err(BoardError::OutOfBounds { col: 0, row: -6 }));
+    assert_eq!(board.get_population(), 2);
+
+    // overwrite clears only the part of the region inside the board
+    board.try_paste(&[Coord { col: 1, row: 0 }], 3, 0, CombineMode::Overwrite, EdgePolicy::Clip).unwrap();
+    assert_eq!(live(&board), vec![Coord { col: 4, row: 0 }]);
+
+    // infinite boards have nothing outside
+    let mut board = Board::new(new_hashed(), None, None);
+    board.try_paste(&cells, 1000, 0, CombineMode::Or, EdgePolicy::Reject).unwrap();
+    assert_eq!(board.get_population(), 3);
+
+    assert_eq!("clip".parse(), Ok(EdgePolicy::Clip));
+    assert!("bounce".parse::<EdgePolicy>().is_err());
+}
+
+#[test]
+fn test_neighbour_count() {
+    let check = |board: &Board| {
+        for col in -6..6 {
+            for row in -6..6 {
+                let count = board.get_vicinity(col, row).into_iter().filter(|&x| x).count();
+                assert_eq!(board.get_neighbour_count(col, row), count, "cell {}:{}", col, row);
+            }
+        }
+    };
+
+    // the cells wrap around the edges of the bounded board
+    for &(cols, rows) in [(None, None), (Some(10), Some(10)), (Some(3), Some(4))].iter() {
+        let mut board = Board::new(new_hashed(), cols, rows);
+
+        board.paste(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 },
+                      Coord { col: 4, row: 4 }, Coord { col: -5, row: -5 }], 0, 0, CombineMode::Or);
+        check(&board);
+
+        board.kill_at(1, 0);
+        board.kill_at(-5, -5);
+        check(&board);
+
+        board.clear_region(&Rect::new(-1, -1, 1, 1));
+        check(&board);
+
+        board.born_at(1, 1);
+        board.trim();
+        check(&board);
+    }
+}
+
+#[test]
+fn test_population_consistency() {
+    // bookkeeping of every kind agrees with the backend after any
+    // sequence of edits, in place of a random generator a fixed LCG
+    let check = |board: &Board| {
+        let alive: HashSet<Coord> = board.into_iter().filter(|c| c.is_alive).map(|c| c.coord).collect();
+        assert_eq!(board.get_population(), alive.len());
+        assert_eq!(board.get_occupied(), &alive);
+        assert_eq!(board.get_bounding_box().map_or(0, |rect| board.population_in(&rect)), alive.len());
+        for coord in alive.iter() {
+            let count = board.get_vicinity(coord.col, coord.row).into_iter().filter(|&x| x).count();
+            assert_eq!(board.get_neighbour_count(coord.col, coord.row), count);
+        }
+    };
+
+    let mut state: u64 = 7;
+    let mut next = |bound: i64| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((state >> 33) % bound as u64) as i64
+    };
+
+    let modes = [CombineMode::Or, CombineMode::Xor, CombineMode::Overwrite];
+
+    for &(cols, rows) in [(None, None), (Some(16), Some(12))].iter() {
+        for backend in 0..2 {
+            let cells = if backend == 0 { new_hashed() } else { new_vect() };
+            let mut board = Board::new(cells, cols, rows);
+
+            for _ in 0..300 {
+                let (col, row) = (next(24) - 12, next(24) - 12);
+
+                match next(8) {
+                    0 | 1 => board.born_at(col, row),
+                    // dead cells are killed too
+                    2 | 3 => board.kill_at(col, row),
+                    4 => {
+                        let pattern: Vec<Coord> = (0..next(6)).map(|_| Coord { col: next(4), row: next(4) }).collect();
+                        board.paste(&pattern, col, row, modes[next(3) as usize]);
+                    }
+                    5 => board.clear_region(&Rect::new(col, row, col + next(6), row + next(6))),
+                    6 => {
+                        let mut other = Board::new(new_hashed(), None, None);
+                        for _ in 0..next(6) {
+                            other.born_at(next(5), next(5));
+                        }
+                        board.merge(&other, Coord { col: col, row: row }, modes[next(3) as usize]);
+                    }
+                    _ => match next(3) {
+                        0 => board.crop(&Rect::new(-10, -10, 10, 10)),
+                        1 => board.trim(),
+                        _ => board.shrink()
+                    }
+                }
+
+                check(&board);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_board_equality() {
+    let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+    let new_glider = |cells: Box<BoardInternal>, col: i64, row: i64| {
+        let mut board = Board::new(cells, None, None);
+        for &(dc, dr) in glider.iter() {
+            board.born_at(col + dc, row + dr);
+        }
+        board
+    };
+
+    // backends and ages don't matter
+    let mut board = new_glider(new_hashed(), 0, 0);
+    let other = new_glider(new_vect(), 0, 0);
+    assert!(board == other);
+    board.born_at_gen(1, 0, 5);
+    assert!(board == other);
+    assert_eq!(board.get_translation(&other), Some(Coord { col: 0, row: 0 }));
+
+    let moved = new_glider(new_hashed(), -3, 7);
+    assert!(board != moved);
+    assert_eq!(board.get_translation(&moved), Some(Coord { col: -3, row: 7 }));
+    assert_eq!(moved.get_translation(&board), Some(Coord { col: 3, row: -7 }));
+
+    // the same population in another shape
+    let mut other = new_glider(new_hashed(), 0, 0);
+    other.kill_at(1, 0);
+    other.born_at(0, 0);
+    assert_eq!(board.get_translation(&other), None);
+
+    let empty = Board::new(new_hashed(), None, None);
+    assert_eq!(empty.get_translation(&Board::new(new_vect(), Some(10), Some(10))), Some(Coord { col: 0, row: 0 }));
+    assert_eq!(empty.get_translation(&board), None);
+
+    let glider: Pattern = "bo$2bo$3o!".parse().unwrap();
+    assert!(moved.contains_pattern_at(&glider, Coord { col: -3, row: 7 }));
+    assert!(!moved.contains_pattern_at(&glider, Coord { col: -3, row: 8 }));
+
+    let row: Pattern = "2o!".parse().unwrap();
+    assert!(moved.contains_pattern_at(&row, Coord { col: -3, row: 9 }));
+    assert!(!moved.contains_pattern_at(&row, Coord { col: -4, row: 9 }));
+    assert!(moved.contains_pattern_at(&Pattern::default(), Coord { col: 100, row: 100 }));
+}
@@ -0,0 +1,433 @@
+// Paged board for sparse patterns spread over a large area: cells are kept
+// in square chunks, only the recently used chunks stay in memory, the
+// others are written to files in a temporary directory and read back when
+// needed. This bounds the memory taken by the chunk storage, it is not an
+// out-of-core board: `Board` still keeps its index of live cells, neighbour
+// counts and changed cells in memory, so memory grows with the population.
+// Pages which can't be written stay in memory, pages which can't be read
+// back are lost; either way the errors are kept for `take_error`
+extern crate time;
+
+use ::board::{BoardInternal, BoardError, Cell, CellIterType, Rect};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write, BufReader, BufWriter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// Side of the square chunks cells are paged in and out by
+pub const CHUNK: i64 = 64;
+// bytes of a paged out cell: position in the chunk and generation
+const CELL_BYTES: usize = 10;
+
+// boards paged out so far, to name their directories
+static PAGED_BOARDS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+
+struct Chunk {
+    cells: Vec<Cell>,
+    // the chunk used longest ago is paged out first
+    used: u64,
+}
+
+struct Pages {
+    chunks: HashMap<(i64, i64), Chunk>,
+    paged_out: HashSet<(i64, i64)>,
+    max_chunks: usize,
+
+    clock: u64,
+    // created with the first paged out chunk
+    dir: Option<PathBuf>,
+
+    // set once a page couldn't be written, chunks stay in memory then
+    stuck: bool,
+    errors: VecDeque<BoardError>,
+}
+
+pub struct PagedBased {
+    pages: RefCell<Pages>,
+}
+
+#[inline]
+fn split(coord: i64) -> (i64, usize) {
+    // chunk and position inside it, rounding towards negative infinity
+    let chunk = if coord >= 0 { coord / CHUNK } else { (coord + 1) / CHUNK - 1 };
+    (chunk, (coord - chunk * CHUNK) as usize)
+}
+
+#[inline]
+fn locate(col: i64, row: i64) -> ((i64, i64), usize) {
+    let ((chunk_col, x), (chunk_row, y)) = (split(col), split(row));
+    ((chunk_col, chunk_row), y * CHUNK as usize + x)
+}
+
+fn write_chunk(cells: &[Cell], writer: &mut Write) -> io::Result<()> {
+    for (pos, cell) in cells.iter().enumerate() {
+        if let Cell::Occupied { gen } = *cell {
+            let mut data = [0u8; CELL_BYTES];
+            data[0] = pos as u8;
+            data[1] = (pos >> 8) as u8;
+            for i in 0..8 {
+                data[2 + i] = (gen as u64 >> (8 * i)) as u8;
+            }
+            writer.write_all(&data)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_chunk(reader: &mut Read) -> io::Result<Vec<Cell>> {
+    let mut cells = vec![Cell::Empty; (CHUNK * CHUNK) as usize];
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    for cell in data.chunks(CELL_BYTES) {
+        if cell.len() != CELL_BYTES || (cell[0] as usize | (cell[1] as usize) << 8) >= cells.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed board page"));
+        }
+
+        let pos = cell[0] as usize | (cell[1] as usize) << 8;
+
+        let gen = (0..8).fold(0u64, |gen, i| gen | (cell[2 + i] as u64) << (8 * i));
+        cells[pos] = Cell::Occupied { gen: gen as usize };
+    }
+
+    Ok(cells)
+}
+
+impl Pages {
+
+    fn get_path(&mut self, key: (i64, i64)) -> io::Result<PathBuf> {
+        let dir = match self.dir {
+            Some(ref dir) => dir.clone(),
+            None => env::temp_dir().join(format!("life-rs-pages-{}-{}", time::precise_time_ns(),
+                                                  PAGED_BOARDS.fetch_add(1, Ordering::SeqCst)))
+        };
+
+        // made again if it was cleaned up meanwhile
+        fs::create_dir_all(&dir)?;
+        self.dir = Some(dir.clone());
+
+        Ok(dir.join(format!("{}_{}", key.0, key.1)))
+    }
+
+    fn write_page(&mut self, key: (i64, i64), cells: &[Cell]) -> io::Result<()> {
+        let path = self.get_path(key)?;
+        let mut writer = BufWriter::new(File::create(&path)?);
+        write_chunk(cells, &mut writer)?;
+        writer.flush()
+    }
+
+    fn page_out(&mut self) {
+        while !self.stuck && self.chunks.len() >= self.max_chunks {
+            let key = match self.chunks.iter().min_by_key(|&(_, chunk)| chunk.used) {
+                Some((&key, _)) => key,
+                None => return
+            };
+
+            let chunk = self.chunks.remove(&key).expect("chunk was just found");
+
+            // chunks without live cells are just dropped
+            if chunk.cells.iter().any(|&cell| cell != Cell::Empty) {
+                match self.write_page(key, &chunk.cells) {
+                    Ok(_) => {
+                        self.paged_out.insert(key);
+                    }
+                    Err(_) => {
+                        // e.g. a full disk, the chunk is kept rather than lost
+                        self.chunks.insert(key, chunk);
+                        self.stuck = true;
+                        self.errors.push_back(BoardError::PagingFailed);
+                    }
+                }
+            }
+        }
+    }
+
+    fn page_in(&mut self, key: (i64, i64)) -> io::Result<Vec<Cell>> {
+        let path = self.get_path(key)?;
+        let cells = read_chunk(&mut BufReader::new(File::open(&path)?))?;
+
+        fs::remove_file(&path)?;
+        self.paged_out.remove(&key);
+        Ok(cells)
+    }
+
+    fn get_chunk(&mut self, key: (i64, i64), create: bool) -> Option<&mut Chunk> {
+        self.clock += 1;
+
+        if !self.chunks.contains_key(&key) {
+            let cells = if self.paged_out.contains(&key) {
+                match self.page_in(key) {
+                    Ok(cells) => cells,
+                    Err(_) => {
+                        // e.g. the temporary directory was cleaned up, the
+                        // chunk starts over empty
+                        self.paged_out.remove(&key);
+                        self.errors.push_back(BoardError::PageLost { col: key.0 * CHUNK, row: key.1 * CHUNK });
+                        vec![Cell::Empty; (CHUNK * CHUNK) as usize]
+                    }
+                }
+            } else if create {
+                vec![Cell::Empty; (CHUNK * CHUNK) as usize]
+            } else {
+                return None;
+            };
+
+            self.page_out();
+            self.chunks.insert(key, Chunk { cells: cells, used: 0 });
+        }
+
+        let clock = self.clock;
+        self.chunks.get_mut(&key).map(|chunk| {
+            chunk.used = clock;
+            chunk
+        })
+    }
+
+    fn get_keys(&self) -> Vec<(i64, i64)> {
+        let mut keys: Vec<(i64, i64)> = self.chunks.keys().chain(self.paged_out.iter()).cloned().collect();
+        keys.sort();
+        keys
+    }
+
+}
+
+impl PagedBased {
+
+    fn with_max_chunks(max_chunks: usize) -> PagedBased {
+        PagedBased {
+            pages: RefCell::new(Pages {
+                chunks: HashMap::new(),
+                paged_out: HashSet::new(),
+                max_chunks: max_chunks.max(1),
+
+                clock: 0,
+                dir: None,
+
+                stuck: false,
+                errors: VecDeque::new(),
+            })
+        }
+    }
+
+    // live cells of a chunk, which is paged in for that
+    fn get_live_cells(&self, key: (i64, i64)) -> Vec<CellIterType> {
+        let mut pages = self.pages.borrow_mut();
+
+        match pages.get_chunk(key, false) {
+            Some(chunk) => chunk.cells.iter().enumerate()
+                .filter(|&(_, &cell)| cell != Cell::Empty)
+                .map(|(pos, &cell)| (key.0 * CHUNK + pos as i64 % CHUNK, key.1 * CHUNK + pos as i64 / CHUNK, cell))
+                .collect(),
+            None => Vec::new()
+        }
+    }
+
+    fn remove_files(&self) {
+        let mut pages = self.pages.borrow_mut();
+        if let Some(dir) = pages.dir.take() {
+            let _ = fs::remove_dir_all(dir);
+        }
+        pages.paged_out.clear();
+    }
+
+}
+
+impl Drop for PagedBased {
+    fn drop(&mut self) {
+        self.remove_files();
+    }
+}
+
+impl BoardInternal for PagedBased {
+
+    fn get_cell(&self, col: i64, row: i64) -> Option<Cell> {
+        let (key, pos) = locate(col, row);
+        self.pages.borrow_mut().get_chunk(key, false).map(|chunk| chunk.cells[pos])
+    }
+
+    fn set_cell(&mut self, col: i64, row: i64, val: Cell) {
+        let (key, pos) = locate(col, row);
+        if let Some(chunk) = self.pages.borrow_mut().get_chunk(key, true) {
+            chunk.cells[pos] = val;
+        }
+    }
+
+    fn ensure_cell(&mut self, _col: i64, _row: i64) {
+        // chunks are created when their first cell is set
+    }
+
+    fn rm_cell(&mut self, col: i64, row: i64) {
+        let (key, pos) = locate(col, row);
+        if let Some(chunk) = self.pages.borrow_mut().get_chunk(key, false) {
+            chunk.cells[pos] = Cell::Empty;
+        }
+    }
+
+    fn get_iter<'a>(&'a self) -> Box<Iterator<Item=CellIterType> + 'a> {
+        // chunk after chunk, so each of them is paged in once
+        let keys = self.pages.borrow().get_keys();
+        Box::new(keys.into_iter().flat_map(move |key| self.get_live_cells(key).into_iter()))
+    }
+
+    fn clear(&mut self) {
+        self.remove_files();
+        self.pages.borrow_mut().chunks.clear();
+    }
+
+    fn prefetch(&self, rect: &Rect) {
+        // at most half of the chunks in memory, so the prefetched
+        // ones don't push each other out
+        let mut pages = self.pages.borrow_mut();
+        let (first, last) = (locate(rect.left, rect.top).0, locate(rect.right, rect.bottom).0);
+
+        let mut keys: Vec<(i64, i64)> = pages.paged_out.iter()
+            .filter(|&&(col, row)| col >= first.0 && col <= last.0 && row >= first.1 && row <= last.1)
+            .cloned()
+            .collect();
+        keys.sort();
+        keys.truncate(pages.max_chunks / 2);
+
+        for key in keys {
+            pages.get_chunk(key, false);
+        }
+    }
+
+    fn take_error(&self) -> Option<BoardError> {
+        self.pages.borrow_mut().errors.pop_front()
+    }
+
+}
+
+/// Board keeping at most the given number of 64x64 chunks in memory
+pub fn new(max_chunks: usize) -> Box<BoardInternal> {
+    Box::new(PagedBased::with_max_chunks(max_chunks))
+}
+
+
+#[cfg(test)]
+use ::board::{Board, Coord};
+#[cfg(test)]
+use ::engine::Engine;
+#[cfg(test)]
+use ::soup::SoupSearch;
+
+#[test]
+fn test_split() {
+    assert_eq!(split(0), (0, 0));
+    assert_eq!(split(65), (1, 1));
+    assert_eq!(split(-1), (-1, 63));
+    assert_eq!(split(-64), (-1, 0));
+    assert_eq!(split(-65), (-2, 63));
+}
+
+#[test]
+fn test_paged_board() {
+    let mut board = Board::new(new(2), None, None);
+
+    // five chunks, three of them paged out
+    let cells: Vec<Coord> = (0..5).map(|i| Coord { col: i * 100 - 200, row: i * 70 }).collect();
+    for (i, cell) in cells.iter().enumerate() {
+        board.born_at_gen(cell.col, cell.row, i + 1);
+    }
+
+    assert!(cells.iter().enumerate().all(|(i, cell)| board.get_cell(cell.col, cell.row) == Cell::Occupied { gen: i + 1 }));
+    assert_eq!(board.into_iter().filter(|cell| cell.is_alive).count(), 5);
+
+    board.kill_at(cells[0].col, cells[0].row);
+    board.prefetch(&Rect::new(-1000, -1000, 1000, 1000));
+    assert_eq!(board.into_iter().filter(|cell| cell.is_alive).count(), 4);
+
+    // pages are removed along with the board
+    let dir = {
+        let mut board = PagedBased::with_max_chunks(1);
+        for &(col, row) in [(0, 0), (1000, 1000), (0, 1000)].iter() {
+            board.set_cell(col, row, Cell::Occupied { gen: 1 });
+        }
+        assert_eq!(board.get_iter().count(), 3);
+        let dir = board.pages.borrow().dir.clone();
+        dir.expect("chunks are paged out")
+    };
+    assert!(!dir.exists());
+}
+
+#[test]
+fn test_paged_iterations() {
+    let mut engines: Vec<Engine> = (0..2).map(|_| Engine::new(Some(300), Some(300))).collect();
+    for engine in engines.iter_mut() {
+        SoupSearch::new(9, Rect::new(-150, -150, 149, 149), 0.05).seed_board(engine);
+    }
+    engines[0].set_paged(Some(4));
+
+    for engine in engines.iter_mut() {
+        engine.iterations(5);
+    }
+
+    let cells: Vec<Vec<(Coord, usize)>> = engines.iter().map(|engine| {
+        let mut cells: Vec<(Coord, usize)> = engine.get_board().into_iter()
+            .filter(|cell| cell.is_alive)
+            .map(|cell| (cell.coord, cell.gen))
+            .collect();
+        cells.sort();
+        cells
+    }).collect();
+
+    assert!(!cells[0].is_empty());
+    assert_eq!(cells[0], cells[1]);
+}
+
+#[test]
+fn test_paging_errors() {
+    // pages which can't be written stay in memory
+    let file = env::temp_dir().join("life-rs-not-a-dir");
+    File::create(&file).unwrap();
+    let mut board = PagedBased::with_max_chunks(1);
+    board.pages.borrow_mut().dir = Some(file.join("pages"));
+    for &(col, row) in [(0, 0), (1000, 1000), (0, 1000)].iter() {
+        board.set_cell(col, row, Cell::Occupied { gen: 1 });
+    }
+    assert_eq!(board.take_error(), Some(BoardError::PagingFailed));
+    assert_eq!(board.take_error(), None);
+    assert_eq!(board.get_iter().count(), 3);
+    board.pages.borrow_mut().dir = None;
+    let _ = fs::remove_file(&file);
+
+    // pages removed behind the board's back are lost, not fatal
+    let mut board = PagedBased::with_max_chunks(1);
+    board.set_cell(0, 0, Cell::Occupied { gen: 1 });
+    board.set_cell(1000, 1000, Cell::Occupied { gen: 1 });
+    let dir = board.pages.borrow().dir.clone().expect("chunk is paged out");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(board.get_cell(0, 0), Some(Cell::Empty));
+    assert_eq!(board.take_error(), Some(BoardError::PageLost { col: 0, row: 0 }));
+    assert_eq!(board.get_iter().count(), 1);
+}
+
+#[test]
+fn test_lost_page_population() {
+    let dir = env::temp_dir().join(format!("life-rs-lost-pages-{}", time::precise_time_ns()));
+    let paged = PagedBased::with_max_chunks(1);
+    paged.pages.borrow_mut().dir = Some(dir.clone());
+
+    let mut board = Board::new(Box::new(paged), None, None);
+    for &(col, row) in [(0, 0), (1, 1), (2, 0), (1000, 1000)].iter() {
+        board.born_at(col, row);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    // the cells are gone from the board along with the page
+    assert!(!board.is_alive(1, 1));
+    assert_eq!(board.take_error(), Some(BoardError::PageLost { col: 0, row: 0 }));
+    assert_eq!(board.get_population(), board.into_iter().filter(|cell| cell.is_alive).count());
+    assert_eq!(board.get_population(), 1);
+    assert_eq!(board.get_occupied().iter().collect::<Vec<_>>(), vec![&Coord { col: 1000, row: 1000 }]);
+    assert_eq!(board.get_neighbour_count(1, 0), 0);
+    assert_eq!(board.connected_components().len(), 1);
+}
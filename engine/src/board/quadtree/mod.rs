@@ -0,0 +1,195 @@
+/// Quadtree of cells with shared identical subtrees, the way Golly
+/// stores patterns. Huge regular patterns, like metapixel constructions,
+/// have just a few distinct subtrees, so they take little memory until
+/// expanded into cells.
+///
+/// Nodes are referred to by their index, children are always created
+/// before their parents. Index 0 is the empty node of any level.
+///
+
+use std::collections::HashMap;
+
+use ::board::Coord;
+
+pub type NodeId = usize;
+
+pub const EMPTY: NodeId = 0;
+
+// leaves are 8x8 squares of cells
+pub const LEAF_LEVEL: u32 = 3;
+const LEAF_SIZE: i64 = 1 << LEAF_LEVEL;
+
+// square of the level covers 2^level cells, bigger ones can't be addressed
+pub const MAX_LEVEL: u32 = 62;
+
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Node {
+    // bit (row * 8 + col) is set for live cells
+    Leaf(u64),
+    // children are nw, ne, sw, se quarters of the next lower level
+    Inner { level: u32, children: [NodeId; 4] },
+}
+
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    cache: HashMap<Node, NodeId>,
+}
+
+impl QuadTree {
+
+    pub fn new() -> Self {
+        QuadTree {
+            // placeholder for the empty node
+            nodes: vec![Node::Leaf(0)],
+            cache: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, node: Node) -> NodeId {
+        let nodes = &mut self.nodes;
+
+        *self.cache.entry(node).or_insert_with(|| {
+            nodes.push(node);
+            nodes.len() - 1
+        })
+    }
+
+    pub fn leaf(&mut self, bits: u64) -> NodeId {
+        if bits == 0 { EMPTY } else { self.add(Node::Leaf(bits)) }
+    }
+
+    pub fn node(&mut self, level: u32, children: [NodeId; 4]) -> NodeId {
+        assert!(level > LEAF_LEVEL && level <= MAX_LEVEL);
+        assert!(children.iter().all(|&id| id == EMPTY || self.get_level(id) == level - 1));
+
+        if children == [EMPTY; 4] {
+            EMPTY
+        } else {
+            self.add(Node::Inner { level: level, children: children })
+        }
+    }
+
+    pub fn get_node(&self, id: NodeId) -> Option<&Node> {
+        if id == EMPTY { None } else { self.nodes.get(id) }
+    }
+
+    /// Non-empty nodes in the order of creation, every node goes after its children
+    pub fn get_nodes(&self) -> &[Node] {
+        &self.nodes[1..]
+    }
+
+    pub fn get_level(&self, id: NodeId) -> u32 {
+        match self.nodes[id] {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::Inner { level, .. } => level,
+        }
+    }
+
+    pub fn from_cells(cells: &[Coord]) -> (QuadTree, NodeId) {
+        // cells coordinates should be non negative, (0, 0) is the top left corner
+        let size = cells.iter().map(|c| c.col.max(c.row) + 1).max().unwrap_or(0);
+
+        let mut level = LEAF_LEVEL;
+        while (1i64 << level) < size {
+            level += 1;
+        }
+
+        let mut tree = QuadTree::new();
+        let root = tree.build(cells.to_vec(), level);
+
+        (tree, root)
+    }
+
+    fn build(&mut self, cells: Vec<Coord>, level: u32) -> NodeId {
+        if cells.is_empty() {
+            return EMPTY;
+        }
+
+        if level == LEAF_LEVEL {
+            let bits = cells.iter().fold(0u64, |bits, c| bits | 1 << (c.row * LEAF_SIZE + c.col));
+            return self.leaf(bits);
+        }
+
+        // split cells into quarters, coordinates become relative to the quarter
+        let half = 1i64 << (level - 1);
+        let mut quarters = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+
+        for c in cells {
+            let idx = (if c.col < half { 0 } else { 1 }) + (if c.row < half { 0 } else { 2 });
+            quarters[idx].push(Coord { col: c.col % half, row: c.row % half });
+        }
+
+        let mut children = [EMPTY; 4];
+        for (idx, quarter) in quarters.iter_mut().enumerate() {
+            children[idx] = self.build(::std::mem::replace(quarter, Vec::new()), level - 1);
+        }
+
+        self.node(level, children)
+    }
+
+    /// Live cells of the subtree relative to its top left corner
+    pub fn get_cells(&self, root: NodeId) -> Vec<Coord> {
+        let mut res = Vec::new();
+        self.expand(root, 0, 0, &mut res);
+        res
+    }
+
+    fn expand(&self, id: NodeId, left: i64, top: i64, res: &mut Vec<Coord>) {
+        match self.get_node(id) {
+            Some(&Node::Leaf(bits)) => {
+                for idx in 0..LEAF_SIZE * LEAF_SIZE {
+                    if bits & (1 << idx) != 0 {
+                        res.push(Coord { col: left + idx % LEAF_SIZE, row: top + idx / LEAF_SIZE });
+                    }
+                }
+            }
+
+            Some(&Node::Inner { level, children }) => {
+                let half = 1i64 << (level - 1);
+
+                self.expand(children[0], left, top, res);
+                self.expand(children[1], left + half, top, res);
+                self.expand(children[2], left, top + half, res);
+                self.expand(children[3], left + half, top + half, res);
+            }
+
+            None => {}
+        }
+    }
+
+}
+
+
+#[test]
+fn test_quadtree_round_trip() {
+    let cells = vec![Coord { col: 0, row: 0 }, Coord { col: 7, row: 7 }, Coord { col: 8, row: 0 },
+                     Coord { col: 100, row: 3 }, Coord { col: 31, row: 250 }];
+
+    let (tree, root) = QuadTree::from_cells(&cells);
+    assert_eq!(tree.get_level(root), 8);
+
+    let mut expanded = tree.get_cells(root);
+    expanded.sort();
+
+    let mut expected = cells.clone();
+    expected.sort();
+
+    assert_eq!(expanded, expected);
+}
+
+#[test]
+fn test_quadtree_sharing() {
+    // the same block repeated in every leaf of a 64x64 square
+    let cells: Vec<Coord> = (0..64).filter(|i| i % 8 < 2)
+        .flat_map(|row| (0..64).filter(|i| i % 8 < 2).map(move |col| Coord { col: col, row: row }))
+        .collect();
+
+    let (tree, root) = QuadTree::from_cells(&cells);
+
+    // one leaf and one node per level
+    assert_eq!(tree.get_nodes().len(), 4);
+    assert_eq!(tree.get_cells(root).len(), cells.len());
+
+    assert_eq!(QuadTree::from_cells(&[]).1, EMPTY);
+}
@@ -0,0 +1,400 @@
+// Sparse board for patterns spread far apart: cells are kept in the leaves
+// of a quadtree, 8x8 squares, with empty quarters left out, so the space
+// between distant objects takes no memory at all, unlike rows of vector
+// based boards. Nodes count their live cells to find population of regions
+// without visiting them
+use ::board::{BoardInternal, Cell, CellIterType, Rect};
+
+// leaves are 8x8 squares of cells
+const LEAF_LEVEL: u32 = 3;
+const LEAF_SIZE: i64 = 1 << LEAF_LEVEL;
+// root of an empty board, a square of 16x16 cells around the origin
+const MIN_LEVEL: u32 = LEAF_LEVEL + 1;
+// squares of the level cover 2^level cells, bigger ones can't be addressed
+const MAX_LEVEL: u32 = 62;
+
+
+enum Kind {
+    // cells by (row * 8 + col), None for unallocated ones
+    Leaf(Vec<Option<Cell>>),
+    // nw, ne, sw, se quarters, None for empty ones
+    Inner([Option<Box<Node>>; 4]),
+}
+
+struct Node {
+    live: usize,
+    // live cells and allocated empty ones
+    allocated: usize,
+    kind: Kind,
+}
+
+impl Node {
+
+    fn new(level: u32) -> Self {
+        let kind = if level == LEAF_LEVEL {
+            Kind::Leaf(vec![None; (LEAF_SIZE * LEAF_SIZE) as usize])
+        } else {
+            Kind::Inner([None, None, None, None])
+        };
+
+        Node { live: 0, allocated: 0, kind: kind }
+    }
+
+    fn get(&self, level: u32, col: i64, row: i64) -> Option<Cell> {
+        // coordinates are relative to the top left corner of the node
+        match self.kind {
+            Kind::Leaf(ref cells) => cells[(row * LEAF_SIZE + col) as usize],
+            Kind::Inner(ref children) => {
+                let half = 1 << (level - 1);
+                let (idx, col, row) = quarter(half, col, row);
+                children[idx].as_ref().and_then(|child| child.get(level - 1, col, row))
+            }
+        }
+    }
+
+    fn set(&mut self, level: u32, col: i64, row: i64, val: Option<Cell>) {
+        match self.kind {
+            Kind::Leaf(ref mut cells) => {
+                let pos = (row * LEAF_SIZE + col) as usize;
+                let (was_live, was_allocated) = counts(cells[pos]);
+                let (is_live, is_allocated) = counts(val);
+
+                cells[pos] = val;
+                self.live = self.live + is_live - was_live;
+                self.allocated = self.allocated + is_allocated - was_allocated;
+            }
+            Kind::Inner(ref mut children) => {
+                let half = 1 << (level - 1);
+                let (idx, col, row) = quarter(half, col, row);
+
+                if children[idx].is_none() {
+                    if val.is_none() {
+                        return;
+                    }
+                    children[idx] = Some(Box::new(Node::new(level - 1)));
+                }
+
+                let emptied = {
+                    let child = children[idx].as_mut().unwrap();
+                    self.live -= child.live;
+                    self.allocated -= child.allocated;
+
+                    child.set(level - 1, col, row, val);
+
+                    self.live += child.live;
+                    self.allocated += child.allocated;
+                    child.allocated == 0
+                };
+
+                if emptied {
+                    children[idx] = None;
+                }
+            }
+        }
+    }
+
+    fn population_in(&self, level: u32, left: i64, top: i64, rect: &Rect) -> usize {
+        let size = 1 << level;
+        let node_rect = Rect::new(left, top, left + size - 1, top + size - 1);
+
+        match node_rect.intersect(rect) {
+            None => 0,
+            Some(common) if common == node_rect => self.live,
+            Some(common) => match self.kind {
+                Kind::Leaf(ref cells) => {
+                    let mut cnt = 0;
+                    for row in common.rows() {
+                        for col in common.cols() {
+                            if let Some(Cell::Occupied { .. }) = cells[((row - top) * LEAF_SIZE + col - left) as usize] {
+                                cnt += 1;
+                            }
+                        }
+                    }
+                    cnt
+                }
+                Kind::Inner(ref children) => {
+                    let half = size / 2;
+                    children.iter().enumerate()
+                        .filter_map(|(idx, child)| child.as_ref().map(|child| (idx as i64, child)))
+                        .map(|(idx, child)| child.population_in(level - 1, left + idx % 2 * half, top + idx / 2 * half, rect))
+                        .sum()
+                }
+            }
+        }
+    }
+
+}
+
+#[inline]
+fn quarter(half: i64, col: i64, row: i64) -> (usize, i64, i64) {
+    // index of the quarter holding the cell and coordinates inside of it
+    let (right, bottom) = (col >= half, row >= half);
+    let idx = (bottom as usize) * 2 + right as usize;
+    (idx, if right { col - half } else { col }, if bottom { row - half } else { row })
+}
+
+#[inline]
+fn counts(cell: Option<Cell>) -> (usize, usize) {
+    match cell {
+        Some(Cell::Occupied { .. }) => (1, 1),
+        Some(Cell::Empty) => (0, 1),
+        None => (0, 0),
+    }
+}
+
+pub struct SparseBased {
+    // the root square is centered at the origin
+    level: u32,
+    root: Option<Box<Node>>,
+}
+
+impl SparseBased {
+
+    #[inline]
+    fn get_half(&self) -> i64 {
+        1 << (self.level - 1)
+    }
+
+    fn covers(&self, col: i64, row: i64) -> bool {
+        let half = self.get_half();
+        col >= -half && col < half && row >= -half && row < half
+    }
+
+    fn grow(&mut self) {
+        assert!(self.level < MAX_LEVEL, "coordinates are out of sparse board range");
+
+        // every quarter of the root becomes the inner corner of a quarter
+        // twice as big, so the root stays centered
+        if let Some(mut root) = self.root.take() {
+            let mut children = [None, None, None, None];
+
+            if let Kind::Inner(ref mut quarters) = root.kind {
+                for (idx, quarter) in quarters.iter_mut().enumerate() {
+                    if let Some(quarter) = quarter.take() {
+                        let mut node = Node::new(self.level);
+                        node.live = quarter.live;
+                        node.allocated = quarter.allocated;
+                        if let Kind::Inner(ref mut corners) = node.kind {
+                            corners[3 - idx] = Some(quarter);
+                        }
+                        children[idx] = Some(Box::new(node));
+                    }
+                }
+            }
+
+            root.kind = Kind::Inner(children);
+            self.root = Some(root);
+        }
+
+        self.level += 1;
+    }
+
+}
+
+pub struct CellsIterator<'a> {
+    // nodes left to visit with their levels and top left corners
+    stack: Vec<(&'a Node, u32, i64, i64)>,
+    // leaf being walked through, its corner and the next position in it
+    leaf: Option<(&'a [Option<Cell>], i64, i64)>,
+    pos: usize,
+}
+
+impl<'a> Iterator for CellsIterator<'a> {
+
+    type Item = CellIterType;
+
+    fn next(&mut self) -> Option<CellIterType> {
+        loop {
+            if let Some((cells, left, top)) = self.leaf {
+                while self.pos < cells.len() {
+                    let pos = self.pos;
+                    self.pos += 1;
+
+                    if let Some(cell) = cells[pos] {
+                        let (col, row) = (pos as i64 % LEAF_SIZE, pos as i64 / LEAF_SIZE);
+                        return Some((left + col, top + row, cell));
+                    }
+                }
+                self.leaf = None;
+            }
+
+            match self.stack.pop() {
+                Some((node, level, left, top)) => match node.kind {
+                    Kind::Leaf(ref cells) => {
+                        self.leaf = Some((&cells[..], left, top));
+                        self.pos = 0;
+                    }
+                    Kind::Inner(ref children) => {
+                        let half = 1 << (level - 1);
+                        for (idx, child) in children.iter().enumerate() {
+                            if let Some(ref child) = *child {
+                                let idx = idx as i64;
+                                self.stack.push((child, level - 1, left + idx % 2 * half, top + idx / 2 * half));
+                            }
+                        }
+                    }
+                },
+                None => return None
+            }
+        }
+    }
+
+}
+
+impl<'a> IntoIterator for &'a SparseBased {
+    type Item = CellIterType;
+    type IntoIter = CellsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let half = self.get_half();
+        CellsIterator {
+            stack: self.root.iter().map(|root| (&**root, self.level, -half, -half)).collect(),
+            leaf: None,
+            pos: 0,
+        }
+    }
+}
+
+impl BoardInternal for SparseBased {
+
+    fn get_cell(&self, col: i64, row: i64) -> Option<Cell> {
+        if !self.covers(col, row) {
+            return None;
+        }
+
+        let half = self.get_half();
+        self.root.as_ref().and_then(|root| root.get(self.level, col + half, row + half))
+    }
+
+    fn set_cell(&mut self, col: i64, row: i64, val: Cell) {
+        while !self.covers(col, row) {
+            self.grow();
+        }
+
+        let (level, half) = (self.level, self.get_half());
+        if self.root.is_none() {
+            self.root = Some(Box::new(Node::new(level)));
+        }
+        if let Some(ref mut root) = self.root {
+            root.set(level, col + half, row + half, Some(val));
+        }
+    }
+
+    fn ensure_cell(&mut self, col: i64, row: i64) {
+        if self.get_cell(col, row) == None {
+            self.set_cell(col, row, Cell::Empty);
+        }
+    }
+
+    fn rm_cell(&mut self, col: i64, row: i64) {
+        if !self.covers(col, row) {
+            return;
+        }
+
+        let (level, half) = (self.level, self.get_half());
+        let emptied = match self.root {
+            Some(ref mut root) => {
+                root.set(level, col + half, row + half, None);
+                root.allocated == 0
+            }
+            None => false
+        };
+
+        if emptied {
+            self.clear();
+        }
+    }
+
+    fn get_iter<'a>(&'a self) -> Box<Iterator<Item=CellIterType> + 'a> {
+        Box::new(IntoIterator::into_iter(self))
+    }
+
+    fn clear(&mut self) {
+        self.root = None;
+        self.level = MIN_LEVEL;
+    }
+
+    fn population_in(&self, rect: &Rect) -> usize {
+        let half = self.get_half();
+        self.root.as_ref().map_or(0, |root| root.population_in(self.level, -half, -half, rect))
+    }
+
+}
+
+pub fn new() -> Box<BoardInternal> {
+    Box::new(SparseBased { level: MIN_LEVEL, root: None })
+}
+
+
+#[cfg(test)]
+use ::board::Coord;
+#[cfg(test)]
+use ::engine::Engine;
+
+#[test]
+fn test_sparse_cells() {
+    let mut board = new();
+    let far = 1 << 40;
+    let coords = [(0, 0), (-1, -1), (far, -far), (-far, 3), (7, 8)];
+
+    for (i, &(col, row)) in coords.iter().enumerate() {
+        board.set_cell(col, row, Cell::Occupied { gen: i });
+    }
+    board.ensure_cell(1, 1);
+    board.ensure_cell(0, 0);
+
+    for (i, &(col, row)) in coords.iter().enumerate() {
+        assert_eq!(board.get_cell(col, row), Some(Cell::Occupied { gen: i }));
+    }
+    assert_eq!(board.get_cell(1, 1), Some(Cell::Empty));
+    assert_eq!(board.get_cell(2, 2), None);
+    assert_eq!(board.get_cell(far, far), None);
+
+    let mut cells: Vec<CellIterType> = board.get_iter().collect();
+    cells.sort_by_key(|&(col, row, _)| (col, row));
+    assert_eq!(cells.len(), 6);
+    assert_eq!(cells[0], (-far, 3, Cell::Occupied { gen: 3 }));
+    assert_eq!(cells[5], (far, -far, Cell::Occupied { gen: 2 }));
+
+    assert_eq!(board.population_in(&Rect::new(-1, -1, 7, 8)), 3);
+    assert_eq!(board.population_in(&Rect::new(-far, -far, far, far)), 5);
+    assert_eq!(board.population_in(&Rect::new(far + 1, 0, far + 5, 5)), 0);
+
+    for &(col, row) in coords.iter() {
+        board.rm_cell(col, row);
+    }
+    assert_eq!(board.get_iter().count(), 1);
+    board.rm_cell(1, 1);
+    assert_eq!(board.get_iter().count(), 0);
+}
+
+#[test]
+fn test_sparse_gliders() {
+    // two gliders a million cells apart
+    let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+    let mut engines: Vec<Engine> = (0..2).map(|_| Engine::new(None, None)).collect();
+    engines[0].set_sparse(true);
+
+    for engine in engines.iter_mut() {
+        for &offset in [0, 1000000].iter() {
+            for &(col, row) in glider.iter() {
+                engine.get_board_mut().born_at(col + offset, row - offset);
+            }
+        }
+        engine.iterations(8);
+    }
+
+    let cells: Vec<Vec<Coord>> = engines.iter().map(|engine| {
+        let mut cells: Vec<Coord> = engine.get_board().into_iter()
+            .filter(|cell| cell.is_alive)
+            .map(|cell| cell.coord)
+            .collect();
+        cells.sort();
+        cells
+    }).collect();
+
+    assert_eq!(cells[0].len(), 10);
+    assert_eq!(cells[0], cells[1]);
+    assert!(cells[0].contains(&Coord { col: 1000003, row: -999998 }));
+    assert!(engines[0].is_sparse());
+}
@@ -1,7 +1,16 @@
 mod symvec;
 
 use self::symvec::SymVec;
-use ::board::{BoardInternal, Cell, CellIterType};
+use ::board::{BoardInternal, Cell, CellIterType, Rect};
+
+#[inline]
+fn to_index(coord: i64) -> isize {
+    // vectors can't hold more cells than memory does, so this can
+    // only fail for huge coordinates on 32 bit platforms
+    assert!(coord >= isize::min_value() as i64 && coord <= isize::max_value() as i64,
+            "coordinate {} is out of vector based board range", coord);
+    coord as isize
+}
 
 pub struct SymVecBased {
     cells: SymVec<SymVec<Cell>>
@@ -22,7 +31,7 @@ impl<'a> Iterator for CellsIterator<'a> {
         match self.iter.next() {
             Some(_) => {
                 self.col += 1;
-                Some((self.col, self.row, self.cells[self.row][self.col]))
+                Some((self.col as i64, self.row as i64, self.cells[self.row][self.col]))
             }
 
             None => {
@@ -36,7 +45,7 @@ impl<'a> Iterator for CellsIterator<'a> {
                         self.iter = Box::new(self.cells[self.row].into_iter());
                         self.iter.next();
 
-                        return Some((self.col, self.row, self.cells[self.row][self.col]));
+                        return Some((self.col as i64, self.row as i64, self.cells[self.row][self.col]));
                     }
                 }
 
@@ -67,22 +76,26 @@ impl<'a> IntoIterator for &'a SymVecBased {
 
 impl BoardInternal for SymVecBased {
 
-    fn get_cell(&self, col: isize, row: isize) -> Option<&Cell> {
+    fn get_cell(&self, col: i64, row: i64) -> Option<Cell> {
+        let (col, row) = (to_index(col), to_index(row));
+
         if !self.cells.is_available(row) || !self.cells[row].is_available(col) {
             None
         } else {
-            Some(&self.cells[row][col])
+            Some(self.cells[row][col])
         }
     }
 
-    fn set_cell(&mut self, col: isize, row: isize, val: Cell) {
-        self.cells[row][col] = val;
+    fn set_cell(&mut self, col: i64, row: i64, val: Cell) {
+        self.cells[to_index(row)][to_index(col)] = val;
     }
 
-    fn ensure_cell(&mut self, col: isize, row: isize) {
+    fn ensure_cell(&mut self, col: i64, row: i64) {
         // extend board by any number of cells if needed
         // maintain them inside board limits
 
+        let (col, row) = (to_index(col), to_index(row));
+
         if row >= 0 {
             while self.cells.need_extend_pos(row) {
                 self.cells.push_front(SymVec::new());
@@ -104,13 +117,81 @@ impl BoardInternal for SymVecBased {
         }
     }
 
-    fn rm_cell(&mut self, col: isize, row: isize) {
+    fn rm_cell(&mut self, col: i64, row: i64) {
         self.set_cell(col, row, Cell::Empty);
     }
 
     fn get_iter<'a>(&'a self) -> Box<Iterator<Item=CellIterType> + 'a> {
         Box::new(IntoIterator::into_iter(self))
     }
+
+    fn clear(&mut self) {
+        self.cells = allocate(2, 2);
+    }
+
+    fn population_in(&self, rect: &Rect) -> usize {
+        // visit only allocated rows and columns which lie inside the region
+        let top = rect.top.max(-(self.cells.len_neg() as i64));
+        let bottom = rect.bottom.min(self.cells.len_pos() as i64 - 1);
+
+        let mut cnt = 0;
+
+        for row in top..bottom + 1 {
+            let cells_row = &self.cells[row as isize];
+
+            let left = rect.left.max(-(cells_row.len_neg() as i64));
+            let right = rect.right.min(cells_row.len_pos() as i64 - 1);
+
+            for col in left..right + 1 {
+                let col = col as isize;
+                if cells_row[col] != Cell::Empty {
+                    cnt += 1;
+                }
+            }
+        }
+
+        cnt
+    }
+
+    fn shrink(&mut self) {
+        // live cells keep their 8 neighbours allocated, the empty cells
+        // farther away at the ends of rows and columns are dropped
+        let top = -(self.cells.len_neg() as isize);
+        let bottom = self.cells.len_pos() as isize - 1;
+
+        // columns of the leftmost and the rightmost live cells of every row
+        let extents: Vec<Option<(isize, isize)>> = (top..bottom + 1).map(|row| {
+            let cells_row = &self.cells[row];
+            let mut live = (-(cells_row.len_neg() as isize)..cells_row.len_pos() as isize)
+                .filter(|&col| cells_row[col] != Cell::Empty);
+
+            live.next().map(|first| (first, live.last().unwrap_or(first)))
+        }).collect();
+
+        for (idx, row) in (top..bottom + 1).enumerate() {
+            let around = &extents[idx.saturating_sub(1)..(idx + 2).min(extents.len())];
+            let needed = around.iter().filter_map(|&extent| extent)
+                .fold(None, |acc: Option<(isize, isize)>, (left, right)| match acc {
+                    Some((l, r)) => Some((l.min(left - 1), r.max(right + 1))),
+                    None => Some((left - 1, right + 1))
+                });
+
+            let (len_neg, len_pos) = needed.map_or((0, 0), |(left, right)| ((-left).max(0), (right + 1).max(0)));
+            self.cells[row].truncate(len_neg as usize, len_pos as usize);
+        }
+
+        // row 0 is kept, iteration starts from it
+        let live_rows: Vec<isize> = (top..bottom + 1).zip(extents.iter())
+            .filter(|&(_, extent)| extent.is_some())
+            .map(|(row, _)| row)
+            .collect();
+
+        let (len_neg, len_pos) = match (live_rows.first(), live_rows.last()) {
+            (Some(&first), Some(&last)) => ((1 - first).max(0), (last + 2).max(1)),
+            _ => (0, 1)
+        };
+        self.cells.truncate(len_neg as usize, len_pos as usize);
+    }
 }
 
 fn allocate(cols: usize, rows: usize) -> SymVec<SymVec<Cell>> {
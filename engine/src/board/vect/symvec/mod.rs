@@ -2,7 +2,7 @@
 /// thus indices could be positive or negative. It is represented as
 /// two independent Vecs inside so both sides extensions are
 /// very fast (approx constant time), indexing is also a constant.
-/// ```
+/// ```ignore
 /// let mut v: SymVec<i32> = SymVec::new();
 /// v.push_back(10);
 /// v.push_back(20);
@@ -147,6 +147,15 @@ impl<T> SymVec<T> {
         }
     }
 
+    /// Keeps at most the given numbers of elements on each side, the
+    /// ones farthest from zero are dropped and the memory is freed
+    pub fn truncate(&mut self, len_neg: usize, len_pos: usize) {
+        self.vec_neg.truncate(len_neg);
+        self.vec_neg.shrink_to_fit();
+        self.vec_pos.truncate(len_pos);
+        self.vec_pos.shrink_to_fit();
+    }
+
     pub fn is_available(&self, idx: isize) -> bool {
         if idx >= 0 {
             !self.need_extend_pos(idx)
@@ -243,3 +252,22 @@ fn test_iterator() {
     assert!(*v2[4] == 3);
 
 }
+
+#[test]
+fn test_truncate() {
+
+    let mut v: SymVec<i32> = SymVec::new();
+    for i in 0..5 {
+        v.push_front(i);
+        v.push_back(-i - 1);
+    }
+
+    v.truncate(2, 3);
+    assert_eq!((v.len_neg(), v.len_pos()), (2, 3));
+    assert_eq!(v.into_iter().cloned().collect::<Vec<i32>>(), vec![-2, -1, 0, 1, 2]);
+
+    // sides shorter than asked are left as they are
+    v.truncate(10, 0);
+    assert_eq!((v.len_neg(), v.len_pos()), (2, 0));
+
+}
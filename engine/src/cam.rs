@@ -1,7 +1,30 @@
+use ::structs::CellProp;
+use ::board::Rect;
+
+// how fast camera approaches its target, bigger is faster
+const ANIMATION_SPEED: f64 = 8.0;
+// camera snaps to its target when it is closer than that
+const ANIMATION_EPS: f64 = 0.5;
+
+// scale limits, zooming out further makes cells invisible
+// and zooming in further leaves just a few cells on the screen
+pub const MIN_SCALE: f64 = 0.02;
+pub const MAX_SCALE: f64 = 20.0;
+
+// patterns fitted into the screen are not zoomed in further than that
+const MAX_FIT_SCALE: f64 = 4.0;
+
+// largest integer which f64 holds exactly, cells further away
+// can't be addressed precisely anyway
+const MAX_EXACT_COORD: f64 = 9007199254740992.0;
+
 pub struct Cam {
     x: f64,
     y: f64,
 
+    // position camera is smoothly moving to, if any
+    target: Option<(f64, f64)>,
+
     scale: f64,
 
     zoom_step: f64,
@@ -15,6 +38,7 @@ impl Cam {
     pub fn new(x: f64, y: f64) -> Self {
         Cam {
             x: x, y: y, scale: 1.0,
+            target: None,
             zoom_step: 0.1,
             default_move_step: 1.0,
             move_step: 1.0,
@@ -25,6 +49,7 @@ impl Cam {
     pub fn reset(&mut self) {
         self.x = 1.0;
         self.y = 1.0;
+        self.target = None;
         self.zoom_step = 0.1;
         self.move_step = self.default_move_step;
     }
@@ -49,6 +74,42 @@ impl Cam {
         self.scale
     }
 
+    pub fn get_position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    pub fn set_position(&mut self, x: f64, y: f64) {
+        self.target = None;
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale.max(MIN_SCALE).min(MAX_SCALE);
+    }
+
+    /// Zooms and moves camera so that the given cells fill the screen
+    /// leaving the given share of it empty around them
+    pub fn fit(&mut self, cell: &CellProp, rect: &Rect, screen_width: f64, screen_height: f64, margin: f64) {
+        // cell size at scale 1
+        let (cell_width, cell_height) = (cell.get_width(self) / self.scale, cell.get_height(self) / self.scale);
+
+        let scale = (screen_width * (1.0 - 2.0 * margin) / (rect.get_width() as f64 * cell_width))
+            .min(screen_height * (1.0 - 2.0 * margin) / (rect.get_height() as f64 * cell_height));
+
+        self.set_scale(scale.min(MAX_FIT_SCALE));
+        self.center(cell, rect);
+    }
+
+    /// Moves camera to the center of the given cells keeping the scale
+    pub fn center(&mut self, cell: &CellProp, rect: &Rect) {
+        let (cell_width, cell_height) = (cell.get_width(self), cell.get_height(self));
+
+        // cell (0, 0) is in the center of the screen when camera is at the origin
+        let (mid_col, mid_row) = (0.5 * (rect.left + rect.right) as f64, 0.5 * (rect.top + rect.bottom) as f64);
+        self.set_position(-mid_col * cell_width, -mid_row * cell_height);
+    }
+
     pub fn translate_x(&self, x: f64) -> f64 {
         x + self.x
     }
@@ -65,31 +126,265 @@ impl Cam {
         (x - self.x, y - self.y)
     }
 
+    /// Moves the view by the given number of pixels, positive values
+    /// move it right and down
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.target = None;
+        self.x -= dx;
+        self.y -= dy;
+    }
+
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        // start smooth movement to the given camera position,
+        // actual movement happens in update
+        self.target = Some((x, y));
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.target.is_some()
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        if let Some((x, y)) = self.target {
+            if (x - self.x).abs() < ANIMATION_EPS && (y - self.y).abs() < ANIMATION_EPS {
+                self.x = x;
+                self.y = y;
+                self.target = None;
+            } else {
+                // exponential approach keeps animation smooth regardless of frame rate
+                let k = 1.0 - (-ANIMATION_SPEED * dt).exp();
+                self.x += (x - self.x) * k;
+                self.y += (y - self.y) * k;
+            }
+        }
+    }
+
     pub fn zoom_out(&mut self) {
-        self.scale -= self.get_zoom_step();
+        // zoom step is relative, so every step looks the same at any scale
+        self.scale = (self.scale / (1.0 + self.get_zoom_step())).max(MIN_SCALE);
     }
 
     pub fn zoom_in(&mut self) {
-        self.scale += self.get_zoom_step();
+        self.scale = (self.scale * (1.0 + self.get_zoom_step())).min(MAX_SCALE);
     }
 
     pub fn move_right(&mut self) {
+        self.target = None;
         self.x -= self.get_move_step();
         self.move_step *= self.move_acc;
     }
 
     pub fn move_left(&mut self) {
+        self.target = None;
         self.x += self.get_move_step();
         self.move_step *= self.move_acc;
     }
 
     pub fn move_up(&mut self) {
+        self.target = None;
         self.y += self.get_move_step();
         self.move_step *= self.move_acc;
     }
 
     pub fn move_down(&mut self) {
+        self.target = None;
         self.y -= self.get_move_step();
         self.move_step *= self.move_acc;
     }
 }
+
+
+/// Conversion between screen and board coordinates for the current
+/// camera position and scale. Cell (0, 0) is centered on the screen
+/// when camera is at its origin.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    cell_width: f64,
+    cell_height: f64,
+
+    // screen position of the top left corner of cell (0, 0)
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl Transform {
+
+    pub fn new(cam: &Cam, cell: &CellProp, screen_width: f64, screen_height: f64) -> Self {
+        let (cell_width, cell_height) = (cell.get_width(cam), cell.get_height(cam));
+
+        let (offset_x, offset_y) = cam.translate(0.5 * (screen_width - cell_width),
+                                                 0.5 * (screen_height - cell_height));
+
+        Transform {
+            cell_width: cell_width,
+            cell_height: cell_height,
+            offset_x: offset_x,
+            offset_y: offset_y,
+        }
+    }
+
+    #[inline]
+    pub fn get_cell_width(&self) -> f64 {
+        self.cell_width
+    }
+
+    #[inline]
+    pub fn get_cell_height(&self) -> f64 {
+        self.cell_height
+    }
+
+    pub fn to_screen(&self, col: i64, row: i64) -> (f64, f64) {
+        // top left corner of the cell
+        (self.offset_x + col as f64 * self.cell_width,
+         self.offset_y + row as f64 * self.cell_height)
+    }
+
+    pub fn to_logical(&self, x: f64, y: f64) -> (i64, i64) {
+        // cell containing the given screen point
+        (Transform::to_coord(((x - self.offset_x) / self.cell_width).floor()),
+         Transform::to_coord(((y - self.offset_y) / self.cell_height).floor()))
+    }
+
+    fn to_coord(x: f64) -> i64 {
+        // converting out of range float is undefined, so clamp it first
+        x.max(-MAX_EXACT_COORD).min(MAX_EXACT_COORD) as i64
+    }
+
+}
+
+
+#[test]
+fn test_zoom_limits() {
+    let mut cam = Cam::new(0.0, 0.0);
+
+    for _ in 0..1000 {
+        cam.zoom_out();
+    }
+    assert_eq!(cam.get_scale(), MIN_SCALE);
+
+    for _ in 0..1000 {
+        cam.zoom_in();
+    }
+    assert_eq!(cam.get_scale(), MAX_SCALE);
+
+    cam.set_scale(0.0);
+    assert_eq!(cam.get_scale(), MIN_SCALE);
+}
+
+#[test]
+fn test_zoom_step_is_proportional() {
+    let mut cam = Cam::new(0.0, 0.0);
+
+    cam.zoom_in();
+    let ratio = cam.get_scale();
+
+    cam.zoom_in();
+    cam.zoom_in();
+    assert!((cam.get_scale() - ratio.powi(3)).abs() < 1e-9);
+
+    // zooming out undoes zooming in
+    for _ in 0..3 {
+        cam.zoom_out();
+    }
+    assert!((cam.get_scale() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_move_to() {
+    let mut cam = Cam::new(0.0, 0.0);
+
+    cam.move_to(100.0, -50.0);
+    assert!(cam.is_moving());
+
+    for _ in 0..100 {
+        cam.update(1.0 / 60.0);
+    }
+
+    assert!(!cam.is_moving());
+    assert_eq!(cam.translate(0.0, 0.0), (100.0, -50.0));
+
+    // manual movement cancels animation
+    cam.move_to(0.0, 0.0);
+    cam.move_left();
+    assert!(!cam.is_moving());
+}
+
+#[test]
+fn test_transform_round_trip() {
+    let cell = CellProp::new(10.0, 10.0);
+    let mut cam = Cam::new(13.7, -4.2);
+
+    for _ in 0..5 {
+        let transform = Transform::new(&cam, &cell, 1024.0, 768.0);
+
+        // any point of a cell maps back to the same cell, corners are
+        // avoided as rounding may put them into a neighbour
+        let half_cell = 0.5 * cell.get_width(&cam);
+        for &(col, row) in &[(0, 0), (1, -1), (-37, 52), (1000, -1000)] {
+            let (x, y) = transform.to_screen(col, row);
+            assert_eq!(transform.to_logical(x + half_cell, y + half_cell), (col, row));
+            assert_eq!(transform.to_logical(x + 0.1 * half_cell, y + 1.9 * half_cell), (col, row));
+        }
+
+        // screen -> cell -> screen lands on the corner of the cell containing the point
+        let cell_width = cell.get_width(&cam);
+        for &(x, y) in &[(0.0, 0.0), (511.9, 384.1), (1023.0, 767.0), (3.3, 700.5)] {
+            let (col, row) = transform.to_logical(x, y);
+            let (left, top) = transform.to_screen(col, row);
+            assert!(left <= x && x < left + cell_width);
+            assert!(top <= y && y < top + cell_width);
+        }
+
+        cam.zoom_out();
+    }
+}
+
+#[test]
+fn test_transform_center() {
+    // cell (0, 0) is centered on the screen
+    let transform = Transform::new(&Cam::new(0.0, 0.0), &CellProp::new(10.0, 10.0), 100.0, 100.0);
+
+    assert_eq!(transform.to_screen(0, 0), (45.0, 45.0));
+    assert_eq!(transform.to_logical(50.0, 50.0), (0, 0));
+    assert_eq!(transform.to_logical(44.9, 55.0), (-1, 1));
+}
+
+#[test]
+fn test_pan() {
+    let cell = CellProp::new(10.0, 10.0);
+    let mut cam = Cam::new(0.0, 0.0);
+    cam.move_to(100.0, 100.0);
+
+    // panning stops animated movement
+    cam.pan(10.0, -5.0);
+    assert!(!cam.is_moving());
+
+    // cells move the opposite way on the screen
+    assert_eq!(Transform::new(&cam, &cell, 100.0, 100.0).to_screen(0, 0), (35.0, 50.0));
+}
+
+#[test]
+fn test_fit() {
+    let cell = CellProp::new(10.0, 10.0);
+    let mut cam = Cam::new(0.0, 0.0);
+
+    for &rect in &[Rect::new(-1000, 20, 1000, 40), Rect::new(5, 5, 7, 6), Rect::new(-3, -300, 50, -10)] {
+        cam.fit(&cell, &rect, 800.0, 600.0, 0.1);
+        let transform = Transform::new(&cam, &cell, 800.0, 600.0);
+
+        // the whole rect is on the screen, outside the margin
+        let (left, top) = transform.to_screen(rect.left, rect.top);
+        let (right, bottom) = transform.to_screen(rect.right + 1, rect.bottom + 1);
+
+        assert!(left >= 80.0 - 1e-6 && right <= 720.0 + 1e-6);
+        assert!(top >= 60.0 - 1e-6 && bottom <= 540.0 + 1e-6);
+
+        // and in the middle of it
+        assert!((left + right - 800.0).abs() < 1e-6);
+        assert!((top + bottom - 600.0).abs() < 1e-6);
+    }
+
+    // small patterns are not blown up
+    cam.fit(&cell, &Rect::new(0, 0, 1, 1), 800.0, 600.0, 0.1);
+    assert_eq!(cam.get_scale(), MAX_FIT_SCALE);
+}
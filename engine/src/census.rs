@@ -0,0 +1,101 @@
+/// Population of every generation along with the number of cells born
+/// and died on the way to it. Unlike `History` nothing is downsampled,
+/// so the whole run can be exported for analysis, e.g. as CSV:
+/// ```
+/// # use engine::census::Census;
+/// let mut census = Census::new();
+/// census.record(0, 3, 0, 0);
+/// census.record(1, 3, 2, 2);
+///
+/// let mut csv = Vec::new();
+/// census.write_csv(&mut csv).unwrap();
+/// assert_eq!(String::from_utf8(csv).unwrap(),
+///            "generation,population,births,deaths\n0,3,0,0\n1,3,2,2\n");
+/// ```
+///
+
+use std::io;
+use std::io::Write;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CensusEntry {
+    pub gen: usize,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+#[derive(Default)]
+pub struct Census {
+    entries: Vec<CensusEntry>,
+}
+
+impl Census {
+
+    pub fn new() -> Self {
+        Census::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn record(&mut self, gen: usize, population: usize, births: usize, deaths: usize) {
+        self.entries.push(CensusEntry {
+            gen: gen,
+            population: population,
+            births: births,
+            deaths: deaths,
+        });
+    }
+
+    pub fn get_entries(&self) -> &[CensusEntry] {
+        &self.entries
+    }
+
+    pub fn write_csv(&self, writer: &mut Write) -> io::Result<()> {
+        writeln!(writer, "generation,population,births,deaths")?;
+
+        for entry in &self.entries {
+            writeln!(writer, "{},{},{},{}", entry.gen, entry.population, entry.births, entry.deaths)?;
+        }
+
+        Ok(())
+    }
+
+}
+
+
+#[cfg(test)]
+use ::engine::Engine;
+#[cfg(test)]
+use ::board::Coord;
+
+#[test]
+fn test_census_blinker() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }]);
+    engine.set_census(Some(Census::new()));
+
+    engine.one_iteration();
+    engine.one_iteration();
+
+    // blinker turns over every generation, two cells die and two are born
+    let census = engine.get_census().unwrap();
+    assert_eq!(census.get_entries(), &[
+        CensusEntry { gen: 0, population: 3, births: 0, deaths: 0 },
+        CensusEntry { gen: 1, population: 3, births: 2, deaths: 2 },
+        CensusEntry { gen: 2, population: 3, births: 2, deaths: 2 },
+    ]);
+
+    // new pattern starts recording over from its first generation
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }]);
+    assert!(engine.get_census().unwrap().get_entries().is_empty());
+
+    engine.one_iteration();
+    assert_eq!(engine.get_census().unwrap().get_entries(), &[
+        CensusEntry { gen: 0, population: 2, births: 0, deaths: 0 },
+        CensusEntry { gen: 1, population: 0, births: 0, deaths: 2 },
+    ]);
+}
@@ -0,0 +1,289 @@
+/// Detection of patterns settling into a cycle. Every generation the
+/// live cells, moved so that their bounding box starts at (0, 0), are
+/// hashed, and a hash seen a few generations before means the pattern
+/// repeats; if it has moved meanwhile it is a spaceship:
+/// ```
+/// # use engine::cycles::CycleDetector;
+/// # use engine::engine::Engine;
+/// # use engine::board::Coord;
+/// let mut engine = Engine::new(None, None);
+/// engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }]);
+/// engine.set_cycles(Some(CycleDetector::new()));
+/// engine.iterations(2);
+///
+/// let cycle = engine.take_cycle().unwrap();
+/// assert_eq!(cycle.to_string(), "period 2 oscillator detected at generation 2");
+/// ```
+///
+/// Hashes of the cells are summed up, so the order the board keeps them
+/// in doesn't matter and no cells are sorted or copied. Only the hashes
+/// are kept, cycles longer than the capacity aren't found.
+///
+/// Generations may be recorded several apart, e.g. the last ones of the
+/// passes of the kernel. A hash seen before such a gap only tells that
+/// the period divides the distance, so nothing is reported then and the
+/// detector asks for the following generations one by one instead, see
+/// `is_suspecting`, which give the exact period.
+///
+
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use ::board::{Board, Coord};
+
+
+// generations remembered, the longest period found
+const DEFAULT_CAPACITY: usize = 512;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Cycle {
+    pub period: usize,
+    // generation the pattern repeated at for the first time
+    pub generation: usize,
+    // how far the pattern moves in a period
+    pub offset: Coord,
+}
+
+impl Cycle {
+
+    #[inline]
+    pub fn is_moving(&self) -> bool {
+        self.offset != Coord { col: 0, row: 0 }
+    }
+
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_moving() {
+            write!(f, "period {} spaceship detected at generation {}", self.period, self.generation)
+        } else if self.period == 1 {
+            write!(f, "still life detected at generation {}", self.generation)
+        } else {
+            write!(f, "period {} oscillator detected at generation {}", self.period, self.generation)
+        }
+    }
+}
+
+// population along with the sum of hashes of the normalized cells
+type Key = (usize, u64);
+
+pub struct CycleDetector {
+    // latest generation every hash was seen at and the top left
+    // corner of the bounding box then
+    seen: HashMap<Key, (usize, Coord)>,
+    // hashes by generation, the oldest ones are forgotten first
+    order: VecDeque<(usize, Key)>,
+    capacity: usize,
+
+    // whether the previous generation repeated, a cycle is reported
+    // when the pattern gets into it, not on every generation after
+    repeating: bool,
+    found: Option<Cycle>,
+
+    // the last generation recorded and the first one of those recorded
+    // one by one since, only hashes seen since then give exact periods
+    last_gen: Option<usize>,
+    exact_since: usize,
+    // generation a hash was seen again across a gap at
+    suspected_at: Option<usize>,
+}
+
+fn get_key(board: &Board) -> Option<(Key, Coord)> {
+    let rect = match board.get_bounding_box() {
+        Some(rect) => rect,
+        // dying out is not a cycle
+        None => return None
+    };
+
+    let sum = board.get_occupied().iter().fold(0u64, |sum, coord| {
+        let mut hasher = DefaultHasher::new();
+        (coord.col - rect.left, coord.row - rect.top).hash(&mut hasher);
+        sum.wrapping_add(hasher.finish())
+    });
+
+    Some(((board.get_population(), sum), Coord { col: rect.left, row: rect.top }))
+}
+
+impl CycleDetector {
+
+    pub fn new() -> Self {
+        CycleDetector::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0);
+
+        CycleDetector {
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity,
+
+            repeating: false,
+            found: None,
+
+            last_gen: None,
+            exact_since: 0,
+            suspected_at: None,
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+        self.repeating = false;
+        self.found = None;
+        self.last_gen = None;
+        self.exact_since = 0;
+        self.suspected_at = None;
+    }
+
+    /// Whether a hash was seen again across generations recorded several
+    /// apart, the next ones have to be recorded one by one to tell the
+    /// period; given up once the capacity of them is recorded
+    #[inline]
+    pub fn is_suspecting(&self) -> bool {
+        self.suspected_at.is_some()
+    }
+
+    /// Hashes the board of the given generation, generations recorded
+    /// several apart are compared only with those of the same pass
+    pub fn record(&mut self, generation: usize, board: &Board) {
+        if self.last_gen.map_or(true, |last_gen| generation != last_gen + 1) {
+            self.exact_since = generation;
+        }
+        self.last_gen = Some(generation);
+
+        if self.suspected_at.map_or(false, |suspected_at| generation.saturating_sub(suspected_at) > self.capacity) {
+            self.suspected_at = None;
+        }
+
+        let (key, corner) = match get_key(board) {
+            Some(res) => res,
+            None => {
+                self.repeating = false;
+                return;
+            }
+        };
+
+        let cycle = match self.seen.get(&key) {
+            Some(&(prev_gen, prev_corner)) if prev_gen < generation => Some((prev_gen >= self.exact_since, Cycle {
+                period: generation - prev_gen,
+                generation: generation,
+                offset: Coord { col: corner.col - prev_corner.col, row: corner.row - prev_corner.row },
+            })),
+            _ => None
+        };
+
+        match cycle {
+            // a multiple of the period, the pattern may have been in the
+            // cycle long before, it's found one generation after another
+            Some((false, _)) if !self.repeating => {
+                if self.suspected_at.is_none() {
+                    self.suspected_at = Some(generation);
+                }
+            }
+            Some((exact, cycle)) => {
+                if exact && !self.repeating {
+                    self.found = Some(cycle);
+                }
+                self.repeating = true;
+                self.suspected_at = None;
+            }
+            None => self.repeating = false
+        }
+
+        self.seen.insert(key, (generation, corner));
+        self.order.push_back((generation, key));
+
+        while self.order.len() > self.capacity {
+            if let Some((old_gen, old_key)) = self.order.pop_front() {
+                // seen again since then, the later generation stays
+                if self.seen.get(&old_key).map_or(false, |&(gen, _)| gen == old_gen) {
+                    self.seen.remove(&old_key);
+                }
+            }
+        }
+    }
+
+    /// Cycle the pattern got into since the last call, if any
+    pub fn take_found(&mut self) -> Option<Cycle> {
+        self.found.take()
+    }
+
+}
+
+impl Default for CycleDetector {
+    fn default() -> Self {
+        CycleDetector::new()
+    }
+}
+
+
+#[cfg(test)]
+use ::engine::Engine;
+#[cfg(test)]
+use ::io::rle::decode_cells;
+
+#[test]
+fn test_detect_cycles() {
+    let run = |pattern: &str, generations: u64| {
+        let mut engine = Engine::new(None, None);
+        engine.set_pattern(&decode_cells(pattern).unwrap());
+        engine.set_cycles(Some(CycleDetector::new()));
+        engine.iterations(generations);
+        engine.take_cycle()
+    };
+
+    let block = run("2o$2o!", 5).unwrap();
+    assert_eq!((block.period, block.generation, block.is_moving()), (1, 1, false));
+    assert_eq!(block.to_string(), "still life detected at generation 1");
+
+    let glider = run("bo$2bo$3o!", 10).unwrap();
+    assert_eq!((glider.period, glider.generation, glider.offset), (4, 4, Coord { col: 1, row: 1 }));
+    assert_eq!(glider.to_string(), "period 4 spaceship detected at generation 4");
+
+    // pentadecathlon, the r-pentomino is still growing
+    assert_eq!(run("2bo4bo$2ob4ob2o$2bo4bo!", 20).unwrap().period, 15);
+    assert_eq!(run("b2o$2o$bo!", 200), None);
+
+    // dying out isn't reported
+    assert_eq!(run("o!", 5), None);
+}
+
+#[test]
+fn test_cycle_reported_once() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("3o!").unwrap());
+    engine.set_cycles(Some(CycleDetector::with_capacity(4)));
+
+    engine.iterations(3);
+    assert_eq!(engine.take_cycle().map(|cycle| cycle.period), Some(2));
+    engine.iterations(10);
+    assert_eq!(engine.take_cycle(), None);
+
+    // a new pattern gets into a cycle of its own
+    engine.get_board_mut().born_at(10, 10);
+    engine.get_board_mut().born_at(11, 10);
+    engine.get_board_mut().born_at(10, 11);
+    engine.get_board_mut().born_at(11, 11);
+    engine.iterations(3);
+    assert_eq!(engine.take_cycle().map(|cycle| (cycle.period, cycle.generation)), Some((2, 16)));
+
+    // periods longer than the capacity aren't found
+    let mut detector = CycleDetector::with_capacity(1);
+    engine.set_pattern(&decode_cells("3o!").unwrap());
+    for generation in 0..6 {
+        detector.record(generation, engine.get_board());
+        engine.one_iteration();
+    }
+    assert_eq!(detector.take_found(), None);
+}
@@ -1,86 +1,418 @@
 extern crate rand;
 extern crate time;
 
-use ::board::{Board, CellDesc};
-use ::board::hashed::new as new_hashed;
-use ::board::vect::new as new_vect;
+use ::board::{Board, BoardError, Cell, CellDesc, Coord, Rect, CombineMode, EdgePolicy};
+use ::backend::{EngineBackend, Auto, BitPacked, Paged, Sparse};
+use ::history::{History, EventKind};
+use ::rule::Rule;
+use ::io::{read_pattern, PatternMeta};
+use ::io::fetch::fetch_pattern;
+use ::io::rle;
+use ::io::rle::RleFile;
+use ::io::ruletable::RuleTable;
+use ::io::compression;
+use ::io::container::Container;
+use ::autosave::Autosave;
+use ::error::{EngineError, EngineResult};
+use ::timeline::Timeline;
+use ::census::Census;
+use ::cycles::{Cycle, CycleDetector};
+use ::snapshot::Snapshot;
+use ::presets::Preset;
+use ::kernel::{Kernel, Stats};
+use ::zones::ZoneMap;
+use self::rand::Rng;
 use self::rand::distributions::{IndependentSample, Range};
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::cmp;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::Path;
 
 const SWITCH_BOARD_INERTIA: usize = 128;
 const ITERATIONS_TO_CLEANUP: usize = 1000;
 
+// simulation updates per second to choose from, the fastest of them
+// is followed by running as fast as possible
+const UPS_STEPS: [u32; 9] = [1, 2, 5, 10, 20, 30, 60, 120, 240];
+const DEFAULT_UPS: u32 = 120;
 
-#[derive(PartialEq, Copy, Clone)]
-enum BoardType {
-    Hashed,
-    SymVec
+// generations computed by the kernel in one pass, the grid has a margin
+// of this many cells around the pattern for it to grow into
+const KERNEL_STEPS: u64 = 16;
+// fraction of the grid which has to be alive for the kernel to pay off
+const KERNEL_DENSITY: f64 = 0.05;
+
+
+/// Why the engine stopped the simulation on its own, along with the
+/// generation it happened at, see `Engine::set_auto_stop`
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Stop {
+    Extinct(usize),
+    // the generation is the same as the one before it
+    Stable(usize),
 }
 
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Stop::Extinct(generation) => write!(f, "population died out at generation {}", generation),
+            Stop::Stable(generation) => write!(f, "pattern stopped changing at generation {}", generation),
+        }
+    }
+}
+
+
 pub struct Engine<'a> {
     cols: Option<usize>,
     rows: Option<usize>,
 
-    board_type: BoardType,
+    rule: Rule,
+    // rule from a Golly rule file, used instead of the B/S one if set
+    rule_table: Option<RuleTable>,
+    // built for the current rule when first needed
+    kernel: Option<Kernel>,
+    // regions following rules of their own
+    zones: ZoneMap,
+    meta: PatternMeta,
+
+    // makes the boards and may compute their generations
+    backend: Box<EngineBackend>,
     iters_from_prev_switch: usize,
     pub board: Board<'a>,
     pub iteration: usize,
     pub last_iter_time: f64,
-}
 
+    history: History,
+
+    autosave: Option<Autosave>,
+    // the last autosave which failed, see `take_autosave_error`
+    autosave_error: Option<io::Error>,
+
+    timeline: Option<Timeline>,
+    census: Option<Census>,
+    cycles: Option<CycleDetector>,
+
+    auto_stop: bool,
+    stop: Option<Stop>,
+    // the last generation changed nothing, it's reported only once
+    settled: bool,
+
+    // storage error of a board already replaced, see `take_board_error`
+    board_error: Option<BoardError>,
+
+    // bumped on every change of the board or the rule, see Snapshot
+    version: usize,
 
-struct MinMax {
-    min: Option<isize>,
-    max: Option<isize>
+    // for edits made by the user, see `Board::try_born_at`
+    edge_policy: EdgePolicy,
+
+    // simulation updates per second, as many as possible if not set
+    ups: Option<u32>,
 }
 
+
 impl<'a> Engine<'a> {
 
     pub fn new(cols: Option<usize>, rows: Option<usize>) -> Self {
-        let board_type = BoardType::Hashed;
+        let backend: Box<EngineBackend> = Box::new(Auto::new());
+        let board = backend.new_board(cols, rows);
         Engine {
             cols: cols,
             rows: rows,
 
-            board_type: board_type,
+            rule: Rule::life(),
+            rule_table: None,
+            kernel: None,
+            zones: ZoneMap::new(),
+            meta: PatternMeta::default(),
+
+            backend: backend,
             iters_from_prev_switch: SWITCH_BOARD_INERTIA,
-            board: Self::new_board(board_type, cols, rows),
+            board: board,
             iteration: 0,
-            last_iter_time: 0f64
+            last_iter_time: 0f64,
+
+            history: History::default(),
+
+            autosave: None,
+            autosave_error: None,
+
+            timeline: None,
+            census: None,
+            cycles: None,
+
+            auto_stop: false,
+            stop: None,
+            settled: false,
+
+            board_error: None,
+
+            version: 0,
+
+            edge_policy: EdgePolicy::default(),
+
+            ups: Some(DEFAULT_UPS),
         }
     }
 
     pub fn reset(&mut self) {
-        self.board = Self::new_board(self.board_type, self.cols, self.rows);
+        self.touch();
+        self.board = self.backend.new_board(self.cols, self.rows);
+        if let Some(ref mut autosave) = self.autosave {
+            autosave.forget_changes();
+        }
         self.iteration = 0;
         self.last_iter_time = 0f64;
-    }
+        self.history.clear();
+        self.meta = PatternMeta::default();
 
-    fn new_board(board_type: BoardType, cols: Option<usize>, rows: Option<usize>) -> Board<'a> {
-        if board_type == BoardType::Hashed {
-            Board::new(new_hashed(), cols, rows)
-        }  else {
-            Board::new(new_vect(), cols, rows)
+        if let Some(ref mut timeline) = self.timeline {
+            timeline.clear();
         }
+        if let Some(ref mut census) = self.census {
+            census.clear();
+        }
+        if let Some(ref mut cycles) = self.cycles {
+            cycles.clear();
+        }
+        self.stop = None;
+        self.settled = false;
     }
 
-    fn clone_board(&self, board_type: BoardType) -> Board<'a> {
+    fn clone_board(&self, backend: &EngineBackend) -> Board<'a> {
 
-        let mut new_board = Self::new_board(board_type,
-                                            self.board.get_cols(), self.board.get_rows());
+        let mut new_board = backend.new_board(self.board.get_cols(), self.board.get_rows());
 
         for CellDesc { coord, gen, is_alive, .. } in self.board.into_iter() {
             if is_alive {
-                new_board.born_at_gen(coord.col, coord.row, gen);
+                backend.set_cell(&mut new_board, coord.col, coord.row, Cell::Occupied { gen: gen });
             }
         }
 
+        // a copy changes nothing
+        new_board.set_changed_cells(self.board.changed_cells().clone());
+
         new_board
 
     }
 
-    pub fn from_file() {}
+    pub fn set_pattern(&mut self, cells: &[Coord]) {
+        // start over with the given cells centered on the board
+        let left = cells.iter().map(|c| c.col).min().unwrap_or(0);
+        let top = cells.iter().map(|c| c.row).min().unwrap_or(0);
+        let width = cells.iter().map(|c| c.col).max().unwrap_or(0) - left + 1;
+        let height = cells.iter().map(|c| c.row).max().unwrap_or(0) - top + 1;
+
+        self.reset();
+
+        let mut board = self.backend.new_board(self.cols, self.rows);
+        board.paste(cells, -left - width / 2, -top - height / 2, CombineMode::Or);
+        self.set_board(board);
+    }
+
+    pub fn load_pattern(&mut self, path: &Path) -> EngineResult<()> {
+        let (cells, rule, meta) = read_pattern(path)?;
+        self.set_loaded_pattern(&cells, rule, meta)
+    }
+
+    /// Loads pattern given by a link or an object code, see `io::fetch`
+    pub fn fetch_pattern(&mut self, source: &str) -> EngineResult<()> {
+        let (cells, rule, meta) = fetch_pattern(source)?;
+        self.set_loaded_pattern(&cells, rule, meta)
+    }
+
+    /// Loads the pattern of a preset with its rule, the camera and
+    /// the speed are up to the caller
+    pub fn load_preset(&mut self, preset: &Preset) -> EngineResult<()> {
+        let meta = PatternMeta { name: Some(preset.name.to_string()), author: None, comments: Vec::new() };
+        self.set_loaded_pattern(&preset.get_cells()?, Some(preset.rule.to_string()), meta)
+    }
+
+    /// Loads cells read elsewhere, e.g. downloaded on another thread
+    /// with `io::fetch::fetch_pattern`, as a pattern file would be
+    pub fn set_loaded_pattern(&mut self, cells: &[Coord], rule: Option<String>, meta: PatternMeta) -> EngineResult<()> {
+        // patterns without a rule are meant for Life
+        let rule = match rule {
+            Some(rule) => rule.parse::<Rule>().map_err(EngineError::Rule)?,
+            None => Rule::life()
+        };
+
+        // the board is left as it was if the pattern doesn't fit
+        if !cells.is_empty() {
+            let width = cells.iter().map(|c| c.col).max().unwrap_or(0) - cells.iter().map(|c| c.col).min().unwrap_or(0) + 1;
+            let height = cells.iter().map(|c| c.row).max().unwrap_or(0) - cells.iter().map(|c| c.row).min().unwrap_or(0) + 1;
+            self.board.check_fits(width as usize, height as usize)?;
+        }
+
+        self.set_pattern(cells);
+        self.replace_rule(rule);
+        self.meta = meta;
+        Ok(())
+    }
+
+    pub fn to_container(&self, with_history: bool) -> Container {
+        let mut container = self.to_container_meta(with_history);
+
+        container.cells = self.board.into_iter()
+            .filter(|cell| cell.is_alive)
+            .map(|cell| cell.coord)
+            .collect();
+
+        container
+    }
+
+    /// Everything `to_container` has but the cells, for saving them
+    /// straight from the board with `Container::save_board`
+    pub fn to_container_meta(&self, with_history: bool) -> Container {
+        let mut container = Container::new();
+
+        container.set_meta("generation", self.iteration);
+        container.set_meta("rule", self.rule);
+        if let Some(ref table) = self.rule_table {
+            container.sections.insert("rule_table".to_string(), table.get_source().to_string());
+        }
+        if !self.zones.is_empty() {
+            container.sections.insert("zones".to_string(), self.zones.to_lines());
+        }
+
+        if let Some(ref name) = self.meta.name {
+            container.set_meta("name", name);
+        }
+        if let Some(ref author) = self.meta.author {
+            container.set_meta("author", author);
+        }
+        if !self.meta.comments.is_empty() {
+            container.sections.insert("comments".to_string(), self.meta.comments.join("\n"));
+        }
+
+        if let Some(cols) = self.cols {
+            container.set_meta("cols", cols);
+        }
+        if let Some(rows) = self.rows {
+            container.set_meta("rows", rows);
+        }
+
+        if with_history {
+            // one line per history bucket: first and last generation, min, max and mean
+            // population, mean number of changed cells
+            let history: Vec<String> = self.history.get_buckets().iter()
+                .map(|b| format!("{} {} {} {} {} {}", b.first_gen, b.last_gen, b.min, b.max, b.mean, b.changed))
+                .collect();
+            container.sections.insert("history".to_string(), history.join("\n"));
+        }
+
+        container
+    }
+
+    pub fn load_container(&mut self, container: &Container) -> EngineResult<()> {
+        let parse = |key: &str| -> io::Result<Option<usize>> {
+            match container.get_meta(key) {
+                Some(value) => match value.parse() {
+                    Ok(value) => Ok(Some(value)),
+                    Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                 format!("malformed value of '{}'", key)))
+                },
+                None => Ok(None)
+            }
+        };
+
+        let cols = parse("cols")?;
+        let rows = parse("rows")?;
+        let generation = parse("generation")?.unwrap_or(0);
+
+        let rule = match container.get_meta("rule") {
+            Some(rule) => rule.parse::<Rule>().map_err(EngineError::Rule)?,
+            None => Rule::life()
+        };
+
+        let rule_table = match container.sections.get("rule_table") {
+            Some(source) => Some(RuleTable::parse(source)?),
+            None => None
+        };
+
+        let zones = match container.sections.get("zones") {
+            Some(lines) => ZoneMap::from_lines(lines)?,
+            None => ZoneMap::new()
+        };
+
+        self.cols = cols;
+        self.rows = rows;
+        self.reset();
+
+        let mut board = self.backend.new_board(cols, rows);
+        for &Coord { col, row } in &container.cells {
+            board.born_at(col, row);
+        }
+
+        if let Some(history) = container.sections.get("history") {
+            // downsampled parts of the history are restored as single samples
+            for line in history.lines() {
+                let values: Vec<f64> = line.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+                // sessions saved before changed cells were recorded have no activity
+                if values.len() == 5 || values.len() == 6 {
+                    let changed = values.get(5).map_or(0, |changed| changed.round() as usize);
+                    self.history.record(values[1] as usize, values[4].round() as usize, changed);
+                }
+            }
+        }
+
+        self.iteration = generation;
+        self.replace_rule(rule);
+        self.rule_table = rule_table;
+        self.kernel = None;
+        self.zones = zones;
+        self.meta = PatternMeta {
+            name: container.get_meta("name").map(|v| v.to_string()),
+            author: container.get_meta("author").map(|v| v.to_string()),
+            comments: container.sections.get("comments")
+                .map_or(Vec::new(), |c| c.lines().map(|l| l.to_string()).collect()),
+        };
+        self.set_board(board);
+
+        Ok(())
+    }
+
+    pub fn to_rle(&self) -> RleFile {
+        // live cells cut to their bounding box
+        let board = self.get_board();
+        let cells = board.get_bounding_box().map_or(Vec::new(), |rect| board.copy_region(&rect));
+
+        RleFile { cells: cells, ..self.to_rle_meta() }
+    }
+
+    fn to_rle_meta(&self) -> RleFile {
+        RleFile {
+            name: self.meta.name.clone(),
+            author: self.meta.author.clone(),
+            comments: self.meta.comments.clone(),
+            rule: Some(self.get_rule_name()),
+            cells: Vec::new(),
+        }
+    }
+
+    /// Same file as `rle::save(&engine.to_rle(), path)`, the cells are
+    /// written as they are read from the board, without being copied
+    pub fn save_rle(&self, path: &Path) -> io::Result<()> {
+        rle::save_board(self.get_board(), &self.to_rle_meta(), path)
+    }
+
+    /// Saves the live cells of the region, or all of them if there is
+    /// no region, with the name, author, comments and rule of the pattern
+    pub fn save_rle_region(&self, region: Option<&Rect>, path: &Path) -> io::Result<()> {
+        match region {
+            Some(rect) => rle::save(&RleFile { cells: self.board.copy_region(rect), ..self.to_rle_meta() }, path),
+            None => self.save_rle(path)
+        }
+    }
+
+    pub fn get_pattern_meta(&self) -> &PatternMeta {
+        &self.meta
+    }
+
+    pub fn set_pattern_meta(&mut self, meta: PatternMeta) {
+        self.meta = meta;
+    }
 
     pub fn cur_iteration(&self) -> usize {
         self.iteration
@@ -90,36 +422,347 @@ impl<'a> Engine<'a> {
         self.last_iter_time
     }
 
+    pub fn get_rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) {
+        if rule != self.rule || self.rule_table.is_some() {
+            self.record_event(EventKind::RuleChanged);
+        }
+        self.replace_rule(rule);
+    }
+
+    // loaded patterns and sessions come with their rule, it's not a change
+    fn replace_rule(&mut self, rule: Rule) {
+        self.touch();
+        self.rule = rule;
+        self.rule_table = None;
+        self.kernel = None;
+        self.clear_cycles();
+    }
+
+    pub fn get_rule_table(&self) -> Option<&RuleTable> {
+        self.rule_table.as_ref()
+    }
+
+    /// Replaces the B/S rule until the next `set_rule`
+    pub fn set_rule_table(&mut self, table: RuleTable) {
+        self.touch();
+        self.record_event(EventKind::RuleChanged);
+        self.rule_table = Some(table);
+        self.kernel = None;
+        self.clear_cycles();
+    }
+
+    /// Updates per second the simulation runs at, independent of the frame
+    /// rate, every update computing as many generations as the speed says;
+    /// None runs updates as fast as possible
+    pub fn get_ups(&self) -> Option<u32> {
+        self.ups
+    }
+
+    pub fn set_ups(&mut self, ups: Option<u32>) {
+        let ups = ups.map(|ups| ups.max(1));
+        if ups != self.ups {
+            self.record_event(EventKind::SpeedChanged);
+        }
+        self.ups = ups;
+    }
+
+    /// Next of the standard rates, or as fast as possible after the fastest one
+    pub fn faster_ups(&mut self) {
+        let ups = match self.ups {
+            Some(ups) => UPS_STEPS.iter().cloned().find(|&step| step > ups),
+            None => None
+        };
+        self.set_ups(ups);
+    }
+
+    pub fn slower_ups(&mut self) {
+        let ups = match self.ups {
+            Some(ups) => Some(UPS_STEPS.iter().cloned().rev().find(|&step| step < ups).unwrap_or(UPS_STEPS[0])),
+            None => Some(UPS_STEPS[UPS_STEPS.len() - 1])
+        };
+        self.set_ups(ups);
+    }
+
+    pub fn get_zones(&self) -> &ZoneMap {
+        &self.zones
+    }
+
+    /// Cells of the region follow the given rule instead of the one of
+    /// the board, see `zones`
+    pub fn add_zone(&mut self, rect: Rect, rule: Rule) {
+        self.touch();
+        self.record_event(EventKind::RuleChanged);
+        self.zones.add(rect, rule);
+    }
+
+    pub fn remove_zone_at(&mut self, col: i64, row: i64) -> bool {
+        self.touch();
+        let removed = self.zones.remove_at(col, row);
+        if removed {
+            self.record_event(EventKind::RuleChanged);
+        }
+        removed
+    }
+
+    pub fn clear_zones(&mut self) {
+        self.touch();
+        if !self.zones.is_empty() {
+            self.record_event(EventKind::RuleChanged);
+        }
+        self.zones.clear();
+    }
+
+    /// Reads Golly `.rule` file, see `io::ruletable`
+    pub fn load_rule_table(&mut self, path: &Path) -> EngineResult<()> {
+        let table = RuleTable::read(&mut compression::open(path)?)?;
+        self.set_rule_table(table);
+        Ok(())
+    }
+
+    /// Name of the rule table if there is one, B/S notation otherwise
+    pub fn get_rule_name(&self) -> String {
+        match self.rule_table {
+            Some(ref table) => table.get_name().to_string(),
+            None => self.rule.to_string()
+        }
+    }
+
+    pub fn get_autosave(&self) -> Option<&Autosave> {
+        self.autosave.as_ref()
+    }
+
+    pub fn set_autosave(&mut self, autosave: Option<Autosave>) {
+        self.autosave = autosave;
+    }
+
+    fn autosave_if_due(&mut self) {
+        let now = time::precise_time_s();
+        let iteration = self.iteration;
+
+        if !self.autosave.as_mut().map_or(false, |autosave| autosave.is_due(iteration, now)) {
+            return;
+        }
+
+        // the cells are gathered only when a full save is due
+        let container = self.to_container_meta(true);
+        let journaled = match self.autosave {
+            Some(ref mut autosave) => autosave.save_changes(&container, &self.board, iteration, now),
+            None => return
+        };
+
+        let saved = match journaled {
+            Ok(None) => {
+                let mut container = self.to_container(true);
+                match self.autosave {
+                    Some(ref mut autosave) => autosave.save(&mut container, &self.board, iteration, now).map(|_| ()),
+                    None => Ok(())
+                }
+            },
+            res => res.map(|_| ())
+        };
+
+        if let Err(err) = saved {
+            self.autosave_error = Some(err);
+        }
+    }
+
+    // the board is about to be replaced by its next generation,
+    // the autosave journal needs the cells changed on it
+    fn track_changes(&mut self) {
+        if let Some(ref mut autosave) = self.autosave {
+            autosave.track_changes(self.board.changed_cells());
+        }
+    }
+
+    /// Error of the last autosave which failed since the last call, if any
+    pub fn take_autosave_error(&mut self) -> Option<io::Error> {
+        self.autosave_error.take()
+    }
+
+    pub fn get_timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
+    }
+
+    pub fn set_timeline(&mut self, timeline: Option<Timeline>) {
+        // recording starts from the current generation
+        self.timeline = timeline;
+
+        if let Some(ref mut timeline) = self.timeline {
+            timeline.record(self.iteration, &self.board);
+        }
+    }
+
+    pub fn get_census(&self) -> Option<&Census> {
+        self.census.as_ref()
+    }
+
+    pub fn set_census(&mut self, census: Option<Census>) {
+        // the current generation is the first one recorded
+        self.census = census;
+
+        let (iteration, population) = (self.iteration, self.backend.get_population(&self.board));
+        if let Some(ref mut census) = self.census {
+            census.record(iteration, population, 0, 0);
+        }
+    }
+
+    /// Cycles are looked for while a detector is set, generations the
+    /// kernel skips are stepped one by one once it suspects a cycle
+    pub fn set_cycles(&mut self, cycles: Option<CycleDetector>) {
+        self.cycles = cycles;
+    }
+
+    pub fn is_detecting_cycles(&self) -> bool {
+        self.cycles.is_some()
+    }
+
+    /// Cycle the pattern got into since the last call, see `cycles`
+    pub fn take_cycle(&mut self) -> Option<Cycle> {
+        self.cycles.as_mut().and_then(|cycles| cycles.take_found())
+    }
+
+    fn clear_cycles(&mut self) {
+        // the same cells under another rule are another story
+        if let Some(ref mut cycles) = self.cycles {
+            cycles.clear();
+        }
+    }
+
+    /// Simulation stops on its own when the population dies out or a
+    /// generation is the same as the previous one, `iterations` leaves
+    /// the rest of the generations then, see `take_stop`
+    pub fn set_auto_stop(&mut self, auto_stop: bool) {
+        self.auto_stop = auto_stop;
+        self.stop = None;
+        self.settled = false;
+    }
+
+    pub fn is_auto_stopping(&self) -> bool {
+        self.auto_stop
+    }
+
+    /// Reason the simulation stopped since the last call, if any
+    pub fn take_stop(&mut self) -> Option<Stop> {
+        self.stop.take()
+    }
+
+    // changes are None if the generations computed were several apart,
+    // equal boards then may as well be an oscillator
+    fn check_stop(&mut self, prev_population: usize, changed: Option<usize>) {
+        if !self.auto_stop {
+            return;
+        }
+
+        let population = self.backend.get_population(&self.board);
+        if population == 0 {
+            if prev_population > 0 {
+                self.stop = Some(Stop::Extinct(self.iteration));
+            }
+        } else if changed == Some(0) && !self.settled {
+            self.stop = Some(Stop::Stable(self.iteration));
+        }
+
+        self.settled = population == 0 || changed == Some(0);
+    }
+
+    pub fn get_history(&self) -> &History {
+        &self.history
+    }
+
+    /// Cells born or died in the last generation, for jumps of
+    /// several generations in the last one of them
+    pub fn get_last_changed(&self) -> usize {
+        self.history.get_last_changed().unwrap_or(0)
+    }
+
     pub fn get_board(&self) -> &Board {
         &self.board
     }
 
     pub fn set_board(&mut self, board: Board<'a>) {
+        self.touch();
         self.board = board;
+        if let Some(ref mut autosave) = self.autosave {
+            autosave.forget_changes();
+        }
+        self.record_event(EventKind::BoardReplaced);
+    }
+
+    // next generation or the same cells elsewhere, errors of the paged
+    // board replaced would be gone along with it
+    fn replace_board(&mut self, board: Board<'a>) {
+        if let Some(err) = self.board.take_error() {
+            self.board_error = Some(err);
+        }
+        self.board = board;
+    }
+
+    /// Storage error of the paged board since the last call, if any
+    pub fn take_board_error(&mut self) -> Option<BoardError> {
+        self.board_error.take().or_else(|| self.board.take_error())
+    }
+
+    pub fn record_event(&mut self, kind: EventKind) {
+        self.history.record_event(self.iteration, kind);
     }
 
     pub fn get_board_mut(&mut self) -> &mut Board<'a> {
+        // the board is assumed to be changed by whoever borrows it
+        self.touch();
         &mut self.board
     }
 
-    pub fn create_random(&self, p: f64) -> Board<'a> {
+    pub fn get_edge_policy(&self) -> EdgePolicy {
+        self.edge_policy
+    }
+
+    pub fn set_edge_policy(&mut self, policy: EdgePolicy) {
+        if policy != self.edge_policy {
+            self.record_event(EventKind::TopologyChanged);
+        }
+        self.edge_policy = policy;
+    }
+
+    pub fn get_version(&self) -> usize {
+        self.version
+    }
+
+    fn touch(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+
+    /// Live cells along with the generation and rule, which don't
+    /// keep the engine borrowed
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(&self.board, self.version, self.iteration, self.rule)
+    }
+
+    pub fn create_random(&self, p: f64, rect: &Rect) -> Board<'a> {
+        self.create_random_with(p, rect, &mut rand::thread_rng())
+    }
 
-        let mut board = Self::new_board(self.board_type,
-                                        self.board.get_cols(), self.board.get_rows());
+    pub fn create_random_with<R: Rng>(&self, p: f64, rect: &Rect, rng: &mut R) -> Board<'a> {
+        // fill the part of the given region which belongs to the board,
+        // so it works for bounded and infinite boards alike
 
-        let cols = self.board.get_cols();
-        let rows = self.board.get_rows();
+        let mut board = self.backend.new_board(self.board.get_cols(), self.board.get_rows());
+
+        let rect = match rect.intersect(&self.backend.get_bounds(&board)) {
+            Some(rect) => rect,
+            None => return board
+        };
 
         let between = Range::new(0f64, 1.);
-        let mut rng = rand::thread_rng();
-
-        if cols.is_some() && rows.is_some() {
-            for col in 0..cols.unwrap() {
-                for row in 0..rows.unwrap() {
-                    let rval = between.ind_sample(&mut rng);
-                    if rval <= p {
-                        board.born_at(col as isize, row as isize);
-                    }
+
+        for col in rect.cols() {
+            for row in rect.rows() {
+                let rval = between.ind_sample(rng);
+                if rval <= p {
+                    self.backend.set_cell(&mut board, col, row, Cell::Occupied { gen: 1 });
                 }
             }
         }
@@ -127,131 +770,885 @@ impl<'a> Engine<'a> {
         board
     }
 
-    pub fn one_iteration(&mut self) {
+    /// Moves the cells onto a board of the given backend, see `backend`
+    pub fn set_backend(&mut self, backend: Box<EngineBackend>) {
+        // the automatic one switching on its own is still the same one
+        if backend.get_name() != self.backend.get_name() {
+            self.record_event(EventKind::BackendChanged);
+        }
 
-        let mut next_gen = Self::new_board(self.board_type,
-                                           self.board.get_cols(), self.board.get_rows());
+        let board = self.clone_board(&*backend);
+        self.replace_board(board);
+        self.backend = backend;
+        self.iters_from_prev_switch = 0;
+    }
 
-        let mut cells_checked = 0;
+    pub fn get_backend_name(&self) -> String {
+        self.backend.get_name()
+    }
 
-        let mut density_table: HashMap<isize, MinMax> = HashMap::new();
+    /// Bit-packed board is much faster for dense patterns, but it
+    /// keeps the whole region the pattern spans in memory
+    pub fn set_bit_packed(&mut self, bit_packed: bool) {
+        if bit_packed != self.is_bit_packed() {
+            self.set_backend(if bit_packed { Box::new(BitPacked) } else { Box::new(Auto::new()) });
+        }
+    }
 
-        for CellDesc { coord, gen, is_alive, .. } in self.board.into_iter() {
+    pub fn is_bit_packed(&self) -> bool {
+        self.backend.get_name() == BitPacked.get_name()
+    }
 
-            let col = coord.col;
-            let row = coord.row;
-
-            if self.board_type == BoardType::Hashed {
-                // for hashed board we maintain a hash table of
-                // min and max coordinates of each row of the board
-                match density_table.entry(row) {
-                    Entry::Occupied(mut min_max_pair) => {
-                        if col < min_max_pair.get().min.unwrap_or(isize::max_value()) {
-                            let max = min_max_pair.get().max;
-                            min_max_pair.insert(MinMax{min: Some(col), max: max});
-                        } else if col > min_max_pair.get().max.unwrap_or(isize::min_value()) {
-                            let min = min_max_pair.get().min;
-                            min_max_pair.insert(MinMax{min: min, max: Some(col)});
-                        }
-                    },
-                    Entry::Vacant(entry) => {
-                        entry.insert(MinMax{min: None, max: None});
-                    },
+    /// Paged board keeps at most the given number of 64x64 chunks in
+    /// memory and pages the rest out to temporary files, for sparse
+    /// patterns spread over a large area. The live cells themselves are
+    /// still indexed in memory, so it doesn't help with huge populations
+    pub fn set_paged(&mut self, max_chunks: Option<usize>) {
+        match max_chunks {
+            Some(max_chunks) => {
+                if self.backend.get_name() != Paged(max_chunks).get_name() {
+                    self.set_backend(Box::new(Paged(max_chunks)));
+                }
+            }
+            None => {
+                if self.backend.get_name().starts_with("paged:") {
+                    self.set_backend(Box::new(Auto::new()));
                 }
-            } else {
-                cells_checked += 1;
             }
+        }
+    }
 
-            // check game rules against current cell
-            let neighbours = self.board.get_vicinity(col, row);
+    /// Sparse board keeps cells in a quadtree, so patterns spread far
+    /// apart, e.g. gliders flying away from each other, take memory only
+    /// for the cells around them
+    pub fn set_sparse(&mut self, sparse: bool) {
+        if sparse != self.is_sparse() {
+            self.set_backend(if sparse { Box::new(Sparse) } else { Box::new(Auto::new()) });
+        }
+    }
 
-            if is_alive {
-                let neighbours_cnt = neighbours.into_iter().filter(|&x| x).count();
-                // any live cell with fewer than two live neighbours dies,
-                // as if caused by underpopulation.
+    pub fn is_sparse(&self) -> bool {
+        self.backend.get_name() == Sparse.get_name()
+    }
 
-                // any live cell with more than three live neighbours
-                // dies, as if by overpopulation.
+    pub fn one_iteration(&mut self) {
 
-                // any live cell with two or three live neighbours
-                // lives on to the next generation.
-                if neighbours_cnt == 2 || neighbours_cnt == 3 {
-                    next_gen.born_at_gen(col, row, gen + 1);
-                }
-            } else {
-                // any dead cell with exactly three live neighbours becomes
-                // a live cell, as if by reproduction.
-                if neighbours.into_iter().filter(|&x| x).count() == 3 {
-                    next_gen.born_at(col, row);
-                }
+        let prev_population = self.backend.get_population(&self.board);
+        self.track_changes();
+
+        // the generation a detector starts from, e.g. a loaded pattern
+        if let Some(ref mut cycles) = self.cycles {
+            if cycles.is_empty() {
+                cycles.record(self.iteration, &self.board);
             }
         }
 
-        self.board = next_gen;
+        if self.rule_table.is_none() && self.zones.is_empty() {
+            if let Some((next_gen, births, deaths)) = self.backend.step(&mut self.board, &self.rule) {
+                self.replace_board(next_gen);
+                self.finish_iteration(prev_population, births, deaths);
+                self.check_stop(prev_population, Some(births + deaths));
+                return;
+            }
+        }
 
-        // compute density of hashed board
-        if self.board_type == BoardType::Hashed {
-            for (_, v) in density_table.iter() {
-                if let Some(x) = v.max {
-                    cells_checked += x;
-                }
-                if let Some(x) = v.min {
-                    cells_checked += x.abs();
+        let mut next_gen = self.backend.new_board(self.board.get_cols(), self.board.get_rows());
+
+        let (mut births, mut deaths) = (0, 0);
+        let mut changed = HashSet::new();
+
+        // boards stepped by the backend before have no neighbours counted
+        self.board.count_neighbours();
+
+        {
+            // only live cells and their neighbours can be alive in the next
+            // generation, so the rest of the board isn't looked at
+            let board = &self.board;
+            let candidates = board.get_occupied().iter()
+                .chain(board.get_neighbourhood().keys().filter(|coord| !board.get_occupied().contains(coord)));
+
+            for &Coord { col, row } in candidates {
+
+                let is_alive = board.is_alive(col, row);
+
+                // in Life (B3/S23) any live cell with two or three live neighbours
+                // lives on to the next generation, others die of under- or
+                // overpopulation, and any dead cell with exactly three live
+                // neighbours becomes a live cell, as if by reproduction
+                let next_alive = match (self.zones.get_rule_at(col, row), &self.rule_table) {
+                    // tables depend on the positions of the neighbours
+                    (None, &Some(ref table)) => table.next_state(is_alive, &board.get_vicinity(col, row)),
+                    (zone_rule, _) => {
+                        let rule = zone_rule.unwrap_or(self.rule);
+                        let neighbours_cnt = board.get_neighbour_count(col, row);
+                        if is_alive { rule.survives(neighbours_cnt) } else { rule.is_born(neighbours_cnt) }
+                    }
+                };
+
+                if is_alive {
+                    if next_alive {
+                        next_gen.born_at_gen(col, row, board.get_cell_gen(col, row) + 1);
+                    } else {
+                        deaths += 1;
+                        changed.insert(Coord { col: col, row: row });
+                    }
+                } else if next_alive {
+                    next_gen.born_at(col, row);
+                    births += 1;
+                    changed.insert(Coord { col: col, row: row });
                 }
             }
         }
 
-        let density = (self.board.get_population() as f64) / (cells_checked as f64);
+        // survivors were born on the new board too
+        next_gen.set_changed_cells(changed);
+        self.replace_board(next_gen);
 
-        if density < 0.03 && self.board_type == BoardType::SymVec {
-            if self.iters_from_prev_switch > SWITCH_BOARD_INERTIA {
-                self.iters_from_prev_switch = 0;
-                println!("switched to hashed board");
-                self.switch_board();
-            }
-        } else if density >= 0.03 && self.board_type == BoardType::Hashed {
-            if self.iters_from_prev_switch > SWITCH_BOARD_INERTIA {
-                self.iters_from_prev_switch = 0;
-                println!("switched to symvec board");
-                self.switch_board();
+        // share of the bounding box which is alive
+        let area = self.board.get_bounding_box().map_or(0, |rect| rect.get_area());
+        let density = (self.backend.get_population(&self.board) as f64) / (area.max(1) as f64);
+
+        if self.iters_from_prev_switch > SWITCH_BOARD_INERTIA {
+            if let Some(backend) = self.backend.get_switch(density) {
+                self.set_backend(backend);
             }
         }
 
         //println!("density {}", density);
 
-        if (self.iteration % ITERATIONS_TO_CLEANUP) == 0 && self.board_type == BoardType::SymVec {
-            // rebuild vector based board once per ITERATIONS_TO_CLEANUP iterations
-            // to improve performance by removing empty cells
-            let new_board = self.clone_board(self.board_type);
-            self.set_board(new_board);
-        }
+        self.finish_iteration(prev_population, births, deaths);
+        self.check_stop(prev_population, Some(births + deaths));
+    }
 
+    // records the generation just computed
+    fn finish_iteration(&mut self, prev_population: usize, births: usize, deaths: usize) {
         self.iteration += 1;
         self.iters_from_prev_switch += 1;
+        self.touch();
+
+        // boards only grow as cells are born, memory of the empty
+        // regions left behind is freed once in a while
+        if (self.iteration % ITERATIONS_TO_CLEANUP) == 0 {
+            self.board.shrink();
+        }
+
+        self.history.record(self.iteration, self.backend.get_population(&self.board), births + deaths);
+
+        if let Some(ref mut timeline) = self.timeline {
+            timeline.record(self.iteration, &self.board);
+        }
+        if let Some(ref mut cycles) = self.cycles {
+            cycles.record(self.iteration, &self.board);
+        }
+        if let Some(ref mut census) = self.census {
+            // the generation census was cleared at, e.g. a loaded pattern
+            if census.get_entries().is_empty() {
+                census.record(self.iteration - 1, prev_population, 0, 0);
+            }
+            census.record(self.iteration, self.backend.get_population(&self.board), births, deaths);
+        }
+
+        self.autosave_if_due();
+    }
+
+    /// Computes the given number of generations on a dense grid, see
+    /// `kernel`, returns false if the board isn't worth it or can't be
+    /// stepped this way, e.g. it wraps around, has rule zones or every
+    /// generation is needed for the timeline, the period of a cycle or stops
+    fn kernel_iterations(&mut self, steps: usize) -> bool {
+        // a short run would leave the detector only generations recorded
+        // across gaps, which never give the exact period
+        let suspecting = self.cycles.as_ref().map_or(false, |cycles| {
+            cycles.is_suspecting() || steps < KERNEL_STEPS as usize
+        });
+
+        // bit-packed, paged and sparse boards deal with big patterns their own way
+        if self.timeline.is_some() || suspecting || self.auto_stop || self.backend.is_chosen() || !self.zones.is_empty() ||
+           self.board.get_cols().is_some() || self.board.get_rows().is_some() {
+            return false;
+        }
+
+        let area = match self.board.get_bounding_box() {
+            Some(rect) => rect.get_width().saturating_add(2 * steps).saturating_mul(rect.get_height().saturating_add(2 * steps)),
+            None => return false
+        };
+        let prev_population = self.backend.get_population(&self.board);
+        if (prev_population as f64) < KERNEL_DENSITY * area as f64 {
+            return false;
+        }
+
+        if self.kernel.is_none() {
+            self.kernel = Some(match self.rule_table {
+                Some(ref table) => Kernel::new(|is_alive, neighbours| table.next_state(is_alive, neighbours)),
+                None => Kernel::from_rule(&self.rule)
+            });
+        }
+
+        // the generation a detector starts from, as in `one_iteration`
+        if let Some(ref mut cycles) = self.cycles {
+            if cycles.is_empty() {
+                cycles.record(self.iteration, &self.board);
+            }
+        }
+
+        let mut next_gen = self.backend.new_board(None, None);
+        let stats = match self.kernel {
+            Some(ref kernel) => kernel.run(&self.board, steps, &mut next_gen),
+            None => return false
+        };
+        let changed = next_gen.get_changes_from(&self.board);
+        next_gen.set_changed_cells(changed);
+        self.track_changes();
+        self.replace_board(next_gen);
+
+        if let Some(ref mut census) = self.census {
+            if census.get_entries().is_empty() {
+                census.record(self.iteration, prev_population, 0, 0);
+            }
+        }
+
+        for stat in stats {
+            self.iteration += 1;
+            self.history.record(self.iteration, stat.population, stat.births + stat.deaths);
+
+            if let Some(ref mut census) = self.census {
+                census.record(self.iteration, stat.population, stat.births, stat.deaths);
+            }
+        }
+
+        // only the last generation of the pass is hashed
+        if let Some(ref mut cycles) = self.cycles {
+            cycles.record(self.iteration, &self.board);
+        }
+
+        self.iters_from_prev_switch += steps;
+        self.touch();
+        self.autosave_if_due();
+
+        true
     }
 
-    pub fn switch_board(&mut self) {
+    /// Replaces the live cells with the given ones, with their ages, which
+    /// are a generation later for each of the given stats and computed
+    /// outside of the engine, e.g. on the GPU. Every generation gets into
+    /// the history and the census, only the last one is on the board
+    pub fn apply_generations(&mut self, cells: &[(Coord, usize)], stats: &[Stats]) {
+        let last = match stats.last() {
+            Some(&stat) => stat,
+            None => return
+        };
+        let steps = stats.len();
+
+        let mut board = self.backend.new_board(self.board.get_cols(), self.board.get_rows());
+        for &(Coord { col, row }, gen) in cells {
+            self.backend.set_cell(&mut board, col, row, Cell::Occupied { gen: gen });
+        }
 
-        // switch internal board representation hash<->symvec
+        let prev_population = self.backend.get_population(&self.board);
+        let changed = board.get_changes_from(&self.board);
+        board.set_changed_cells(changed);
 
-        if self.board_type == BoardType::Hashed {
-            self.board_type = BoardType::SymVec;
-        }  else {
-            self.board_type = BoardType::Hashed;
+        self.track_changes();
+        self.replace_board(board);
+        if steps > 1 {
+            // skipped generations would make the periods wrong
+            self.clear_cycles();
+        }
+
+        if let Some(ref mut census) = self.census {
+            if census.get_entries().is_empty() {
+                census.record(self.iteration, prev_population, 0, 0);
+            }
         }
 
-        let new_board = self.clone_board(self.board_type);
+        // the last generation is recorded along with the board
+        for stat in &stats[..steps - 1] {
+            self.iteration += 1;
+            self.history.record(self.iteration, stat.population, stat.births + stat.deaths);
 
-        self.set_board(new_board);
+            if let Some(ref mut census) = self.census {
+                census.record(self.iteration, stat.population, stat.births, stat.deaths);
+            }
+        }
+        self.iters_from_prev_switch += steps - 1;
+
+        let last_population = if steps > 1 { stats[steps - 2].population } else { prev_population };
+        self.finish_iteration(prev_population, last.births, last.deaths);
+        self.check_stop(last_population, Some(last.births + last.deaths));
     }
 
+    /// Dense boards are stepped several generations at a time by the
+    /// kernel, which leaves only the last of them on the board
     pub fn iterations(&mut self, n: u64) -> f64 {
         let st = time::precise_time_s();
-        for _ in 0..n {
-            self.one_iteration();
+
+        // a stop nobody took yet doesn't hold up the simulation
+        let pending = self.stop.is_some();
+
+        let mut left = n;
+        while left > 0 {
+            let steps = cmp::min(left, KERNEL_STEPS);
+            if steps > 1 && self.kernel_iterations(steps as usize) {
+                left -= steps;
+            } else {
+                self.one_iteration();
+                left -= 1;
+            }
+
+            if !pending && self.stop.is_some() {
+                break;
+            }
         }
 
         self.last_iter_time = time::precise_time_s() - st;
         self.last_iter_time
     }
 }
+
+
+#[cfg(test)]
+use ::soup::SoupSearch;
+#[cfg(test)]
+use ::backend::{Hashed, SymVec};
+#[cfg(test)]
+use ::io::rle::decode_cells;
+
+#[test]
+fn test_create_random_region() {
+    let rect = Rect::new(-5, -5, 4, 4);
+
+    // infinite board gets exactly the requested region filled
+    let engine = Engine::new(None, None);
+    let board = engine.create_random(1.0, &rect);
+    assert_eq!(board.get_population(), 100);
+    assert_eq!(board.get_bounding_box(), Some(rect));
+
+    // bounded board is filled only where the region overlaps it
+    let engine = Engine::new(Some(6), Some(6));
+    let board = engine.create_random(1.0, &rect);
+    assert_eq!(board.get_population(), 36);
+
+    assert_eq!(engine.create_random(1.0, &Rect::new(10, 10, 20, 20)).get_population(), 0);
+}
+
+#[test]
+fn test_rule() {
+    // a domino dies in Life but is a replicator seed in B2/S
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }]);
+    engine.one_iteration();
+    assert_eq!(engine.get_board().get_population(), 0);
+
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }]);
+    engine.set_rule("B2/S".parse().unwrap());
+    engine.one_iteration();
+    assert_eq!(engine.get_board().get_population(), 4);
+
+    // rule survives saving
+    let container = engine.to_container(false);
+    let mut restored = Engine::new(None, None);
+    restored.load_container(&container).unwrap();
+    assert_eq!(restored.get_rule(), engine.get_rule());
+}
+
+#[test]
+fn test_rule_table() {
+    // B2/S as a table of von Neumann neighbours, so diagonal neighbours don't count
+    let source = "@RULE Cross\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:permute\n\
+                  var a={0,1}\nvar b={a}\nvar c={a}\nvar d={a}\n\
+                  0,1,1,0,0,1\n1,a,b,c,d,0\n";
+
+    let mut engine = Engine::new(None, None);
+    engine.get_board_mut().born_at(0, 0);
+    engine.get_board_mut().born_at(1, 1);
+    engine.set_rule_table(RuleTable::parse(source).unwrap());
+    assert_eq!(engine.get_rule_name(), "Cross");
+
+    // only the two cells touching both of the diagonal ones are born
+    engine.one_iteration();
+    assert_eq!(engine.get_board().get_population(), 2);
+    assert!(engine.get_board().is_alive(1, 0) && engine.get_board().is_alive(0, 1));
+
+    // rule table is saved with the session
+    let mut restored = Engine::new(None, None);
+    restored.load_container(&engine.to_container(false)).unwrap();
+    assert_eq!(restored.get_rule_name(), "Cross");
+    assert_eq!(engine.to_rle().rule, Some("Cross".to_string()));
+
+    restored.set_rule(Rule::life());
+    assert!(restored.get_rule_table().is_none());
+    assert_eq!(restored.get_rule_name(), "B3/S23");
+}
+
+#[test]
+fn test_rule_zones() {
+    let mut engine = Engine::new(None, None);
+    for &(col, row) in [(0, 0), (1, 0), (2, 0), (10, 0), (10, 1), (10, 2)].iter() {
+        engine.get_board_mut().born_at(col, row);
+    }
+
+    // nothing is born and everything survives around the first blinker
+    let frozen: Rule = "B/S012345678".parse().unwrap();
+    engine.add_zone(Rect::new(-1, -1, 3, 1), frozen);
+
+    engine.iterations(5);
+    let alive = |engine: &Engine, cells: &[(i64, i64)]| cells.iter().all(|&(col, row)| engine.get_board().is_alive(col, row));
+    assert!(alive(&engine, &[(0, 0), (1, 0), (2, 0), (9, 1), (10, 1), (11, 1)]));
+    assert_eq!(engine.get_board().get_population(), 6);
+
+    // zones are saved with the session
+    let mut restored = Engine::new(None, None);
+    restored.load_container(&engine.to_container(false)).unwrap();
+    assert_eq!(restored.get_zones(), engine.get_zones());
+
+    // cells on the boundary follow the zone, the ones next to it don't
+    assert!(engine.remove_zone_at(0, 0));
+    engine.add_zone(Rect::new(0, 0, 2, 0), frozen);
+    engine.one_iteration();
+    assert!(alive(&engine, &[(0, 0), (1, 0), (2, 0), (1, -1), (1, 1)]));
+
+    engine.clear_zones();
+    assert!(engine.get_zones().is_empty());
+}
+
+#[test]
+fn test_ups() {
+    let mut engine = Engine::new(None, None);
+    assert_eq!(engine.get_ups(), Some(DEFAULT_UPS));
+
+    engine.faster_ups();
+    assert_eq!(engine.get_ups(), Some(240));
+    engine.faster_ups();
+    assert_eq!(engine.get_ups(), None);
+    engine.faster_ups();
+    assert_eq!(engine.get_ups(), None);
+
+    engine.slower_ups();
+    assert_eq!(engine.get_ups(), Some(240));
+
+    // rates set directly snap to the steps when changed
+    engine.set_ups(Some(3));
+    engine.slower_ups();
+    assert_eq!(engine.get_ups(), Some(2));
+    engine.slower_ups();
+    engine.slower_ups();
+    assert_eq!(engine.get_ups(), Some(1));
+
+    engine.set_ups(Some(0));
+    assert_eq!(engine.get_ups(), Some(1));
+}
+
+#[test]
+fn test_pattern_meta() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&[Coord { col: 5, row: 5 }, Coord { col: 6, row: 5 }]);
+
+    let meta = PatternMeta {
+        name: Some("Domino".to_string()),
+        author: None,
+        comments: vec!["Dies at once".to_string(), "in Life".to_string()],
+    };
+    engine.set_pattern_meta(meta.clone());
+
+    let rle = engine.to_rle();
+    assert_eq!(rle.name, meta.name);
+    assert_eq!(rle.comments, meta.comments);
+    assert_eq!(rle.cells, vec![Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }]);
+
+    // metadata is saved along with the board
+    let container = engine.to_container(false);
+    let mut restored = Engine::new(None, None);
+    restored.load_container(&container).unwrap();
+    assert_eq!(restored.get_pattern_meta(), &meta);
+
+    // comments and names which look like parts of the file stay what they are
+    let meta = PatternMeta {
+        name: Some("C:\\patterns\\x".to_string()),
+        author: Some("two\nlines".to_string()),
+        comments: vec!["[cells]".to_string(), "  [history]".to_string(), "hello".to_string(),
+                       "checksum = 0".to_string(), "key = value".to_string(), "\\[zones]".to_string()],
+    };
+    engine.set_pattern_meta(meta.clone());
+
+    let mut data: Vec<u8> = Vec::new();
+    engine.to_container(true).write(&mut data).unwrap();
+    let container = Container::read(&mut &data[..]).unwrap();
+    restored.load_container(&container).unwrap();
+    assert_eq!(restored.get_pattern_meta(), &meta);
+    assert_eq!(restored.get_board().get_population(), 2);
+
+    restored.reset();
+    assert_eq!(restored.get_pattern_meta(), &PatternMeta::default());
+}
+
+#[test]
+fn test_load_pattern_meta() {
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    let path = env::temp_dir().join("life-rs-meta-test.rle");
+    fs::File::create(&path).unwrap()
+        .write_all(b"#N Glider\n#O Richard K. Guy\n#C Moves diagonally\nx = 3, y = 3\nbob$2bo$3o!\n")
+        .unwrap();
+
+    let mut engine = Engine::new(None, None);
+    engine.load_pattern(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let meta = engine.get_pattern_meta();
+    assert_eq!(meta.name, Some("Glider".to_string()));
+    assert_eq!(meta.author, Some("Richard K. Guy".to_string()));
+    assert_eq!(meta.comments, vec!["Moves diagonally".to_string()]);
+
+    // and it is written back on export
+    assert_eq!(engine.to_rle().author, meta.author);
+
+    // of the selected cells only, if there is a selection
+    let rect = engine.get_board().get_bounding_box().unwrap();
+    let path = env::temp_dir().join("life-rs-region-test.rle");
+    engine.save_rle_region(Some(&Rect::new(rect.left, rect.bottom, rect.right, rect.bottom)), &path).unwrap();
+
+    let mut saved = Engine::new(None, None);
+    saved.load_pattern(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(saved.get_board().get_population(), 3);
+    assert_eq!(saved.get_pattern_meta().name, Some("Glider".to_string()));
+}
+
+#[test]
+fn test_load_errors() {
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    let path = env::temp_dir().join("life-rs-errors-test.rle");
+    fs::File::create(&path).unwrap()
+        .write_all(b"x = 12, y = 1, rule = B3/S23\n12o!\n")
+        .unwrap();
+
+    // the board is kept as it was
+    let mut engine = Engine::new(Some(10), Some(10));
+    engine.set_pattern(&[Coord { col: 0, row: 0 }]);
+    match engine.load_pattern(&path) {
+        Err(EngineError::Board(err)) => assert_eq!(err, BoardError::TooLarge { width: 12, height: 1 }),
+        res => panic!("unexpected result {:?}", res)
+    }
+    assert_eq!(engine.get_board().get_population(), 1);
+
+    assert!(Engine::new(Some(12), Some(10)).load_pattern(&path).is_ok());
+
+    fs::File::create(&path).unwrap().write_all(b"x = 1, y = 1, rule = B3/S239\no!\n").unwrap();
+    match engine.load_pattern(&path) {
+        Err(EngineError::Rule(_)) => {}
+        res => panic!("unexpected result {:?}", res)
+    }
+    fs::remove_file(&path).unwrap();
+
+    match engine.load_pattern(&path) {
+        Err(EngineError::Io(_)) => {}
+        res => panic!("unexpected result {:?}", res)
+    }
+}
+
+#[test]
+fn test_autosave_errors() {
+    use std::env;
+    use std::fs;
+
+    // autosaves go to a directory under a plain file
+    let file = env::temp_dir().join("life-rs-autosave-errors-test");
+    fs::File::create(&file).unwrap();
+
+    let mut engine = Engine::new(None, None);
+    engine.set_autosave(Some(Autosave::new(&file.join("autosave"), 1, 1, 0.0)));
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }]);
+
+    engine.one_iteration();
+    assert!(engine.take_autosave_error().is_none());
+    engine.one_iteration();
+    assert!(engine.take_autosave_error().is_some());
+    assert!(engine.take_autosave_error().is_none());
+
+    fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_autosave_journal() {
+    use std::env;
+    use std::fs;
+
+    let dir = env::temp_dir().join("life-rs-engine-journal-test");
+    fs::create_dir_all(&dir).unwrap();
+    let mut autosave = Autosave::new(&dir, 2, 1, 0.0);
+    autosave.set_journal_limit(100);
+    autosave.clear();
+
+    let mut engine = Engine::new(None, None);
+    engine.set_autosave(Some(autosave));
+    engine.set_pattern(&[Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 }, Coord { col: 0, row: 2 },
+                         Coord { col: 1, row: 2 }, Coord { col: 2, row: 2 }]);
+    for _ in 0..10 {
+        engine.one_iteration();
+    }
+    // edits between generations are saved too
+    engine.get_board_mut().born_at(50, 50);
+    for _ in 0..5 {
+        engine.one_iteration();
+    }
+
+    let path = engine.get_autosave().unwrap().find_latest().unwrap();
+    let mut restored = Autosave::restore(&path).unwrap();
+    let mut cells = engine.to_container(false).cells;
+    restored.cells.sort();
+    cells.sort();
+    assert_eq!(restored.get_meta("generation"), Some("15"));
+    assert_eq!(restored.cells, cells);
+
+    engine.get_autosave().unwrap().clear();
+}
+
+#[test]
+fn test_kernel_iterations() {
+    // dense soup stepped by the kernel ends up like the one stepped
+    // one generation after another, with every generation counted
+    let mut engines: Vec<Engine> = (0..2).map(|_| Engine::new(None, None)).collect();
+    for engine in engines.iter_mut() {
+        SoupSearch::new(3, Rect::new(0, 0, 31, 31), 0.5).seed_board(engine);
+        engine.set_census(Some(Census::new()));
+    }
+
+    engines[0].iterations(37);
+    for _ in 0..37 {
+        engines[1].one_iteration();
+    }
+
+    let get_cells = |engine: &Engine| {
+        let mut cells: Vec<(Coord, usize)> = engine.get_board().into_iter()
+            .filter(|cell| cell.is_alive)
+            .map(|cell| (cell.coord, cell.gen))
+            .collect();
+        cells.sort();
+        cells
+    };
+
+    assert!(engines[0].kernel.is_some());
+    assert_eq!(get_cells(&engines[0]), get_cells(&engines[1]));
+    assert_eq!(engines[0].cur_iteration(), 37);
+    assert_eq!(engines[0].get_census().unwrap().get_entries(), engines[1].get_census().unwrap().get_entries());
+}
+
+#[test]
+fn test_kernel_with_cycles() {
+    // census and cycle detection are on in the UI, the kernel still
+    // steps dense patterns and a field of blinkers is found to repeat
+    // every two generations, not every pass of the kernel
+    let mut cells = Vec::new();
+    for i in 0..10 {
+        for j in 0..10 {
+            for k in 0..3 {
+                cells.push(Coord { col: 4 * i + k, row: 4 * j });
+            }
+        }
+    }
+
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&cells);
+    engine.set_census(Some(Census::new()));
+    engine.set_cycles(Some(CycleDetector::new()));
+
+    engine.iterations(64);
+    assert!(engine.kernel.is_some());
+    assert_eq!(engine.cur_iteration(), 64);
+    assert_eq!(engine.get_board().get_population(), 300);
+
+    let cycle = engine.take_cycle().unwrap();
+    assert_eq!((cycle.period, cycle.is_moving()), (2, false));
+    assert!(cycle.generation > KERNEL_STEPS as usize);
+    // the cycle goes on through the next passes, it's reported once
+    engine.iterations(64);
+    assert_eq!(engine.take_cycle(), None);
+}
+
+#[test]
+fn test_sparse_iteration() {
+    // glider on a huge board moves as usual, one on a small
+    // bounded board comes back after crossing the edges
+    let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    let mut engine = Engine::new(Some(2000), Some(2000));
+    engine.set_backend(Box::new(SymVec));
+    for &(col, row) in glider.iter() {
+        engine.get_board_mut().born_at(col, row);
+    }
+
+    engine.iterations(4);
+    assert_eq!(engine.get_board().get_population(), 5);
+    assert!(glider.iter().all(|&(col, row)| engine.get_board().is_alive(col + 1, row + 1)));
+
+    let mut engine = Engine::new(Some(8), Some(8));
+    for &(col, row) in glider.iter() {
+        engine.get_board_mut().born_at(col, row);
+    }
+
+    engine.iterations(32);
+    assert_eq!(engine.get_board().get_population(), 5);
+    assert!(glider.iter().all(|&(col, row)| engine.get_board().is_alive(col, row)));
+}
+
+#[test]
+fn test_apply_generations() {
+    let mut engines: Vec<Engine> = (0..2).map(|_| Engine::new(Some(40), Some(40))).collect();
+    for engine in engines.iter_mut() {
+        SoupSearch::new(3, Rect::new(-10, -10, 9, 9), 0.4).seed_board(engine);
+    }
+
+    for engine in engines.iter_mut() {
+        engine.set_census(Some(Census::new()));
+    }
+
+    let mut stats = Vec::new();
+    for _ in 0..7 {
+        engines[0].one_iteration();
+        let entry = *engines[0].get_census().unwrap().get_entries().last().unwrap();
+        stats.push(Stats { population: entry.population, births: entry.births, deaths: entry.deaths });
+    }
+    let cells: Vec<(Coord, usize)> = engines[0].get_board().into_iter()
+        .filter(|cell| cell.is_alive)
+        .map(|cell| (cell.coord, cell.gen))
+        .collect();
+    engines[1].apply_generations(&cells, &stats);
+
+    assert_eq!(engines[1].cur_iteration(), 7);
+    assert_eq!(engines[1].get_board().get_population(), engines[0].get_board().get_population());
+    assert!(cells.iter().all(|&(coord, gen)| engines[1].get_board().get_cell(coord.col, coord.row) == Cell::Occupied { gen: gen }));
+    // every generation is in the census, not just the last one
+    assert_eq!(engines[1].get_census().unwrap().get_entries(), engines[0].get_census().unwrap().get_entries());
+}
+
+#[test]
+fn test_changed_cells() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }]);
+
+    // the ends of the blinker die and two cells are born
+    let before = engine.get_board().get_occupied().clone();
+    engine.one_iteration();
+    let changed = engine.get_board().changed_cells().clone();
+    assert_eq!(changed.len(), 4);
+    assert_eq!(changed, engine.get_board().get_changes_from(&{
+        let mut board = Hashed.new_board(None, None);
+        for coord in before {
+            board.born_at(coord.col, coord.row);
+        }
+        board
+    }));
+
+    // edits are added to the changes of the generation
+    let cell = *engine.get_board().get_occupied().difference(&changed).next().unwrap();
+    engine.get_board_mut().kill_at(cell.col, cell.row);
+    assert_eq!(engine.get_board().changed_cells().len(), 5);
+    engine.get_board_mut().born_at(cell.col, cell.row);
+    assert_eq!(engine.get_board().changed_cells().len(), 4);
+
+    // generations skipped at once count as one, the blinker is back
+    assert!(engine.kernel_iterations(2));
+    assert!(engine.get_board().changed_cells().is_empty());
+    engine.one_iteration();
+    assert_eq!(engine.get_board().changed_cells().len(), 4);
+
+    // the history keeps every generation's count, sessions too
+    assert_eq!(engine.get_last_changed(), 4);
+    let mut restored = Engine::new(None, None);
+    restored.load_container(&engine.to_container(true)).unwrap();
+    assert_eq!(restored.get_last_changed(), 4);
+}
+
+#[test]
+fn test_auto_stop() {
+    let mut engine = Engine::new(None, None);
+    engine.set_auto_stop(true);
+
+    // turns into a block, the generation after it is the same
+    engine.set_pattern(&decode_cells("2o$o!").unwrap());
+    engine.iterations(10);
+    assert_eq!(engine.take_stop(), Some(Stop::Stable(2)));
+    assert_eq!(engine.cur_iteration(), 2);
+
+    // told once, the simulation can go on after that
+    engine.iterations(10);
+    assert_eq!((engine.take_stop(), engine.cur_iteration()), (None, 12));
+
+    engine.set_pattern(&decode_cells("2o!").unwrap());
+    engine.iterations(5);
+    let stop = engine.take_stop().unwrap();
+    assert_eq!(stop.to_string(), "population died out at generation 1");
+    engine.iterations(5);
+    assert_eq!(engine.take_stop(), None);
+
+    // a blinker two generations later is the same, but it isn't stable
+    engine.set_pattern(&decode_cells("3o!").unwrap());
+    engine.iterations(10);
+    let cells: Vec<(Coord, usize)> = engine.get_board().get_occupied().iter().map(|&coord| (coord, 1)).collect();
+    let stats = Stats { population: 3, births: 2, deaths: 2 };
+    engine.apply_generations(&cells, &[stats, stats]);
+    assert_eq!(engine.take_stop(), None);
+
+    engine.set_auto_stop(false);
+    engine.set_pattern(&decode_cells("2o!").unwrap());
+    engine.iterations(5);
+    assert_eq!((engine.take_stop(), engine.cur_iteration()), (None, 5));
+}
+
+#[test]
+fn test_chosen_backend_kept() {
+    // a sparse pair of blocks and a dense glider, run past the switching inertia
+    let blocks = decode_cells("2o$2o500b2o$502b2o!").unwrap();
+    let glider = decode_cells("bo$2bo$3o!").unwrap();
+
+    for &(cells, name) in [(&blocks, "symvec"), (&glider, "hashed")].iter() {
+        let mut engine = Engine::new(None, None);
+        engine.set_backend(::backend::find(name).unwrap());
+        engine.set_pattern(cells);
+        engine.iterations(SWITCH_BOARD_INERTIA as u64 + 10);
+        assert_eq!(engine.get_backend_name(), name);
+    }
+
+    // the default one switches to vectors for the glider and stays itself
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&glider);
+    engine.iterations(SWITCH_BOARD_INERTIA as u64 + 10);
+    assert_eq!(engine.get_backend_name(), "auto");
+    assert_eq!(engine.backend.get_board_name(), "symvec");
+}
+
+#[test]
+fn test_history_events() {
+    let kinds = |engine: &Engine| -> Vec<EventKind> {
+        engine.get_history().get_events().iter().map(|event| event.kind.clone()).collect()
+    };
+
+    // switching on its own isn't an event, loading a pattern with its rule isn't a rule change
+    let mut engine = Engine::new(None, None);
+    engine.load_preset(&::presets::PRESETS[0]).unwrap();
+    engine.iterations(SWITCH_BOARD_INERTIA as u64 + 10);
+    assert_eq!(kinds(&engine), vec![EventKind::BoardReplaced]);
+
+    // setting what is already set isn't a change either
+    let rule = engine.get_rule();
+    engine.set_rule(rule);
+    engine.set_edge_policy(EdgePolicy::default());
+    engine.clear_zones();
+    assert_eq!(kinds(&engine).len(), 1);
+
+    engine.set_rule("B36/S23".parse().unwrap());
+    engine.add_zone(Rect::new(0, 0, 10, 10), Rule::life());
+    engine.set_ups(Some(7));
+    engine.faster_ups();
+    engine.set_edge_policy(EdgePolicy::Reject);
+    engine.set_backend(Box::new(SymVec));
+
+    assert_eq!(kinds(&engine)[1..].to_vec(), vec![EventKind::RuleChanged, EventKind::RuleChanged,
+                                                 EventKind::SpeedChanged, EventKind::SpeedChanged,
+                                                 EventKind::TopologyChanged, EventKind::BackendChanged]);
+}
@@ -0,0 +1,75 @@
+/// Errors of the engine operations: files which can't be read or are
+/// malformed, rules which can't be parsed and patterns which can't be
+/// placed on the board
+/// ```
+/// # use engine::engine::Engine;
+/// # use engine::error::EngineError;
+/// # use engine::io::container::Container;
+/// let mut container = Container::new();
+/// container.set_meta("rule", "B3/S9");
+///
+/// match Engine::new(None, None).load_container(&container) {
+///     Err(EngineError::Rule(msg)) => assert!(msg.contains('9')),
+///     res => panic!("unexpected result {:?}", res)
+/// }
+/// ```
+///
+
+use ::board::BoardError;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+
+#[derive(Debug)]
+pub enum EngineError {
+    Io(io::Error),
+    // malformed rule of a pattern or a session
+    Rule(String),
+    Board(BoardError),
+}
+
+pub type EngineResult<T> = Result<T, EngineError>;
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EngineError::Io(ref err) => write!(f, "{}", err),
+            EngineError::Rule(ref msg) => write!(f, "{}", msg),
+            EngineError::Board(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn description(&self) -> &str {
+        match *self {
+            EngineError::Io(ref err) => err.description(),
+            EngineError::Rule(ref msg) => msg,
+            EngineError::Board(ref err) => err.description(),
+        }
+    }
+}
+
+impl From<io::Error> for EngineError {
+    fn from(err: io::Error) -> EngineError {
+        EngineError::Io(err)
+    }
+}
+
+impl From<BoardError> for EngineError {
+    fn from(err: BoardError) -> EngineError {
+        EngineError::Board(err)
+    }
+}
+
+// for callers reporting everything as I/O errors, like exports
+impl From<EngineError> for io::Error {
+    fn from(err: EngineError) -> io::Error {
+        match err {
+            EngineError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+        }
+    }
+}
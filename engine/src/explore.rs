@@ -0,0 +1,208 @@
+/// Rule space explorer: a pattern is run for a while under a rule and
+/// under every rule differing from it by one birth or survival condition,
+/// each on its own thread, to see where a small change of the rule leads.
+/// Outcomes come in as the threads finish:
+/// ```
+/// # use engine::board::Coord;
+/// # use engine::explore::Explorer;
+/// # use engine::rule::Rule;
+/// let blinker: Vec<Coord> = (0..3).map(|col| Coord { col: col, row: 0 }).collect();
+/// let mut explorer = Explorer::start(&blinker, &Rule::life(), 10);
+/// explorer.wait();
+///
+/// let outcome = explorer.get_outcomes()[0].as_ref().unwrap();
+/// assert_eq!((outcome.rule, outcome.population), (Rule::life(), 3));
+/// ```
+///
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use ::board::Coord;
+use ::engine::Engine;
+use ::rule::Rule;
+
+
+// thumbnails are squares of that many cells on a side
+pub const THUMBNAIL_SIZE: usize = 24;
+
+// runs are cut short when the pattern explodes
+const MAX_POPULATION: usize = 50000;
+
+
+pub struct Outcome {
+    pub rule: Rule,
+    pub population: usize,
+    // generations computed, fewer than asked if the pattern died
+    // out or exploded
+    pub generations: usize,
+    // live cells scaled down to THUMBNAIL_SIZE squared, by rows
+    pub thumbnail: Vec<bool>,
+}
+
+impl Outcome {
+
+    #[inline]
+    pub fn is_extinct(&self) -> bool {
+        self.population == 0
+    }
+
+    #[inline]
+    pub fn is_exploded(&self) -> bool {
+        self.population > MAX_POPULATION
+    }
+
+}
+
+pub struct Explorer {
+    // the given rule first, then its mutations
+    outcomes: Vec<Option<Outcome>>,
+    receiver: Receiver<(usize, Outcome)>,
+    // set when the explorer is dropped, so the threads give up
+    stop: Arc<AtomicBool>,
+}
+
+fn get_thumbnail(engine: &Engine) -> Vec<bool> {
+    let mut thumbnail = vec![false; THUMBNAIL_SIZE * THUMBNAIL_SIZE];
+
+    let board = engine.get_board();
+    let rect = match board.get_bounding_box() {
+        Some(rect) => rect,
+        None => return thumbnail
+    };
+
+    // the pattern is fit keeping its proportions
+    let side = rect.get_width().max(rect.get_height()).max(THUMBNAIL_SIZE);
+    for coord in board.get_occupied() {
+        let col = (coord.col - rect.left) as usize * THUMBNAIL_SIZE / side;
+        let row = (coord.row - rect.top) as usize * THUMBNAIL_SIZE / side;
+        thumbnail[row * THUMBNAIL_SIZE + col] = true;
+    }
+
+    thumbnail
+}
+
+fn run(cells: &[Coord], rule: Rule, generations: usize, stop: &AtomicBool) -> Outcome {
+    let mut engine = Engine::new(None, None);
+    engine.set_rule(rule);
+    for cell in cells {
+        engine.get_board_mut().born_at(cell.col, cell.row);
+    }
+
+    let mut done = 0;
+    while done < generations && !stop.load(Ordering::Relaxed) {
+        let population = engine.get_board().get_population();
+        if population == 0 || population > MAX_POPULATION {
+            break;
+        }
+
+        engine.one_iteration();
+        done += 1;
+    }
+
+    Outcome {
+        rule: rule,
+        population: engine.get_board().get_population(),
+        generations: done,
+        thumbnail: get_thumbnail(&engine),
+    }
+}
+
+impl Explorer {
+
+    pub fn start(cells: &[Coord], rule: &Rule, generations: usize) -> Self {
+        let mut rules = vec![*rule];
+        rules.extend(rule.get_mutations());
+
+        let (sender, receiver): (Sender<(usize, Outcome)>, Receiver<(usize, Outcome)>) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        for (idx, &rule) in rules.iter().enumerate() {
+            let (cells, sender, stop) = (cells.to_vec(), sender.clone(), stop.clone());
+
+            thread::spawn(move || {
+                let outcome = run(&cells, rule, generations, &stop);
+                // the explorer may be gone already
+                let _ = sender.send((idx, outcome));
+            });
+        }
+
+        Explorer {
+            outcomes: rules.iter().map(|_| None).collect(),
+            receiver: receiver,
+            stop: stop,
+        }
+    }
+
+    /// Takes the outcomes finished so far without waiting, returns
+    /// whether there were any
+    pub fn poll(&mut self) -> bool {
+        let mut received = false;
+
+        while let Ok((idx, outcome)) = self.receiver.try_recv() {
+            self.outcomes[idx] = Some(outcome);
+            received = true;
+        }
+
+        received
+    }
+
+    /// Waits for all the outcomes
+    pub fn wait(&mut self) {
+        while !self.is_finished() {
+            match self.receiver.recv() {
+                Ok((idx, outcome)) => self.outcomes[idx] = Some(outcome),
+                Err(_) => break
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.is_some())
+    }
+
+    pub fn get_outcomes(&self) -> &[Option<Outcome>] {
+        &self.outcomes
+    }
+
+}
+
+impl Drop for Explorer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+
+#[test]
+fn test_explore_rules() {
+    // r-pentomino
+    let cells: Vec<Coord> = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)].iter()
+        .map(|&(col, row)| Coord { col: col, row: row })
+        .collect();
+
+    let mut explorer = Explorer::start(&cells, &Rule::life(), 30);
+    explorer.wait();
+    assert!(explorer.is_finished());
+    assert!(!explorer.poll());
+
+    let outcomes: Vec<&Outcome> = explorer.get_outcomes().iter().map(|outcome| outcome.as_ref().unwrap()).collect();
+    assert_eq!(outcomes.len(), 18);
+
+    // the same pattern under the same rule as on a board of its own
+    let mut engine = Engine::new(None, None);
+    for cell in cells.iter() {
+        engine.get_board_mut().born_at(cell.col, cell.row);
+    }
+    engine.iterations(30);
+    assert_eq!(outcomes[0].population, engine.get_board().get_population());
+    assert_eq!(outcomes[0].generations, 30);
+    assert_eq!(outcomes[0].thumbnail, get_thumbnail(&engine));
+
+    // without births of three nothing new appears and it dies out
+    let no_b3 = outcomes.iter().find(|outcome| outcome.rule.to_string() == "B/S23").unwrap();
+    assert!(no_b3.is_extinct() && no_b3.generations < 30);
+    assert!(no_b3.thumbnail.iter().all(|&cell| !cell));
+}
@@ -0,0 +1,308 @@
+/// Population history of a running game. The most recent generations
+/// are kept at full resolution in a ring buffer, older ones are folded
+/// into buckets holding min/max/mean values. Once there are too many
+/// buckets neighbouring pairs are merged, so memory stays bounded while
+/// the history still covers the whole run.
+///
+/// Along with population every generation has the number of cells born
+/// or died on the way to it, a measure of how active the pattern is,
+/// downsampled to its mean.
+///
+/// Apart from population the history keeps events that happened during
+/// the run (like replacing the whole board or changing the rule), so the
+/// graph stays interpretable for long sessions.
+///
+/// Example usage:
+/// ```
+/// # use engine::history::{History, EventKind};
+/// let mut history = History::new(1000, 500);
+/// history.record(0, 5, 0);
+/// history.record(1, 7, 4);
+/// history.record_event(1, EventKind::BoardReplaced);
+/// ```
+///
+
+use std::collections::VecDeque;
+
+const DEFAULT_RECENT_CAPACITY: usize = 4096;
+const DEFAULT_BUCKETS_CAPACITY: usize = 1024;
+const EVENTS_CAPACITY: usize = 1024;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bucket {
+    pub first_gen: usize,
+    pub last_gen: usize,
+
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+
+    // mean number of cells changed per generation
+    pub changed: f64,
+
+    // number of samples folded into this bucket
+    pub samples: usize,
+}
+
+impl Bucket {
+
+    fn from_sample(gen: usize, population: usize, changed: usize) -> Self {
+        Bucket {
+            first_gen: gen,
+            last_gen: gen,
+            min: population,
+            max: population,
+            mean: population as f64,
+            changed: changed as f64,
+            samples: 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Bucket) {
+        let total = self.samples + other.samples;
+
+        self.mean = (self.mean * self.samples as f64 + other.mean * other.samples as f64) /
+            total as f64;
+        self.changed = (self.changed * self.samples as f64 + other.changed * other.samples as f64) /
+            total as f64;
+
+        self.first_gen = self.first_gen.min(other.first_gen);
+        self.last_gen = self.last_gen.max(other.last_gen);
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.samples = total;
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EventKind {
+    // board was replaced as a whole, e.g. filled with a random configuration
+    BoardReplaced,
+    // another rule or rule table, or zones added or removed
+    RuleChanged,
+    // generations per second, either updates per second or generations per update
+    SpeedChanged,
+    // edges of a bounded board wrap or not
+    TopologyChanged,
+    // cells moved onto another kind of board
+    BackendChanged,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HistoryEvent {
+    pub gen: usize,
+    pub kind: EventKind,
+}
+
+pub struct History {
+    // full resolution samples: (generation, population, changed cells)
+    recent: VecDeque<(usize, usize, usize)>,
+    recent_capacity: usize,
+
+    // downsampled part, oldest bucket goes first
+    buckets: VecDeque<Bucket>,
+    buckets_capacity: usize,
+
+    // bucket being filled with samples evicted from the recent buffer
+    pending: Option<Bucket>,
+    // how many samples each bucket holds at the current resolution
+    bucket_span: usize,
+
+    events: VecDeque<HistoryEvent>,
+}
+
+impl History {
+
+    pub fn new(recent_capacity: usize, buckets_capacity: usize) -> Self {
+        assert!(recent_capacity > 0 && buckets_capacity > 1);
+
+        History {
+            recent: VecDeque::with_capacity(recent_capacity),
+            recent_capacity: recent_capacity,
+
+            buckets: VecDeque::new(),
+            buckets_capacity: buckets_capacity,
+
+            pending: None,
+            bucket_span: 1,
+
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        self.buckets.clear();
+        self.pending = None;
+        self.bucket_span = 1;
+        self.events.clear();
+    }
+
+    pub fn record(&mut self, gen: usize, population: usize, changed: usize) {
+        if self.recent.len() == self.recent_capacity {
+            if let Some((old_gen, old_population, old_changed)) = self.recent.pop_front() {
+                self.downsample(Bucket::from_sample(old_gen, old_population, old_changed));
+            }
+        }
+        self.recent.push_back((gen, population, changed));
+    }
+
+    pub fn record_event(&mut self, gen: usize, kind: EventKind) {
+        // events are rare, so just forget the oldest ones instead of downsampling
+        if self.events.len() == EVENTS_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(HistoryEvent { gen: gen, kind: kind });
+    }
+
+    pub fn get_events(&self) -> &VecDeque<HistoryEvent> {
+        &self.events
+    }
+
+    fn downsample(&mut self, sample: Bucket) {
+        let bucket = match self.pending.take() {
+            Some(mut bucket) => {
+                bucket.merge(&sample);
+                bucket
+            }
+            None => sample
+        };
+
+        if bucket.samples < self.bucket_span {
+            self.pending = Some(bucket);
+            return;
+        }
+
+        if self.buckets.len() == self.buckets_capacity {
+            self.halve_resolution();
+            // current bucket is now only a half of the new span, keep filling it
+            if bucket.samples < self.bucket_span {
+                self.pending = Some(bucket);
+                return;
+            }
+        }
+
+        self.buckets.push_back(bucket);
+    }
+
+    fn halve_resolution(&mut self) {
+        // merge neighbouring buckets pairwise, the odd one (if any) stays as is
+        let mut merged = VecDeque::with_capacity(self.buckets_capacity);
+
+        while let Some(mut first) = self.buckets.pop_front() {
+            if let Some(second) = self.buckets.pop_front() {
+                first.merge(&second);
+            }
+            merged.push_back(first);
+        }
+
+        self.buckets = merged;
+        self.bucket_span *= 2;
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        // number of data points available, both downsampled and full resolution ones
+        self.buckets.len() + self.pending.iter().count() + self.recent.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_last(&self) -> Option<(usize, usize)> {
+        self.recent.back().map(|&(gen, population, _)| (gen, population))
+    }
+
+    /// Cells born or died in the last recorded generation
+    pub fn get_last_changed(&self) -> Option<usize> {
+        self.recent.back().map(|&(_, _, changed)| changed)
+    }
+
+    pub fn get_bucket_span(&self) -> usize {
+        self.bucket_span
+    }
+
+    pub fn get_buckets(&self) -> Vec<Bucket> {
+        // all the history in chronological order, full resolution samples
+        // are returned as buckets containing exactly one sample
+        let mut res: Vec<Bucket> = self.buckets.iter().cloned().collect();
+
+        if let Some(pending) = self.pending {
+            res.push(pending);
+        }
+
+        res.extend(self.recent.iter().map(|&(gen, population, changed)| Bucket::from_sample(gen, population, changed)));
+
+        res
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_RECENT_CAPACITY, DEFAULT_BUCKETS_CAPACITY)
+    }
+}
+
+
+#[test]
+fn test_history_full_resolution() {
+    let mut history = History::new(10, 4);
+
+    for gen in 0..5 {
+        history.record(gen, gen * 2, gen);
+    }
+
+    let buckets = history.get_buckets();
+
+    assert_eq!(buckets.len(), 5);
+    assert_eq!(buckets[3], Bucket::from_sample(3, 6, 3));
+    assert_eq!(history.get_last(), Some((4, 8)));
+    assert_eq!(history.get_last_changed(), Some(4));
+}
+
+#[test]
+fn test_history_downsampling() {
+    let mut history = History::new(4, 4);
+
+    for gen in 0..1000 {
+        history.record(gen, gen, 2 * gen);
+    }
+
+    let buckets = history.get_buckets();
+
+    // memory stays bounded
+    assert!(buckets.len() <= 4 + 4 + 1);
+
+    // nothing is lost and the order is preserved
+    assert_eq!(buckets[0].first_gen, 0);
+    assert_eq!(buckets[buckets.len() - 1].last_gen, 999);
+    assert_eq!(buckets.iter().map(|b| b.samples).sum::<usize>(), 1000);
+
+    for pair in buckets.windows(2) {
+        assert_eq!(pair[0].last_gen + 1, pair[1].first_gen);
+    }
+
+    // min/max/mean are aggregated properly
+    let first = buckets[0];
+    assert_eq!(first.min, first.first_gen);
+    assert_eq!(first.max, first.last_gen);
+    assert_eq!(first.mean, (first.first_gen + first.last_gen) as f64 / 2.0);
+    assert_eq!(first.changed, 2.0 * first.mean);
+}
+
+#[test]
+fn test_history_events() {
+    let mut history = History::new(4, 4);
+
+    history.record(0, 1, 0);
+    history.record_event(0, EventKind::BoardReplaced);
+
+    assert_eq!(history.get_events().len(), 1);
+    assert_eq!(history.get_events()[0], HistoryEvent { gen: 0, kind: EventKind::BoardReplaced });
+
+    history.clear();
+    assert!(history.get_events().is_empty());
+}
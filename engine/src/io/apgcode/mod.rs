@@ -0,0 +1,109 @@
+/// Object codes used by Catagolue, e.g. `xq4_153` for a glider. The part
+/// after the prefix is extended Wechsler format: the pattern is cut into
+/// strips 5 cells high, every strip is a run of columns, each column is
+/// a base 32 digit whose bits are its cells from top to bottom.
+/// Runs of empty columns are shortened: `w` is 2 of them, `x` is 3 and
+/// `y` followed by a digit is 4 and more, `z` starts the next strip:
+/// ```
+/// # use engine::io::apgcode::decode;
+/// assert_eq!(decode("xq4_153").unwrap().len(), 5);
+/// assert_eq!(decode("xp15_4r4z4r4").unwrap().len(), 12);
+/// ```
+///
+
+use std::io;
+
+use ::board::Coord;
+use super::invalid_data;
+
+const STRIP_HEIGHT: i64 = 5;
+
+
+/// Still lifes start with `xs`, oscillators with `xp`, spaceships with `xq`
+pub fn is_apgcode(code: &str) -> bool {
+    let mut parts = code.splitn(2, '_');
+
+    match (parts.next(), parts.next()) {
+        (Some(prefix), Some(cells)) => {
+            (prefix.starts_with("xs") || prefix.starts_with("xp") || prefix.starts_with("xq")) &&
+                prefix[2..].chars().all(|ch| ch.is_digit(10)) &&
+                !cells.is_empty() && cells.chars().all(|ch| ch.is_digit(36))
+        }
+        _ => false
+    }
+}
+
+pub fn decode(code: &str) -> io::Result<Vec<Coord>> {
+    if !is_apgcode(code) {
+        return invalid_data(&format!("'{}' is not an object code", code));
+    }
+
+    let cells = code.splitn(2, '_').nth(1).unwrap_or("");
+
+    let mut res = Vec::new();
+    let (mut col, mut strip) = (0, 0);
+    let mut chars = cells.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            'w' => col += 2,
+            'x' => col += 3,
+            'y' => match chars.next().and_then(|ch| ch.to_digit(36)) {
+                Some(n) => col += 4 + n as i64,
+                None => return invalid_data("object code ends with 'y'")
+            },
+            'z' => {
+                col = 0;
+                strip += 1;
+            }
+            _ => {
+                // digits up to 'v' are 5 bit columns
+                let bits = ch.to_digit(32).unwrap_or(0);
+                for bit in 0..STRIP_HEIGHT {
+                    if bits & (1 << bit) != 0 {
+                        res.push(Coord { col: col, row: strip * STRIP_HEIGHT + bit });
+                    }
+                }
+                col += 1;
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+
+#[cfg(test)]
+fn decode_sorted(code: &str) -> Vec<Coord> {
+    let mut cells = decode(code).unwrap();
+    cells.sort();
+    cells
+}
+
+#[test]
+fn test_apgcode_decode() {
+    let cell = |col, row| Coord { col: col, row: row };
+
+    assert_eq!(decode_sorted("xs4_33"), vec![cell(0, 0), cell(0, 1), cell(1, 0), cell(1, 1)]);
+    assert_eq!(decode_sorted("xq4_153"), vec![cell(0, 0), cell(1, 0), cell(1, 2), cell(2, 0), cell(2, 1)]);
+
+    // runs of empty columns
+    assert_eq!(decode_sorted("xs2_1w1"), vec![cell(0, 0), cell(3, 0)]);
+    assert_eq!(decode_sorted("xs2_1x1"), vec![cell(0, 0), cell(4, 0)]);
+    assert_eq!(decode_sorted("xs2_1ya1"), vec![cell(0, 0), cell(15, 0)]);
+
+    // second strip
+    assert_eq!(decode_sorted("xs2_1z1"), vec![cell(0, 0), cell(0, 5)]);
+}
+
+#[test]
+fn test_apgcode_errors() {
+    assert!(!is_apgcode("glider"));
+    assert!(!is_apgcode("xs4_"));
+    assert!(!is_apgcode("xr4_33"));
+    assert!(!is_apgcode("xs4_3-3"));
+    assert!(is_apgcode("xp2_7"));
+
+    assert!(decode("xs4_33y").is_err());
+    assert!(decode("https://example.com").is_err());
+}
@@ -0,0 +1,145 @@
+/// Zip archives of patterns, like the collections distributed with
+/// Golly or downloaded from LifeWiki. Entries are read directly from
+/// the archive, nothing is unpacked to disk. An entry is named by the
+/// path of the archive followed by its name in it, e.g.
+/// `patterns.zip/Oscillators/pulsar.rle`, which `read_pattern` reads.
+///
+
+extern crate zip;
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use super::compression;
+
+const PATTERN_EXTENSIONS: [&'static str; 5] = ["rle", "cells", "lif", "life", "mc"];
+
+
+pub fn is_pattern_file(name: &str) -> bool {
+    // compressed patterns are recognized by their inner extension
+    let name = name.to_lowercase();
+    let name = name.trim_right_matches(".gz");
+
+    match name.rfind('.') {
+        Some(pos) => PATTERN_EXTENSIONS.contains(&&name[pos + 1..]),
+        None => false
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.to_lowercase() == "zip")
+}
+
+/// Archive and the name of the entry in it, if the path goes through an archive
+pub fn split_path(path: &Path) -> Option<(PathBuf, String)> {
+    for archive in path.ancestors().skip(1) {
+        if is_archive(archive) && archive.is_file() {
+            // names in zip archives are always separated by slashes
+            let entry: Vec<String> = path.strip_prefix(archive).ok()?.components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            return Some((archive.to_path_buf(), entry.join("/")));
+        }
+    }
+
+    None
+}
+
+pub struct Archive<R: Read + Seek> {
+    zip: zip::ZipArchive<R>,
+}
+
+impl Archive<File> {
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Archive::new(File::open(path)?)
+    }
+
+}
+
+impl<R: Read + Seek> Archive<R> {
+
+    pub fn new(reader: R) -> io::Result<Self> {
+        Ok(Archive { zip: zip::ZipArchive::new(reader)? })
+    }
+
+    pub fn get_entries(&mut self) -> Vec<String> {
+        // names of pattern files, directories and other files are skipped
+        let mut res = Vec::new();
+
+        for idx in 0..self.zip.len() {
+            if let Ok(entry) = self.zip.by_index(idx) {
+                if is_pattern_file(entry.name()) {
+                    res.push(entry.name().to_string());
+                }
+            }
+        }
+
+        res.sort();
+        res
+    }
+
+    pub fn read_entry(&mut self, name: &str) -> io::Result<String> {
+        let data = compression::read_all(&mut self.zip.by_name(name)?)?;
+
+        String::from_utf8(data)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "entry is not a text file"))
+    }
+
+}
+
+
+#[test]
+fn test_is_pattern_file() {
+    assert!(is_pattern_file("Oscillators/pulsar.rle"));
+    assert!(is_pattern_file("GLIDER.CELLS"));
+    assert!(is_pattern_file("spaceships/big.mc.gz"));
+
+    assert!(!is_pattern_file("Oscillators/"));
+    assert!(!is_pattern_file("readme.txt"));
+    assert!(!is_pattern_file("rle"));
+}
+
+#[cfg(test)]
+use std::env;
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::io::Write;
+
+#[test]
+fn test_archive_entries() {
+    let dir = env::temp_dir().join("life-rs-archive-test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("Patterns.ZIP");
+    {
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("Oscillators/blinker.rle", options).unwrap();
+        zip.write_all(b"#N Blinker\nx = 3, y = 1\n3o!\n").unwrap();
+        zip.start_file("readme.txt", options).unwrap();
+        zip.finish().unwrap();
+    }
+
+    let mut archive = Archive::open(&path).unwrap();
+    assert_eq!(archive.get_entries(), vec!["Oscillators/blinker.rle"]);
+    assert!(archive.read_entry("Oscillators/blinker.rle").unwrap().starts_with("#N Blinker"));
+
+    // entries are read as pattern files are
+    let entry = path.join("Oscillators").join("blinker.rle");
+    assert_eq!(split_path(&entry), Some((path.clone(), "Oscillators/blinker.rle".to_string())));
+    let (cells, _, meta) = super::read_pattern(&entry).unwrap();
+    assert_eq!((cells.len(), meta.name), (3, Some("Blinker".to_string())));
+
+    assert!(is_archive(&path));
+    assert_eq!(split_path(&path), None);
+    assert_eq!(split_path(&dir.join("other.zip/blinker.rle")), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
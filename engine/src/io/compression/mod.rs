@@ -0,0 +1,185 @@
+/// Transparent gzip compression of saved files. Compressed data is
+/// detected by the gzip magic number, so readers don't need to know
+/// in advance whether a file was compressed. Writers choose compression
+/// on their own, e.g. by the `.gz` extension of the file name.
+///
+/// Compression support depends on the `compression` feature, without
+/// it compressed files can be neither written nor read.
+///
+
+#[cfg(feature = "compression")]
+extern crate flate2;
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write, BufRead, BufReader, BufWriter};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+#[cfg(not(feature = "compression"))]
+use super::invalid_data;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const GZIP_EXTENSION: &'static str = "gz";
+
+
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.len() >= GZIP_MAGIC.len() && data[..GZIP_MAGIC.len()] == GZIP_MAGIC
+}
+
+/// Whether a file is meant to be compressed by its name, e.g. `glider.rle.gz`
+pub fn is_compressed_path(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == GZIP_EXTENSION)
+}
+
+/// Appends `.gz` to the file name, unless it's already there
+pub fn with_gz_extension(path: &Path) -> PathBuf {
+    if is_compressed_path(path) {
+        return path.to_path_buf();
+    }
+
+    let mut name = path.file_name().map_or(OsString::new(), |name| name.to_os_string());
+    name.push(".");
+    name.push(GZIP_EXTENSION);
+    path.with_file_name(name)
+}
+
+#[cfg(feature = "compression")]
+pub fn compress(data: &[u8], writer: &mut Write) -> io::Result<()> {
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::Default);
+    encoder.write_all(data)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn compress(_data: &[u8], _writer: &mut Write) -> io::Result<()> {
+    invalid_data("compression support is not enabled")
+}
+
+/// Bare zlib stream, as used inside PNG images
+#[cfg(feature = "compression")]
+pub fn zlib_compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::Default);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn zlib_compress(_data: &[u8]) -> io::Result<Vec<u8>> {
+    invalid_data("compression support is not enabled")
+}
+
+#[cfg(feature = "compression")]
+fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut res = Vec::new();
+    flate2::read::GzDecoder::new(data)?.read_to_end(&mut res)?;
+    Ok(res)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_data: &[u8]) -> io::Result<Vec<u8>> {
+    invalid_data("file is compressed, but compression support is not enabled")
+}
+
+#[cfg(feature = "compression")]
+fn decoder<R: Read + 'static>(reader: R) -> io::Result<Box<BufRead>> {
+    Ok(Box::new(BufReader::new(flate2::read::GzDecoder::new(reader)?)))
+}
+
+#[cfg(not(feature = "compression"))]
+fn decoder<R: Read + 'static>(_reader: R) -> io::Result<Box<BufRead>> {
+    invalid_data("file is compressed, but compression support is not enabled")
+}
+
+/// Opens the file for reading, decompressing data as it's read if
+/// needed, so the file is never loaded into memory at once
+pub fn open(path: &Path) -> io::Result<Box<BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    if is_compressed(reader.fill_buf()?) {
+        decoder(reader)
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+pub fn read_all(reader: &mut Read) -> io::Result<Vec<u8>> {
+    // read everything, decompressing data if needed
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    if is_compressed(&data) {
+        decompress(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+pub fn write_all(data: &[u8], writer: &mut Write, compressed: bool) -> io::Result<()> {
+    if compressed {
+        compress(data, writer)
+    } else {
+        writer.write_all(data)
+    }
+}
+
+#[cfg(feature = "compression")]
+fn save_compressed<F>(path: &Path, write: F) -> io::Result<()>
+    where F: FnOnce(&mut Write) -> io::Result<()> {
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::Default);
+    write(&mut encoder)?;
+    encoder.finish()?.flush()
+}
+
+#[cfg(not(feature = "compression"))]
+fn save_compressed<F>(_path: &Path, _write: F) -> io::Result<()>
+    where F: FnOnce(&mut Write) -> io::Result<()> {
+
+    invalid_data("compression support is not enabled")
+}
+
+/// Creates the file and lets `write` fill it, the data is written,
+/// and compressed if asked, as it comes
+pub fn save_with<F>(path: &Path, compressed: bool, write: F) -> io::Result<()>
+    where F: FnOnce(&mut Write) -> io::Result<()> {
+
+    if compressed {
+        return save_compressed(path, write);
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    write(&mut file)?;
+    file.flush()
+}
+
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_compression_round_trip() {
+    let data = "bo$2bo$3o!".repeat(100);
+
+    let mut compressed = Vec::new();
+    write_all(data.as_bytes(), &mut compressed, true).unwrap();
+
+    assert!(is_compressed(&compressed));
+    assert_eq!(read_all(&mut &compressed[..]).unwrap(), data.as_bytes());
+}
+
+#[test]
+fn test_compressed_path() {
+    assert!(is_compressed_path(Path::new("soup.rle.gz")));
+    assert!(!is_compressed_path(Path::new("soup.rle")));
+    assert!(!is_compressed_path(Path::new("gz")));
+
+    assert_eq!(with_gz_extension(Path::new("dir/soup.rle")), PathBuf::from("dir/soup.rle.gz"));
+    assert_eq!(with_gz_extension(Path::new("dir/soup.rle.gz")), PathBuf::from("dir/soup.rle.gz"));
+}
+
+#[test]
+fn test_plain_data_passes_through() {
+    let data = b"life-rs 1.0\n";
+    assert_eq!(read_all(&mut &data[..]).unwrap(), data.to_vec());
+}
@@ -0,0 +1,629 @@
+/// Native save format. It is a text file which starts with a header
+/// holding format version, followed by `key = value` metadata lines
+/// and sections. Each section starts with `[name]` line, the only
+/// mandatory section is `[cells]` containing RLE encoded live cells.
+///
+/// ```text
+/// life-rs 1.0
+/// name = Glider
+/// left = -1
+/// top = -1
+///
+/// [cells]
+/// bo$2bo$3o!
+/// ```
+///
+/// Forward compatibility rules:
+/// * incompatible changes bump the major version, files of unknown
+///   major version are rejected;
+/// * compatible extensions bump the minor version, unknown metadata keys
+///   and sections are not an error and are kept as is, so they survive
+///   loading and saving by an older version of the program.
+///
+/// Since version 1.1 the file holds a `checksum` of all its other lines,
+/// so corrupted files are rejected instead of producing a garbled board.
+/// Files written by 1.0 are never verified, older versions copy unknown
+/// keys as is and would leave a stale checksum there.
+///
+/// Since version 1.2 the file tells which version of the program wrote
+/// it, `written_by = life-rs 0.1.0`. Files of older versions are brought
+/// up to date on load by migrations, one for every minor version which
+/// changed the contents, so the rest of the program sees only the
+/// current layout.
+///
+/// Since version 1.3 lines of sections which would read as a section
+/// header or a checksum, or start with a backslash, are written with a
+/// backslash in front, and metadata values have backslashes and line
+/// breaks escaped as `\\`, `\n` and `\r`. Comments like `[cells]`
+/// stay comments that way.
+///
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Read, Write, BufRead, BufReader};
+use std::path::Path;
+
+use ::board::{Board, Coord};
+use super::invalid_data;
+use super::rle::{Encoder, Decoder};
+use super::compression;
+
+pub const FORMAT_MAJOR: u32 = 1;
+pub const FORMAT_MINOR: u32 = 3;
+
+pub const PROGRAM_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+const SIGNATURE: &'static str = "life-rs";
+const CELLS_SECTION: &'static str = "cells";
+const CHECKSUM_KEY: &'static str = "checksum";
+const WRITTEN_BY_KEY: &'static str = "written_by";
+
+// first version which stores checksums
+const CHECKSUM_MINOR: u32 = 1;
+// first version which escapes section lines and metadata values
+const ESCAPED_MINOR: u32 = 3;
+
+const MISMATCH_ERROR: &'static str = "checksum mismatch, file is corrupted";
+const MISSING_ERROR: &'static str = "checksum is missing";
+
+
+// 64 bit FNV-1a over lines, line endings don't matter. Text written
+// into it is hashed the same way as its lines are, so the checksum of
+// a file is known without keeping the file in memory
+struct Checksum {
+    hash: u64,
+    // carriage return is left out if it ends a line
+    pending_cr: bool,
+}
+
+impl Checksum {
+
+    fn new() -> Self {
+        Checksum { hash: 0xcbf29ce484222325, pending_cr: false }
+    }
+
+    fn add_byte(&mut self, byte: u8) {
+        self.hash ^= byte as u64;
+        self.hash = self.hash.wrapping_mul(0x100000001b3);
+    }
+
+    fn add_line(&mut self, line: &str) {
+        for byte in line.bytes().chain(Some(b'\n')) {
+            self.add_byte(byte);
+        }
+    }
+
+}
+
+impl Write for Checksum {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            if self.pending_cr && byte != b'\n' {
+                self.add_byte(b'\r');
+            }
+            self.pending_cr = byte == b'\r';
+            if !self.pending_cr {
+                self.add_byte(byte);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// minor version a migration applies to and the migration itself,
+// which turns the contents into those of the next minor version
+const MIGRATIONS: [(u32, fn(&mut Container)); 1] = [
+    (1, migrate_written_by),
+];
+
+fn migrate_written_by(container: &mut Container) {
+    // older files don't tell which version wrote them
+    container.set_meta(WRITTEN_BY_KEY, "unknown");
+}
+
+fn is_section_header(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('[') && line.ends_with(']')
+}
+
+// section lines are kept as they are unless they would be taken for
+// something else
+fn escape_line(line: &str) -> String {
+    if line.starts_with('\\') || is_section_header(line) || is_checksum_line(line) {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+fn unescape_line(line: &str) -> &str {
+    if line.starts_with('\\') { &line[1..] } else { line }
+}
+
+fn escape_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut res = String::new();
+    let mut chars = value.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            res.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some(other) => res.push(other),
+            None => res.push('\\')
+        }
+    }
+
+    res
+}
+
+fn is_checksum_line(line: &str) -> bool {
+    let mut parts = line.splitn(2, '=');
+    parts.next().map(|key| key.trim()) == Some(CHECKSUM_KEY) && parts.next().is_some()
+}
+
+
+pub struct Container {
+    pub version: (u32, u32),
+
+    pub meta: BTreeMap<String, String>,
+
+    // absolute coordinates of live cells
+    pub cells: Vec<Coord>,
+
+    // optional sections (history, annotations...) in their textual form
+    pub sections: BTreeMap<String, String>,
+}
+
+impl Container {
+
+    pub fn new() -> Self {
+        Container {
+            version: (FORMAT_MAJOR, FORMAT_MINOR),
+            meta: BTreeMap::new(),
+            cells: Vec::new(),
+            sections: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(|v| v.as_str())
+    }
+
+    pub fn set_meta<T: ToString>(&mut self, key: &str, value: T) {
+        self.meta.insert(key.to_string(), value.to_string());
+    }
+
+    fn parse_version(header: &str) -> io::Result<(u32, u32)> {
+        let mut parts = header.split_whitespace();
+
+        if parts.next() != Some(SIGNATURE) {
+            return invalid_data("not a life-rs file");
+        }
+
+        let version: Vec<u32> = match parts.next() {
+            Some(version) => version.split('.').filter_map(|v| v.parse().ok()).collect(),
+            None => return invalid_data("format version is missing")
+        };
+
+        if version.len() != 2 {
+            return invalid_data("malformed format version");
+        }
+
+        if version[0] != FORMAT_MAJOR {
+            return invalid_data(&format!("unsupported format version {}.{}", version[0], version[1]));
+        }
+
+        Ok((version[0], version[1]))
+    }
+
+    /// Version of the program which wrote the file, `unknown` for old files
+    pub fn get_written_by(&self) -> Option<&str> {
+        self.get_meta(WRITTEN_BY_KEY)
+    }
+
+    /// Whether reading failed because the checksum doesn't match the
+    /// file or is missing from it, i.e. the file has been damaged
+    pub fn is_corrupted(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::InvalidData &&
+            err.get_ref().map_or(false, |err| {
+                let msg = err.to_string();
+                msg == MISMATCH_ERROR || msg == MISSING_ERROR
+            })
+    }
+
+    fn migrate(&mut self) {
+        // version stays as it was in the file, newer files need no migrations
+        for &(minor, migration) in MIGRATIONS.iter() {
+            if self.version.1 <= minor {
+                migration(self);
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Container> {
+        // compressed files are detected automatically
+        Container::read(&mut compression::open(path)?)
+    }
+
+    /// Reads only the header and the metadata, to tell files apart without
+    /// decoding their cells; the checksum isn't verified then
+    pub fn load_meta(path: &Path) -> io::Result<Container> {
+        let mut reader = compression::open(path)?;
+        let mut line = String::new();
+
+        let mut container = Container::new();
+        reader.read_line(&mut line)?;
+        container.version = Container::parse_version(line.trim_right_matches(|ch| ch == '\n' || ch == '\r'))?;
+
+        let escaped = container.version.1 >= ESCAPED_MINOR;
+
+        for line in reader.lines() {
+            let line = line?;
+            if is_section_header(&line) {
+                break;
+            }
+
+            let trimmed = line.trim();
+            if let Some(pos) = trimmed.find('=') {
+                let (key, value) = trimmed.split_at(pos);
+                let value = value[1..].trim();
+                container.set_meta(key.trim(), if escaped { unescape_value(value) } else { value.to_string() });
+            }
+        }
+
+        Ok(container)
+    }
+
+    pub fn save(&self, path: &Path, compressed: bool) -> io::Result<()> {
+        compression::save_with(path, compressed, |writer| self.write(writer))
+    }
+
+    /// Same as `save`, with the live cells of the board, see `write_board`
+    pub fn save_board(&self, board: &Board, path: &Path, compressed: bool) -> io::Result<()> {
+        compression::save_with(path, compressed, |writer| self.write_board(board, writer))
+    }
+
+    /// Reads the file line by line, cells are decoded as their lines
+    /// come, the checksum is verified once the whole file is read
+    pub fn read(reader: &mut Read) -> io::Result<Container> {
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+
+        let mut container = Container::new();
+        reader.read_line(&mut line)?;
+        let header = line.trim_right_matches(|ch| ch == '\n' || ch == '\r').to_string();
+        container.version = Container::parse_version(&header)?;
+
+        let escaped = container.version.1 >= ESCAPED_MINOR;
+
+        let mut sum = Checksum::new();
+        sum.add_line(&header);
+        // value of the first checksum line, if it's readable
+        let mut stored: Option<Option<u64>> = None;
+        // the file may be corrupted, which is the error to report then
+        let mut error: Option<io::Error> = None;
+
+        let mut section: Option<String> = None;
+        let mut sections: BTreeMap<String, String> = BTreeMap::new();
+        let mut decoder: Option<Decoder> = None;
+        let mut cells = Vec::new();
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            let line = line.trim_right_matches('\n');
+            let line = line.trim_right_matches('\r');
+
+            if is_checksum_line(line) {
+                if stored.is_none() {
+                    stored = Some(line.splitn(2, '=').nth(1).and_then(|value| u64::from_str_radix(value.trim(), 16).ok()));
+                }
+            } else {
+                sum.add_line(line);
+            }
+
+            if error.is_some() {
+                continue;
+            }
+
+            let trimmed = line.trim();
+
+            if is_section_header(line) {
+                let name = trimmed[1..trimmed.len() - 1].trim().to_string();
+                if name == CELLS_SECTION {
+                    cells.clear();
+                    decoder = Some(Decoder::new());
+                } else {
+                    sections.insert(name.clone(), String::new());
+                }
+                section = Some(name);
+                continue;
+            }
+
+            match section {
+                Some(ref name) if name == CELLS_SECTION => {
+                    if let Some(ref mut decoder) = decoder {
+                        if let Err(err) = decoder.feed(line, |cell| cells.push(cell)) {
+                            error = Some(err);
+                        }
+                    }
+                }
+                Some(ref name) => {
+                    if let Some(contents) = sections.get_mut(name) {
+                        contents.push_str(if escaped { unescape_line(line) } else { line });
+                        contents.push('\n');
+                    }
+                }
+                None => {
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    match trimmed.find('=') {
+                        Some(pos) => {
+                            let (key, value) = trimmed.split_at(pos);
+                            if key.trim() != CHECKSUM_KEY {
+                                let value = value[1..].trim();
+                                if escaped {
+                                    container.set_meta(key.trim(), unescape_value(value));
+                                } else {
+                                    container.set_meta(key.trim(), value);
+                                }
+                            }
+                        }
+                        None => error = invalid_data::<()>(&format!("malformed metadata line '{}'", trimmed)).err()
+                    }
+                }
+            }
+        }
+
+        if container.version.1 >= CHECKSUM_MINOR {
+            match stored {
+                Some(Some(stored)) => {
+                    if stored != sum.hash {
+                        return invalid_data(MISMATCH_ERROR);
+                    }
+                }
+                _ => return invalid_data(MISSING_ERROR)
+            }
+        }
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        match decoder {
+            Some(decoder) => decoder.finish()?,
+            None => return invalid_data("cells section is missing")
+        }
+
+        let left: i64 = container.get_meta("left").and_then(|v| v.parse().ok()).unwrap_or(0);
+        let top: i64 = container.get_meta("top").and_then(|v| v.parse().ok()).unwrap_or(0);
+
+        for cell in cells.iter_mut() {
+            cell.col += left;
+            cell.row += top;
+        }
+        container.cells = cells;
+
+        // trailing empty lines belong to the file layout, not to the section
+        container.sections = sections.into_iter()
+            .map(|(name, contents)| (name, contents.trim_right().to_string()))
+            .collect();
+
+        container.migrate();
+        Ok(container)
+    }
+
+    // everything after the checksum line, cells given relative to the
+    // top left corner and sorted row by row
+    fn write_body(&self, cells: &[&Coord], left: i64, top: i64, writer: &mut Write) -> io::Result<()> {
+        for (key, value) in &self.meta {
+            if key != "left" && key != "top" && key != CHECKSUM_KEY && key != WRITTEN_BY_KEY {
+                writeln!(writer, "{} = {}", key, escape_value(value))?;
+            }
+        }
+
+        writeln!(writer, "{} = {} {}", WRITTEN_BY_KEY, SIGNATURE, PROGRAM_VERSION)?;
+
+        writeln!(writer, "left = {}", left)?;
+        writeln!(writer, "top = {}", top)?;
+
+        writeln!(writer, "\n[{}]", CELLS_SECTION)?;
+        let mut encoder = Encoder::new(&mut *writer);
+        for cell in cells {
+            encoder.push(Coord { col: cell.col - left, row: cell.row - top })?;
+        }
+        encoder.finish()?.write_all(b"\n")?;
+
+        for (name, contents) in &self.sections {
+            writeln!(writer, "\n[{}]", name)?;
+            for line in contents.lines() {
+                writeln!(writer, "{}", escape_line(line))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_cells<'c, I>(&self, cells: I, writer: &mut Write) -> io::Result<()>
+        where I: Iterator<Item=&'c Coord> {
+
+        // only references are sorted, the cells aren't copied
+        let mut sorted: Vec<&Coord> = cells.collect();
+        sorted.sort_by(|a, b| (a.row, a.col).cmp(&(b.row, b.col)));
+        sorted.dedup();
+
+        let left = sorted.iter().map(|c| c.col).min().unwrap_or(0);
+        let top = sorted.first().map_or(0, |c| c.row);
+
+        // always written with the current version, unknown parts are just copied
+        let header = format!("{} {}.{}", SIGNATURE, FORMAT_MAJOR, FORMAT_MINOR);
+
+        // the checksum line goes right after the header, so the body is
+        // written twice, first just to be hashed
+        let mut sum = Checksum::new();
+        sum.add_line(&header);
+        self.write_body(&sorted, left, top, &mut sum)?;
+
+        writeln!(writer, "{}", header)?;
+        writeln!(writer, "{} = {:016x}", CHECKSUM_KEY, sum.hash)?;
+        self.write_body(&sorted, left, top, writer)
+    }
+
+    pub fn write(&self, writer: &mut Write) -> io::Result<()> {
+        self.write_cells(self.cells.iter(), writer)
+    }
+
+    /// Writes the live cells of the board instead of `cells`, without
+    /// copying them into the container first
+    pub fn write_board(&self, board: &Board, writer: &mut Write) -> io::Result<()> {
+        self.write_cells(board.get_occupied().iter(), writer)
+    }
+}
+
+
+#[cfg(test)]
+fn checksum<'a, I: Iterator<Item=&'a str>>(lines: I) -> u64 {
+    let mut sum = Checksum::new();
+    for line in lines {
+        sum.add_line(line);
+    }
+    sum.hash
+}
+
+#[test]
+fn test_container_round_trip() {
+    let mut container = Container::new();
+
+    container.set_meta("name", "Glider");
+    container.cells = vec![Coord { col: -1, row: 5 }, Coord { col: 0, row: 6 }];
+    container.sections.insert("history".to_string(), "0 2\n1 2".to_string());
+
+    let mut data: Vec<u8> = Vec::new();
+    container.write(&mut data).unwrap();
+
+    let loaded = Container::read(&mut &data[..]).unwrap();
+
+    assert_eq!(loaded.version, (FORMAT_MAJOR, FORMAT_MINOR));
+    assert_eq!(loaded.get_meta("name"), Some("Glider"));
+    assert_eq!(loaded.cells, container.cells);
+    assert_eq!(loaded.sections, container.sections);
+    assert_eq!(loaded.get_written_by(), Some(format!("life-rs {}", PROGRAM_VERSION).as_str()));
+}
+
+#[test]
+fn test_container_migration() {
+    // 1.0 files have neither checksum nor program version
+    let loaded = Container::read(&mut "life-rs 1.0\nname = x\n\n[cells]\n2o!\n".as_bytes()).unwrap();
+
+    assert_eq!(loaded.version, (1, 0));
+    assert_eq!(loaded.get_written_by(), Some("unknown"));
+    assert_eq!(loaded.get_meta("name"), Some("x"));
+
+    // saved again it's stamped with the current version
+    let mut data: Vec<u8> = Vec::new();
+    loaded.write(&mut data).unwrap();
+
+    let text = String::from_utf8(data).unwrap();
+    assert!(text.starts_with(&format!("life-rs {}.{}\n", FORMAT_MAJOR, FORMAT_MINOR)));
+    assert_eq!(text.matches("written_by").count(), 1);
+    assert!(text.contains(&format!("written_by = life-rs {}", PROGRAM_VERSION)));
+
+    // nothing was escaped before 1.3
+    let loaded = Container::read(&mut "life-rs 1.0\nname = a\\nb\n\n[cells]\n2o!\n\n[notes]\n\\x\n".as_bytes()).unwrap();
+    assert_eq!(loaded.get_meta("name"), Some("a\\nb"));
+    assert_eq!(loaded.sections.get("notes").map(|s| s.as_str()), Some("\\x"));
+}
+
+#[test]
+fn test_container_forward_compatibility() {
+    let body = "name = x\nfuture_key = 42\n\n[cells]\n2o!\n\n[layers]\nsomething new\n";
+    let data = format!("life-rs 1.7\nchecksum = {:016x}\n{}",
+                       checksum(Some("life-rs 1.7").into_iter().chain(body.lines())), body);
+
+    let loaded = Container::read(&mut data.as_bytes()).unwrap();
+
+    assert_eq!(loaded.version, (1, 7));
+    assert_eq!(loaded.get_meta("future_key"), Some("42"));
+    assert_eq!(loaded.sections.get("layers").map(|s| s.as_str()), Some("something new"));
+    assert_eq!(loaded.cells.len(), 2);
+
+    assert!(Container::read(&mut "life-rs 2.0\n[cells]\n!".as_bytes()).is_err());
+    assert!(Container::read(&mut "something else".as_bytes()).is_err());
+    assert!(Container::read(&mut "life-rs 1.0\nname = x\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_container_checksum() {
+    let mut container = Container::new();
+    container.cells = vec![Coord { col: 0, row: 0 }, Coord { col: 1, row: 2 }];
+
+    let mut data: Vec<u8> = Vec::new();
+    container.write(&mut data).unwrap();
+
+    let text = String::from_utf8(data).unwrap();
+    assert!(Container::read(&mut text.as_bytes()).is_ok());
+
+    // checksum is not exposed as metadata
+    assert_eq!(Container::read(&mut text.as_bytes()).unwrap().get_meta(CHECKSUM_KEY), None);
+
+    // line endings don't matter
+    let crlf = text.replace("\n", "\r\n");
+    assert!(Container::read(&mut crlf.as_bytes()).is_ok());
+
+    let corrupted = text.replace("bo!", "2o!");
+    assert!(corrupted != text);
+    assert!(Container::is_corrupted(&Container::read(&mut corrupted.as_bytes()).err().unwrap()));
+
+    let missing: String = text.lines().filter(|l| !l.starts_with(CHECKSUM_KEY))
+        .map(|l| format!("{}\n", l)).collect();
+    assert!(Container::is_corrupted(&Container::read(&mut missing.as_bytes()).err().unwrap()));
+
+    // other malformed files aren't reported as corrupted
+    assert!(!Container::is_corrupted(&Container::read(&mut "something else".as_bytes()).err().unwrap()));
+}
+
+#[cfg(test)]
+use ::board::hashed::new as new_hashed;
+
+#[test]
+fn test_container_write_board() {
+    let mut board = Board::new(new_hashed(), None, None);
+    for &(col, row) in [(7, -3), (5, -2), (6, -2), (5, -2)].iter() {
+        board.born_at(col, row);
+    }
+
+    let mut container = Container::new();
+    container.set_meta("name", "L");
+
+    let mut from_board: Vec<u8> = Vec::new();
+    container.write_board(&board, &mut from_board).unwrap();
+
+    // same file whether the cells come from the board or the container
+    container.cells = vec![Coord { col: 5, row: -2 }, Coord { col: 6, row: -2 }, Coord { col: 7, row: -3 }];
+    let mut from_cells: Vec<u8> = Vec::new();
+    container.write(&mut from_cells).unwrap();
+    assert_eq!(from_board, from_cells);
+
+    let mut loaded = Container::read(&mut &from_board[..]).unwrap();
+    loaded.cells.sort_by(|a, b| (a.row, a.col).cmp(&(b.row, b.col)));
+    assert_eq!(loaded.cells, vec![Coord { col: 7, row: -3 }, Coord { col: 5, row: -2 }, Coord { col: 6, row: -2 }]);
+
+    // cells cut off before the end of the pattern
+    assert!(Container::read(&mut "life-rs 1.0\n[cells]\n2o$\n".as_bytes()).is_err());
+}
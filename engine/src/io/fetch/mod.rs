@@ -0,0 +1,168 @@
+/// Patterns referenced on the web: a Catagolue object code, a link to
+/// a Catagolue object or a LifeWiki article, or a direct link to a
+/// pattern file. Object codes are decoded right away, everything else
+/// is downloaded, which needs the `network` feature. Downloads give up
+/// when the server stops sending for `READ_TIMEOUT` seconds or sends
+/// more than `MAX_SIZE` bytes; they block, so interactive callers run
+/// them on a thread of their own.
+///
+/// Example usage:
+/// ```
+/// # use engine::io::fetch::{parse_source, Source};
+/// assert_eq!(parse_source("https://catagolue.hatsya.com/object/xq4_153/b3s23"),
+///            Some(Source::Apgcode("xq4_153".to_string(), Some("B3/S23".to_string()))));
+/// assert_eq!(parse_source("https://conwaylife.com/wiki/Gosper_glider_gun"),
+///            Some(Source::Url("https://conwaylife.com/patterns/gosperglidergun.rle".to_string())));
+/// ```
+///
+
+use std::io;
+#[cfg(feature = "network")]
+use std::io::Read;
+#[cfg(feature = "network")]
+use std::time::Duration;
+
+#[cfg(feature = "network")]
+extern crate hyper;
+
+use ::board::Coord;
+use super::{apgcode, compression, parse_pattern, invalid_data, PatternMeta};
+
+const WIKI_ARTICLE: &'static str = "conwaylife.com/wiki/";
+const WIKI_PATTERNS: &'static str = "https://conwaylife.com/patterns/";
+const CATAGOLUE_OBJECT: &'static str = "/object/";
+
+pub const READ_TIMEOUT: u64 = 20;
+// pattern files, even of huge soups, are much smaller
+pub const MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    // object code and rule
+    Apgcode(String, Option<String>),
+    Url(String),
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Rule as written in Catagolue links, e.g. `b36s23`
+fn parse_catagolue_rule(rule: &str) -> Option<String> {
+    let rule = rule.to_uppercase();
+
+    match rule.find('S') {
+        Some(pos) if rule.starts_with('B') => Some(format!("{}/{}", &rule[..pos], &rule[pos..])),
+        _ => None
+    }
+}
+
+pub fn parse_source(source: &str) -> Option<Source> {
+    let source = source.trim();
+
+    if apgcode::is_apgcode(source) {
+        return Some(Source::Apgcode(source.to_string(), None));
+    }
+
+    if !is_url(source) {
+        return None;
+    }
+
+    if let Some(pos) = source.find(CATAGOLUE_OBJECT) {
+        // .../object/<code>/<rule>
+        let mut parts = source[pos + CATAGOLUE_OBJECT.len()..].split('/');
+        let code = parts.next().unwrap_or("");
+
+        if apgcode::is_apgcode(code) {
+            let rule = parts.next().and_then(parse_catagolue_rule);
+            return Some(Source::Apgcode(code.to_string(), rule));
+        }
+    }
+
+    if let Some(pos) = source.find(WIKI_ARTICLE) {
+        // articles have RLE files named after them in lowercase, without spaces
+        let name: String = source[pos + WIKI_ARTICLE.len()..].chars()
+            .take_while(|&ch| ch != '#' && ch != '?')
+            .filter(|ch| ch.is_alphanumeric())
+            .flat_map(|ch| ch.to_lowercase())
+            .collect();
+
+        if !name.is_empty() {
+            return Some(Source::Url(format!("{}{}.rle", WIKI_PATTERNS, name)));
+        }
+    }
+
+    Some(Source::Url(source.to_string()))
+}
+
+#[cfg(feature = "network")]
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let to_io_error = |err: hyper::Error| io::Error::new(io::ErrorKind::Other, err.to_string());
+
+    let mut client = hyper::Client::new();
+    client.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT)));
+    client.set_write_timeout(Some(Duration::from_secs(READ_TIMEOUT)));
+
+    let response = client.get(url).send().map_err(to_io_error)?;
+    if response.status != hyper::Ok {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("server responded {}", response.status)));
+    }
+
+    // one byte more tells that the limit is exceeded
+    let mut data = Vec::new();
+    response.take(MAX_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_SIZE {
+        return invalid_data(&format!("response is larger than {} MB", MAX_SIZE / 1024 / 1024));
+    }
+    Ok(data)
+}
+
+#[cfg(not(feature = "network"))]
+fn download(_url: &str) -> io::Result<Vec<u8>> {
+    invalid_data("network support is not enabled")
+}
+
+/// Same as `read_pattern`, but the pattern is given by an object code or a link
+pub fn fetch_pattern(source: &str) -> io::Result<(Vec<Coord>, Option<String>, PatternMeta)> {
+    match parse_source(source) {
+        Some(Source::Apgcode(code, rule)) => {
+            let meta = PatternMeta { name: Some(code.clone()), author: None, comments: Vec::new() };
+            Ok((apgcode::decode(&code)?, rule, meta))
+        }
+
+        Some(Source::Url(url)) => {
+            // pattern files are sometimes served gzipped
+            let data = download(&url)?;
+            parse_pattern(&compression::read_all(&mut &data[..])?)
+        }
+
+        None => invalid_data(&format!("'{}' is neither a link nor an object code", source))
+    }
+}
+
+
+#[test]
+fn test_parse_source() {
+    let url = |url: &str| Some(Source::Url(url.to_string()));
+
+    assert_eq!(parse_source("xs4_33"), Some(Source::Apgcode("xs4_33".to_string(), None)));
+    assert_eq!(parse_source("https://catagolue.hatsya.com/object/xp2_7/b36s23"),
+               Some(Source::Apgcode("xp2_7".to_string(), Some("B36/S23".to_string()))));
+
+    assert_eq!(parse_source("https://conwaylife.com/wiki/Glider#Gallery"), url("https://conwaylife.com/patterns/glider.rle"));
+    assert_eq!(parse_source("http://example.com/soup.rle.gz"), url("http://example.com/soup.rle.gz"));
+
+    assert_eq!(parse_source("glider.rle"), None);
+}
+
+#[test]
+fn test_fetch_apgcode() {
+    let (cells, rule, meta) = fetch_pattern("https://catagolue.hatsya.com/object/xq4_153/b3s23").unwrap();
+
+    assert_eq!(cells.len(), 5);
+    assert_eq!(rule, Some("B3/S23".to_string()));
+    assert_eq!(meta.name, Some("xq4_153".to_string()));
+
+    assert!(fetch_pattern("not a pattern").is_err());
+}
@@ -0,0 +1,85 @@
+/// Life 1.06 format, a header line followed by coordinates
+/// of live cells, one `x y` pair per line:
+/// ```text
+/// #Life 1.06
+/// 0 -1
+/// 1 0
+/// -1 1
+/// 0 1
+/// 1 1
+/// ```
+///
+
+use std::io;
+use std::io::{Read, Write};
+
+use ::board::Coord;
+use super::invalid_data;
+
+pub const HEADER: &'static str = "#Life 1.06";
+
+
+pub fn is_life106(data: &str) -> bool {
+    data.trim_left().starts_with(HEADER)
+}
+
+pub fn read(reader: &mut Read) -> io::Result<Vec<Coord>> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+
+    if !is_life106(&contents) {
+        return invalid_data("not a Life 1.06 file");
+    }
+
+    let mut res = Vec::new();
+
+    for line in contents.lines().skip_while(|line| line.trim().is_empty()).skip(1) {
+        let line = line.trim();
+
+        // some tools put comments after the header
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let coords: Vec<i64> = line.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+
+        if coords.len() != 2 || line.split_whitespace().count() != 2 {
+            return invalid_data(&format!("malformed cell coordinates '{}'", line));
+        }
+
+        res.push(Coord { col: coords[0], row: coords[1] });
+    }
+
+    Ok(res)
+}
+
+pub fn write(cells: &[Coord], writer: &mut Write) -> io::Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+
+    for cell in cells {
+        writeln!(writer, "{} {}", cell.col, cell.row)?;
+    }
+
+    Ok(())
+}
+
+
+#[test]
+fn test_life106_round_trip() {
+    let cells = vec![Coord { col: 0, row: -1 }, Coord { col: 1, row: 0 }, Coord { col: -1, row: 1 }];
+
+    let mut data: Vec<u8> = Vec::new();
+    write(&cells, &mut data).unwrap();
+
+    assert_eq!(read(&mut &data[..]).unwrap(), cells);
+}
+
+#[test]
+fn test_life106_errors() {
+    assert!(read(&mut "#Life 1.05\n0 0\n".as_bytes()).is_err());
+    assert!(read(&mut "#Life 1.06\n0 0 0\n".as_bytes()).is_err());
+    assert!(read(&mut "#Life 1.06\n0 x\n".as_bytes()).is_err());
+
+    let cells = read(&mut "#Life 1.06\n#D comment\n\n5 -3\n".as_bytes()).unwrap();
+    assert_eq!(cells, vec![Coord { col: 5, row: -3 }]);
+}
@@ -0,0 +1,229 @@
+/// Golly's Macrocell format, a quadtree of the pattern with identical
+/// subtrees stored only once, which keeps huge regular patterns small:
+/// ```text
+/// [M2] (life-rs)
+/// #R B3/S23
+/// .*$..*$***$
+/// 4 1 0 0 0
+/// ```
+///
+/// After the `[M2]` header and `#` comment lines every line is a node.
+/// Lines of `.`, `*` and `$` are 8x8 leaves (dead cell, live cell, end
+/// of row), `level nw ne sw se` lines are nodes of size 2^level with
+/// their quarters given by line numbers, 0 is an empty quarter.
+/// The last node is the whole pattern.
+///
+
+use std::io;
+use std::io::{Read, Write};
+
+use ::board::Coord;
+use ::board::quadtree::{QuadTree, Node, NodeId, EMPTY, LEAF_LEVEL, MAX_LEVEL};
+use super::invalid_data;
+
+pub const HEADER: &'static str = "[M2]";
+
+
+/// Pattern stored in a Macrocell file
+#[derive(Debug, PartialEq, Default)]
+pub struct MacrocellFile {
+    pub rule: Option<String>,
+    pub comments: Vec<String>,
+
+    // live cells relative to the top left corner of the pattern
+    pub cells: Vec<Coord>,
+}
+
+pub fn is_macrocell(data: &str) -> bool {
+    data.trim_left().starts_with(HEADER)
+}
+
+fn parse_leaf(line: &str) -> io::Result<u64> {
+    let (mut row, mut col) = (0, 0);
+    let mut bits = 0u64;
+
+    for ch in line.chars() {
+        match ch {
+            '.' => col += 1,
+            '*' => {
+                if row >= 8 || col >= 8 {
+                    return invalid_data(&format!("leaf '{}' is larger than 8x8", line));
+                }
+                bits |= 1 << (row * 8 + col);
+                col += 1;
+            }
+            '$' => {
+                row += 1;
+                col = 0;
+            }
+            _ => return invalid_data(&format!("unexpected symbol '{}' in leaf", ch))
+        }
+    }
+
+    Ok(bits)
+}
+
+fn parse_node(line: &str, tree: &QuadTree, ids: &[NodeId]) -> io::Result<(u32, [NodeId; 4])> {
+    let values: Vec<usize> = line.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+
+    if values.len() != 5 || line.split_whitespace().count() != 5 {
+        return invalid_data(&format!("malformed node '{}'", line));
+    }
+
+    let level = values[0] as u32;
+    if level <= LEAF_LEVEL {
+        // multi-state patterns use nodes of the lowest levels
+        return invalid_data("only two-state Macrocell patterns are supported");
+    }
+    if level > MAX_LEVEL {
+        return invalid_data("pattern is too large");
+    }
+
+    let mut children = [EMPTY; 4];
+
+    for (idx, &line_num) in values[1..].iter().enumerate() {
+        // nodes refer to the preceding lines only
+        let id = match ids.get(line_num) {
+            Some(&id) => id,
+            None => return invalid_data(&format!("node refers to unknown node {}", line_num))
+        };
+
+        if id != EMPTY && tree.get_level(id) != level - 1 {
+            return invalid_data(&format!("node refers to node {} of wrong size", line_num));
+        }
+
+        children[idx] = id;
+    }
+
+    Ok((level, children))
+}
+
+pub fn read(reader: &mut Read) -> io::Result<MacrocellFile> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+
+    if !is_macrocell(&contents) {
+        return invalid_data("not a Macrocell file");
+    }
+
+    let mut pattern = MacrocellFile::default();
+    let mut tree = QuadTree::new();
+
+    // node ids by line number, line 0 is the empty node
+    let mut ids: Vec<NodeId> = vec![EMPTY];
+
+    for line in contents.lines().skip_while(|line| line.trim().is_empty()).skip(1) {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('#') {
+            let text = line.chars().skip(2).collect::<String>().trim().to_string();
+            match line[1..].chars().next() {
+                Some('R') => pattern.rule = Some(text),
+                Some('C') | Some('D') => pattern.comments.push(text),
+                // generation and other tags are ignored
+                _ => {}
+            }
+        } else if line.starts_with('.') || line.starts_with('*') || line.starts_with('$') {
+            let bits = parse_leaf(line)?;
+            ids.push(tree.leaf(bits));
+        } else {
+            let (level, children) = parse_node(line, &tree, &ids)?;
+            ids.push(tree.node(level, children));
+        }
+    }
+
+    pattern.cells = tree.get_cells(*ids.last().unwrap_or(&EMPTY));
+    Ok(pattern)
+}
+
+fn leaf_to_string(bits: u64) -> String {
+    let mut res = String::new();
+
+    for row in 0..8 {
+        // trailing dead cells and empty rows are omitted
+        let width = (0..8).rev().find(|col| bits & (1 << (row * 8 + col)) != 0).map_or(0, |col| col + 1);
+
+        for col in 0..width {
+            res.push(if bits & (1 << (row * 8 + col)) != 0 { '*' } else { '.' });
+        }
+        res.push('$');
+    }
+
+    let len = res.trim_right_matches('$').len();
+    res.truncate(len + 1);
+    res
+}
+
+pub fn write(pattern: &MacrocellFile, writer: &mut Write) -> io::Result<()> {
+    writeln!(writer, "{} (life-rs)", HEADER)?;
+    writeln!(writer, "#R {}", pattern.rule.as_ref().map(|r| r.as_str()).unwrap_or("B3/S23"))?;
+
+    for comment in &pattern.comments {
+        writeln!(writer, "#C {}", comment)?;
+    }
+
+    // tree nodes go in the order of creation, so line
+    // numbers in the file match node ids of the tree
+    let (tree, _) = QuadTree::from_cells(&pattern.cells);
+
+    for node in tree.get_nodes() {
+        match node {
+            &Node::Leaf(bits) => writeln!(writer, "{}", leaf_to_string(bits))?,
+            &Node::Inner { level, children } =>
+                writeln!(writer, "{} {} {} {} {}", level, children[0], children[1], children[2], children[3])?,
+        }
+    }
+
+    Ok(())
+}
+
+
+#[test]
+fn test_macrocell_read() {
+    // glider in the top left corner of a 16x16 square
+    let data = "[M2] (golly 2.0)\n#R B3/S23\n#C The smallest spaceship.\n.*$..*$***$\n4 1 0 0 0\n";
+
+    let pattern = read(&mut data.as_bytes()).unwrap();
+
+    assert_eq!(pattern.rule, Some("B3/S23".to_string()));
+    assert_eq!(pattern.comments, vec!["The smallest spaceship.".to_string()]);
+    assert_eq!(pattern.cells, vec![Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 },
+                                   Coord { col: 0, row: 2 }, Coord { col: 1, row: 2 },
+                                   Coord { col: 2, row: 2 }]);
+
+    // the same leaf used in two quarters
+    let pattern = read(&mut "[M2]\n$$$$$$$.......*$\n4 1 1 0 1\n".as_bytes()).unwrap();
+    assert_eq!(pattern.cells, vec![Coord { col: 7, row: 7 }, Coord { col: 15, row: 7 },
+                                   Coord { col: 15, row: 15 }]);
+}
+
+#[test]
+fn test_macrocell_round_trip() {
+    let cells: Vec<Coord> = (0..1000).map(|i| Coord { col: i * 37 % 2000, row: i * 3 }).collect();
+
+    let pattern = MacrocellFile { cells: cells.clone(), ..MacrocellFile::default() };
+
+    let mut data: Vec<u8> = Vec::new();
+    write(&pattern, &mut data).unwrap();
+
+    let mut decoded = read(&mut &data[..]).unwrap().cells;
+    decoded.sort();
+
+    let mut expected = cells;
+    expected.sort();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn test_macrocell_errors() {
+    assert!(read(&mut "#R B3/S23\n*$\n".as_bytes()).is_err());
+    assert!(read(&mut "[M2]\n*$\n4 2 0 0 0\n".as_bytes()).is_err());
+    assert!(read(&mut "[M2]\n*$\n5 1 0 0 0\n".as_bytes()).is_err());
+    assert!(read(&mut "[M2]\n1 1 0 0 0\n".as_bytes()).is_err());
+    assert!(read(&mut "[M2]\n*********$\n".as_bytes()).is_err());
+}
@@ -0,0 +1,82 @@
+/// Reading and writing patterns in various file formats.
+///
+/// All the parsers report malformed input as `io::Error` of
+/// `InvalidData` kind, so format errors and errors of the underlying
+/// reader are handled uniformly by the caller.
+///
+
+pub mod rle;
+pub mod life106;
+pub mod plaintext;
+pub mod macrocell;
+pub mod container;
+pub mod compression;
+#[cfg(feature = "archives")]
+pub mod archive;
+pub mod apgcode;
+pub mod fetch;
+pub mod ruletable;
+
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+use ::board::Coord;
+
+
+/// Descriptive information about a pattern kept along with its cells
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PatternMeta {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+}
+
+
+pub fn invalid_data<T>(msg: &str) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()))
+}
+
+/// Reads pattern file of any supported format, possibly compressed or
+/// in a zip archive, returns its live cells, rule if the file specifies
+/// one and whatever name, author and comments the format can store
+pub fn read_pattern(path: &Path) -> io::Result<(Vec<Coord>, Option<String>, PatternMeta)> {
+    // entries of zip archives are read into memory, see `archive`
+    #[cfg(feature = "archives")]
+    {
+        if let Some((path, entry)) = archive::split_path(path) {
+            return parse_pattern(archive::Archive::open(&path)?.read_entry(&entry)?.as_bytes());
+        }
+    }
+
+    read_pattern_from(&mut compression::open(path)?)
+}
+
+/// Same as `read_pattern`, but for the contents of a file already in memory
+pub fn parse_pattern(data: &[u8]) -> io::Result<(Vec<Coord>, Option<String>, PatternMeta)> {
+    read_pattern_from(&mut &data[..])
+}
+
+fn read_pattern_from<R: BufRead>(reader: &mut R) -> io::Result<(Vec<Coord>, Option<String>, PatternMeta)> {
+    // format is detected by contents, not by file extension, the first
+    // buffered bytes are enough to see the header
+    let text = String::from_utf8_lossy(reader.fill_buf()?).into_owned();
+
+    if life106::is_life106(&text) {
+        Ok((life106::read(reader)?, None, PatternMeta::default()))
+    } else if plaintext::is_plaintext(&text) {
+        let pattern = plaintext::read(reader)?;
+        let meta = PatternMeta { name: pattern.name, author: None, comments: pattern.comments };
+        Ok((pattern.cells, None, meta))
+    } else if macrocell::is_macrocell(&text) {
+        let pattern = macrocell::read(reader)?;
+        let meta = PatternMeta { name: None, author: None, comments: pattern.comments };
+        Ok((pattern.cells, pattern.rule, meta))
+    } else {
+        // RLE is decoded line by line, without reading the file first
+        let mut cells = Vec::new();
+        let pattern = rle::read_with(reader, |cell| cells.push(cell))?;
+        let meta = PatternMeta { name: pattern.name, author: pattern.author, comments: pattern.comments };
+        Ok((cells, pattern.rule, meta))
+    }
+}
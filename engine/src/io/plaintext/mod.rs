@@ -0,0 +1,138 @@
+/// Plaintext `.cells` format used by LifeWiki. Lines starting with `!`
+/// are comments (`!Name:` gives pattern name), every other line is
+/// a row of cells, `.` is a dead cell and `O` is a live one:
+/// ```text
+/// !Name: Glider
+/// .O
+/// ..O
+/// OOO
+/// ```
+///
+/// Dead cells at the end of a row may be omitted, so an empty line is
+/// an empty row.
+///
+
+use std::io;
+use std::io::{Read, Write};
+
+use ::board::Coord;
+use super::invalid_data;
+
+const NAME_PREFIX: &'static str = "!Name:";
+
+
+/// Pattern stored in a plaintext file
+#[derive(Debug, PartialEq, Default)]
+pub struct PlaintextFile {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+
+    // live cells relative to the top left corner of the pattern
+    pub cells: Vec<Coord>,
+}
+
+fn is_row(line: &str) -> bool {
+    // '*' is used for live cells by some older files
+    line.chars().all(|ch| ch == '.' || ch == 'O' || ch == '*')
+}
+
+pub fn is_plaintext(data: &str) -> bool {
+    match data.lines().map(|line| line.trim()).find(|line| !line.is_empty()) {
+        Some(line) => line.starts_with('!') || is_row(line),
+        None => false
+    }
+}
+
+pub fn read(reader: &mut Read) -> io::Result<PlaintextFile> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+
+    let mut pattern = PlaintextFile::default();
+    let mut row = 0;
+
+    for line in contents.lines().map(|line| line.trim()) {
+        if line.is_empty() && row == 0 {
+            // empty lines around comments are not rows yet
+            continue;
+        } else if line.starts_with(NAME_PREFIX) {
+            pattern.name = Some(line[NAME_PREFIX.len()..].trim().to_string());
+        } else if line.starts_with('!') {
+            pattern.comments.push(line[1..].trim().to_string());
+        } else if is_row(line) {
+            for (col, ch) in line.chars().enumerate() {
+                if ch != '.' {
+                    pattern.cells.push(Coord { col: col as i64, row: row });
+                }
+            }
+            row += 1;
+        } else {
+            return invalid_data(&format!("malformed row '{}'", line));
+        }
+    }
+
+    Ok(pattern)
+}
+
+pub fn write(pattern: &PlaintextFile, writer: &mut Write) -> io::Result<()> {
+    if let Some(ref name) = pattern.name {
+        writeln!(writer, "{} {}", NAME_PREFIX, name)?;
+    }
+    for comment in &pattern.comments {
+        writeln!(writer, "! {}", comment)?;
+    }
+
+    // cells coordinates should be non negative, (0, 0) is the top left corner
+    let mut sorted: Vec<Coord> = pattern.cells.clone();
+    sorted.sort_by(|a, b| (a.row, a.col).cmp(&(b.row, b.col)));
+    sorted.dedup();
+
+    let height = sorted.last().map_or(0, |c| c.row + 1);
+    let mut rows = vec![String::new(); height as usize];
+
+    for cell in &sorted {
+        let row = &mut rows[cell.row as usize];
+        while (row.len() as i64) < cell.col {
+            row.push('.');
+        }
+        row.push('O');
+    }
+
+    for row in rows {
+        // lone empty lines are easy to lose, so empty rows get a dot
+        writeln!(writer, "{}", if row.is_empty() { "." } else { &row })?;
+    }
+
+    Ok(())
+}
+
+
+#[test]
+fn test_plaintext_round_trip() {
+    let data = "!Name: Glider\n!The smallest spaceship.\n.O\n..O\nOOO\n";
+
+    let pattern = read(&mut data.as_bytes()).unwrap();
+
+    assert_eq!(pattern.name, Some("Glider".to_string()));
+    assert_eq!(pattern.comments, vec!["The smallest spaceship.".to_string()]);
+    assert_eq!(pattern.cells, vec![Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 },
+                                   Coord { col: 0, row: 2 }, Coord { col: 1, row: 2 },
+                                   Coord { col: 2, row: 2 }]);
+
+    let mut written: Vec<u8> = Vec::new();
+    write(&pattern, &mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), "!Name: Glider\n! The smallest spaceship.\n.O\n..O\nOOO\n");
+}
+
+#[test]
+fn test_plaintext_errors() {
+    assert!(is_plaintext("!Name: Block\nOO\nOO\n"));
+    assert!(is_plaintext("\n..O\n"));
+    assert!(!is_plaintext("x = 2, y = 2\n2o$2o!\n"));
+    assert!(!is_plaintext("#Life 1.06\n0 0\n"));
+
+    assert!(read(&mut "OO\nbo\n".as_bytes()).is_err());
+
+    // empty rows are kept, both written as dots and as empty lines
+    let pattern = read(&mut "!comment\n\nO\n.\n\nO".as_bytes()).unwrap();
+    assert_eq!(pattern.cells, vec![Coord { col: 0, row: 0 }, Coord { col: 0, row: 3 }]);
+}
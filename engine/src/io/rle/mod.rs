@@ -0,0 +1,511 @@
+/// Run Length Encoded cells, the format used by Golly and LifeWiki.
+/// Rows are separated by `$`, `b` is a dead cell, `o` is a live one,
+/// every tag may be prefixed by a run count, `!` ends the pattern:
+/// ```
+/// # use engine::io::rle::{encode_cells, decode_cells};
+/// # use engine::board::Coord;
+/// let glider = decode_cells("bo$2bo$3o!").unwrap();
+/// assert_eq!(glider.len(), 5);
+/// assert_eq!(encode_cells(&glider), "bo$2bo$3o!");
+/// ```
+///
+/// Files start with optional `#` comment lines (`#N` is pattern name,
+/// `#O` is its author, `#C` is a comment), followed by the header line
+/// with pattern size and rule, `x = 3, y = 3, rule = B3/S23`, and cells.
+///
+/// Big patterns don't have to fit in memory as text: `Encoder` writes
+/// runs out as soon as they end and `Decoder` takes the data a piece at
+/// a time, see `write_board` and `read_with`.
+///
+
+use std::io;
+use std::io::{Read, Write, BufRead, BufReader};
+use std::path::Path;
+
+use ::board::{Board, Coord};
+use ::rule::Rule;
+use super::invalid_data;
+use super::compression;
+
+const MAX_LINE_LENGTH: usize = 70;
+
+
+/// Writes RLE cells as they come, so no more than a run of them is kept
+/// in memory. Cells are given row by row, left to right, relative to the
+/// top left corner of the pattern
+pub struct Encoder<W: Write> {
+    writer: W,
+    line_len: usize,
+
+    // the cell right after the last one given
+    row: i64,
+    col: i64,
+    alive_run: usize,
+}
+
+impl<W: Write> Encoder<W> {
+
+    pub fn new(writer: W) -> Self {
+        Encoder {
+            writer: writer,
+            line_len: 0,
+
+            row: 0,
+            col: 0,
+            alive_run: 0,
+        }
+    }
+
+    fn push_run(&mut self, cnt: usize, tag: char) -> io::Result<()> {
+        if cnt == 0 {
+            return Ok(());
+        }
+
+        let run = if cnt == 1 { tag.to_string() } else { format!("{}{}", cnt, tag) };
+
+        // long lines are split, runs are never broken
+        if self.line_len + run.len() > MAX_LINE_LENGTH {
+            self.writer.write_all(b"\n")?;
+            self.line_len = 0;
+        }
+
+        self.line_len += run.len();
+        self.writer.write_all(run.as_bytes())
+    }
+
+    pub fn push(&mut self, cell: Coord) -> io::Result<()> {
+        if (cell.row, cell.col) < (self.row, self.col) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "RLE cells are out of order"));
+        }
+
+        if cell.row != self.row || cell.col != self.col {
+            let alive_run = self.alive_run;
+            self.push_run(alive_run, 'o')?;
+            self.alive_run = 0;
+
+            if cell.row != self.row {
+                let rows = (cell.row - self.row) as usize;
+                self.push_run(rows, '$')?;
+                self.row = cell.row;
+                self.col = 0;
+            }
+
+            let cols = (cell.col - self.col) as usize;
+            self.push_run(cols, 'b')?;
+        }
+
+        self.alive_run += 1;
+        self.col = cell.col + 1;
+        Ok(())
+    }
+
+    /// Ends the pattern, the writer is given back
+    pub fn finish(mut self) -> io::Result<W> {
+        let alive_run = self.alive_run;
+        self.push_run(alive_run, 'o')?;
+        self.writer.write_all(b"!")?;
+        Ok(self.writer)
+    }
+
+}
+
+pub fn encode_cells(cells: &[Coord]) -> String {
+    // cells coordinates should be non negative, (0, 0) is the top left corner
+    let mut sorted: Vec<Coord> = cells.to_vec();
+    sorted.sort_by(|a, b| (a.row, a.col).cmp(&(b.row, b.col)));
+    sorted.dedup();
+
+    let mut encoder = Encoder::new(Vec::new());
+    let encoded = sorted.into_iter().map(|cell| encoder.push(cell)).collect::<io::Result<()>>()
+        .and_then(|_| encoder.finish())
+        .expect("cells are sorted and written to memory");
+
+    String::from_utf8(encoded).expect("RLE is ASCII")
+}
+
+/// Decodes RLE cells given in pieces, e.g. line by line as they are
+/// read, so the whole text is never kept in memory
+#[derive(Debug, Default)]
+pub struct Decoder {
+    row: i64,
+    col: i64,
+    cnt: Option<i64>,
+    finished: bool,
+}
+
+impl Decoder {
+
+    pub fn new() -> Self {
+        Decoder::default()
+    }
+
+    /// Live cells of the next piece are passed to the given function,
+    /// whatever follows the terminating `!` is ignored
+    pub fn feed<F: FnMut(Coord)>(&mut self, data: &str, mut f: F) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+
+        for ch in data.chars() {
+            match ch {
+                '0'...'9' => {
+                    let digit = ch as i64 - '0' as i64;
+                    self.cnt = match self.cnt.unwrap_or(0).checked_mul(10).and_then(|c| c.checked_add(digit)) {
+                        Some(cnt) => Some(cnt),
+                        None => return invalid_data("run count is too large")
+                    };
+                }
+
+                'b' | '.' => {
+                    self.col += self.cnt.unwrap_or(1);
+                    self.cnt = None;
+                }
+
+                // any other letter is some live state of multi-state rules
+                'o' | 'A'...'Z' => {
+                    for _ in 0..self.cnt.unwrap_or(1) {
+                        f(Coord { col: self.col, row: self.row });
+                        self.col += 1;
+                    }
+                    self.cnt = None;
+                }
+
+                '$' => {
+                    self.row += self.cnt.unwrap_or(1);
+                    self.col = 0;
+                    self.cnt = None;
+                }
+
+                '!' => {
+                    self.finished = true;
+                    return Ok(());
+                }
+
+                _ if ch.is_whitespace() => {}
+
+                _ => return invalid_data(&format!("unexpected symbol '{}' in RLE data", ch))
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Checks that the data has ended with `!`
+    pub fn finish(&self) -> io::Result<()> {
+        if self.finished { Ok(()) } else { invalid_data("RLE data is not terminated with '!'") }
+    }
+
+}
+
+pub fn decode_cells(data: &str) -> io::Result<Vec<Coord>> {
+    let mut res = Vec::new();
+
+    let mut decoder = Decoder::new();
+    decoder.feed(data, |cell| res.push(cell))?;
+    decoder.finish()?;
+
+    Ok(res)
+}
+
+/// Pattern stored in an RLE file
+#[derive(Debug, PartialEq, Default)]
+pub struct RleFile {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<String>,
+
+    // live cells relative to the top left corner of the pattern
+    pub cells: Vec<Coord>,
+}
+
+pub fn is_life_rule(rule: &str) -> bool {
+    // Conway's Life in any notation
+    rule.parse::<Rule>() == Ok(Rule::life())
+}
+
+fn parse_header(line: &str, pattern: &mut RleFile) -> io::Result<()> {
+    for part in line.split(',') {
+        let mut kv = part.splitn(2, '=');
+
+        let key = kv.next().unwrap_or("").trim();
+        let value = match kv.next() {
+            Some(value) => value.trim(),
+            None => return invalid_data(&format!("malformed RLE header '{}'", line))
+        };
+
+        match key {
+            "x" | "y" => {
+                if value.parse::<u64>().is_err() {
+                    return invalid_data(&format!("malformed pattern size '{}'", value));
+                }
+            }
+            "rule" => pattern.rule = Some(value.to_string()),
+            // unknown header fields are ignored
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+pub fn read(reader: &mut Read) -> io::Result<RleFile> {
+    let mut cells = Vec::new();
+    let mut pattern = read_with(&mut BufReader::new(reader), |cell| cells.push(cell))?;
+
+    pattern.cells = cells;
+    Ok(pattern)
+}
+
+/// Reads an RLE file line by line, live cells are passed to the given
+/// function as they are decoded, the cells of the returned pattern are
+/// left empty
+pub fn read_with<R: BufRead, F: FnMut(Coord)>(reader: &mut R, mut f: F) -> io::Result<RleFile> {
+    let mut pattern = RleFile::default();
+    // set once the header is read
+    let mut decoder: Option<Decoder> = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim();
+
+        if let Some(ref mut decoder) = decoder {
+            decoder.feed(line, &mut f)?;
+            if decoder.is_finished() {
+                break;
+            }
+        } else if line.starts_with('#') {
+            // '#' is followed by a one letter tag
+            let text = line.chars().skip(2).collect::<String>().trim().to_string();
+            match line[1..].chars().next() {
+                Some('N') => pattern.name = Some(text),
+                Some('O') => pattern.author = Some(text),
+                Some('C') | Some('c') => pattern.comments.push(text),
+                _ => {}
+            }
+        } else if line.starts_with('x') {
+            parse_header(line, &mut pattern)?;
+            decoder = Some(Decoder::new());
+        } else if !line.is_empty() {
+            return invalid_data("RLE header is missing");
+        }
+    }
+
+    match decoder {
+        Some(decoder) => decoder.finish()?,
+        None => return invalid_data("RLE header is missing")
+    }
+
+    Ok(pattern)
+}
+
+// comments and the header line, the cells of the pattern aren't looked at
+fn write_header(pattern: &RleFile, width: i64, height: i64, writer: &mut Write) -> io::Result<()> {
+    if let Some(ref name) = pattern.name {
+        writeln!(writer, "#N {}", name)?;
+    }
+    if let Some(ref author) = pattern.author {
+        writeln!(writer, "#O {}", author)?;
+    }
+    for comment in &pattern.comments {
+        writeln!(writer, "#C {}", comment)?;
+    }
+
+    writeln!(writer, "x = {}, y = {}, rule = {}", width, height,
+             pattern.rule.as_ref().map(|r| r.as_str()).unwrap_or("B3/S23"))
+}
+
+// cells are sorted by reference, so they aren't copied
+fn write_cells<'c, I>(cells: I, left: i64, top: i64, writer: &mut Write) -> io::Result<()>
+    where I: Iterator<Item=&'c Coord> {
+
+    let mut sorted: Vec<&Coord> = cells.collect();
+    sorted.sort_by(|a, b| (a.row, a.col).cmp(&(b.row, b.col)));
+    sorted.dedup();
+
+    let mut encoder = Encoder::new(writer);
+    for cell in sorted {
+        encoder.push(Coord { col: cell.col - left, row: cell.row - top })?;
+    }
+
+    encoder.finish()?.write_all(b"\n")
+}
+
+pub fn write(pattern: &RleFile, writer: &mut Write) -> io::Result<()> {
+    let width = pattern.cells.iter().map(|c| c.col + 1).max().unwrap_or(0);
+    let height = pattern.cells.iter().map(|c| c.row + 1).max().unwrap_or(0);
+
+    write_header(pattern, width, height, writer)?;
+    write_cells(pattern.cells.iter(), 0, 0, writer)
+}
+
+/// Live cells of the board cut to their bounding box, written as they
+/// are encoded. Name, author, comments and rule are those of the given
+/// pattern, its own cells are left out
+pub fn write_board(board: &Board, pattern: &RleFile, writer: &mut Write) -> io::Result<()> {
+    // an empty board has no bounding box, not even a single cell one
+    let (left, top, width, height) = match board.get_bounding_box() {
+        Some(rect) => (rect.left, rect.top, rect.get_width() as i64, rect.get_height() as i64),
+        None => (0, 0, 0, 0)
+    };
+
+    write_header(pattern, width, height, writer)?;
+    write_cells(board.get_occupied().iter(), left, top, writer)
+}
+
+/// Writes the pattern into a file, gzipped if its name ends with `.gz`
+pub fn save(pattern: &RleFile, path: &Path) -> io::Result<()> {
+    compression::save_with(path, compression::is_compressed_path(path), |writer| write(pattern, writer))
+}
+
+/// Same as `save`, with the live cells of the board, see `write_board`
+pub fn save_board(board: &Board, pattern: &RleFile, path: &Path) -> io::Result<()> {
+    compression::save_with(path, compression::is_compressed_path(path), |writer| write_board(board, pattern, writer))
+}
+
+
+#[test]
+fn test_rle_round_trip() {
+    let cells = vec![
+        Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 },
+        Coord { col: 5, row: 0 }, Coord { col: 3, row: 3 },
+    ];
+
+    let encoded = encode_cells(&cells);
+    assert_eq!(encoded, "3o2bo3$3bo!");
+
+    let mut decoded = decode_cells(&encoded).unwrap();
+    decoded.sort();
+
+    let mut expected = cells.clone();
+    expected.sort();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn test_rle_long_lines() {
+    let cells: Vec<Coord> = (0..200).map(|i| Coord { col: i * 2, row: 0 }).collect();
+
+    let encoded = encode_cells(&cells);
+
+    assert!(encoded.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    assert_eq!(decode_cells(&encoded).unwrap(), cells);
+}
+
+#[test]
+fn test_rle_errors() {
+    assert!(decode_cells("bo$2bo").is_err());
+    assert!(decode_cells("bo$2xo!").is_err());
+    assert!(decode_cells("99999999999999999999o!").is_err());
+    assert_eq!(decode_cells("!").unwrap(), vec![]);
+}
+
+#[test]
+fn test_rle_file() {
+    let data = "#N Glider\n#O Richard K. Guy\n#C The smallest spaceship.\n\
+                x = 3, y = 3, rule = B3/S23\nbo$2b\no$3o!\n";
+
+    let pattern = read(&mut data.as_bytes()).unwrap();
+
+    assert_eq!(pattern.name, Some("Glider".to_string()));
+    assert_eq!(pattern.author, Some("Richard K. Guy".to_string()));
+    assert_eq!(pattern.comments, vec!["The smallest spaceship.".to_string()]);
+    assert_eq!(pattern.rule, Some("B3/S23".to_string()));
+    assert_eq!(pattern.cells.len(), 5);
+
+    let mut written: Vec<u8> = Vec::new();
+    write(&pattern, &mut written).unwrap();
+    assert_eq!(read(&mut &written[..]).unwrap(), pattern);
+
+    assert!(read(&mut "bo$2bo$3o!".as_bytes()).is_err());
+    assert!(read(&mut "x = a, y = 3\n3o!".as_bytes()).is_err());
+}
+
+#[cfg(test)]
+use std::fs::File;
+#[cfg(test)]
+use ::board::hashed::new as new_hashed;
+
+#[test]
+fn test_rle_streaming() {
+    // counts and runs split between pieces
+    let mut decoder = Decoder::new();
+    let mut cells = Vec::new();
+    for piece in ["1", "2o$", "b2", "o", "!", "3o"].iter() {
+        decoder.feed(piece, |cell| cells.push(cell)).unwrap();
+    }
+    assert!(decoder.is_finished());
+    assert_eq!(cells, decode_cells("12o$b2o!").unwrap());
+    assert!(Decoder::new().finish().is_err());
+
+    let mut encoder = Encoder::new(Vec::new());
+    encoder.push(Coord { col: 1, row: 0 }).unwrap();
+    encoder.push(Coord { col: 0, row: 2 }).unwrap();
+    assert!(encoder.push(Coord { col: 0, row: 1 }).is_err());
+    assert_eq!(encoder.finish().unwrap(), b"bo2$o!".to_vec());
+
+    // the board is written as the pattern cut out of it would be
+    let mut board = Board::new(new_hashed(), None, None);
+    for cell in decode_cells("bo$2bo$3o!").unwrap() {
+        board.born_at(cell.col - 10, cell.row + 7);
+    }
+    let pattern = RleFile { name: Some("Glider".to_string()), cells: Vec::new(), ..RleFile::default() };
+
+    let mut written: Vec<u8> = Vec::new();
+    write_board(&board, &pattern, &mut written).unwrap();
+    assert_eq!(String::from_utf8(written.clone()).unwrap(), "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+    let mut streamed = Vec::new();
+    let header = read_with(&mut &written[..], |cell| streamed.push(cell)).unwrap();
+    assert_eq!((header.name, header.cells.len(), streamed.len()), (pattern.name, 0, 5));
+
+    // nothing to cut out of an empty board
+    let mut written: Vec<u8> = Vec::new();
+    write_board(&Board::new(new_hashed(), None, None), &RleFile::default(), &mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), "x = 0, y = 0, rule = B3/S23\n!\n");
+}
+
+#[test]
+fn test_life_rule() {
+    assert!(is_life_rule("B3/S23"));
+    assert!(is_life_rule("b3/s23"));
+    assert!(is_life_rule("23/3"));
+    assert!(is_life_rule("B3/S23:T100,100"));
+    assert!(!is_life_rule("B36/S23"));
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_rle_save_compressed() {
+    use std::env;
+    use std::fs;
+
+    let pattern = RleFile {
+        name: Some("Glider".to_string()),
+        cells: decode_cells("bo$2bo$3o!").unwrap(),
+        ..RleFile::default()
+    };
+
+    let path = env::temp_dir().join("life-rs-test-glider.rle.gz");
+    save(&pattern, &path).unwrap();
+
+    let mut data = Vec::new();
+    File::open(&path).unwrap().read_to_end(&mut data).unwrap();
+    assert!(compression::is_compressed(&data));
+
+    let (cells, _, meta) = super::read_pattern(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(cells, pattern.cells);
+    assert_eq!(meta.name, pattern.name);
+}
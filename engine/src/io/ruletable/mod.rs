@@ -0,0 +1,562 @@
+/// Golly `.rule` files, which give a rule by a table of transitions
+/// (`@TABLE`) or by a decision tree (`@TREE`) instead of B/S notation,
+/// so rules that aren't outer totalistic can be run too. The board has
+/// only dead and live cells, so only two-state rules are accepted, with
+/// either Moore or von Neumann neighbourhood. Other sections, like
+/// `@COLORS` or `@ICONS`, are skipped.
+///
+/// Example usage:
+/// ```
+/// # use engine::io::ruletable::RuleTable;
+/// // Seeds: cells with two live neighbours are born, live cells always die
+/// let seeds = RuleTable::parse("@RULE Seeds\n\
+///                               @TABLE\n\
+///                               n_states:2\n\
+///                               neighborhood:Moore\n\
+///                               symmetries:permute\n\
+///                               var a={0,1}\n\
+///                               var b={a}\n\
+///                               var c={a}\n\
+///                               var d={a}\n\
+///                               var e={a}\n\
+///                               var f={a}\n\
+///                               var g={a}\n\
+///                               var h={a}\n\
+///                               0,1,1,0,0,0,0,0,0,1\n\
+///                               1,a,b,c,d,e,f,g,h,0\n").unwrap();
+///
+/// assert_eq!(seeds.get_name(), "Seeds");
+/// assert!(seeds.next_state(false, &[true, false, true, false, false, false, false, false]));
+/// assert!(!seeds.next_state(true, &[true, false, true, false, false, false, false, false]));
+/// ```
+///
+
+use std::io;
+use std::io::Read;
+
+use super::invalid_data;
+
+// neighbours of a cell, clockwise from north, the way Golly lists them
+const MOORE: usize = 8;
+const VON_NEUMANN: usize = 4;
+
+// every state of a cell and its Moore neighbours
+const CONFIGURATIONS: usize = 1 << (MOORE + 1);
+
+
+#[derive(Clone)]
+pub struct RuleTable {
+    name: String,
+    // text of the file, kept so that sessions can be saved with the rule
+    source: String,
+    // next state for every configuration, the cell is the lowest bit
+    // followed by its neighbours clockwise from north
+    next: Vec<bool>,
+}
+
+impl RuleTable {
+
+    pub fn parse(source: &str) -> io::Result<RuleTable> {
+        let mut name = None;
+        let mut section = String::new();
+        let (mut table, mut tree) = (Vec::new(), Vec::new());
+
+        for line in source.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+
+            if line.starts_with('@') {
+                let mut parts = line.splitn(2, char::is_whitespace);
+                section = parts.next().unwrap_or("").to_uppercase();
+
+                if section == "@RULE" {
+                    name = parts.next().map(|name| name.trim().to_string());
+                }
+            } else if !line.is_empty() {
+                match &section[..] {
+                    "@TABLE" => table.push(line),
+                    "@TREE" => tree.push(line),
+                    _ => {}
+                }
+            }
+        }
+
+        let next = if !table.is_empty() {
+            parse_table(&table)?
+        } else if !tree.is_empty() {
+            parse_tree(&tree)?
+        } else {
+            return invalid_data("rule file has neither @TABLE nor @TREE");
+        };
+
+        // only cells near live ones are simulated, so empty space can't come alive
+        if next[0] {
+            return invalid_data("rules where cells are born without live neighbours are not supported");
+        }
+
+        Ok(RuleTable {
+            name: name.unwrap_or(String::new()),
+            source: source.to_string(),
+            next: next,
+        })
+    }
+
+    pub fn read(reader: &mut Read) -> io::Result<RuleTable> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source)?;
+        RuleTable::parse(&source)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    /// Neighbours go clockwise from the west, as `Board::get_vicinity` gives them
+    #[inline]
+    pub fn next_state(&self, alive: bool, neighbours: &[bool]) -> bool {
+        let mut idx = alive as usize;
+        for (i, &neighbour) in neighbours.iter().enumerate() {
+            if neighbour {
+                idx |= 1 << (1 + (i + MOORE - 2) % MOORE);
+            }
+        }
+        self.next[idx]
+    }
+
+}
+
+/// Cell and its neighbours of a configuration, von Neumann neighbourhood
+/// has only the orthogonal ones
+fn get_cells(config: usize, neighbours: usize) -> Vec<u8> {
+    let step = MOORE / neighbours;
+    let mut res = vec![(config & 1) as u8];
+    res.extend((0..neighbours).map(|i| ((config >> (1 + i * step)) & 1) as u8));
+    res
+}
+
+fn parse_neighbourhood(name: &str) -> io::Result<usize> {
+    match &name.to_lowercase()[..] {
+        "moore" => Ok(MOORE),
+        "vonneumann" => Ok(VON_NEUMANN),
+        _ => invalid_data(&format!("unsupported neighborhood '{}'", name))
+    }
+}
+
+fn check_states(states: &str) -> io::Result<()> {
+    match states.trim().parse::<u32>() {
+        Ok(2) => Ok(()),
+        Ok(n) => invalid_data(&format!("only two-state rules are supported, this one has {} states", n)),
+        Err(_) => invalid_data(&format!("malformed number of states '{}'", states))
+    }
+}
+
+
+#[derive(Debug, Clone)]
+enum Slot {
+    Values(Vec<u8>),
+    // index of a variable, all its occurrences in a transition take the same value
+    Var(usize),
+}
+
+struct Table {
+    vars: Vec<(String, Vec<u8>)>,
+    transitions: Vec<Vec<Slot>>,
+    // orders neighbours of a transition are matched in, one per symmetry
+    orders: Vec<Vec<usize>>,
+    permute: bool,
+}
+
+/// Orders of neighbours for the symmetries, rotations by a quarter turn
+/// or by one neighbour and reflections of all of them
+fn get_orders(symmetries: &str, neighbours: usize) -> io::Result<(Vec<Vec<usize>>, bool)> {
+    let rotate = |step: usize, reflect: bool| -> Vec<Vec<usize>> {
+        let mut res = Vec::new();
+        for start in (0..neighbours).filter(|i| i % step == 0) {
+            res.push((0..neighbours).map(|i| (start + i) % neighbours).collect());
+            if reflect {
+                res.push((0..neighbours).map(|i| (start + neighbours - i) % neighbours).collect());
+            }
+        }
+        res
+    };
+
+    let quarter = neighbours / 4;
+
+    match &symmetries.to_lowercase()[..] {
+        "none" => Ok((rotate(neighbours, false), false)),
+        "reflect" => Ok((rotate(neighbours, true), false)),
+        "rotate4" => Ok((rotate(quarter, false), false)),
+        "rotate4reflect" => Ok((rotate(quarter, true), false)),
+        "rotate8" if neighbours == MOORE => Ok((rotate(1, false), false)),
+        "rotate8reflect" if neighbours == MOORE => Ok((rotate(1, true), false)),
+        "permute" => Ok((rotate(neighbours, false), true)),
+        _ => invalid_data(&format!("unsupported symmetries '{}'", symmetries))
+    }
+}
+
+/// Splits a transition into its parts, `0,{1,2},a,...` or without
+/// commas, `012a...`, if every part is a single character
+fn split_transition(line: &str) -> Vec<String> {
+    if !line.contains(',') {
+        return line.chars().filter(|ch| !ch.is_whitespace()).map(|ch| ch.to_string()).collect();
+    }
+
+    let (mut res, mut part, mut depth) = (Vec::new(), String::new(), 0);
+    for ch in line.chars() {
+        match ch {
+            ',' if depth == 0 => {
+                res.push(part.trim().to_string());
+                part = String::new();
+                continue;
+            }
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        part.push(ch);
+    }
+    res.push(part.trim().to_string());
+    res
+}
+
+impl Table {
+
+    fn find_var(&self, name: &str) -> Option<usize> {
+        self.vars.iter().position(|&(ref var, _)| var == name)
+    }
+
+    /// States in `{0,1,a}`, variables stand for all of their states
+    fn parse_values(&self, values: &str) -> io::Result<Vec<u8>> {
+        let mut res = Vec::new();
+
+        for value in values.trim_matches(|ch: char| ch == '{' || ch == '}').split(',').map(|v| v.trim()) {
+            match (value.parse::<u8>(), self.find_var(value)) {
+                (Ok(state), _) if state < 2 => res.push(state),
+                (_, Some(var)) => res.extend(self.vars[var].1.iter().cloned()),
+                _ => return invalid_data(&format!("unexpected state '{}' in rule table", value))
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn parse_slot(&self, slot: &str) -> io::Result<Slot> {
+        if slot.starts_with('{') {
+            return Ok(Slot::Values(self.parse_values(slot)?));
+        }
+
+        match self.find_var(slot) {
+            Some(var) => Ok(Slot::Var(var)),
+            None => Ok(Slot::Values(self.parse_values(slot)?))
+        }
+    }
+
+    /// Checks the slot against the state, binding its variable if it has one
+    fn match_slot(&self, slot: &Slot, state: u8, bound: &mut Vec<Option<u8>>) -> bool {
+        match *slot {
+            Slot::Values(ref values) => values.contains(&state),
+            Slot::Var(var) => match bound[var] {
+                Some(value) => value == state,
+                None if self.vars[var].1.contains(&state) => {
+                    bound[var] = Some(state);
+                    true
+                }
+                None => false
+            }
+        }
+    }
+
+    /// Any order of neighbours matches, they are tried one value at a time
+    fn match_permuted(&self, slots: &[Slot], neighbours: &[u8], used: &mut Vec<bool>,
+                      bound: &mut Vec<Option<u8>>) -> bool {
+        let slot = match slots.first() {
+            Some(slot) => slot,
+            None => return true
+        };
+
+        let mut tried = Vec::new();
+        for i in 0..neighbours.len() {
+            if used[i] || tried.contains(&neighbours[i]) {
+                continue;
+            }
+            tried.push(neighbours[i]);
+
+            let mut attempt = bound.clone();
+            if self.match_slot(slot, neighbours[i], &mut attempt) {
+                used[i] = true;
+                if self.match_permuted(&slots[1..], neighbours, used, &mut attempt) {
+                    *bound = attempt;
+                    return true;
+                }
+                used[i] = false;
+            }
+        }
+
+        false
+    }
+
+    fn apply(&self, transition: &[Slot], cells: &[u8]) -> Option<u8> {
+        let last = transition.len() - 1;
+
+        let output = |bound: &Vec<Option<u8>>| match transition[last] {
+            Slot::Values(ref values) => values.first().cloned(),
+            Slot::Var(var) => bound[var],
+        };
+
+        let mut bound = vec![None; self.vars.len()];
+        if !self.match_slot(&transition[0], cells[0], &mut bound) {
+            return None;
+        }
+
+        if self.permute {
+            let mut used = vec![false; cells.len() - 1];
+            return if self.match_permuted(&transition[1..last], &cells[1..], &mut used, &mut bound) {
+                output(&bound)
+            } else {
+                None
+            };
+        }
+
+        for order in &self.orders {
+            let mut attempt = bound.clone();
+            let matched = order.iter().enumerate()
+                .all(|(i, &neighbour)| self.match_slot(&transition[1 + i], cells[1 + neighbour], &mut attempt));
+
+            if matched {
+                return output(&attempt);
+            }
+        }
+
+        None
+    }
+
+}
+
+fn parse_table(lines: &[&str]) -> io::Result<Vec<bool>> {
+    let mut table = Table { vars: Vec::new(), transitions: Vec::new(), orders: Vec::new(), permute: false };
+    let mut neighbours = MOORE;
+    let mut symmetries = "none".to_string();
+
+    for line in lines {
+        if line.starts_with("var ") {
+            // var name={0,1}
+            let mut parts = line[4..].splitn(2, '=');
+            let (name, values) = (parts.next().unwrap_or("").trim(), parts.next().unwrap_or(""));
+
+            let values = table.parse_values(values.trim())?;
+            table.vars.push((name.to_string(), values));
+        } else if let Some(pos) = line.find(':') {
+            let (key, value) = (line[..pos].trim(), line[pos + 1..].trim());
+            match key {
+                "n_states" => check_states(value)?,
+                "neighborhood" => neighbours = parse_neighbourhood(value)?,
+                "symmetries" => symmetries = value.to_string(),
+                _ => return invalid_data(&format!("unknown rule table setting '{}'", key))
+            }
+        } else {
+            let slots = split_transition(line).iter()
+                .map(|slot| table.parse_slot(slot))
+                .collect::<io::Result<Vec<Slot>>>()?;
+
+            if slots.len() != neighbours + 2 {
+                return invalid_data(&format!("transition '{}' should have {} states", line, neighbours + 2));
+            }
+            if let Slot::Var(var) = slots[neighbours + 1] {
+                if !slots[..neighbours + 1].iter().any(|slot| if let Slot::Var(v) = *slot { v == var } else { false }) {
+                    return invalid_data(&format!("output of transition '{}' is not bound", line));
+                }
+            }
+
+            table.transitions.push(slots);
+        }
+    }
+
+    let (orders, permute) = get_orders(&symmetries, neighbours)?;
+    table.orders = orders;
+    table.permute = permute;
+
+    // the first matching transition wins, cells stay as they are if none matches
+    Ok((0..CONFIGURATIONS)
+        .map(|config| {
+            let cells = get_cells(config, neighbours);
+            let next = table.transitions.iter().filter_map(|transition| table.apply(transition, &cells)).next();
+            next.unwrap_or(cells[0]) == 1
+        })
+        .collect())
+}
+
+fn parse_tree(lines: &[&str]) -> io::Result<Vec<bool>> {
+    let mut neighbours = MOORE;
+    // level and children of every node, children of level 1 nodes are states
+    let mut nodes: Vec<(usize, Vec<usize>)> = Vec::new();
+
+    for line in lines {
+        if let Some(pos) = line.find('=') {
+            let (key, value) = (line[..pos].trim(), line[pos + 1..].trim());
+            match key {
+                "num_states" => check_states(value)?,
+                "num_neighbors" => neighbours = match value {
+                    "8" => MOORE,
+                    "4" => VON_NEUMANN,
+                    _ => return invalid_data(&format!("unsupported number of neighbors '{}'", value))
+                },
+                "num_nodes" => {}
+                _ => return invalid_data(&format!("unknown rule tree setting '{}'", key))
+            }
+            continue;
+        }
+
+        let values: Vec<usize> = match line.split_whitespace().map(|v| v.parse()).collect() {
+            Ok(values) => values,
+            Err(_) => return invalid_data(&format!("malformed rule tree node '{}'", line))
+        };
+
+        // children are states at level 1 and earlier nodes of the level below otherwise
+        let valid = values.len() == 3 && match values[0] {
+            0 => false,
+            1 => values[1..].iter().all(|&state| state < 2),
+            level => values[1..].iter().all(|&child| child < nodes.len() && nodes[child].0 == level - 1),
+        };
+        if !valid {
+            return invalid_data(&format!("malformed rule tree node '{}'", line));
+        }
+
+        nodes.push((values[0], values[1..].to_vec()));
+    }
+
+    let root = nodes.len().wrapping_sub(1);
+    if nodes.last().map_or(true, |node| node.0 != neighbours + 1) {
+        return invalid_data("rule tree should end with the root node");
+    }
+
+    // the tree looks at the corners first, then at the orthogonal
+    // neighbours and at the cell itself last
+    let order: Vec<usize> = if neighbours == MOORE {
+        vec![8, 2, 6, 4, 1, 7, 3, 5, 0]
+    } else {
+        vec![1, 4, 2, 3, 0]
+    };
+
+    Ok((0..CONFIGURATIONS)
+        .map(|config| {
+            let cells = get_cells(config, neighbours);
+            let state = order.iter().fold(root, |node, &cell| nodes[node].1[cells[cell] as usize]);
+            state == 1
+        })
+        .collect())
+}
+
+
+#[cfg(test)]
+use ::rule::Rule;
+
+#[cfg(test)]
+const LIFE_TABLE: &'static str = "@RULE LifeTable\n\
+                                  # Conway's Life as a table\n\
+                                  @TABLE\n\
+                                  n_states:2\n\
+                                  neighborhood:Moore\n\
+                                  symmetries:permute\n\
+                                  var a={0,1}\n\
+                                  var b={a}\n\
+                                  var c={a}\n\
+                                  var d={a}\n\
+                                  var e={a}\n\
+                                  var f={a}\n\
+                                  var g={a}\n\
+                                  var h={a}\n\
+                                  0,1,1,1,0,0,0,0,0,1\n\
+                                  1,1,1,0,0,0,0,0,0,1\n\
+                                  1,1,1,1,0,0,0,0,0,1\n\
+                                  1,a,b,c,d,e,f,g,h,0\n\
+                                  @COLORS\n\
+                                  1 255 255 255\n";
+
+#[cfg(test)]
+fn check_totalistic(table: &RuleTable, rule: &Rule) {
+    for config in 0..CONFIGURATIONS {
+        let neighbours: Vec<bool> = (0..MOORE).map(|i| config & (2 << i) != 0).collect();
+        let count = neighbours.iter().filter(|&&n| n).count();
+        let alive = config & 1 != 0;
+
+        let expected = if alive { rule.survives(count) } else { rule.is_born(count) };
+        assert_eq!(table.next_state(alive, &neighbours), expected, "{:09b}", config);
+    }
+}
+
+#[test]
+fn test_rule_table() {
+    let table = RuleTable::parse(LIFE_TABLE).unwrap();
+
+    assert_eq!(table.get_name(), "LifeTable");
+    assert_eq!(table.get_source(), LIFE_TABLE);
+    check_totalistic(&table, &Rule::life());
+
+    // the same without commas and with rotations instead of permutations,
+    // a cell with one live neighbour in the north dies, one in the south stays
+    let table = RuleTable::parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:none\n\
+                                  110000\n").unwrap();
+    assert!(!table.next_state(true, &[false, false, true, false, false, false, false, false]));
+    assert!(table.next_state(true, &[false, false, false, false, false, false, true, false]));
+
+    let table = RuleTable::parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:rotate4\n\
+                                  110000\n").unwrap();
+    assert!(!table.next_state(true, &[false, false, false, false, false, false, true, false]));
+    // corners are not neighbours
+    assert!(table.next_state(true, &[false, true, false, true, false, true, false, true]));
+}
+
+#[test]
+fn test_rule_tree() {
+    // B3/S23 as a tree of neighbour counts, node k of level l tells
+    // what to do with k live neighbours among those seen so far
+    let mut lines = vec!["@RULE LifeTree".to_string(), "@TREE".to_string(),
+                         "num_states=2".to_string(), "num_neighbors=8".to_string()];
+
+    let life = Rule::life();
+    let mut level_nodes: Vec<usize> = Vec::new();
+    let mut count = 0;
+
+    // level 1 nodes for 0..=8 neighbours, then levels 2..=9 for fewer seen neighbours
+    for n in 0..9 {
+        lines.push(format!("1 {} {}", life.is_born(n) as u8, life.survives(n) as u8));
+        level_nodes.push(count);
+        count += 1;
+    }
+    for level in 2..10 {
+        let seen = 10 - level;
+        let mut next = Vec::new();
+        for n in 0..seen {
+            lines.push(format!("{} {} {}", level, level_nodes[n], level_nodes[n + 1]));
+            next.push(count);
+            count += 1;
+        }
+        level_nodes = next;
+    }
+    lines.push("@COLORS".to_string());
+
+    let table = RuleTable::parse(&lines.join("\n")).unwrap();
+    assert_eq!(table.get_name(), "LifeTree");
+    check_totalistic(&table, &life);
+}
+
+#[test]
+fn test_rule_table_errors() {
+    let parse = |source: &str| RuleTable::parse(source).err().map(|err| err.to_string());
+
+    assert_eq!(parse("@RULE Empty\n@COLORS\n"), Some("rule file has neither @TABLE nor @TREE".to_string()));
+    assert!(parse("@TABLE\nn_states:3\nneighborhood:Moore\n").unwrap().contains("3 states"));
+    assert!(parse("@TABLE\nn_states:2\nneighborhood:hexagonal\n").is_some());
+    assert!(parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:rotate8\n").is_some());
+    assert!(parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\n1100\n").is_some());
+    assert!(parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\nvar a={0,1}\n1100a0a\n").is_some());
+    assert!(parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\nvar a={0,1}\n11000a\n").is_some());
+
+    // B0
+    assert!(parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\n000001\n").is_some());
+
+    assert!(parse("@TREE\nnum_states=2\nnum_neighbors=4\n1 0 1\n2 0 5\n").is_some());
+}
@@ -0,0 +1,135 @@
+// SSSE3 stepping of outer totalistic rules: live neighbours of 16 cells
+// are summed by adding shifted rows byte by byte, and their next states
+// are looked up in the rule counts with a byte shuffle. The stats are
+// counted on bit masks of the 16 cells, ages are updated for live cells only
+use std::arch::x86_64::*;
+
+use super::{Grid, Stats};
+
+
+const LANES: usize = 16;
+
+#[inline]
+unsafe fn load(cells: &[u8], idx: usize) -> __m128i {
+    _mm_loadu_si128(cells[idx..idx + LANES].as_ptr() as *const __m128i)
+}
+
+/// Same as `Kernel::step_scalar` for a kernel with rule counts
+#[target_feature(enable = "ssse3")]
+pub unsafe fn step(counts: &([u8; 16], [u8; 16]), grid: &Grid, next: &mut Grid) -> Stats {
+    let mut stats = Stats { population: 0, births: 0, deaths: 0 };
+
+    let birth = load(&counts.0, 0);
+    let survival = load(&counts.1, 0);
+    let zero = _mm_setzero_si128();
+
+    let width = grid.width;
+
+    for y in 1..grid.height - 1 {
+        let mut x = 1;
+
+        // loads reach one cell past the last of the 16
+        while x + LANES < width {
+            let (up, mid, down) = ((y - 1) * width + x, y * width + x, (y + 1) * width + x);
+
+            let mut count = zero;
+            for &idx in [up - 1, up, up + 1, mid - 1, mid + 1, down - 1, down, down + 1].iter() {
+                count = _mm_add_epi8(count, load(&grid.cells, idx));
+            }
+
+            let cells = load(&grid.cells, mid);
+            let is_alive = _mm_cmpgt_epi8(cells, zero);
+            let next_cells = _mm_or_si128(_mm_and_si128(is_alive, _mm_shuffle_epi8(survival, count)),
+                                          _mm_andnot_si128(is_alive, _mm_shuffle_epi8(birth, count)));
+
+            _mm_storeu_si128(next.cells[mid..mid + LANES].as_mut_ptr() as *mut __m128i, next_cells);
+
+            // one bit per cell
+            let was_alive = _mm_movemask_epi8(is_alive) as u32;
+            let mut alive = _mm_movemask_epi8(_mm_cmpgt_epi8(next_cells, zero)) as u32;
+
+            stats.population += alive.count_ones() as usize;
+            stats.births += (alive & !was_alive).count_ones() as usize;
+            stats.deaths += (was_alive & !alive).count_ones() as usize;
+
+            while alive != 0 {
+                let idx = mid + alive.trailing_zeros() as usize;
+                next.ages[idx] = if grid.cells[idx] != 0 { grid.ages[idx] + 1 } else { 1 };
+                alive &= alive - 1;
+            }
+
+            x += LANES;
+        }
+
+        // the rest of the row which doesn't fill the lanes
+        while x < width - 1 {
+            let (up, mid, down) = ((y - 1) * width + x, y * width + x, (y + 1) * width + x);
+
+            let count = [up - 1, up, up + 1, mid - 1, mid + 1, down - 1, down, down + 1].iter()
+                .map(|&idx| grid.cells[idx] as usize)
+                .sum::<usize>();
+            let next_state = if grid.cells[mid] != 0 { counts.1[count] } else { counts.0[count] };
+
+            grid.update(next, mid, next_state != 0, &mut stats);
+            x += 1;
+        }
+    }
+
+    stats
+}
+
+
+#[cfg(test)]
+use super::Kernel;
+#[cfg(test)]
+use ::rule::Rule;
+
+#[cfg(test)]
+fn random_grid(width: usize, height: usize, seed: u64) -> Grid {
+    let mut grid = Grid::new(0, 0, width, height);
+    let mut state = seed;
+
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            if (state >> 33) % 3 == 0 {
+                grid.cells[y * width + x] = 1;
+                grid.ages[y * width + x] = 1;
+            }
+        }
+    }
+    grid
+}
+
+#[cfg(test)]
+fn live_cells(grid: &Grid) -> Vec<(usize, usize)> {
+    grid.cells.iter().enumerate()
+        .filter(|&(_, &cell)| cell != 0)
+        .map(|(idx, _)| (idx, grid.ages[idx]))
+        .collect()
+}
+
+#[test]
+fn test_simd_step() {
+    if !is_x86_feature_detected!("ssse3") {
+        return;
+    }
+
+    for rule in ["B3/S23", "B36/S125"].iter() {
+        let kernel = Kernel::from_rule(&rule.parse::<Rule>().unwrap());
+        let counts = kernel.counts.unwrap();
+
+        // rows of 4 lanes and a tail
+        let mut grid = random_grid(70, 24, 1);
+        let mut scalar = Grid::new(0, 0, 70, 24);
+        let mut vector = Grid::new(0, 0, 70, 24);
+
+        for _ in 0..8 {
+            let expected = kernel.step_scalar(&grid, &mut scalar);
+            assert_eq!(unsafe { step(&counts, &grid, &mut vector) }, expected);
+            assert_eq!(live_cells(&vector), live_cells(&scalar));
+
+            ::std::mem::swap(&mut grid, &mut vector);
+        }
+    }
+}
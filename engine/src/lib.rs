@@ -2,3 +2,22 @@ pub mod cam;
 pub mod board;
 pub mod structs;
 pub mod engine;
+pub mod error;
+pub mod history;
+pub mod rule;
+pub mod io;
+pub mod soup;
+pub mod autosave;
+pub mod timeline;
+pub mod census;
+pub mod cycles;
+pub mod search;
+pub mod snapshot;
+pub mod presets;
+pub mod kernel;
+pub mod simd;
+pub mod progress;
+pub mod explore;
+pub mod zones;
+pub mod backend;
+pub mod pattern;
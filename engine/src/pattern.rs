@@ -0,0 +1,264 @@
+/// Pattern as a value of its own: live cells relative to the top left
+/// corner of the pattern, its size, rule and name, author and comments
+/// of the file it came from. Patterns are read from files, RLE strings
+/// or regions of the board, turned around and placed back:
+/// ```
+/// # use engine::pattern::Pattern;
+/// # use engine::board::Coord;
+/// let glider: Pattern = "bo$2bo$3o!".parse().unwrap();
+/// assert_eq!((glider.get_width(), glider.get_height()), (3, 3));
+///
+/// // turned clockwise it heads down to the left
+/// let turned = glider.rotate();
+/// assert_eq!(turned, "o$obo$2o!".parse().unwrap());
+/// assert_eq!(turned.translate(10, 20)[0], Coord { col: 10, row: 20 });
+/// ```
+///
+
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use ::board::{Board, Coord, Rect};
+use ::io::{read_pattern, parse_pattern, PatternMeta};
+use ::io::rle::{decode_cells, RleFile};
+
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pattern {
+    // sorted, none of them left of or above (0, 0)
+    cells: Vec<Coord>,
+    // at least the bounding box of the cells, more for regions
+    // of the board with empty margins
+    width: usize,
+    height: usize,
+
+    pub rule: Option<String>,
+    pub meta: PatternMeta,
+}
+
+fn get_extent(cells: &[Coord]) -> (usize, usize) {
+    let right = cells.iter().map(|c| c.col + 1).max().unwrap_or(0);
+    let bottom = cells.iter().map(|c| c.row + 1).max().unwrap_or(0);
+    (right as usize, bottom as usize)
+}
+
+impl Pattern {
+
+    /// Pattern of the given cells, moved so that their bounding box
+    /// starts at (0, 0)
+    pub fn new(cells: &[Coord]) -> Self {
+        let left = cells.iter().map(|c| c.col).min().unwrap_or(0);
+        let top = cells.iter().map(|c| c.row).min().unwrap_or(0);
+
+        let mut cells: Vec<Coord> = cells.iter().map(|c| Coord { col: c.col - left, row: c.row - top }).collect();
+        cells.sort();
+        cells.dedup();
+
+        let (width, height) = get_extent(&cells);
+
+        Pattern {
+            cells: cells,
+            width: width,
+            height: height,
+
+            rule: None,
+            meta: PatternMeta::default(),
+        }
+    }
+
+    /// Live cells of a region of the board, the region is kept as it
+    /// is, empty margins included
+    pub fn from_selection(board: &Board, rect: &Rect) -> Self {
+        let cells = board.copy_region(rect);
+
+        Pattern {
+            cells: cells,
+            width: rect.get_width(),
+            height: rect.get_height(),
+
+            rule: None,
+            meta: PatternMeta::default(),
+        }
+    }
+
+    /// Pattern file of any supported format, see `io::read_pattern`
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let (cells, rule, meta) = read_pattern(path)?;
+        Ok(Pattern { rule: rule, meta: meta, ..Pattern::new(&cells) })
+    }
+
+    /// Contents of a pattern file already in memory
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let (cells, rule, meta) = parse_pattern(data)?;
+        Ok(Pattern { rule: rule, meta: meta, ..Pattern::new(&cells) })
+    }
+
+    #[inline]
+    pub fn get_cells(&self) -> &[Coord] {
+        &self.cells
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    #[inline]
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Smallest region containing the live cells
+    pub fn get_bounding_box(&self) -> Option<Rect> {
+        if self.cells.is_empty() {
+            return None;
+        }
+
+        let left = self.cells.iter().map(|c| c.col).min().unwrap_or(0);
+        let top = self.cells.iter().map(|c| c.row).min().unwrap_or(0);
+        let (right, bottom) = get_extent(&self.cells);
+        Some(Rect::new(left, top, right as i64 - 1, bottom as i64 - 1))
+    }
+
+    // cells moved by the given function, the size is kept or swapped
+    // as the function turns the pattern
+    fn map<F>(&self, swap: bool, f: F) -> Pattern
+        where F: Fn(i64, i64) -> (i64, i64) {
+
+        let height = self.height as i64;
+        let mut cells: Vec<Coord> = self.cells.iter()
+            .map(|c| {
+                let (col, row) = f(c.col, c.row);
+                // back into the box the pattern occupies
+                Coord { col: col + if swap { height - 1 } else { 0 }, row: row }
+            })
+            .collect();
+        cells.sort();
+
+        Pattern {
+            cells: cells,
+            width: if swap { self.height } else { self.width },
+            height: if swap { self.width } else { self.height },
+            ..self.clone()
+        }
+    }
+
+    /// Turned clockwise by a quarter
+    pub fn rotate(&self) -> Pattern {
+        self.map(true, |col, row| (-row, col))
+    }
+
+    /// Mirrored left to right
+    pub fn flip_horizontal(&self) -> Pattern {
+        let right = self.width as i64 - 1;
+        self.map(false, |col, row| (right - col, row))
+    }
+
+    /// Mirrored top to bottom
+    pub fn flip_vertical(&self) -> Pattern {
+        let bottom = self.height as i64 - 1;
+        self.map(false, |col, row| (col, bottom - row))
+    }
+
+    /// Turned clockwise `orientation % 4` times and then mirrored left to
+    /// right if `orientation` is 4 or above, the 8 ways a pattern can lie
+    pub fn orient(&self, orientation: usize) -> Pattern {
+        let mut res = self.clone();
+        for _ in 0..orientation % 4 {
+            res = res.rotate();
+        }
+        if orientation >= 4 { res.flip_horizontal() } else { res }
+    }
+
+    /// Cells of the pattern placed with its top left corner at the
+    /// given cell of the board
+    pub fn translate(&self, col: i64, row: i64) -> Vec<Coord> {
+        self.cells.iter().map(|c| Coord { col: c.col + col, row: c.row + row }).collect()
+    }
+
+}
+
+impl From<RleFile> for Pattern {
+    fn from(file: RleFile) -> Self {
+        let meta = PatternMeta { name: file.name, author: file.author, comments: file.comments };
+        Pattern { rule: file.rule, meta: meta, ..Pattern::new(&file.cells) }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = io::Error;
+
+    /// Contents of a pattern file or bare RLE cells like `bo$2bo$3o!`
+    fn from_str(data: &str) -> io::Result<Self> {
+        Pattern::parse(data.as_bytes())
+            .or_else(|_| decode_cells(data).map(|cells| Pattern::new(&cells)))
+    }
+}
+
+
+#[cfg(test)]
+use ::board::hashed::new as new_hashed;
+
+#[test]
+fn test_pattern_transforms() {
+    let glider: Pattern = "bo$2bo$3o!".parse().unwrap();
+    assert_eq!(glider.len(), 5);
+    assert_eq!(glider.get_bounding_box(), Some(Rect::new(0, 0, 2, 2)));
+
+    // a quarter turn four times, a flip twice
+    let mut turned = glider.clone();
+    for _ in 0..4 {
+        turned = turned.rotate();
+    }
+    assert_eq!(turned, glider);
+    assert_eq!(glider.flip_vertical().flip_vertical(), glider);
+    assert_eq!(glider.flip_horizontal(), "bo$o$3o!".parse().unwrap());
+    assert_eq!(glider.flip_vertical(), "3o$2bo$bo!".parse().unwrap());
+
+    // turning swaps the sides and keeps the cells inside
+    let line: Pattern = "o$o$o$o!".parse().unwrap();
+    let turned = line.rotate();
+    assert_eq!((turned.get_width(), turned.get_height()), (4, 1));
+    assert_eq!(turned.get_cells(), Pattern::new(&[Coord { col: 5, row: -2 }, Coord { col: 6, row: -2 },
+                                                  Coord { col: 7, row: -2 }, Coord { col: 8, row: -2 }]).get_cells());
+
+    let distinct: Vec<Pattern> = (0..8).map(|orientation| glider.orient(orientation)).collect();
+    assert!((0..8).all(|a| (0..8).all(|b| a == b || distinct[a] != distinct[b])));
+
+    assert!("not a pattern".parse::<Pattern>().is_err());
+}
+
+#[test]
+fn test_pattern_from_selection() {
+    let mut board = Board::new(new_hashed(), None, None);
+    for &(col, row) in [(5, 5), (6, 5), (6, 6)].iter() {
+        board.born_at(col, row);
+    }
+
+    // the margins of the selection are kept
+    let pattern = Pattern::from_selection(&board, &Rect::new(4, 4, 8, 7));
+    assert_eq!((pattern.get_width(), pattern.get_height()), (5, 4));
+    assert_eq!(pattern.get_bounding_box(), Some(Rect::new(1, 1, 2, 2)));
+    assert_eq!(pattern.translate(4, 4), vec![Coord { col: 5, row: 5 }, Coord { col: 6, row: 5 }, Coord { col: 6, row: 6 }]);
+
+    // turned within the selection
+    assert_eq!(pattern.rotate().get_bounding_box(), Some(Rect::new(1, 1, 2, 2)));
+    assert_eq!(pattern.flip_horizontal().get_bounding_box(), Some(Rect::new(2, 1, 3, 2)));
+
+    let rle = RleFile { name: Some("L".to_string()), rule: Some("B3/S23".to_string()),
+                        cells: pattern.get_cells().to_vec(), ..RleFile::default() };
+    let pattern = Pattern::from(rle);
+    assert_eq!(pattern.meta.name, Some("L".to_string()));
+    assert_eq!(pattern.get_cells()[0], Coord { col: 0, row: 0 });
+}
@@ -0,0 +1,121 @@
+/// Ready to run demos: a pattern together with the rule it is meant for,
+/// the way it is best looked at and how fast it should go.
+///
+/// Example usage:
+/// ```
+/// # use engine::presets;
+/// # use engine::engine::Engine;
+/// let mut engine = Engine::new(None, None);
+/// engine.load_preset(presets::find("acorn").unwrap()).unwrap();
+///
+/// assert_eq!(engine.get_board().get_population(), 7);
+/// ```
+///
+
+use std::io;
+
+use ::board::Coord;
+use ::io::rle::decode_cells;
+
+
+pub struct Preset {
+    // short name used on the command line
+    pub id: &'static str,
+    pub name: &'static str,
+    // cells as RLE, without the header
+    pub pattern: &'static str,
+    pub rule: &'static str,
+    // board zoom, the whole pattern is fit on the screen if not given
+    pub zoom: Option<f64>,
+    // generations per simulation tick
+    pub speed: u64,
+}
+
+pub const PRESETS: [Preset; 5] = [
+    Preset {
+        id: "gun",
+        name: "Gosper gun + eater",
+        pattern: "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$\
+                  10bo5bo7bo$11bo3bo$12b2o4$26b2o$26bobo$28bo$28b2o!",
+        rule: "B3/S23",
+        zoom: None,
+        speed: 1,
+    },
+    Preset {
+        id: "r-pentomino",
+        name: "R-pentomino",
+        pattern: "b2o$2o$bo!",
+        rule: "B3/S23",
+        zoom: Some(0.5),
+        speed: 2,
+    },
+    Preset {
+        id: "acorn",
+        name: "Acorn",
+        pattern: "bo$3bo$2o2b3o!",
+        rule: "B3/S23",
+        zoom: Some(0.3),
+        speed: 4,
+    },
+    Preset {
+        id: "diehard",
+        name: "Diehard",
+        pattern: "6bo$2o$bo3b3o!",
+        rule: "B3/S23",
+        zoom: Some(1.0),
+        speed: 1,
+    },
+    Preset {
+        id: "replicator",
+        name: "HighLife replicator",
+        pattern: "2b3o$bo2bo$o3bo$o2bo$3o!",
+        rule: "B36/S23",
+        zoom: Some(0.5),
+        speed: 1,
+    },
+];
+
+
+pub fn find(id: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.id == id)
+}
+
+impl Preset {
+
+    pub fn get_cells(&self) -> io::Result<Vec<Coord>> {
+        decode_cells(self.pattern)
+    }
+
+}
+
+
+#[cfg(test)]
+use ::engine::Engine;
+
+#[test]
+fn test_presets() {
+    for preset in PRESETS.iter() {
+        assert!(!preset.get_cells().unwrap().is_empty());
+        assert!(preset.speed > 0);
+        assert!(find(preset.id).is_some());
+    }
+
+    assert!(find("breeder").is_none());
+
+    // the eater keeps the gun from filling the board with gliders
+    let mut engine = Engine::new(None, None);
+    engine.load_preset(find("gun").unwrap()).unwrap();
+
+    engine.iterations(300);
+    let population = engine.get_board().get_population();
+    engine.iterations(300);
+    assert_eq!(engine.get_board().get_population(), population);
+    assert!(engine.get_board().get_bounding_box().unwrap().get_width() < 40);
+
+    engine.load_preset(find("replicator").unwrap()).unwrap();
+    assert_eq!(engine.get_rule().to_string(), "B36/S23");
+    assert_eq!(engine.get_pattern_meta().name, Some("HighLife replicator".to_string()));
+
+    engine.iterations(12);
+    assert_eq!(engine.get_board().get_population(), 24);
+}
@@ -0,0 +1,145 @@
+/// Progress of a long run of generations: how many are done, the
+/// wall-clock time it took so far and the time left, estimated from
+/// the rate of the last few seconds, so that it follows patterns
+/// growing or dying out on the way. Times are seconds from any clock,
+/// e.g. `time::precise_time_s()`:
+/// ```
+/// # use engine::progress::Progress;
+/// let mut progress = Progress::new(1000, 10.0);
+/// progress.update(250, 15.0);
+///
+/// assert_eq!(progress.get_eta(), Some(15.0));
+/// assert_eq!(progress.to_string(), "250/1000 generations, 0:05 elapsed, 0:15 left");
+/// ```
+///
+
+use std::collections::VecDeque;
+use std::fmt;
+
+
+// seconds the generation rate is averaged over
+const RATE_WINDOW: f64 = 5.0;
+
+
+pub struct Progress {
+    total: u64,
+    done: u64,
+
+    started: f64,
+    // time and generations done of the recent updates, the oldest first
+    samples: VecDeque<(f64, u64)>,
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs.max(0.0).round() as u64;
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+impl Progress {
+
+    pub fn new(total: u64, now: f64) -> Self {
+        let mut samples = VecDeque::new();
+        samples.push_back((now, 0));
+
+        Progress {
+            total: total,
+            done: 0,
+
+            started: now,
+            samples: samples,
+        }
+    }
+
+    pub fn update(&mut self, done: u64, now: f64) {
+        self.done = done;
+        self.samples.push_back((now, done));
+
+        // the oldest sample inside of the window is kept to measure from
+        while self.samples.len() > 2 && now - self.samples[1].0 >= RATE_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    #[inline]
+    pub fn get_done(&self) -> u64 {
+        self.done
+    }
+
+    #[inline]
+    pub fn get_total(&self) -> u64 {
+        self.total
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.done >= self.total
+    }
+
+    /// Seconds from the start to the last update
+    pub fn get_elapsed(&self) -> f64 {
+        self.samples.back().map_or(0.0, |&(time, _)| time - self.started)
+    }
+
+    /// Generations per second over the last few seconds
+    pub fn get_rate(&self) -> Option<f64> {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(first_time, first_done)), Some(&(last_time, last_done))) if last_time > first_time =>
+                Some((last_done - first_done) as f64 / (last_time - first_time)),
+            _ => None
+        }
+    }
+
+    /// Seconds left until all the generations are done
+    pub fn get_eta(&self) -> Option<f64> {
+        if self.is_finished() {
+            return Some(0.0);
+        }
+
+        match self.get_rate() {
+            Some(rate) if rate > 0.0 => Some((self.total - self.done) as f64 / rate),
+            _ => None
+        }
+    }
+
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} generations, {} elapsed", self.done, self.total, format_duration(self.get_elapsed()))?;
+
+        match self.get_eta() {
+            Some(eta) => write!(f, ", {} left", format_duration(eta)),
+            None => Ok(())
+        }
+    }
+}
+
+
+#[test]
+fn test_progress_rate() {
+    let mut progress = Progress::new(10000, 0.0);
+    assert_eq!(progress.get_rate(), None);
+    assert_eq!(progress.get_eta(), None);
+    assert_eq!(progress.to_string(), "0/10000 generations, 0:00 elapsed");
+
+    // fast start, then the pattern grows and slows down, only
+    // the last seconds count
+    progress.update(5000, 1.0);
+    for secs in 2..11 {
+        progress.update(5000 + (secs - 1) * 100, secs as f64);
+    }
+
+    assert_eq!(progress.get_rate(), Some(100.0));
+    assert_eq!(progress.get_eta(), Some(41.0));
+    assert_eq!(progress.to_string(), "5900/10000 generations, 0:10 elapsed, 0:41 left");
+
+    progress.update(10000, 3700.0);
+    assert!(progress.is_finished());
+    assert_eq!(progress.to_string(), "10000/10000 generations, 1:01:40 elapsed, 0:00 left");
+}
@@ -0,0 +1,160 @@
+/// Outer totalistic rule of a two-state automaton: a dead cell is born
+/// and a live cell survives depending on the number of its live
+/// neighbours. Written in B/S notation, `B3/S23` is Conway's Life,
+/// the older S/B notation `23/3` is accepted too:
+/// ```
+/// # use engine::rule::Rule;
+/// let highlife: Rule = "B36/S23".parse().unwrap();
+/// assert!(highlife.is_born(6));
+/// assert!(highlife.survives(2));
+/// assert_eq!(highlife.to_string(), "B36/S23");
+/// ```
+///
+
+use std::fmt;
+use std::str::FromStr;
+
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Rule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+}
+
+impl Rule {
+
+    pub fn life() -> Self {
+        "B3/S23".parse().expect("malformed Life rule")
+    }
+
+    #[inline]
+    pub fn is_born(&self, neighbours: usize) -> bool {
+        self.birth[neighbours]
+    }
+
+    #[inline]
+    pub fn survives(&self, neighbours: usize) -> bool {
+        self.survival[neighbours]
+    }
+
+    /// Rules differing from this one by a single birth or survival
+    /// condition, births first; B0 is skipped as it isn't supported
+    pub fn get_mutations(&self) -> Vec<Rule> {
+        let births = (1..9).map(|n| {
+            let mut rule = *self;
+            rule.birth[n] = !rule.birth[n];
+            rule
+        });
+        let survivals = (0..9).map(|n| {
+            let mut rule = *self;
+            rule.survival[n] = !rule.survival[n];
+            rule
+        });
+
+        births.chain(survivals).collect()
+    }
+
+    fn parse_counts(digits: &str) -> Result<[bool; 9], String> {
+        let mut res = [false; 9];
+
+        for ch in digits.chars() {
+            match ch.to_digit(10) {
+                Some(n) if n <= 8 => res[n as usize] = true,
+                _ => return Err(format!("unexpected symbol '{}' in rule", ch))
+            }
+        }
+
+        Ok(res)
+    }
+
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::life()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // bounded grid specification after ':' is not a part of the rule
+        let rule: String = s.split(':').next().unwrap_or("")
+            .chars().filter(|c| !c.is_whitespace()).collect();
+        let rule = rule.to_uppercase();
+
+        let parts: Vec<&str> = rule.split('/').collect();
+        if parts.len() != 2 {
+            return Err(format!("malformed rule '{}'", s));
+        }
+
+        let (birth, survival) = if parts[0].starts_with('B') && parts[1].starts_with('S') {
+            (&parts[0][1..], &parts[1][1..])
+        } else if parts[0].starts_with('S') && parts[1].starts_with('B') {
+            (&parts[1][1..], &parts[0][1..])
+        } else {
+            // S/B notation without letters
+            (parts[1], parts[0])
+        };
+
+        let rule = Rule {
+            birth: Rule::parse_counts(birth)?,
+            survival: Rule::parse_counts(survival)?,
+        };
+
+        // only cells near live ones are simulated, so empty space can't come alive
+        if rule.birth[0] {
+            return Err(format!("rules with B0 are not supported: '{}'", s));
+        }
+
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |values: &[bool; 9]| -> String {
+            (0..9).filter(|&n| values[n]).map(|n| n.to_string()).collect()
+        };
+
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+
+#[test]
+fn test_rule_parse() {
+    let life = Rule::life();
+
+    assert_eq!("b3/s23".parse(), Ok(life));
+    assert_eq!("23/3".parse(), Ok(life));
+    assert_eq!("S23/B3".parse(), Ok(life));
+    assert_eq!("B3/S23:T100,100".parse(), Ok(life));
+
+    assert!(life.is_born(3) && !life.is_born(2));
+    assert!(life.survives(2) && life.survives(3) && !life.survives(4));
+
+    let seeds: Rule = "B2/S".parse().unwrap();
+    assert_eq!(seeds.to_string(), "B2/S");
+    assert!((0..9).all(|n| !seeds.survives(n)));
+
+    assert!("B3".parse::<Rule>().is_err());
+    assert!("B39/S23".parse::<Rule>().is_err());
+    assert!("B03/S23".parse::<Rule>().is_err());
+    assert!("W110".parse::<Rule>().is_err());
+}
+
+#[test]
+fn test_rule_mutations() {
+    let mutations = Rule::life().get_mutations();
+    assert_eq!(mutations.len(), 17);
+
+    let names: Vec<String> = mutations.iter().map(|rule| rule.to_string()).collect();
+    assert_eq!(names[0], "B13/S23");
+    assert_eq!(names[2], "B/S23");
+    assert_eq!(names[5], "B36/S23");
+    assert_eq!(names[8], "B3/S023");
+    assert_eq!(names[11], "B3/S2");
+    assert!(!mutations.contains(&Rule::life()));
+}
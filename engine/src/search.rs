@@ -0,0 +1,261 @@
+/// Searching the board for all occurrences of a small pattern, rotated
+/// and mirrored in any of the 8 ways. An occurrence is exact: cells of
+/// the pattern bounding box match it and the cells around the box are
+/// dead, so a glider isn't found inside of a bigger blob.
+///
+/// Example usage:
+/// ```
+/// # use engine::search::find;
+/// # use engine::engine::Engine;
+/// # use engine::io::rle::decode_cells;
+/// let mut engine = Engine::new(None, None);
+/// engine.set_pattern(&decode_cells("bo$2bo$3o9$10b3o$10bo$11bo!").unwrap());
+///
+/// // two gliders, one of them mirrored
+/// assert_eq!(find(engine.get_board(), &decode_cells("bo$2bo$3o!").unwrap()).len(), 2);
+/// ```
+///
+
+use ::board::{Board, LifeBoard, Coord, Rect, CombineMode};
+use ::pattern::Pattern;
+
+use std::collections::BTreeMap;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Match {
+    // where the oriented pattern is on the board
+    pub rect: Rect,
+    // the way pattern is turned, see orient()
+    pub orientation: usize,
+}
+
+
+/// Cells turned clockwise `orientation % 4` times and then mirrored
+/// if `orientation` is 4 or above, moved so that the top left corner
+/// of the result is (0, 0)
+pub fn orient(cells: &[Coord], orientation: usize) -> Vec<Coord> {
+    Pattern::new(cells).orient(orientation).get_cells().to_vec()
+}
+
+fn matches_at<B: LifeBoard + ?Sized>(board: &B, cells: &[Coord], rect: &Rect) -> bool {
+    // the bounding box with one cell around it
+    for row in rect.top - 1..rect.bottom + 2 {
+        for col in rect.left - 1..rect.right + 2 {
+            let expected = cells.binary_search(&Coord { col: col - rect.left, row: row - rect.top }).is_ok();
+            if board.is_alive(col, row) != expected {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+pub fn find<B: LifeBoard + ?Sized>(board: &B, cells: &[Coord]) -> Vec<Match> {
+    if cells.is_empty() {
+        return Vec::new();
+    }
+
+    // symmetric patterns look the same in several orientations
+    let mut orientations: Vec<(usize, Vec<Coord>)> = Vec::new();
+    for orientation in 0..8 {
+        let oriented = orient(cells, orientation);
+        if orientations.iter().all(|&(_, ref other)| *other != oriented) {
+            orientations.push((orientation, oriented));
+        }
+    }
+
+    let live: Vec<Coord> = board.get_live_cells().collect();
+
+    let mut res = Vec::new();
+
+    for &(orientation, ref oriented) in &orientations {
+        let width = oriented.iter().map(|c| c.col).max().unwrap_or(0);
+        let height = oriented.iter().map(|c| c.row).max().unwrap_or(0);
+
+        // the first cell of the pattern sits on a live cell of the board
+        let first = oriented[0];
+
+        for cell in &live {
+            let (left, top) = (cell.col - first.col, cell.row - first.row);
+            let rect = Rect::new(left, top, left + width, top + height);
+
+            if matches_at(board, oriented, &rect) {
+                res.push(Match { rect: rect, orientation: orientation });
+            }
+        }
+    }
+
+    // reading order, as the matches are listed to the user
+    res.sort_by(|a, b| (a.rect.top, a.rect.left).cmp(&(b.rect.top, b.rect.left)));
+    res
+}
+
+/// Replaces every match with the given cells turned the same way as the
+/// match, their top left corners are at the same place. Returns previous
+/// state of the changed cells, so the whole replacement can be undone
+/// with restore()
+pub fn replace(board: &mut Board, matches: &[Match], cells: &[Coord]) -> Vec<(Coord, bool)> {
+    let mut before: BTreeMap<Coord, bool> = BTreeMap::new();
+
+    for found in matches {
+        let oriented = orient(cells, found.orientation);
+        let (left, top) = (found.rect.left, found.rect.top);
+
+        let width = oriented.iter().map(|c| c.col).max().unwrap_or(0);
+        let height = oriented.iter().map(|c| c.row).max().unwrap_or(0);
+        let pasted = Rect::new(left, top, left.saturating_add(width), top.saturating_add(height));
+
+        for rect in &[found.rect, pasted] {
+            for row in rect.rows() {
+                for col in rect.cols() {
+                    before.entry(Coord { col: col, row: row }).or_insert(board.is_alive(col, row));
+                }
+            }
+        }
+
+        board.clear_region(&found.rect);
+        board.paste(&oriented, left, top, CombineMode::Or);
+    }
+
+    before.into_iter()
+        .filter(|&(cell, alive)| board.is_alive(cell.col, cell.row) != alive)
+        .collect()
+}
+
+pub fn restore<B: LifeBoard + ?Sized>(board: &mut B, cells: &[(Coord, bool)]) {
+    for &(cell, alive) in cells {
+        if alive && !board.is_alive(cell.col, cell.row) {
+            board.born_at(cell.col, cell.row);
+        } else if !alive && board.is_alive(cell.col, cell.row) {
+            board.kill_at(cell.col, cell.row);
+        }
+    }
+}
+
+
+#[cfg(test)]
+use ::engine::Engine;
+#[cfg(test)]
+use ::io::rle::decode_cells;
+
+#[cfg(test)]
+fn decode_sorted(data: &str) -> Vec<Coord> {
+    let mut cells = decode_cells(data).unwrap();
+    cells.sort();
+    cells
+}
+
+#[test]
+fn test_orient() {
+    let glider = decode_sorted("bo$2bo$3o!");
+
+    assert_eq!(orient(&glider, 0), glider);
+    assert_eq!(orient(&glider, 1), decode_sorted("o$obo$2o!"));
+
+    // four turns make a full circle
+    let mut turned = glider.clone();
+    for _ in 0..4 {
+        turned = orient(&turned, 1);
+    }
+    assert_eq!(turned, glider);
+
+    // mirroring twice changes nothing
+    assert_eq!(orient(&orient(&glider, 4), 4), glider);
+}
+
+#[test]
+fn test_find() {
+    // block, a glider in two orientations and a blinker touching a glider
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("2o$2o5$bo8b3o$2bo7bo$3o8bo5$20bo$20bo$20bo$21bo$22bo$20b3o!").unwrap());
+
+    let glider = decode_cells("bo$2bo$3o!").unwrap();
+    let found = find(engine.get_board(), &glider);
+
+    // the glider next to the blinker is not isolated
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].orientation, 0);
+    assert!(found[1].orientation != 0);
+
+    for found in &found {
+        assert_eq!(found.rect.get_width(), 3);
+        assert_eq!(engine.get_board().population_in(&found.rect), 5);
+    }
+
+    let block = decode_cells("2o$2o!").unwrap();
+    assert_eq!(find(engine.get_board(), &block).len(), 1);
+}
+
+#[test]
+fn test_replace() {
+    // pre-blocks become blocks, one of them is turned
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("2o4b2o$o6bo!").unwrap());
+
+    let pre_block = decode_sorted("2o$o!");
+    let before = live_cells(engine.get_board());
+
+    let found = find(engine.get_board(), &pre_block);
+    assert_eq!(found.len(), 2);
+
+    let undo = replace(engine.get_board_mut(), &found, &decode_sorted("2o$2o!"));
+    assert_eq!(undo.len(), 2);
+    assert_eq!(engine.get_board().get_population(), 8);
+    assert_eq!(find(engine.get_board(), &decode_sorted("2o$2o!")).len(), 2);
+
+    restore(engine.get_board_mut(), &undo);
+    assert_eq!(live_cells(engine.get_board()), before);
+    assert_eq!(engine.get_board().get_population(), 6);
+}
+
+#[cfg(test)]
+fn live_cells(board: &Board) -> Vec<Coord> {
+    let mut cells: Vec<Coord> = board.into_iter().filter(|cell| cell.is_alive).map(|cell| cell.coord).collect();
+    cells.sort();
+    cells
+}
+
+#[test]
+fn test_find_in_other_boards() {
+    use std::collections::HashSet;
+
+    // live cells only, without neighbour counts or generations
+    struct Cells(HashSet<Coord>);
+
+    impl LifeBoard for Cells {
+        fn born_at(&mut self, col: i64, row: i64) {
+            self.0.insert(Coord { col: col, row: row });
+        }
+
+        fn kill_at(&mut self, col: i64, row: i64) {
+            self.0.remove(&Coord { col: col, row: row });
+        }
+
+        fn is_alive(&self, col: i64, row: i64) -> bool {
+            self.0.contains(&Coord { col: col, row: row })
+        }
+
+        fn get_live_cells<'b>(&'b self) -> Box<Iterator<Item=Coord> + 'b> {
+            Box::new(self.0.iter().cloned())
+        }
+
+        fn get_bounds(&self) -> Rect {
+            Rect::new(i64::min_value(), i64::min_value(), i64::max_value(), i64::max_value())
+        }
+    }
+
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("2o$2o5$bo8b3o$2bo7bo$3o8bo!").unwrap());
+    let mut cells = Cells(engine.get_board().get_occupied().clone());
+
+    let glider = decode_cells("bo$2bo$3o!").unwrap();
+    assert_eq!(find(&cells, &glider), find(engine.get_board(), &glider));
+    assert_eq!(cells.get_bounding_box(), engine.get_board().get_bounding_box());
+
+    let block = engine.get_board().get_bounding_box().unwrap();
+    restore(&mut cells, &[(Coord { col: block.left, row: block.top }, false)]);
+    assert_eq!(find(&cells, &glider).len(), 2);
+    assert_eq!(cells.0.len(), engine.get_board().get_population() - 1);
+}
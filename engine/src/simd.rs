@@ -0,0 +1,26 @@
+/// Switch for the vector code of the kernel and the bit-packed board.
+/// It's built with the `simd` feature only and used if the processor has
+/// the instructions it needs, otherwise the same generations are computed
+/// by the scalar code. Turning it off shows how much faster it is:
+/// ```
+/// # use engine::simd;
+/// simd::set_enabled(false);
+/// assert!(!simd::is_enabled());
+/// simd::set_enabled(true);
+/// ```
+///
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the vector code is built and not turned off, the processor
+/// is checked where it's used
+pub fn is_enabled() -> bool {
+    cfg!(all(feature = "simd", target_arch = "x86_64")) && ENABLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,95 @@
+/// Copy of everything needed to draw the board, taken in one go, so
+/// that the renderer doesn't keep the engine borrowed while drawing.
+/// Every change of the board or the rule bumps the engine version,
+/// a snapshot remembers the version it was taken at, so it's retaken
+/// only when the engine has changed since.
+///
+/// Example usage:
+/// ```
+/// # use engine::engine::Engine;
+/// # use engine::board::Coord;
+/// let mut engine = Engine::new(None, None);
+/// engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }]);
+///
+/// let snapshot = engine.snapshot();
+/// assert_eq!(snapshot.cells.len(), 3);
+/// assert!(!snapshot.is_outdated(&engine));
+///
+/// engine.one_iteration();
+/// assert!(snapshot.is_outdated(&engine));
+/// ```
+///
+
+use ::board::{Board, CellDesc};
+use ::engine::Engine;
+use ::rule::Rule;
+
+
+pub struct Snapshot {
+    version: usize,
+
+    pub iteration: usize,
+    pub rule: Rule,
+    pub population: usize,
+
+    // live cells only
+    pub cells: Vec<CellDesc>,
+}
+
+impl Snapshot {
+
+    pub fn new(board: &Board, version: usize, iteration: usize, rule: Rule) -> Self {
+        Snapshot {
+            version: version,
+
+            iteration: iteration,
+            rule: rule,
+            population: board.get_population(),
+
+            // backends keep empty cells around the live ones, going
+            // through all of them on every frame is slow for dense boards
+            cells: board.get_occupied().iter()
+                .map(|&coord| CellDesc {
+                    coord: coord,
+                    gen: board.get_cell_gen(coord.col, coord.row),
+                    is_alive: true,
+                    new_line: false,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_outdated(&self, engine: &Engine) -> bool {
+        self.version != engine.get_version()
+    }
+
+}
+
+
+#[cfg(test)]
+use ::board::Coord;
+
+#[test]
+fn test_snapshot_version() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }]);
+
+    let snapshot = engine.snapshot();
+    assert_eq!(snapshot.population, 2);
+    assert!(!snapshot.is_outdated(&engine));
+
+    // reading doesn't change anything
+    engine.get_board().get_bounding_box();
+    engine.to_rle();
+    assert!(!snapshot.is_outdated(&engine));
+
+    // editing does, even before the cells are actually changed
+    engine.get_board_mut().born_at(5, 5);
+    assert!(snapshot.is_outdated(&engine));
+
+    let snapshot = engine.snapshot();
+    assert_eq!(snapshot.cells.len(), 3);
+
+    engine.set_rule("B36/S23".parse().unwrap());
+    assert!(snapshot.is_outdated(&engine));
+}
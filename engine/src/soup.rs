@@ -0,0 +1,171 @@
+/// Hands-off soup search: random soups are seeded one after another,
+/// each one runs until it stabilizes or dies out, then its census
+/// is recorded and the next soup is seeded with an incremented seed,
+/// so any interesting soup can be reproduced later from its seed.
+///
+
+extern crate rand;
+
+use ::engine::Engine;
+use ::board::Rect;
+
+use self::rand::{SeedableRng, StdRng};
+use std::collections::VecDeque;
+
+// longest oscillator period which is recognized as stabilization
+const MAX_PERIOD: usize = 64;
+
+// population has to repeat for that many generations to consider soup stable,
+// comparing populations rather than cells lets escaping gliders be ignored
+const STABLE_WINDOW: usize = 256;
+
+// soups still active after that many generations are given up
+const MAX_GENERATIONS: usize = 20000;
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Census {
+    pub seed: u64,
+    pub generations: usize,
+    // period of the final state, 0 if the soup didn't stabilize
+    pub period: usize,
+    pub population: usize,
+}
+
+pub struct SoupSearch {
+    seed: u64,
+    rect: Rect,
+    density: f64,
+
+    // populations of recent generations, the newest first
+    recent: VecDeque<usize>,
+    last_gen: Option<usize>,
+
+    censuses: Vec<Census>,
+}
+
+impl SoupSearch {
+
+    pub fn new(first_seed: u64, rect: Rect, density: f64) -> Self {
+        SoupSearch {
+            seed: first_seed,
+            rect: rect,
+            density: density,
+
+            recent: VecDeque::with_capacity(STABLE_WINDOW + MAX_PERIOD),
+            last_gen: None,
+
+            censuses: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_censuses(&self) -> &[Census] {
+        &self.censuses
+    }
+
+    pub fn seed_board(&mut self, engine: &mut Engine) {
+        let mut rng: StdRng = SeedableRng::from_seed(&[self.seed as usize][..]);
+
+        engine.reset();
+        let board = engine.create_random_with(self.density, &self.rect, &mut rng);
+        engine.set_board(board);
+
+        self.recent.clear();
+        self.last_gen = None;
+    }
+
+    fn get_period(&self) -> Option<usize> {
+        // smallest period population sequence has over the whole window
+        if self.recent.len() < STABLE_WINDOW + MAX_PERIOD {
+            return None;
+        }
+
+        (1..MAX_PERIOD + 1).find(|&period| {
+            (0..STABLE_WINDOW).all(|idx| self.recent[idx] == self.recent[idx + period])
+        })
+    }
+
+    pub fn update(&mut self, engine: &mut Engine) -> Option<Census> {
+        // should be called after every generation, returns census of the
+        // finished soup, in that case the board is already reseeded
+
+        let gen = engine.cur_iteration();
+
+        if self.last_gen == Some(gen) {
+            return None;
+        }
+        self.last_gen = Some(gen);
+
+        let population = engine.get_board().get_population();
+
+        self.recent.push_front(population);
+        self.recent.truncate(STABLE_WINDOW + MAX_PERIOD);
+
+        let period = if population == 0 { Some(1) } else { self.get_period() };
+
+        let period = match period {
+            Some(period) => period,
+            None if gen >= MAX_GENERATIONS => 0,
+            None => return None
+        };
+
+        let census = Census { seed: self.seed, generations: gen, period: period, population: population };
+        self.censuses.push(census);
+
+        self.seed += 1;
+        self.seed_board(engine);
+
+        Some(census)
+    }
+
+}
+
+
+#[test]
+fn test_soup_search() {
+    let rect = Rect::new(0, 0, 5, 5);
+
+    let mut engine = Engine::new(None, None);
+    let mut search = SoupSearch::new(1, rect, 0.4);
+
+    // same seed always gives the same soup
+    search.seed_board(&mut engine);
+    let first = engine.get_board().get_population();
+    search.seed_board(&mut engine);
+    assert_eq!(engine.get_board().get_population(), first);
+
+    let mut census = None;
+    while census.is_none() {
+        engine.one_iteration();
+        census = search.update(&mut engine);
+    }
+
+    let census = census.unwrap();
+    assert_eq!(census.seed, 1);
+    assert_eq!(search.get_seed(), 2);
+    assert_eq!(search.get_censuses().len(), 1);
+    assert!(census.period <= MAX_PERIOD);
+
+    // next soup is already seeded
+    assert_eq!(engine.cur_iteration(), 0);
+}
+
+#[test]
+fn test_soup_extinction() {
+    let mut engine = Engine::new(None, None);
+    let mut search = SoupSearch::new(7, Rect::new(0, 0, 0, 0), 1.0);
+
+    // single cell dies out right away
+    search.seed_board(&mut engine);
+    assert_eq!(engine.get_board().get_population(), 1);
+
+    engine.one_iteration();
+    let census = search.update(&mut engine).unwrap();
+
+    assert_eq!(census, Census { seed: 7, generations: 1, period: 1, population: 0 });
+}
@@ -2,7 +2,7 @@ extern crate piston_window;
 
 use ::cam::Cam;
 use self::piston_window::PistonWindow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 
@@ -40,29 +40,71 @@ impl CellProp {
 
 pub struct GraphicsWindow {
 
-    window: Rc<RefCell<PistonWindow>>,
+    // none when events are replayed without a window
+    window: Option<Rc<RefCell<PistonWindow>>>,
 
-    width: f64,
-    height: f64,
+    // current size in logical units, it changes when window is resized
+    width: Cell<f64>,
+    height: Cell<f64>,
+
+    // physical pixels per logical unit, more than 1 on high DPI screens
+    dpi_factor: Cell<f64>,
 }
 
 
 impl GraphicsWindow {
 
     pub fn new(window_width: f64, window_height: f64, window: PistonWindow) -> Self {
-        GraphicsWindow { width: window_width,
-                         height: window_height,
-                         window: Rc::new(RefCell::new(window)) }
+        GraphicsWindow { width: Cell::new(window_width),
+                         height: Cell::new(window_height),
+                         dpi_factor: Cell::new(1.0),
+                         window: Some(Rc::new(RefCell::new(window))) }
+    }
+
+    /// Window of the given size which is never shown, events come from
+    /// a recording instead
+    pub fn headless(window_width: f64, window_height: f64) -> Self {
+        GraphicsWindow { width: Cell::new(window_width),
+                         height: Cell::new(window_height),
+                         dpi_factor: Cell::new(1.0),
+                         window: None }
+    }
+
+    pub fn resize(&self, width: f64, height: f64) -> bool {
+        // returns true if size has actually changed
+        if width == self.width.get() && height == self.height.get() {
+            return false;
+        }
+
+        self.width.set(width);
+        self.height.set(height);
+        true
+    }
+
+    #[inline]
+    pub fn get_dpi_factor(&self) -> f64 {
+        self.dpi_factor.get()
+    }
+
+    pub fn set_dpi_factor(&self, dpi_factor: f64) {
+        if dpi_factor > 0.0 {
+            self.dpi_factor.set(dpi_factor);
+        }
     }
 
     #[inline]
     pub fn get_width(&self) -> f64 {
-        self.width
+        self.width.get()
     }
 
     #[inline]
     pub fn get_height(&self) -> f64 {
-        self.height
+        self.height.get()
+    }
+
+    #[inline]
+    pub fn get_viewport(&self) -> [f64; 4] {
+        [0.0, 0.0, self.get_width(), self.get_height()]
     }
 
     #[inline]
@@ -76,8 +118,8 @@ impl GraphicsWindow {
     }
 
     #[inline]
-    pub fn get_window(&self) -> &Rc<RefCell<PistonWindow>> {
-        &self.window
+    pub fn get_window(&self) -> Option<&Rc<RefCell<PistonWindow>>> {
+        self.window.as_ref()
     }
 
 }
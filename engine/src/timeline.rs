@@ -0,0 +1,249 @@
+/// Recorded run of a game, any recorded generation can be restored.
+/// Every `interval`-th generation is kept as a keyframe, live cells
+/// compressed as RLE, generations in between are stored as births and
+/// deaths relative to the previous generation. Longer interval takes
+/// less memory, shorter one restores generations faster.
+///
+/// Example usage:
+/// ```
+/// # use engine::timeline::Timeline;
+/// # use engine::engine::Engine;
+/// # use engine::board::Coord;
+/// let mut engine = Engine::new(None, None);
+/// engine.set_pattern(&[Coord { col: 0, row: 0 }, Coord { col: 1, row: 0 }, Coord { col: 2, row: 0 }]);
+/// engine.set_timeline(Some(Timeline::new(16)));
+/// engine.one_iteration();
+///
+/// let timeline = engine.get_timeline().unwrap();
+/// assert_eq!(timeline.reconstruct(0).unwrap().len(), 3);
+/// ```
+///
+
+use std::mem;
+
+use ::board::{Board, Coord};
+use ::io::rle::{encode_cells, decode_cells};
+
+
+struct Keyframe {
+    // top left corner of the pattern, RLE coordinates are relative to it
+    left: i64,
+    top: i64,
+    data: String,
+}
+
+impl Keyframe {
+
+    fn new(cells: &[Coord]) -> Self {
+        let left = cells.iter().map(|c| c.col).min().unwrap_or(0);
+        let top = cells.iter().map(|c| c.row).min().unwrap_or(0);
+
+        let relative: Vec<Coord> = cells.iter()
+            .map(|c| Coord { col: c.col - left, row: c.row - top })
+            .collect();
+
+        Keyframe {
+            left: left,
+            top: top,
+            data: encode_cells(&relative),
+        }
+    }
+
+    fn get_cells(&self) -> Vec<Coord> {
+        let cells = decode_cells(&self.data).expect("keyframe is encoded by the timeline itself");
+
+        let mut res: Vec<Coord> = cells.iter()
+            .map(|c| Coord { col: c.col + self.left, row: c.row + self.top })
+            .collect();
+
+        res.sort();
+        res
+    }
+}
+
+struct Delta {
+    born: Vec<Coord>,
+    died: Vec<Coord>,
+}
+
+impl Delta {
+
+    fn new(prev: &[Coord], next: &[Coord]) -> Self {
+        // both lists are sorted, so they are compared in one pass
+        let mut delta = Delta { born: Vec::new(), died: Vec::new() };
+        let (mut i, mut j) = (0, 0);
+
+        while i < prev.len() || j < next.len() {
+            if j == next.len() || (i < prev.len() && prev[i] < next[j]) {
+                delta.died.push(prev[i]);
+                i += 1;
+            } else if i == prev.len() || next[j] < prev[i] {
+                delta.born.push(next[j]);
+                j += 1;
+            } else {
+                i += 1;
+                j += 1;
+            }
+        }
+
+        delta
+    }
+
+    fn apply(&self, cells: &mut Vec<Coord>) {
+        cells.retain(|cell| self.died.binary_search(cell).is_err());
+        cells.extend_from_slice(&self.born);
+        cells.sort();
+    }
+}
+
+pub struct Timeline {
+    interval: usize,
+
+    first_gen: Option<usize>,
+    keyframes: Vec<Keyframe>,
+    // deltas[i] turns generation first_gen + i into the next one
+    deltas: Vec<Delta>,
+
+    // live cells of the last recorded generation, sorted
+    last: Vec<Coord>,
+}
+
+impl Timeline {
+
+    pub fn new(interval: usize) -> Self {
+        Timeline {
+            interval: interval.max(1),
+
+            first_gen: None,
+            keyframes: Vec::new(),
+            deltas: Vec::new(),
+
+            last: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.first_gen = None;
+        self.keyframes.clear();
+        self.deltas.clear();
+        self.last.clear();
+    }
+
+    pub fn get_first_gen(&self) -> Option<usize> {
+        self.first_gen
+    }
+
+    pub fn get_last_gen(&self) -> Option<usize> {
+        self.first_gen.map(|first| first + self.deltas.len())
+    }
+
+    pub fn record(&mut self, gen: usize, board: &Board) {
+        // generations are recorded one after another, anything
+        // else starts the timeline over from the given one
+        if self.get_last_gen().map(|last| last + 1) != Some(gen) {
+            self.clear();
+            self.first_gen = Some(gen);
+        }
+
+        let mut cells: Vec<Coord> = board.into_iter()
+            .filter(|cell| cell.is_alive)
+            .map(|cell| cell.coord)
+            .collect();
+        cells.sort();
+
+        if self.first_gen != Some(gen) {
+            self.deltas.push(Delta::new(&self.last, &cells));
+        }
+
+        if (gen - self.first_gen.unwrap_or(gen)) % self.interval == 0 {
+            self.keyframes.push(Keyframe::new(&cells));
+        }
+
+        self.last = cells;
+    }
+
+    /// Live cells of the given generation, sorted
+    pub fn reconstruct(&self, gen: usize) -> Option<Vec<Coord>> {
+        let first = match self.first_gen {
+            Some(first) if gen >= first && gen - first <= self.deltas.len() => first,
+            _ => return None
+        };
+
+        let keyframe = (gen - first) / self.interval;
+        let mut cells = self.keyframes[keyframe].get_cells();
+
+        for delta in &self.deltas[keyframe * self.interval..gen - first] {
+            delta.apply(&mut cells);
+        }
+
+        Some(cells)
+    }
+
+    /// Approximate memory taken by the recorded generations in bytes
+    pub fn get_size(&self) -> usize {
+        let keyframes: usize = self.keyframes.iter()
+            .map(|keyframe| mem::size_of::<Keyframe>() + keyframe.data.len())
+            .sum();
+
+        let deltas: usize = self.deltas.iter()
+            .map(|delta| mem::size_of::<Delta>() + (delta.born.len() + delta.died.len()) * mem::size_of::<Coord>())
+            .sum();
+
+        keyframes + deltas
+    }
+
+}
+
+
+#[cfg(test)]
+use ::engine::Engine;
+
+#[cfg(test)]
+fn live_cells(board: &Board) -> Vec<Coord> {
+    let mut cells: Vec<Coord> = board.into_iter().filter(|cell| cell.is_alive).map(|cell| cell.coord).collect();
+    cells.sort();
+    cells
+}
+
+#[test]
+fn test_timeline_reconstruct() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("bo$3bo$2o2b3o!").unwrap());
+
+    let mut timeline = Timeline::new(8);
+    let mut expected = Vec::new();
+
+    for gen in 0..30 {
+        if gen > 0 {
+            engine.one_iteration();
+        }
+        timeline.record(gen, engine.get_board());
+        expected.push(live_cells(engine.get_board()));
+    }
+
+    assert_eq!(timeline.get_first_gen(), Some(0));
+    assert_eq!(timeline.get_last_gen(), Some(29));
+
+    for gen in 0..30 {
+        assert_eq!(timeline.reconstruct(gen).as_ref(), Some(&expected[gen]));
+    }
+    assert_eq!(timeline.reconstruct(30), None);
+}
+
+#[test]
+fn test_timeline_restart() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("3o!").unwrap());
+
+    let mut timeline = Timeline::new(4);
+    timeline.record(0, engine.get_board());
+    timeline.record(1, engine.get_board());
+
+    // gap in generations starts the timeline over
+    engine.one_iteration();
+    timeline.record(5, engine.get_board());
+
+    assert_eq!(timeline.get_first_gen(), Some(5));
+    assert_eq!(timeline.reconstruct(0), None);
+    assert_eq!(timeline.reconstruct(5), Some(live_cells(engine.get_board())));
+}
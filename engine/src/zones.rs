@@ -0,0 +1,140 @@
+/// Rectangular zones of the board running rules of their own, to see how
+/// domains of different rules act on each other. A cell follows the rule
+/// of the zone it is in, cells on the zone boundary included, the zone
+/// added last wins where zones overlap, and the rest of the board follows
+/// the rule of the engine:
+/// ```
+/// # use engine::board::Rect;
+/// # use engine::rule::Rule;
+/// # use engine::zones::ZoneMap;
+/// let highlife: Rule = "B36/S23".parse().unwrap();
+/// let mut zones = ZoneMap::new();
+/// zones.add(Rect::new(0, 0, 9, 9), highlife);
+///
+/// assert_eq!(zones.get_rule_at(9, 9), Some(highlife));
+/// assert_eq!(zones.get_rule_at(10, 9), None);
+/// ```
+///
+
+use std::io;
+
+use ::board::Rect;
+use ::rule::Rule;
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuleZone {
+    pub rect: Rect,
+    pub rule: Rule,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ZoneMap {
+    // in the order they were added
+    zones: Vec<RuleZone>,
+}
+
+impl ZoneMap {
+
+    pub fn new() -> Self {
+        ZoneMap { zones: Vec::new() }
+    }
+
+    pub fn add(&mut self, rect: Rect, rule: Rule) {
+        self.zones.push(RuleZone { rect: rect, rule: rule });
+    }
+
+    /// Removes the zone the cell follows the rule of, returns false
+    /// if the cell is outside of all zones
+    pub fn remove_at(&mut self, col: i64, row: i64) -> bool {
+        match self.zones.iter().rposition(|zone| zone.rect.contains(col, row)) {
+            Some(idx) => {
+                self.zones.remove(idx);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.zones.clear();
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn get_zones(&self) -> &[RuleZone] {
+        &self.zones
+    }
+
+    #[inline]
+    pub fn get_rule_at(&self, col: i64, row: i64) -> Option<Rule> {
+        self.zones.iter().rev()
+            .find(|zone| zone.rect.contains(col, row))
+            .map(|zone| zone.rule)
+    }
+
+    /// One zone per line: left, top, right and bottom bound and the rule
+    pub fn to_lines(&self) -> String {
+        let lines: Vec<String> = self.zones.iter()
+            .map(|zone| format!("{} {} {} {} {}", zone.rect.left, zone.rect.top, zone.rect.right, zone.rect.bottom, zone.rule))
+            .collect();
+        lines.join("\n")
+    }
+
+    pub fn from_lines(lines: &str) -> io::Result<Self> {
+        let mut zones = ZoneMap::new();
+
+        for line in lines.lines().filter(|line| !line.trim().is_empty()) {
+            let malformed = || io::Error::new(io::ErrorKind::InvalidData, format!("malformed rule zone '{}'", line));
+
+            let values: Vec<&str> = line.split_whitespace().collect();
+            if values.len() != 5 {
+                return Err(malformed());
+            }
+
+            let bounds: Vec<i64> = values[..4].iter().filter_map(|value| value.parse().ok()).collect();
+            let rule = values[4].parse::<Rule>().map_err(|_| malformed())?;
+            if bounds.len() != 4 || bounds[0] > bounds[2] || bounds[1] > bounds[3] {
+                return Err(malformed());
+            }
+
+            zones.add(Rect::new(bounds[0], bounds[1], bounds[2], bounds[3]), rule);
+        }
+
+        Ok(zones)
+    }
+
+}
+
+
+#[test]
+fn test_zone_map() {
+    let (highlife, seeds): (Rule, Rule) = ("B36/S23".parse().unwrap(), "B2/S".parse().unwrap());
+
+    let mut zones = ZoneMap::new();
+    zones.add(Rect::new(-10, -10, 10, 10), highlife);
+    zones.add(Rect::new(5, 5, 20, 20), seeds);
+
+    assert_eq!(zones.get_rule_at(-10, 0), Some(highlife));
+    assert_eq!(zones.get_rule_at(5, 5), Some(seeds));
+    assert_eq!(zones.get_rule_at(20, 0), None);
+
+    let restored = ZoneMap::from_lines(&zones.to_lines()).unwrap();
+    assert_eq!(restored, zones);
+    assert_eq!(zones.to_lines(), "-10 -10 10 10 B36/S23\n5 5 20 20 B2/S");
+
+    assert!(ZoneMap::from_lines("1 2 3 B3/S23").is_err());
+    assert!(ZoneMap::from_lines("3 0 1 1 B3/S23").is_err());
+    assert!(ZoneMap::from_lines("0 0 1 1 B9").is_err());
+
+    // the zone on top goes first
+    assert!(zones.remove_at(7, 7));
+    assert_eq!(zones.get_rule_at(7, 7), Some(highlife));
+    assert!(!zones.remove_at(30, 30));
+
+    zones.clear();
+    assert!(zones.is_empty());
+}
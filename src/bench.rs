@@ -0,0 +1,168 @@
+// Headless benchmark, runs a preset or a random soup for the given number
+// of generations without opening a window and prints the speed and the
+// memory it took, so changes of the engine can be measured on the same runs.
+// `backends` runs the soup on each of the compared boards in turn, with and
+// without the vector code if it's built
+use engine::backend;
+use engine::simd;
+use engine::engine::Engine;
+use engine::board::Rect;
+use engine::presets;
+use engine::progress::Progress;
+use engine::soup::SoupSearch;
+
+use std::cmp;
+use std::fs::File;
+use std::io::Read;
+use std::time::Instant;
+
+
+pub const SOUP: &'static str = "soup";
+pub const BACKENDS: &'static str = "backends";
+// boards compared by `backends` and whether they run the vector code,
+// the first one is the baseline; `auto` steps dense soups by the kernel
+const COMPARED: [(&'static str, bool); 5] = [("symvec", false), ("bits", false), ("bits", true), ("auto", false), ("auto", true)];
+// run unless asked otherwise
+pub const GENERATIONS: u64 = 1000;
+
+// the soup is the same on every run
+const SOUP_SEED: u64 = 1;
+const SOUP_SIZE: i64 = 256;
+const SOUP_DENSITY: f64 = 0.35;
+
+// generations computed between looking at the clock
+const CHUNK: u64 = 100;
+// seconds between progress lines
+const REPORT_TIME: f64 = 5.0;
+
+
+pub fn is_known(name: &str) -> bool {
+    name == SOUP || name == BACKENDS || presets::find(name).is_some()
+}
+
+fn get_secs(since: Instant) -> f64 {
+    let elapsed = since.elapsed();
+    elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9
+}
+
+fn parse_peak_memory(status: &str) -> Option<usize> {
+    // high water mark of the resident set, e.g. "VmHWM:	   12345 kB"
+    status.lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<usize>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// Most memory the process took so far in bytes, known on Linux only
+fn get_peak_memory() -> Option<usize> {
+    let mut status = String::new();
+    match File::open("/proc/self/status").and_then(|mut file| file.read_to_string(&mut status)) {
+        Ok(_) => parse_peak_memory(&status),
+        Err(_) => None
+    }
+}
+
+fn print_peak_memory() {
+    match get_peak_memory() {
+        Some(bytes) => println!("peak memory {:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+        None => println!("peak memory unknown")
+    }
+}
+
+// generations per second of the run
+fn measure(engine: &mut Engine, name: &str, generations: u64) -> Result<f64, String> {
+    if name == SOUP {
+        let rect = Rect::new(-SOUP_SIZE / 2, -SOUP_SIZE / 2, SOUP_SIZE / 2 - 1, SOUP_SIZE / 2 - 1);
+        SoupSearch::new(SOUP_SEED, rect, SOUP_DENSITY).seed_board(engine);
+    } else {
+        let preset = match presets::find(name) {
+            Some(preset) => preset,
+            None => return Err(format!("unknown benchmark '{}'", name))
+        };
+        engine.load_preset(preset).map_err(|err| err.to_string())?;
+    }
+
+    let started = Instant::now();
+    let mut progress = Progress::new(generations, 0.0);
+    let mut reported = 0.0;
+
+    while !progress.is_finished() {
+        let steps = cmp::min(CHUNK, generations - progress.get_done());
+        engine.iterations(steps);
+
+        let (done, now) = (progress.get_done() + steps, get_secs(started));
+        progress.update(done, now);
+
+        if now - reported >= REPORT_TIME && !progress.is_finished() {
+            println!("{}", progress);
+            reported = now;
+        }
+    }
+
+    let secs = progress.get_elapsed();
+    let rate = if secs > 0.0 { generations as f64 / secs } else { 0.0 };
+
+    println!("{}: {} generations in {:.3} s, {:.1} generations/s, final population {}",
+             name, generations, secs, rate, engine.get_board().get_population());
+
+    Ok(rate)
+}
+
+pub fn run(engine: &mut Engine, name: &str, generations: u64) -> Result<(), String> {
+    measure(engine, name, generations)?;
+    print_peak_memory();
+
+    Ok(())
+}
+
+/// Runs the soup on every compared board, the speed of each of them is
+/// given relative to the first one; memory is the peak of all the runs
+pub fn compare(generations: u64) -> Result<(), String> {
+    let built = simd::is_enabled();
+    let mut baseline = None;
+
+    for &(name, vector) in COMPARED.iter().filter(|&&(_, vector)| built || !vector) {
+        let mut engine = Engine::new(None, None);
+        engine.set_backend(backend::find(name).expect("compared boards are known"));
+
+        println!("{} board{}", name, if vector { " with SIMD" } else { "" });
+        simd::set_enabled(vector);
+        let res = measure(&mut engine, SOUP, generations);
+        simd::set_enabled(built);
+        let rate = res?;
+
+        match baseline {
+            Some(base) if base > 0.0 => println!("{:.1}x the speed of the {} board", rate / base, COMPARED[0].0),
+            Some(_) => (),
+            None => baseline = Some(rate)
+        }
+    }
+
+    print_peak_memory();
+
+    Ok(())
+}
+
+
+#[test]
+fn test_parse_peak_memory() {
+    let status = "Name:\tlife-rs\nVmPeak:\t  200000 kB\nVmHWM:\t   12345 kB\nVmRSS:\t   12000 kB\n";
+    assert_eq!(parse_peak_memory(status), Some(12345 * 1024));
+    assert_eq!(parse_peak_memory("Name:\tlife-rs\n"), None);
+}
+
+#[test]
+fn test_bench_run() {
+    let mut engine = Engine::new(None, None);
+    assert!(run(&mut engine, "r-pentomino", 250).is_ok());
+    assert_eq!(engine.iteration, 250);
+    assert!(run(&mut engine, "breeder", 10).is_err());
+    assert!(is_known(SOUP) && is_known("acorn") && !is_known("breeder"));
+}
+
+#[test]
+fn test_bench_compare() {
+    assert!(is_known(BACKENDS));
+    assert!(compare(5).is_ok());
+}
@@ -7,9 +7,13 @@ extern crate find_folder;
 extern crate engine;
 extern crate ui;
 
+mod bench;
+
 use structs::GraphicsWindow;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::path::Path;
+use std::process;
 
 use find_folder::Search;
 use piston_window::{PistonWindow, WindowSettings};
@@ -18,6 +22,13 @@ use opengl_graphics::glyph_cache::GlyphCache;
 
 use engine::structs;
 use engine::engine::Engine;
+use engine::backend::{self, EngineBackend, BitPacked, Paged, Sparse};
+use engine::io::fetch;
+use engine::presets;
+
+use ui::config::Config;
+use ui::recording;
+use ui::recording::Recorder;
 
 
 struct Game<'a> {
@@ -26,25 +37,23 @@ struct Game<'a> {
 
 impl<'a> Game<'a> {
 
-    fn new(width: f64, height: f64) -> Game<'a> {
+    fn new(window: GraphicsWindow, config: Config, chosen: Option<Box<EngineBackend>>) -> Game<'a> {
 
-        let window: PistonWindow = WindowSettings::new(
-            "My Rust Life",
-            [width as u32, height as u32]
-        ).opengl(ui::OPENGL)
-            .samples(8)
-            .exit_on_esc(true)
-            .build()
-            .unwrap();
+        // backend given on the command line wins over the config
+        let mut engine = Engine::new(Some(200), Some(200));
+        if let Some(chosen) = chosen.or_else(|| config.get("engine.backend").and_then(backend::find)) {
+            engine.set_backend(chosen);
+        }
 
         Game {
-            ui_manager: ui::new(Rc::new(GraphicsWindow::new(width, height, window)),
-                                Rc::new(RefCell::new(Engine::new(Some(200), Some(200)))),
+            ui_manager: ui::new(Rc::new(window),
+                                Rc::new(RefCell::new(engine)),
                                 Rc::new(RefCell::new(ui::Resources {
                                     font: GlyphCache::new(Search::ParentsThenKids(3, 3).
                                     for_folder("assets").unwrap().
                                     join("Roboto-Regular.ttf")).unwrap()
-                                }))
+                                })),
+                                Rc::new(RefCell::new(config))
             ),
         }
     }
@@ -55,9 +64,329 @@ impl<'a> Game<'a> {
 
 }
 
+fn open_window(width: f64, height: f64, fullscreen: bool) -> GraphicsWindow {
+    let window: PistonWindow = WindowSettings::new(
+        "My Rust Life",
+        [width as u32, height as u32]
+    ).opengl(ui::OPENGL)
+        .samples(8)
+        .fullscreen(fullscreen)
+        .exit_on_esc(true)
+        .build()
+        .unwrap();
+
+    GraphicsWindow::new(width, height, window)
+}
+
+
+const USAGE: &'static str = "usage: life-rs [PATTERN|URL|APGCODE] [--preset ID] [--paused] [--scale ZOOM] [--demo] [--screensaver] [--bit-packed] [--paged CHUNKS] [--sparse] [--backend NAME] [--bench ID|soup|backends [--generations N]] [--gpu] [--record FILE|--replay FILE]";
+
+
+#[derive(Debug, PartialEq, Default)]
+struct Args {
+    pattern: Option<String>,
+    paused: bool,
+    scale: Option<f64>,
+    demo: bool,
+    screensaver: bool,
+    preset: Option<String>,
+    bit_packed: bool,
+    paged: Option<usize>,
+    sparse: bool,
+    backend: Option<String>,
+    gpu: bool,
+    bench: Option<String>,
+    generations: Option<u64>,
+    record: Option<String>,
+    replay: Option<String>,
+}
+
+fn parse_args<I: Iterator<Item=String>>(mut args: I) -> Result<Args, String> {
+    let mut res = Args::default();
+
+    while let Some(arg) = args.next() {
+        // options with a value are accepted both as --key value and --key=value
+        let (key, value) = match arg.find('=') {
+            Some(pos) if arg.starts_with("--") => (arg[..pos].to_string(), Some(arg[pos + 1..].to_string())),
+            _ => (arg.clone(), None)
+        };
+
+        match &key[..] {
+            "--paused" => res.paused = true,
+            "--demo" => res.demo = true,
+            "--screensaver" => res.screensaver = true,
+            "--bit-packed" => res.bit_packed = true,
+            "--sparse" => res.sparse = true,
+            "--gpu" => res.gpu = true,
+
+            "--scale" | "--file" | "--preset" | "--paged" | "--backend" | "--bench" | "--generations" | "--record" | "--replay" => {
+                let value = match value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => return Err(format!("{} needs a value", key))
+                };
+
+                if key == "--file" {
+                    res.pattern = Some(value);
+                } else if key == "--preset" {
+                    if presets::find(&value).is_none() {
+                        let ids: Vec<&str> = presets::PRESETS.iter().map(|preset| preset.id).collect();
+                        return Err(format!("unknown preset '{}', known ones are {}", value, ids.join(", ")));
+                    }
+                    res.preset = Some(value);
+                } else if key == "--paged" {
+                    match value.parse::<usize>() {
+                        Ok(chunks) if chunks > 0 => res.paged = Some(chunks),
+                        _ => return Err(format!("malformed number of chunks '{}'", value))
+                    }
+                } else if key == "--backend" {
+                    if backend::find(&value).is_none() {
+                        return Err(format!("unknown backend '{}', known ones are hashed, symvec, bits, sparse and paged:CHUNKS", value));
+                    }
+                    res.backend = Some(value);
+                } else if key == "--bench" {
+                    if !bench::is_known(&value) {
+                        return Err(format!("unknown benchmark '{}', run a preset, {} or {}", value, bench::SOUP, bench::BACKENDS));
+                    }
+                    res.bench = Some(value);
+                } else if key == "--record" {
+                    res.record = Some(value);
+                } else if key == "--replay" {
+                    res.replay = Some(value);
+                } else if key == "--generations" {
+                    match value.parse::<u64>() {
+                        Ok(generations) if generations > 0 => res.generations = Some(generations),
+                        _ => return Err(format!("malformed number of generations '{}'", value))
+                    }
+                } else {
+                    match value.parse::<f64>() {
+                        Ok(scale) if scale > 0.0 => res.scale = Some(scale),
+                        _ => return Err(format!("malformed scale '{}'", value))
+                    }
+                }
+            }
+
+            _ if key.starts_with("--") => return Err(format!("unknown option {}", key)),
+
+            _ if res.pattern.is_none() => res.pattern = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg))
+        }
+    }
+
+    if res.pattern.is_some() && res.preset.is_some() {
+        return Err("a preset replaces the pattern, give only one of them".to_string());
+    }
+
+    if res.screensaver && (res.pattern.is_some() || res.preset.is_some() || res.demo) {
+        return Err("the screensaver shows its own soups, give no pattern, preset or demo".to_string());
+    }
+
+    if res.bench.is_some() && (res.pattern.is_some() || res.preset.is_some() || res.demo || res.screensaver || res.gpu) {
+        return Err("the benchmark runs without a window, give no pattern, preset, demo, screensaver or gpu".to_string());
+    }
+
+    if res.replay.is_some() && (res.record.is_some() || res.bench.is_some() || res.screensaver || res.gpu) {
+        return Err("the recording is replayed without a window, give no record, benchmark, screensaver or gpu".to_string());
+    }
+
+    if res.record.is_some() && res.bench.is_some() {
+        return Err("the benchmark has no window to record".to_string());
+    }
+
+    if res.bench.as_ref().map_or(false, |name| name == bench::BACKENDS) &&
+       (res.bit_packed || res.paged.is_some() || res.sparse || res.backend.is_some()) {
+        return Err("the backends benchmark runs on each of the boards it compares, give no board".to_string());
+    }
+
+    if res.generations.is_some() && res.bench.is_none() {
+        return Err("--generations is the length of a benchmark, give --bench too".to_string());
+    }
+
+    if [res.bit_packed, res.paged.is_some(), res.sparse, res.backend.is_some()].iter().filter(|&&chosen| chosen).count() > 1 {
+        return Err("--bit-packed, --paged, --sparse and --backend are different boards, give only one of them".to_string());
+    }
+
+    Ok(res)
+}
+
+fn get_backend(args: &Args) -> Option<Box<EngineBackend>> {
+    if args.bit_packed {
+        Some(Box::new(BitPacked))
+    } else if let Some(max_chunks) = args.paged {
+        Some(Box::new(Paged(max_chunks)))
+    } else if args.sparse {
+        Some(Box::new(Sparse))
+    } else {
+        args.backend.as_ref().and_then(|name| backend::find(name))
+    }
+}
 
 fn main() {
-    let mut game = Game::new(1024.0, 768.0);
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            println!("{}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Some(ref name) = args.bench {
+        let generations = args.generations.unwrap_or(bench::GENERATIONS);
+
+        let res = if name == bench::BACKENDS {
+            bench::compare(generations)
+        } else {
+            // same board choice as in the window, but unbounded
+            let mut engine = Engine::new(None, None);
+            if let Some(chosen) = get_backend(&args) {
+                engine.set_backend(chosen);
+            }
+            bench::run(&mut engine, name, generations)
+        };
+
+        if let Err(err) = res {
+            println!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // events are read first, so a missing recording doesn't open anything
+    let replayed = match args.replay {
+        Some(ref path) => match recording::read(Path::new(path)) {
+            Ok(events) => Some(events),
+            Err(err) => {
+                println!("Unable to read recording {}: {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => None
+    };
+
+    // replays leave ~/.life-rs and the autosaves alone and don't depend
+    // on them, so they run the same on every machine
+    let (window, config) = if replayed.is_some() {
+        (GraphicsWindow::headless(1024.0, 768.0), Config::isolated())
+    } else {
+        (open_window(1024.0, 768.0, args.screensaver), Config::load())
+    };
+    let mut game = Game::new(window, config, get_backend(&args));
+
+    if let Some(ref pattern) = args.pattern {
+        // loaded pattern runs right away unless asked otherwise
+        if !Path::new(pattern).exists() && fetch::parse_source(pattern).is_some() {
+            game.ui_manager.fetch_pattern(pattern);
+        } else {
+            game.ui_manager.open_pattern(Path::new(pattern));
+        }
+        game.ui_manager.set_paused(args.paused);
+    }
+
+    if let Some(preset) = args.preset.as_ref().and_then(|id| presets::find(id)) {
+        // presets run right away too
+        game.ui_manager.load_preset(preset);
+        game.ui_manager.set_paused(args.paused);
+    }
+
+    if let Some(scale) = args.scale {
+        game.ui_manager.set_zoom(scale);
+    }
+
+    if args.gpu {
+        game.ui_manager.set_gpu(true);
+    }
+
+    if args.demo {
+        game.ui_manager.start_demo();
+    }
+
+    if args.screensaver {
+        game.ui_manager.start_screensaver();
+    }
+
+    if let Some(events) = replayed {
+        let count = events.len();
+        game.ui_manager.replay(events);
+
+        let engine = game.ui_manager.get_engine();
+        let engine = engine.borrow();
+        println!("Replayed {} events: generation {}, population {}, {} windows open",
+                 count, engine.cur_iteration(), engine.get_board().get_population(),
+                 game.ui_manager.get_window_count());
+        return;
+    }
+
+    if let Some(ref path) = args.record {
+        match Recorder::new(Path::new(path)) {
+            Ok(recorder) => game.ui_manager.set_recorder(Some(recorder)),
+            Err(err) => println!("Unable to record into {}: {}", path, err)
+        }
+    }
 
     game.event_dispatcher();
 }
+
+
+#[cfg(test)]
+fn parse(args: &[&str]) -> Result<Args, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_parse_args() {
+    assert_eq!(parse(&[]), Ok(Args::default()));
+
+    let args = parse(&["pattern.rle", "--paused", "--scale", "2"]).unwrap();
+    assert_eq!(args.pattern, Some("pattern.rle".to_string()));
+    assert!(args.paused);
+    assert_eq!(args.scale, Some(2.0));
+
+    assert_eq!(parse(&["--scale=0.5", "--file", "gun.mc"]).unwrap(),
+               Args { pattern: Some("gun.mc".to_string()), scale: Some(0.5), ..Args::default() });
+
+    assert!(parse(&["--scale"]).is_err());
+    assert!(parse(&["--scale", "-1"]).is_err());
+    assert!(parse(&["--fast"]).is_err());
+    assert!(parse(&["a.rle", "b.rle"]).is_err());
+
+    assert_eq!(parse(&["--preset=acorn"]).unwrap().preset, Some("acorn".to_string()));
+    assert!(parse(&["--preset", "breeder"]).is_err());
+    assert!(parse(&["gun.rle", "--preset", "gun"]).is_err());
+
+    assert!(parse(&["soup.rle", "--bit-packed"]).unwrap().bit_packed);
+
+    assert_eq!(parse(&["huge.mc", "--paged=4096"]).unwrap().paged, Some(4096));
+    assert!(parse(&["--paged", "0"]).is_err());
+    assert!(parse(&["--paged", "64", "--bit-packed"]).is_err());
+
+    assert!(parse(&["gliders.rle", "--sparse"]).unwrap().sparse);
+    assert!(parse(&["--sparse", "--paged", "64"]).is_err());
+
+    let args = parse(&["soup.rle", "--backend=symvec"]).unwrap();
+    assert_eq!(get_backend(&args).unwrap().get_name(), "symvec");
+    assert_eq!(get_backend(&parse(&["--paged", "64"]).unwrap()).unwrap().get_name(), "paged:64");
+    assert!(get_backend(&Args::default()).is_none());
+    assert!(parse(&["--backend", "hashlife"]).is_err());
+    assert!(parse(&["--backend", "bits", "--sparse"]).is_err());
+
+    assert!(parse(&["soup.rle", "--gpu"]).unwrap().gpu);
+
+    let args = parse(&["--bench", "acorn", "--generations=5000", "--bit-packed"]).unwrap();
+    assert_eq!((args.bench, args.generations), (Some("acorn".to_string()), Some(5000)));
+    assert!(parse(&["--bench", "breeder"]).is_err());
+    assert!(parse(&["--bench", "soup", "--generations", "0"]).is_err());
+    assert!(parse(&["--bench", "soup", "gun.rle"]).is_err());
+    assert!(parse(&["--generations", "100"]).is_err());
+    assert!(parse(&["--bench", "backends"]).is_ok());
+    assert!(parse(&["--bench", "backends", "--bit-packed"]).is_err());
+
+    assert!(parse(&["--screensaver", "--gpu"]).unwrap().screensaver);
+    assert!(parse(&["--screensaver", "--demo"]).is_err());
+    assert!(parse(&["gun.rle", "--screensaver"]).is_err());
+
+    let args = parse(&["gun.rle", "--record", "session.txt"]).unwrap();
+    assert_eq!(args.record, Some("session.txt".to_string()));
+    assert_eq!(parse(&["--replay=session.txt", "--paused"]).unwrap().replay, Some("session.txt".to_string()));
+    assert!(parse(&["--replay", "a.txt", "--record", "b.txt"]).is_err());
+    assert!(parse(&["--replay", "a.txt", "--bench", "acorn"]).is_err());
+    assert!(parse(&["--record", "a.txt", "--bench", "acorn"]).is_err());
+}
@@ -0,0 +1,44 @@
+// Error boundary around window code: a panic in one window is caught,
+// so the window can be closed while the rest of the program goes on
+use std::any::Any;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+
+/// Runs the closure, returns the panic message if it panicked.
+/// Whatever the closure borrowed may be left half-updated, it's up
+/// to the caller to throw it away
+pub fn catch<F, R>(f: F) -> Result<R, String>
+    where F: FnOnce() -> R {
+
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|err| get_message(&err))
+}
+
+fn get_message(err: &Box<Any + Send>) -> String {
+    // panic!() with a literal carries &str, formatted panics carry String
+    if let Some(msg) = err.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = err.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown error".to_string()
+    }
+}
+
+
+#[test]
+fn test_catch() {
+    assert_eq!(catch(|| 42), Ok(42));
+
+    assert_eq!(catch(|| -> () { panic!("broken") }), Err("broken".to_string()));
+    assert_eq!(catch(|| -> () { panic!("window {} is broken", 3) }), Err("window 3 is broken".to_string()));
+
+    // the closure may change what it borrows until it panics
+    let mut cells = vec![1, 2, 3];
+    let res = catch(|| {
+        cells.push(4);
+        panic!("broken");
+    });
+    assert_eq!(res, Err::<(), String>("broken".to_string()));
+    assert_eq!(cells.len(), 4);
+}
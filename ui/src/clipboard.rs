@@ -0,0 +1,56 @@
+// System clipboard through the usual command line tools, so that no
+// platform specific libraries are needed: pbcopy on macOS, clip on
+// Windows and wl-copy, xclip or xsel, whichever is installed, elsewhere
+use std::io;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[cfg(target_os = "macos")]
+const COPY_COMMANDS: &'static [&'static [&'static str]] = &[&["pbcopy"]];
+
+#[cfg(target_os = "windows")]
+const COPY_COMMANDS: &'static [&'static [&'static str]] = &[&["clip"]];
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const COPY_COMMANDS: &'static [&'static [&'static str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+
+pub fn set_text(text: &str) -> io::Result<()> {
+    let mut res = Err(io::Error::new(io::ErrorKind::NotFound, "no clipboard tool is installed"));
+
+    for command in COPY_COMMANDS {
+        res = run_with_input(command, text);
+
+        // missing tool is not an error while others are left to try
+        match res {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+            _ => break
+        }
+    }
+
+    res
+}
+
+fn run_with_input(command: &[&str], input: &str) -> io::Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+        // stdin is closed here, so the tool knows the input is over
+    }
+
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, format!("{} has failed", command[0])))
+    }
+}
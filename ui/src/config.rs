@@ -0,0 +1,248 @@
+/// User configuration kept in `~/.life-rs` file. Every line is
+/// a `key = value` pair, lines starting with `#` are comments.
+///
+/// Example usage:
+/// ```
+/// # use ui::config::Config;
+/// let mut config = Config::parse("speed = 10");
+/// config.set("window.stats", "10,10,400,120,1");
+/// assert_eq!(config.get_parsed::<u32>("speed"), Some(10));
+/// ```
+///
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Keys;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+const CONFIG_FILE_NAME: &'static str = ".life-rs";
+
+
+pub struct Config {
+    path: Option<PathBuf>,
+    values: BTreeMap<String, String>,
+
+    // lines which are not `key = value` pairs
+    errors: Vec<String>,
+
+    // false if there was no config file on load
+    existed: bool,
+    // modification time of the file when it was read
+    modified: Option<SystemTime>,
+}
+
+impl Config {
+
+    pub fn new() -> Self {
+        Config {
+            path: None,
+            values: BTreeMap::new(),
+            errors: Vec::new(),
+            existed: false,
+            modified: None,
+        }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        env::home_dir().map(|home| home.join(CONFIG_FILE_NAME))
+    }
+
+    pub fn load() -> Self {
+        // missing or unreadable config is not an error,
+        // just start with defaults in that case
+        match Config::default_path() {
+            Some(path) => {
+                let mut config = Config::from_file(path.clone()).unwrap_or(Config::new());
+                config.path = Some(path);
+                config
+            }
+            None => Config::new()
+        }
+    }
+
+    pub fn from_file(path: PathBuf) -> io::Result<Self> {
+        let modified = Config::get_modified(&path);
+
+        let mut contents = String::new();
+        File::open(&path)?.read_to_string(&mut contents)?;
+
+        let mut config = Config::parse(&contents);
+        config.path = Some(path);
+        config.existed = true;
+        config.modified = modified;
+
+        Ok(config)
+    }
+
+    fn get_modified(path: &PathBuf) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Re-reads the config file if it has been modified since it was read,
+    /// returns keys which have been added, changed or removed
+    pub fn reload_if_changed(&mut self) -> io::Result<Vec<String>> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Ok(Vec::new())
+        };
+
+        let modified = Config::get_modified(&path);
+        if modified.is_none() || modified == self.modified {
+            return Ok(Vec::new());
+        }
+
+        let mut config = Config::from_file(path)?;
+        let changed = self.diff(&config);
+
+        config.modified = modified;
+        *self = config;
+
+        Ok(changed)
+    }
+
+    pub fn diff(&self, other: &Config) -> Vec<String> {
+        let mut keys: Vec<String> = self.values.keys().chain(other.values.keys())
+            .filter(|key| self.get(key) != other.get(key))
+            .cloned()
+            .collect();
+
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// Config without a file behind it for replays and tests, which
+    /// shouldn't depend on the machine they run on: autosaves are off
+    /// and the tutorial counts as seen
+    pub fn isolated() -> Self {
+        let mut config = Config::parse("autosave.generations = 0\nautosave.interval = 0");
+        config.existed = true;
+        config
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut config = Config::new();
+
+        for (num, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.find('=') {
+                Some(pos) if pos > 0 => {
+                    let (key, value) = line.split_at(pos);
+                    config.set(key.trim(), value[1..].trim());
+                }
+                // malformed lines are skipped, the rest of the file is still usable
+                _ => config.errors.push(format!("line {}: expected 'key = value'", num + 1))
+            }
+        }
+
+        config
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        if let Some(ref path) = self.path {
+            let mut file = File::create(path)?;
+            file.write_all(self.to_string().as_bytes())?;
+        }
+
+        // our own changes shouldn't be reloaded
+        self.modified = self.path.as_ref().and_then(Config::get_modified);
+        Ok(())
+    }
+
+    pub fn existed(&self) -> bool {
+        self.existed
+    }
+
+    pub fn get_errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    pub fn get_keys<'a>(&'a self) -> Keys<'a, String, String> {
+        self.values.keys()
+    }
+
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    pub fn set<T: ToString>(&mut self, key: &str, value: T) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut res = String::new();
+        for (key, value) in &self.values {
+            res.push_str(&format!("{} = {}\n", key, value));
+        }
+        res
+    }
+
+}
+
+
+#[test]
+fn test_config_parse() {
+    let config = Config::parse("# comment\n\nwindow.stats = 1,2,3,4,1\n  speed=10  \nbroken line\n");
+
+    assert_eq!(config.get("window.stats"), Some("1,2,3,4,1"));
+    assert_eq!(config.get_parsed::<u32>("speed"), Some(10));
+    assert_eq!(config.get("broken line"), None);
+    assert_eq!(config.get_errors(), &["line 5: expected 'key = value'".to_string()]);
+}
+
+#[test]
+fn test_config_round_trip() {
+    let mut config = Config::new();
+    config.set("a", 1);
+    config.set("b", "text");
+
+    let parsed = Config::parse(&config.to_string());
+
+    assert_eq!(parsed.get_parsed::<i32>("a"), Some(1));
+    assert_eq!(parsed.get("b"), Some("text"));
+}
+
+#[test]
+fn test_config_diff() {
+    let old = Config::parse("a = 1\nb = 2\nc = 3");
+    let new = Config::parse("a = 1\nb = 5\nd = 4");
+
+    assert_eq!(new.diff(&new), Vec::<String>::new());
+    assert_eq!(old.diff(&new), vec!["b".to_string(), "c".to_string(), "d".to_string()]);
+}
+
+#[test]
+fn test_config_reload() {
+    let path = env::temp_dir().join("life-rs-config-test");
+
+    File::create(&path).unwrap().write_all(b"a = 1\nb = 2\n").unwrap();
+    let mut config = Config::from_file(path.clone()).unwrap();
+
+    // nothing happened to the file yet
+    assert_eq!(config.reload_if_changed().unwrap(), Vec::<String>::new());
+
+    File::create(&path).unwrap().write_all(b"a = 1\nb = 3\noops\n").unwrap();
+    // modification time may have too coarse resolution to notice the change
+    config.modified = None;
+
+    assert_eq!(config.reload_if_changed().unwrap(), vec!["b".to_string()]);
+    assert_eq!(config.get_parsed::<u32>("b"), Some(3));
+    assert_eq!(config.get_errors().len(), 1);
+
+    fs::remove_file(&path).unwrap();
+}
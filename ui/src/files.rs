@@ -0,0 +1,116 @@
+// Directory listing for the file dialog, only directories and files
+// which look like patterns or Golly rules are shown. Zip archives are
+// listed as directories holding their pattern entries
+extern crate engine;
+
+use self::engine::io::archive;
+use self::engine::io::archive::Archive;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+
+const PATTERN_EXTENSIONS: [&'static str; 7] = ["rle", "cells", "lif", "life", "mc", "gz", "rule"];
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Golly `.rule` files set the rule instead of the pattern
+pub fn is_rule_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.to_lowercase() == "rule")
+}
+
+pub fn is_pattern_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| PATTERN_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Directories and archives can be listed
+pub fn is_dir(path: &Path) -> bool {
+    path.is_dir() || (archive::is_archive(path) && path.is_file())
+}
+
+/// Files and entries of archives can be opened
+pub fn is_file(path: &Path) -> bool {
+    path.is_file() || archive::split_path(path).is_some()
+}
+
+/// Directories go first, then pattern files, both sorted by name,
+/// the parent directory is listed as `..`
+pub fn list_dir(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut res = if archive::is_archive(dir) && dir.is_file() { list_archive(dir)? } else { list_files(dir)? };
+
+    res.sort_by(|a, b| (!a.is_dir, a.name.to_lowercase()).cmp(&(!b.is_dir, b.name.to_lowercase())));
+
+    if let Some(parent) = dir.parent() {
+        res.insert(0, Entry { name: "..".to_string(), path: parent.to_path_buf(), is_dir: true });
+    }
+
+    Ok(res)
+}
+
+fn list_files(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut res = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_dir = is_dir(&path);
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        // hidden files only clutter the list
+        if name.starts_with('.') || !(is_dir || is_pattern_file(&path)) {
+            continue;
+        }
+
+        res.push(Entry { name: name, path: path, is_dir: is_dir });
+    }
+
+    Ok(res)
+}
+
+fn list_archive(path: &Path) -> io::Result<Vec<Entry>> {
+    // patterns in the directories of the archive are listed with them, e.g. `Oscillators/pulsar.rle`
+    Ok(Archive::open(path)?.get_entries().into_iter()
+        .map(|name| Entry { path: path.join(&name), name: name, is_dir: false })
+        .collect())
+}
+
+
+#[cfg(test)]
+use std::env;
+#[cfg(test)]
+use std::fs::File;
+
+#[test]
+fn test_list_dir() {
+    let dir = env::temp_dir().join("life-rs-files-test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("patterns")).unwrap();
+
+    for name in &["glider.rle", "Acorn.RLE", "soup.rle.gz", "notes.txt", ".hidden.rle", "Wire.rule"] {
+        File::create(dir.join(name)).unwrap();
+    }
+    // not a zip archive inside, it can't be listed
+    File::create(dir.join("collection.zip")).unwrap();
+
+    let names: Vec<String> = list_dir(&dir).unwrap().into_iter().map(|entry| entry.name).collect();
+    assert_eq!(names, vec!["..", "collection.zip", "patterns", "Acorn.RLE", "glider.rle", "soup.rle.gz", "Wire.rule"]);
+    assert!(is_dir(&dir.join("collection.zip")) && !is_dir(&dir.join("glider.rle")));
+    assert!(list_dir(&dir.join("collection.zip")).is_err());
+    assert!(is_rule_file(&dir.join("Wire.rule")) && !is_rule_file(&dir.join("glider.rle")));
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(list_dir(&dir).is_err());
+}
@@ -0,0 +1,76 @@
+// Keyboard focus management for non-modal windows
+use piston_window::{Event, Input, Button, Key};
+
+use super::windows::{WindowBase, PostAction, Modifiers};
+
+
+pub struct FocusManager;
+
+impl FocusManager {
+
+    pub fn new() -> Self {
+        FocusManager
+    }
+
+    pub fn event_dispatcher<'a>(&mut self, event: &Event, modifiers: &Modifiers,
+                                stack: &mut Vec<Box<WindowBase + 'a>>) {
+
+        if stack.len() != 0 && stack[0].is_modal() {
+            // modal window owns the keyboard until it is gone
+            return;
+        }
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Tab))) => {
+                let forward = !modifiers.shift();
+                FocusManager::cycle(stack, forward);
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::W))) if modifiers.ctrl() => {
+                FocusManager::close_focused(stack);
+            }
+
+            _ => {}
+
+        }
+    }
+
+    fn cycle<'a>(stack: &mut Vec<Box<WindowBase + 'a>>, forward: bool) {
+
+        let focusable: Vec<usize> = stack.iter().enumerate()
+            .filter(|&(_, w)| w.is_focusable())
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let cnt = focusable.len();
+
+        if cnt == 0 {
+            return;
+        }
+
+        let cur = focusable.iter().position(|&idx| stack[idx].is_focused());
+
+        let next = match cur {
+            Some(pos) => if forward { (pos + 1) % cnt } else { (pos + cnt - 1) % cnt },
+            None => if forward { 0 } else { cnt - 1 }
+        };
+
+        if let Some(pos) = cur {
+            stack[focusable[pos]].set_focused(false);
+        }
+
+        stack[focusable[next]].set_focused(true);
+    }
+
+    fn close_focused<'a>(stack: &mut Vec<Box<WindowBase + 'a>>) {
+
+        if let Some(idx) = stack.iter().position(|w| w.is_focused()) {
+            stack[idx].set_focused(false);
+            if let PostAction::Pop = stack[idx].close() {
+                stack.remove(idx);
+            }
+        }
+    }
+
+}
@@ -0,0 +1,54 @@
+// Golden image comparison for rendering tests.
+// Golden images are binary PPM files in the `golden` directory of the
+// crate, run tests with UPDATE_GOLDEN=1 to (re)create them.
+use super::image::Canvas;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+// maximum allowed difference of a color channel, 0..255
+const TOLERANCE: u8 = 2;
+
+
+fn to_ppm(canvas: &Canvas) -> Vec<u8> {
+    let mut res = format!("P6\n{} {}\n255\n", canvas.get_width(), canvas.get_height()).into_bytes();
+    res.extend(canvas.to_bytes());
+    res
+}
+
+fn matches_ppm(canvas: &Canvas, ppm: &[u8]) -> bool {
+    // header is compared as is, it is always written by to_ppm
+    let header = format!("P6\n{} {}\n255\n", canvas.get_width(), canvas.get_height()).into_bytes();
+
+    if ppm.len() != header.len() + canvas.get_width() * canvas.get_height() * 3 || !ppm.starts_with(&header) {
+        return false;
+    }
+
+    canvas.to_bytes().iter().zip(ppm[header.len()..].iter())
+        .all(|(&a, &b)| (a as i16 - b as i16).abs() <= TOLERANCE as i16)
+}
+
+pub fn check_golden(name: &str, canvas: &Canvas) {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("golden");
+    path.push(format!("{}.ppm", name));
+
+    if env::var("UPDATE_GOLDEN").is_ok() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(&path).unwrap().write_all(&to_ppm(canvas)).unwrap();
+        return;
+    }
+
+    let mut golden = Vec::new();
+    File::open(&path).and_then(|mut f| f.read_to_end(&mut golden))
+        .expect(&format!("golden image {:?} is missing, run tests with UPDATE_GOLDEN=1", path));
+
+    if !matches_ppm(canvas, &golden) {
+        // keep the result around to make investigation easier
+        path.set_extension("actual.ppm");
+        File::create(&path).and_then(|mut f| f.write_all(&to_ppm(canvas))).ok();
+        panic!("rendering differs from golden image {}, actual image is saved to {:?}", name, path);
+    }
+}
@@ -0,0 +1,401 @@
+// Stepping of bounded boards on the GPU: the board is kept in two float
+// textures, a texel per cell holding its age or 0 for an empty cell, and
+// every generation is drawn from one of them into the other by a fragment
+// shader. Cells get back to the CPU only when they are read explicitly,
+// the population, births and deaths of every generation are counted on the
+// GPU by occlusion queries, a pass per number, whose fragments are discarded
+// for the cells not counted
+extern crate engine;
+extern crate gl;
+
+use self::engine::board::{Board, Coord, Rect};
+use self::engine::rule::Rule;
+use self::engine::kernel::Stats;
+
+use self::gl::types::{GLchar, GLenum, GLint, GLsizei, GLsizeiptr, GLuint};
+
+use std::ffi::CString;
+use std::mem;
+use std::ptr;
+
+const VERTEX_SHADER: &'static str = "
+#version 150
+in vec2 pos;
+
+void main() {
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+";
+
+// the board wraps around, so neighbours are fetched modulo its size;
+// ages are exact up to 2^24 generations and stop growing after that
+const FRAGMENT_SHADER: &'static str = "
+#version 150
+uniform sampler2D cells;
+uniform int birth;
+uniform int survival;
+out float age;
+
+void main() {
+    ivec2 size = textureSize(cells, 0);
+    ivec2 pos = ivec2(gl_FragCoord.xy);
+
+    int count = 0;
+    for (int dy = -1; dy <= 1; dy++) {
+        for (int dx = -1; dx <= 1; dx++) {
+            if (dx != 0 || dy != 0) {
+                count += int(texelFetch(cells, (pos + ivec2(dx, dy) + size) % size, 0).r > 0.0);
+            }
+        }
+    }
+
+    float cur = texelFetch(cells, pos, 0).r;
+    if (cur > 0.0) {
+        age = ((survival >> count) & 1) != 0 ? cur + 1.0 : 0.0;
+    } else {
+        age = ((birth >> count) & 1) != 0 ? 1.0 : 0.0;
+    }
+}
+";
+
+// live cells, births or deaths of the generation, by the mode
+const COUNT_SHADER: &'static str = "
+#version 150
+uniform sampler2D cells;
+uniform sampler2D prev;
+uniform int mode;
+out float counted;
+
+void main() {
+    ivec2 pos = ivec2(gl_FragCoord.xy);
+    bool alive = texelFetch(cells, pos, 0).r > 0.0;
+    bool was_alive = texelFetch(prev, pos, 0).r > 0.0;
+
+    if (!(mode == 0 ? alive : mode == 1 ? alive && !was_alive : was_alive && !alive)) {
+        discard;
+    }
+    counted = 1.0;
+}
+";
+
+// modes of the count shader, in the order of the queries of a generation
+const COUNT_POPULATION: GLint = 0;
+const COUNT_BIRTHS: GLint = 1;
+const COUNT_DEATHS: GLint = 2;
+
+// the whole texture as a triangle strip
+const QUAD: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+
+
+pub struct GpuStepper {
+    program: GLuint,
+    count_program: GLuint,
+    vertex_array: GLuint,
+    vertex_buffer: GLuint,
+    framebuffer: GLuint,
+    // count passes draw here, the textures are read by them
+    count_framebuffer: GLuint,
+    count_renderbuffer: GLuint,
+
+    textures: [GLuint; 2],
+    // texture holding the current generation
+    cur: usize,
+
+    bounds: Rect,
+}
+
+unsafe fn get_log(object: GLuint, is_shader: bool) -> String {
+    let mut len = 0;
+    if is_shader {
+        gl::GetShaderiv(object, gl::INFO_LOG_LENGTH, &mut len);
+    } else {
+        gl::GetProgramiv(object, gl::INFO_LOG_LENGTH, &mut len);
+    }
+
+    let mut buf = vec![0u8; len.max(1) as usize];
+    if is_shader {
+        gl::GetShaderInfoLog(object, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+    } else {
+        gl::GetProgramInfoLog(object, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+    }
+
+    String::from_utf8_lossy(&buf).trim_right_matches('\0').to_string()
+}
+
+unsafe fn compile(kind: GLenum, source: &str) -> Result<GLuint, String> {
+    let shader = gl::CreateShader(kind);
+    let source = CString::new(source).expect("shader sources have no zero bytes");
+
+    gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+    gl::CompileShader(shader);
+
+    let mut status = gl::FALSE as GLint;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+    if status != gl::TRUE as GLint {
+        let log = get_log(shader, true);
+        gl::DeleteShader(shader);
+        return Err(format!("unable to compile shader: {}", log));
+    }
+
+    Ok(shader)
+}
+
+unsafe fn link(fragment_shader: &str) -> Result<GLuint, String> {
+    let vertex = compile(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+    let fragment = match compile(gl::FRAGMENT_SHADER, fragment_shader) {
+        Ok(fragment) => fragment,
+        Err(err) => {
+            gl::DeleteShader(vertex);
+            return Err(err);
+        }
+    };
+
+    let program = gl::CreateProgram();
+    gl::AttachShader(program, vertex);
+    gl::AttachShader(program, fragment);
+    gl::LinkProgram(program);
+
+    // shaders are kept by the program until it is deleted
+    gl::DeleteShader(vertex);
+    gl::DeleteShader(fragment);
+
+    let mut status = gl::FALSE as GLint;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+    if status != gl::TRUE as GLint {
+        let log = get_log(program, false);
+        gl::DeleteProgram(program);
+        return Err(format!("unable to link shaders: {}", log));
+    }
+
+    Ok(program)
+}
+
+unsafe fn get_uniform(program: GLuint, name: &str) -> GLint {
+    let name = CString::new(name).expect("uniform names have no zero bytes");
+    gl::GetUniformLocation(program, name.as_ptr())
+}
+
+fn get_rule_mask<F: Fn(usize) -> bool>(f: F) -> GLint {
+    (0..9).filter(|&n| f(n)).fold(0, |mask, n| mask | 1 << n)
+}
+
+impl GpuStepper {
+
+    /// Needs the OpenGL context of the window to be current, the board
+    /// has to be bounded in both dimensions
+    pub fn new(bounds: &Rect) -> Result<GpuStepper, String> {
+        let (width, height) = (bounds.get_width() as GLint, bounds.get_height() as GLint);
+
+        unsafe {
+            let mut max_size = 0;
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size);
+            if width > max_size || height > max_size {
+                return Err(format!("board is larger than the largest texture, {} cells", max_size));
+            }
+
+            let program = link(FRAGMENT_SHADER)?;
+            let count_program = match link(COUNT_SHADER) {
+                Ok(count_program) => count_program,
+                Err(err) => {
+                    gl::DeleteProgram(program);
+                    return Err(err);
+                }
+            };
+
+            let mut textures = [0; 2];
+            gl::GenTextures(2, textures.as_mut_ptr());
+            for &texture in textures.iter() {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::R32F as GLint, width, height, 0,
+                               gl::RED, gl::FLOAT, ptr::null());
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            let (mut vertex_array, mut vertex_buffer) = (0, 0);
+            gl::GenVertexArrays(1, &mut vertex_array);
+            gl::BindVertexArray(vertex_array);
+
+            gl::GenBuffers(1, &mut vertex_buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+            gl::BufferData(gl::ARRAY_BUFFER, (QUAD.len() * mem::size_of::<f32>()) as GLsizeiptr,
+                           QUAD.as_ptr() as *const _, gl::STATIC_DRAW);
+
+            let name = CString::new("pos").expect("attribute name has no zero bytes");
+            let pos = gl::GetAttribLocation(program, name.as_ptr()) as GLuint;
+            gl::EnableVertexAttribArray(pos);
+            gl::VertexAttribPointer(pos, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            let (mut framebuffer, mut count_framebuffer, mut count_renderbuffer) = (0, 0, 0);
+            gl::GenFramebuffers(1, &mut framebuffer);
+
+            gl::GenRenderbuffers(1, &mut count_renderbuffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, count_renderbuffer);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::R8, width, height);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut count_framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, count_framebuffer);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, count_renderbuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            Ok(GpuStepper {
+                program: program,
+                count_program: count_program,
+                vertex_array: vertex_array,
+                vertex_buffer: vertex_buffer,
+                framebuffer: framebuffer,
+                count_framebuffer: count_framebuffer,
+                count_renderbuffer: count_renderbuffer,
+
+                textures: textures,
+                cur: 0,
+
+                bounds: *bounds,
+            })
+        }
+    }
+
+    pub fn get_bounds(&self) -> &Rect {
+        &self.bounds
+    }
+
+    /// Replaces the current generation with the live cells of the board
+    pub fn upload(&mut self, board: &Board) {
+        let width = self.bounds.get_width();
+        let mut ages = vec![0.0f32; width * self.bounds.get_height()];
+
+        for cell in board.into_iter().filter(|cell| cell.is_alive) {
+            let (x, y) = ((cell.coord.col - self.bounds.left) as usize, (cell.coord.row - self.bounds.top) as usize);
+            ages[y * width + x] = cell.gen as f32;
+        }
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.textures[self.cur]);
+            gl::TexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, width as GLint, self.bounds.get_height() as GLint,
+                              gl::RED, gl::FLOAT, ages.as_ptr() as *const _);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Computes the given number of generations and counts the cells of
+    /// each of them, the GL state other than the current program is
+    /// restored afterwards
+    pub fn step(&mut self, rule: &Rule, steps: usize) -> Vec<Stats> {
+        let mut queries = vec![0; 3 * steps];
+
+        unsafe {
+            let mut viewport = [0; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+
+            gl::Viewport(0, 0, self.bounds.get_width() as GLint, self.bounds.get_height() as GLint);
+            gl::Disable(gl::BLEND);
+
+            gl::UseProgram(self.count_program);
+            gl::Uniform1i(get_uniform(self.count_program, "cells"), 0);
+            gl::Uniform1i(get_uniform(self.count_program, "prev"), 1);
+            let mode = get_uniform(self.count_program, "mode");
+
+            gl::UseProgram(self.program);
+            gl::Uniform1i(get_uniform(self.program, "cells"), 0);
+            gl::Uniform1i(get_uniform(self.program, "birth"), get_rule_mask(|n| rule.is_born(n)));
+            gl::Uniform1i(get_uniform(self.program, "survival"), get_rule_mask(|n| rule.survives(n)));
+
+            gl::BindVertexArray(self.vertex_array);
+            gl::GenQueries(queries.len() as GLsizei, queries.as_mut_ptr());
+
+            for generation in queries.chunks(3) {
+                let (prev, next) = (self.cur, 1 - self.cur);
+
+                gl::UseProgram(self.program);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.textures[next], 0);
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, self.textures[prev]);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                self.cur = next;
+
+                // results are waited for only once all the generations are drawn
+                gl::UseProgram(self.count_program);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.count_framebuffer);
+                gl::BindTexture(gl::TEXTURE_2D, self.textures[next]);
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_2D, self.textures[prev]);
+                for (&query, &counted) in generation.iter().zip([COUNT_POPULATION, COUNT_BIRTHS, COUNT_DEATHS].iter()) {
+                    gl::Uniform1i(mode, counted);
+                    gl::BeginQuery(gl::SAMPLES_PASSED, query);
+                    gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                    gl::EndQuery(gl::SAMPLES_PASSED);
+                }
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+
+            gl::BindVertexArray(0);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Enable(gl::BLEND);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+
+        let counts: Vec<usize> = queries.iter().map(|&query| {
+            let mut count = 0;
+            unsafe {
+                gl::GetQueryObjectuiv(query, gl::QUERY_RESULT, &mut count);
+            }
+            count as usize
+        }).collect();
+
+        unsafe {
+            gl::DeleteQueries(queries.len() as GLsizei, queries.as_ptr());
+        }
+
+        counts.chunks(3).map(|counts| Stats { population: counts[0], births: counts[1], deaths: counts[2] }).collect()
+    }
+
+    /// Live cells of the current generation inside the given region, with their ages
+    pub fn read_region(&self, rect: &Rect) -> Vec<(Coord, usize)> {
+        let bounds = &self.bounds;
+        let (left, top) = (rect.left.max(bounds.left), rect.top.max(bounds.top));
+        let (right, bottom) = (rect.right.min(bounds.right), rect.bottom.min(bounds.bottom));
+        if left > right || top > bottom {
+            return Vec::new();
+        }
+
+        let (width, height) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+        let mut ages = vec![0.0f32; width * height];
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.textures[self.cur], 0);
+            gl::ReadPixels((left - bounds.left) as GLint, (top - bounds.top) as GLint, width as GLint, height as GLint,
+                           gl::RED, gl::FLOAT, ages.as_mut_ptr() as *mut _);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        ages.iter().enumerate()
+            .filter(|&(_, &age)| age > 0.0)
+            .map(|(idx, &age)| (Coord { col: left + (idx % width) as i64, row: top + (idx / width) as i64 }, age as usize))
+            .collect()
+    }
+
+}
+
+impl Drop for GpuStepper {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteFramebuffers(1, &self.count_framebuffer);
+            gl::DeleteRenderbuffers(1, &self.count_renderbuffer);
+            gl::DeleteBuffers(1, &self.vertex_buffer);
+            gl::DeleteVertexArrays(1, &self.vertex_array);
+            gl::DeleteTextures(2, self.textures.as_ptr());
+            gl::DeleteProgram(self.program);
+            gl::DeleteProgram(self.count_program);
+        }
+    }
+}
@@ -0,0 +1,430 @@
+// Software rasterizer for rendered quads, used for exported images
+// and rendering tests, images are saved as PNG or animated GIF
+extern crate engine;
+
+use super::render::Quad;
+
+use self::engine::io::compression::zlib_compress;
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+// 8 bits per channel RGB, no interlacing
+const PNG_BIT_DEPTH: u8 = 8;
+const PNG_COLOR_TYPE_RGB: u8 = 2;
+
+// GIF frames have up to 256 colors, frames having more are
+// reduced to 8 levels of red and green and 4 levels of blue
+const GIF_MAX_COLORS: usize = 256;
+const GIF_MAX_CODE: u16 = 4096;
+const GIF_MAX_SIZE: usize = 65535;
+
+
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    // RGB, every channel is 0..1
+    pixels: Vec<[f64; 3]>,
+}
+
+impl Canvas {
+
+    pub fn new(width: usize, height: usize, background: [f32; 4]) -> Self {
+        let background = [background[0] as f64, background[1] as f64, background[2] as f64];
+        Canvas { width: width, height: height, pixels: vec![background; width * height] }
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn fill(&mut self, quad: &Quad) {
+        // blend quad color into every pixel proportionally to the covered area
+        let (left, top) = (quad.rect[0], quad.rect[1]);
+        let (right, bottom) = (left + quad.rect[2], top + quad.rect[3]);
+
+        let x_range = (left.floor().max(0.0) as usize, right.ceil().min(self.width as f64).max(0.0) as usize);
+        let y_range = (top.floor().max(0.0) as usize, bottom.ceil().min(self.height as f64).max(0.0) as usize);
+
+        for y in y_range.0..y_range.1 {
+            for x in x_range.0..x_range.1 {
+                let covered_x = right.min(x as f64 + 1.0) - left.max(x as f64);
+                let covered_y = bottom.min(y as f64 + 1.0) - top.max(y as f64);
+                let alpha = quad.color[3] as f64 * covered_x * covered_y;
+
+                let pixel = &mut self.pixels[y * self.width + x];
+                for i in 0..3 {
+                    pixel[i] = pixel[i] * (1.0 - alpha) + quad.color[i] as f64 * alpha;
+                }
+            }
+        }
+    }
+
+    /// Pixels row by row, three bytes per pixel
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pixels.iter()
+            .flat_map(|p| p.iter().map(|c| (c.max(0.0).min(1.0) * 255.0).round() as u8).collect::<Vec<u8>>())
+            .collect()
+    }
+
+    pub fn write_png(&self, writer: &mut Write) -> io::Result<()> {
+        writer.write_all(&PNG_SIGNATURE)?;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&to_be_bytes(self.width as u32));
+        header.extend_from_slice(&to_be_bytes(self.height as u32));
+        // no compression, filtering or interlace options besides the defaults
+        header.extend_from_slice(&[PNG_BIT_DEPTH, PNG_COLOR_TYPE_RGB, 0, 0, 0]);
+        write_png_chunk(writer, b"IHDR", &header)?;
+
+        // every row starts with its filter type, rows are not filtered
+        let bytes = self.to_bytes();
+        let mut data = Vec::with_capacity(bytes.len() + self.height);
+        for row in 0..self.height {
+            data.push(0);
+            data.extend_from_slice(&bytes[row * self.width * 3..(row + 1) * self.width * 3]);
+        }
+        write_png_chunk(writer, b"IDAT", &zlib_compress(&data)?)?;
+
+        write_png_chunk(writer, b"IEND", &[])
+    }
+
+}
+
+/// Animated GIF written frame by frame, the animation loops forever
+pub struct GifWriter<W: Write> {
+    writer: W,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> GifWriter<W> {
+
+    pub fn new(mut writer: W, width: usize, height: usize) -> io::Result<Self> {
+        if width > GIF_MAX_SIZE || height > GIF_MAX_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "image is too large for GIF"));
+        }
+
+        writer.write_all(b"GIF89a")?;
+
+        // logical screen without global color table
+        writer.write_all(&to_le_bytes(width as u16))?;
+        writer.write_all(&to_le_bytes(height as u16))?;
+        writer.write_all(&[0, 0, 0])?;
+
+        // application extension making the animation loop
+        writer.write_all(&[0x21, 0xff, 0x0b])?;
+        writer.write_all(b"NETSCAPE2.0")?;
+        writer.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+        Ok(GifWriter { writer: writer, width: width, height: height })
+    }
+
+    /// Adds frame shown for the given number of hundredths of a second,
+    /// frame must be of the animation size
+    pub fn add_frame(&mut self, canvas: &Canvas, delay: u16) -> io::Result<()> {
+        if canvas.get_width() != self.width || canvas.get_height() != self.height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame size differs from the animation size"));
+        }
+
+        let (colors, indices) = gif_palette(&canvas.to_bytes());
+
+        // graphic control extension with frame delay
+        self.writer.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        self.writer.write_all(&to_le_bytes(delay))?;
+        self.writer.write_all(&[0x00, 0x00])?;
+
+        // image descriptor, every frame covers the whole animation and has its own colors
+        let table_bits = gif_table_bits(colors.len());
+        self.writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.writer.write_all(&to_le_bytes(self.width as u16))?;
+        self.writer.write_all(&to_le_bytes(self.height as u16))?;
+        self.writer.write_all(&[0x80 | (table_bits - 1)])?;
+
+        for idx in 0..1 << table_bits {
+            self.writer.write_all(&colors.get(idx).cloned().unwrap_or([0, 0, 0]))?;
+        }
+
+        // codes start one bit wider than color indices, but at least 3 bits wide
+        let min_code_size = table_bits.max(2);
+        self.writer.write_all(&[min_code_size])?;
+
+        for block in lzw_encode(&indices, min_code_size).chunks(255) {
+            self.writer.write_all(&[block.len() as u8])?;
+            self.writer.write_all(block)?;
+        }
+        self.writer.write_all(&[0])
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        Ok(self.writer)
+    }
+
+}
+
+fn gif_table_bits(colors: usize) -> u8 {
+    let mut bits = 1;
+    while (1 << bits) < colors {
+        bits += 1;
+    }
+    bits
+}
+
+fn gif_palette(bytes: &[u8]) -> (Vec<[u8; 3]>, Vec<u8>) {
+    // exact colors when there are few of them
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    let mut known: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(bytes.len() / 3);
+
+    for pixel in bytes.chunks(3) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+
+        if let Some(&idx) = known.get(&color) {
+            indices.push(idx);
+            continue;
+        }
+
+        if colors.len() == GIF_MAX_COLORS {
+            return gif_palette_reduced(bytes);
+        }
+
+        known.insert(color, colors.len() as u8);
+        indices.push(colors.len() as u8);
+        colors.push(color);
+    }
+
+    (colors, indices)
+}
+
+fn gif_palette_reduced(bytes: &[u8]) -> (Vec<[u8; 3]>, Vec<u8>) {
+    // fixed palette, 3 bits of red, 3 bits of green and 2 bits of blue
+    let colors = (0..GIF_MAX_COLORS)
+        .map(|idx| [((idx >> 5) * 255 / 7) as u8, ((idx >> 2 & 7) * 255 / 7) as u8, ((idx & 3) * 255 / 3) as u8])
+        .collect();
+
+    let indices = bytes.chunks(3)
+        .map(|p| ((p[0] as usize * 7 + 127) / 255 << 5 | (p[1] as usize * 7 + 127) / 255 << 2 |
+                  (p[2] as usize * 3 + 127) / 255) as u8)
+        .collect();
+
+    (colors, indices)
+}
+
+fn lzw_encode(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    // variable width LZW as required by GIF, codes are packed starting
+    // from the least significant bits, dictionary is restarted when full
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut dict: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size + 1;
+
+    let mut res = Vec::new();
+    let (mut bits, mut bit_count) = (0u32, 0);
+    let mut put = |code: u16, size: u8, res: &mut Vec<u8>| {
+        bits |= (code as u32) << bit_count;
+        bit_count += size;
+        while bit_count >= 8 {
+            res.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    put(clear, code_size, &mut res);
+
+    let mut prefix: Option<u16> = None;
+    for &idx in indices {
+        let code = match prefix {
+            Some(code) => code,
+            None => {
+                prefix = Some(idx as u16);
+                continue;
+            }
+        };
+
+        if let Some(&longer) = dict.get(&(code, idx)) {
+            prefix = Some(longer);
+            continue;
+        }
+
+        put(code, code_size, &mut res);
+
+        if next_code < GIF_MAX_CODE {
+            if next_code == 1 << code_size {
+                code_size += 1;
+            }
+            dict.insert((code, idx), next_code);
+            next_code += 1;
+        } else {
+            put(clear, code_size, &mut res);
+            dict.clear();
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        }
+
+        prefix = Some(idx as u16);
+    }
+
+    if let Some(code) = prefix {
+        put(code, code_size, &mut res);
+    }
+    put(end, code_size, &mut res);
+    put(0, 7, &mut res);
+
+    res
+}
+
+fn to_le_bytes(value: u16) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8]
+}
+
+fn to_be_bytes(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xffffffffu32;
+
+    for part in parts {
+        for &byte in part.iter() {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+            }
+        }
+    }
+
+    !crc
+}
+
+fn write_png_chunk(writer: &mut Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    // length, type, data and checksum of type and data
+    writer.write_all(&to_be_bytes(data.len() as u32))?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&to_be_bytes(crc32(&[kind, data])))
+}
+
+
+#[test]
+fn test_canvas_fill() {
+    let mut canvas = Canvas::new(4, 2, [0.0, 0.0, 1.0, 1.0]);
+
+    // covers pixel (1, 0) fully and half of (2, 0)
+    canvas.fill(&Quad { color: [1.0, 0.0, 0.0, 1.0], rect: [1.0, 0.0, 1.5, 1.0] });
+
+    let bytes = canvas.to_bytes();
+    assert_eq!(&bytes[0..3], &[0, 0, 255]);
+    assert_eq!(&bytes[3..6], &[255, 0, 0]);
+    assert_eq!(&bytes[6..9], &[128, 0, 128]);
+    assert_eq!(&bytes[12..15], &[0, 0, 255]);
+}
+
+#[test]
+fn test_png_chunks() {
+    let mut png = Vec::new();
+    Canvas::new(3, 2, [0.0, 0.0, 0.0, 1.0]).write_png(&mut png).unwrap();
+
+    assert!(png.starts_with(&PNG_SIGNATURE));
+
+    // header with size and format goes first
+    assert_eq!(&png[8..16], &[0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+    assert_eq!(&png[16..29], &[0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+
+    // empty IEND chunk with its well-known checksum finishes the file
+    assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+}
+
+#[cfg(test)]
+fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut dict: Vec<Vec<u8>> = Vec::new();
+    let mut code_size = min_code_size + 1;
+    let mut prev: Option<Vec<u8>> = None;
+    let mut res = Vec::new();
+
+    let (mut pos, total) = (0, data.len() * 8);
+    while pos + code_size as usize <= total {
+        let code = (0..code_size as usize)
+            .fold(0u16, |code, bit| code | ((data[(pos + bit) / 8] >> ((pos + bit) % 8) & 1) as u16) << bit);
+        pos += code_size as usize;
+
+        if code == clear {
+            dict = (0..clear).map(|c| vec![c as u8]).collect();
+            dict.push(Vec::new());
+            dict.push(Vec::new());
+            code_size = min_code_size + 1;
+            prev = None;
+            continue;
+        }
+        if code == end {
+            break;
+        }
+
+        let entry = match (dict.get(code as usize).cloned(), prev.clone()) {
+            (Some(entry), _) => entry,
+            (None, Some(prev)) => { let mut e = prev.clone(); e.push(prev[0]); e }
+            (None, None) => panic!("unknown code")
+        };
+
+        if let Some(mut prev) = prev {
+            prev.push(entry[0]);
+            dict.push(prev);
+            if dict.len() == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+        }
+
+        res.extend_from_slice(&entry);
+        prev = Some(entry);
+    }
+
+    res
+}
+
+#[test]
+fn test_lzw_round_trip() {
+    // long enough to fill the dictionary several times
+    let indices: Vec<u8> = (0..100000u64).map(|i| ((i * i / 7 + i / 13) % 4) as u8).collect();
+    assert_eq!(lzw_decode(&lzw_encode(&indices, 2), 2), indices);
+
+    let noise: Vec<u8> = (0..50000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+    assert_eq!(lzw_decode(&lzw_encode(&noise, 8), 8), noise);
+}
+
+#[test]
+fn test_gif_frames() {
+    let mut canvas = Canvas::new(3, 2, [0.0, 0.0, 0.0, 1.0]);
+    canvas.fill(&Quad { color: [1.0, 0.0, 0.0, 1.0], rect: [1.0, 0.0, 1.0, 1.0] });
+
+    let mut gif = GifWriter::new(Vec::new(), 3, 2).unwrap();
+    gif.add_frame(&canvas, 10).unwrap();
+    assert!(gif.add_frame(&Canvas::new(2, 2, [0.0, 0.0, 0.0, 1.0]), 10).is_err());
+    let gif = gif.finish().unwrap();
+
+    assert!(gif.starts_with(b"GIF89a\x03\x00\x02\x00"));
+    assert_eq!(gif[gif.len() - 1], 0x3b);
+
+    // two colors, black goes first
+    let (colors, indices) = gif_palette(&canvas.to_bytes());
+    assert_eq!(colors, vec![[0, 0, 0], [255, 0, 0]]);
+    assert_eq!(indices, vec![0, 1, 0, 0, 0, 0]);
+
+    // too many colors are reduced
+    let gradient: Vec<u8> = (0..300).flat_map(|i| vec![(i % 256) as u8, if i < 256 { 0 } else { 255 }, 0]).collect();
+    let (colors, indices) = gif_palette(&gradient);
+    assert_eq!(colors.len(), 256);
+    assert_eq!(colors[indices[299] as usize], [36, 255, 0]);
+}
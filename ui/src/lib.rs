@@ -2,37 +2,173 @@ extern crate opengl_graphics;
 extern crate piston_window;
 extern crate engine;
 
+pub mod config;
+pub mod rules;
+pub mod recording;
 mod windows;
-
-use self::windows::{WindowBase, PostAction, States};
+mod focus;
+mod render;
+mod image;
+mod clipboard;
+mod library;
+mod prompts;
+mod files;
+mod boundary;
+mod log;
+mod report;
+mod svg;
+#[cfg(feature = "gpu")]
+mod gpu;
+
+#[cfg(test)]
+mod golden;
+
+use self::windows::{WindowBase, WindowLayout, PostAction, States, Modifiers, HudMode};
 use self::windows::board::GameBoard;
 use self::windows::hud::HUDWindow;
 use self::windows::confirm::{ConfirmationWindow, UserChoice};
 use self::windows::info::InfoWindow;
+use self::windows::stats::StatsWindow;
+use self::windows::onboarding::OnboardingWindow;
+use self::windows::demo::DemoWindow;
+use self::windows::screensaver::ScreensaverWindow;
+use self::windows::soup::SoupWindow;
+use self::windows::toast::ToastWindow;
+use self::windows::rules::RulePickerWindow;
+use self::windows::meta::MetaEditorWindow;
+use self::windows::gif::GifExportWindow;
+use self::windows::browser::PatternBrowserWindow;
+use self::windows::find::FindWindow;
+use self::windows::prompts::PromptSettingsWindow;
+use self::windows::files::{FileDialogWindow, FileDialogMode};
+use self::windows::presets::PresetWindow;
+use self::windows::jump::JumpWindow;
+use self::windows::explorer::RuleExplorerWindow;
+use self::focus::FocusManager;
+use self::report::Report;
+use self::recording::Recorder;
+use self::config::Config;
+use self::rules::{RuleRegistry, TableInfo, Palette};
 
 use self::engine::structs::GraphicsWindow;
 use self::engine::engine::Engine;
+use self::engine::backend;
+use self::engine::board::{Coord, Rect, EdgePolicy};
+use self::engine::io::container;
+use self::engine::io::container::Container;
+use self::engine::io::compression;
+use self::engine::io::rle;
+use self::engine::io::rle::RleFile;
+use self::engine::io::fetch;
+use self::engine::io::PatternMeta;
+use self::engine::autosave::Autosave;
+use self::engine::error::{EngineError, EngineResult};
+use self::engine::census::Census;
+use self::engine::pattern::Pattern;
+use self::engine::cycles::CycleDetector;
+use self::engine::search;
+use self::engine::presets;
+use self::engine::presets::Preset;
 
 use opengl_graphics::GlGraphics;
 use opengl_graphics::glyph_cache::GlyphCache;
 
 use std::rc::Rc;
 use std::cell::{RefCell, Cell};
-
-use piston_window::{OpenGL, Event, Input, Button, Key, Context, clear};
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH, Instant, Duration};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::thread;
+
+use piston_window::{OpenGL, Event, Input, Button, Key, Context, EventLoop, clear};
 
 pub const OPENGL: piston_window::OpenGL = OpenGL::V3_2;
 
+// seconds without user input before the demo starts, 0 disables it
+const DEFAULT_DEMO_IDLE_TIMEOUT: f64 = 300.0;
+
+const SESSION_FILE_NAME: &'static str = ".life-rs-session";
+
+// seconds between checks of the config file for changes
+const CONFIG_CHECK_INTERVAL: f64 = 1.0;
+
+// frame and update rates, lowered when the simulation is paused
+// and there was no user input for a while to save CPU and GPU
+const FPS: u64 = 60;
+const UPS: u64 = 120;
+const IDLE_FPS: u64 = 10;
+const IDLE_UPS: u64 = 10;
+const IDLE_THROTTLE_DELAY: f64 = 2.0;
+
+// running simulation is saved every that many generations or seconds,
+// whichever comes first, into one of several files in turn
+const DEFAULT_AUTOSAVE_GENERATIONS: usize = 5000;
+const DEFAULT_AUTOSAVE_INTERVAL: f64 = 60.0;
+const AUTOSAVE_SLOTS: usize = 3;
+const DEFAULT_AUTOSAVE_JOURNAL: usize = 10;
+
+// downloads are given up after that many seconds, however slowly
+// the data comes, connecting included
+const FETCH_TIMEOUT: u64 = 60;
+
 
 pub struct UI<'a> {
 
     cur_state: Cell<States>,
 
+    // currently selected region of the board, shared between windows
+    selection: Rc<Cell<Option<Rect>>>,
+    // region of the board visible on the screen
+    viewport: Rc<Cell<Option<Rect>>>,
+    // region the board camera should move to, set by other windows
+    look_at: Rc<Cell<Option<Rect>>>,
+    // generation and population computed on the GPU ahead of the engine
+    gpu_progress: Rc<Cell<Option<(usize, usize)>>>,
+    // cells copied on the board with Ctrl+C
+    clipboard: Rc<RefCell<Pattern>>,
+    // previous state of cells changed by the last replacement and
+    // the generation it was made at, it can't be undone after that
+    undo: Rc<RefCell<Option<(usize, Vec<(Coord, bool)>)>>>,
+    // pattern file chosen in the file dialog, opened on the next event
+    open_request: Rc<RefCell<Option<PathBuf>>>,
+    // preset chosen in the start menu, loaded on the next event
+    preset_request: Rc<Cell<Option<usize>>>,
+
     stack: Vec<Box<WindowBase + 'a>>,
+    focus: FocusManager,
+    modifiers: Modifiers,
+
+    // seconds since the last user input
+    idle_time: f64,
+    // seconds since the config file was checked for changes
+    config_check_time: f64,
+    // frame rate is lowered
+    throttled: bool,
+    // overlays drawn over the board, cycled with I
+    hud_mode: HudMode,
+    // events coming from the window are written here if set
+    recorder: Option<Recorder>,
+    // pattern being downloaded on another thread, see `fetch_pattern`
+    fetching: Option<Fetching>,
 
     window: Rc<GraphicsWindow>,
     engine: Rc<RefCell<Engine<'a>>>,
     resources: Rc<RefCell<Resources>>,
+    config: Rc<RefCell<Config>>,
+    rules: Rc<RefCell<RuleRegistry>>,
+}
+
+// link or object code, the result of the download and when it was started
+struct Fetching {
+    source: String,
+    receiver: Receiver<io::Result<(Vec<Coord>, Option<String>, PatternMeta)>>,
+    started: Instant,
 }
 
 pub struct Resources {
@@ -45,6 +181,17 @@ impl<'a> UI<'a> {
         self.stack.push(w);
     }
 
+    fn create_board_window(&self) -> Box<WindowBase + 'a> {
+        Box::new(GameBoard::new(self.get_window(),
+                                self.get_engine(),
+                                self.get_rules(),
+                                self.get_selection(),
+                                self.get_viewport(),
+                                self.get_look_at(),
+                                self.get_clipboard(),
+                                self.gpu_progress.clone()))
+    }
+
     pub fn push_front(&mut self, w: Box<WindowBase + 'a>) {
         if self.stack.len() != 0 {
             if !self.stack[0].is_modal() {
@@ -65,7 +212,83 @@ impl<'a> UI<'a> {
         self.resources.clone()
     }
 
-    fn create_prompt_window<F: 'a>(&mut self, msg: &'a str, callback: F)  where
+    pub fn get_config(&self) -> Rc<RefCell<Config>> {
+        self.config.clone()
+    }
+
+    pub fn get_rules(&self) -> Rc<RefCell<RuleRegistry>> {
+        self.rules.clone()
+    }
+
+    fn load_rules(&mut self) -> Vec<String> {
+        let mut rules = RuleRegistry::new();
+        let errors = rules.add_from_config(&self.config.borrow());
+
+        // tables opened from files stay on the list
+        for info in self.rules.borrow().get_tables().iter().filter(|info| info.opened) {
+            rules.add_table(info.clone());
+        }
+
+        *self.rules.borrow_mut() = rules;
+        errors
+    }
+
+    pub fn get_selection(&self) -> Rc<Cell<Option<Rect>>> {
+        self.selection.clone()
+    }
+
+    pub fn get_viewport(&self) -> Rc<Cell<Option<Rect>>> {
+        self.viewport.clone()
+    }
+
+    pub fn get_look_at(&self) -> Rc<Cell<Option<Rect>>> {
+        self.look_at.clone()
+    }
+
+    pub fn get_clipboard(&self) -> Rc<RefCell<Pattern>> {
+        self.clipboard.clone()
+    }
+
+    fn is_modal_open(&self) -> bool {
+        self.stack.first().map_or(false, |window| window.is_modal())
+    }
+
+    fn is_keyboard_taken(&self) -> bool {
+        self.stack.iter().any(|window| window.takes_keys())
+    }
+
+    /// Windows open, the board and the overlays included
+    pub fn get_window_count(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn restore_layouts(&mut self) {
+        let config = self.config.borrow();
+
+        for window in self.stack.iter_mut() {
+            if let Some(name) = window.get_name() {
+                if let Some(layout) = WindowLayout::load(&config, name) {
+                    window.set_layout(layout);
+                }
+            }
+        }
+    }
+
+    fn save_layouts(&mut self) {
+        let mut config = self.config.borrow_mut();
+
+        for window in self.stack.iter() {
+            if let (Some(name), Some(layout)) = (window.get_name(), window.get_layout()) {
+                layout.save(&mut config, name);
+            }
+        }
+
+        if let Err(err) = config.save() {
+            log::write(&format!("unable to save config: {}", err));
+        }
+    }
+
+    fn create_prompt_window<F: 'a>(&mut self, msg: &str, callback: F)  where
         F: FnMut(Rc<RefCell<Engine<'a>>>, UserChoice) {
 
         let confirm_window = Box::new(ConfirmationWindow::new(self.get_resources(), self.get_engine(),
@@ -76,7 +299,38 @@ impl<'a> UI<'a> {
         self.push_front(confirm_window);
     }
 
-    fn create_info_window(&mut self, msg: &'a str) {
+    /// Confirmation of a destructive action, which the user can turn off,
+    /// the action is done at once if it has been turned off
+    fn create_safety_prompt<F: 'a>(&mut self, id: &'static str, msg: &str, callback: F)  where
+        F: FnMut(Rc<RefCell<Engine<'a>>>, UserChoice) {
+
+        if !prompts::is_enabled(&self.config.borrow(), id) {
+            let mut callback = callback;
+            return callback(self.get_engine(), UserChoice::Ok);
+        }
+
+        let confirm_window = Box::new(ConfirmationWindow::new(self.get_resources(), self.get_engine(),
+                                         callback, msg,
+                                         self.get_window().get_width(),
+                                         self.get_window().get_height())
+                                      .with_dont_ask(id, self.get_config()));
+
+        self.push_front(confirm_window);
+    }
+
+    /// Errors the user has to do something about, like a malformed rule or
+    /// a pattern larger than the board, are shown in a dialog, others in a toast
+    fn show_engine_error(&mut self, msg: &str, err: &EngineError) {
+        match *err {
+            EngineError::Io(_) => self.show_toast(format!("{}: {}", msg, err)),
+            EngineError::Rule(_) | EngineError::Board(_) => {
+                log::write(&format!("{}: {}", msg, err));
+                self.create_info_window(&format!("{}: {}", msg, err));
+            }
+        }
+    }
+
+    fn create_info_window(&mut self, msg: &str) {
 
         let info_window = Box::new(InfoWindow::new(
             self.get_resources(), self.get_engine(),
@@ -89,14 +343,767 @@ impl<'a> UI<'a> {
 
     }
 
+    fn show_toast(&mut self, msg: String) {
+        log::write(&msg);
+
+        let toast_window = Box::new(ToastWindow::new(self.get_window(), self.get_resources(), msg));
+        self.push_front(toast_window);
+    }
+
+    fn check_config(&mut self, e: &Event) {
+        if let &Event::Update(args) = e {
+            self.config_check_time += args.dt;
+
+            if self.config_check_time < CONFIG_CHECK_INTERVAL {
+                return;
+            }
+            self.config_check_time = 0.0;
+
+            // broken config is reported, the app keeps working with what it could read
+            let reloaded = self.config.borrow_mut().reload_if_changed();
+            let changed = match reloaded {
+                Ok(changed) => changed,
+                Err(err) => {
+                    self.show_toast(format!("Unable to reload config: {}", err));
+                    return;
+                }
+            };
+
+            if changed.is_empty() {
+                return;
+            }
+
+            let mut errors = self.config.borrow().get_errors().to_vec();
+            errors.extend(self.apply_config(&changed));
+
+            if errors.is_empty() {
+                self.show_toast(format!("Config reloaded: {}", changed.join(", ")));
+            } else {
+                self.show_toast(format!("Config error: {}", errors.join("; ")));
+            }
+        }
+    }
+
+    fn apply_config(&mut self, changed: &[String]) -> Vec<String> {
+        // most settings are read from the config when they are needed,
+        // those which are not are applied here, returns validation errors
+        let mut errors = Vec::new();
+
+        // rules are defined by several keys each, so reload all of them
+        if changed.iter().any(|key| key.starts_with("rules.")) {
+            errors.extend(self.load_rules());
+        }
+
+        let config = self.config.borrow();
+
+        for key in changed {
+            if config.get(key).is_none() {
+                // removed settings fall back to defaults on their own
+                continue;
+            }
+
+            let is_valid = match key.as_str() {
+                "demo.idle_timeout" => config.get_parsed::<f64>(key).map_or(false, |t| t >= 0.0),
+                "soup.next_seed" => config.get_parsed::<u64>(key).is_some(),
+                "save.compress" => config.get_parsed::<u32>(key).is_some(),
+
+                "edit.edges" => match config.get_parsed::<EdgePolicy>(key) {
+                    Some(policy) => {
+                        self.engine.borrow_mut().set_edge_policy(policy);
+                        true
+                    }
+                    None => false
+                },
+                "pause.auto" => match config.get_parsed::<u32>(key) {
+                    Some(auto_stop) => {
+                        self.engine.borrow_mut().set_auto_stop(auto_stop != 0);
+                        true
+                    }
+                    None => false
+                },
+                "engine.backend" => match config.get(key).and_then(backend::find) {
+                    Some(backend) => {
+                        self.engine.borrow_mut().set_backend(backend);
+                        true
+                    }
+                    None => false
+                },
+                _ if key.starts_with("confirm.") => config.get_parsed::<u32>(key).is_some(),
+
+                _ if key.starts_with("window.") => {
+                    let name = &key["window.".len()..];
+
+                    match WindowLayout::load(&config, name) {
+                        Some(layout) => {
+                            for window in self.stack.iter_mut() {
+                                if window.get_name() == Some(name) {
+                                    window.set_layout(layout);
+                                }
+                            }
+                            true
+                        }
+                        None => false
+                    }
+                }
+
+                // checked when rules are reloaded
+                _ => true
+            };
+
+            if !is_valid {
+                errors.push(format!("malformed value of '{}'", key));
+            }
+        }
+
+        errors
+    }
+
+    /// Replaces the board with pattern from the file and shows the whole
+    /// pattern, simulation is paused unless `load.pause` is 0 in the config
+    pub fn open_pattern(&mut self, path: &Path) {
+        if files::is_rule_file(path) {
+            return self.open_rule(path);
+        }
+
+        let file_name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+
+        let loaded = self.engine.borrow_mut().load_pattern(path);
+        self.on_pattern_opened(&file_name, loaded);
+    }
+
+    /// Runs the board by the rule from Golly `.rule` file, the pattern stays
+    pub fn open_rule(&mut self, path: &Path) {
+        let file_name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+
+        let loaded = self.engine.borrow_mut().load_rule_table(path);
+        match loaded {
+            Ok(_) => {
+                // the rule picker offers it until the end of the session
+                if let Some(table) = self.engine.borrow().get_rule_table() {
+                    let name = if table.get_name().is_empty() { file_name.clone() } else { table.get_name().to_string() };
+
+                    self.rules.borrow_mut().add_table(TableInfo {
+                        table: table.clone(),
+                        name: name,
+                        description: format!("Opened from {}", file_name),
+                        palette: Palette::default(),
+                        opened: true,
+                    });
+                }
+
+                let name = self.engine.borrow().get_rule_name();
+                self.show_toast(format!("Rule {} loaded from {}", name, file_name));
+            }
+            Err(err) => self.show_engine_error(&format!("Unable to read rule {}", file_name), &err)
+        }
+    }
+
+    /// Same as `open_pattern`, but for a link or a Catagolue object code,
+    /// the pattern is downloaded on another thread and loaded once it's
+    /// there, see `check_fetch`
+    pub fn fetch_pattern(&mut self, source: &str) {
+        let (sender, receiver) = mpsc::channel();
+        let thread_source = source.to_string();
+
+        // the result is dropped if another download has replaced this one
+        thread::spawn(move || {
+            let _ = sender.send(fetch::fetch_pattern(&thread_source));
+        });
+
+        self.fetching = Some(Fetching { source: source.to_string(), receiver: receiver, started: Instant::now() });
+        self.show_toast(format!("Fetching {}...", source));
+    }
+
+    fn on_pattern_opened(&mut self, name: &str, loaded: EngineResult<()>) {
+        if let Err(err) = loaded {
+            return self.show_engine_error(&format!("Unable to read {}", name), &err);
+        }
+
+        if self.config.borrow().get_parsed("load.pause").unwrap_or(1) != 0 {
+            self.cur_state.set(States::Paused);
+        }
+
+        let rect = self.engine.borrow().get_board().get_bounding_box();
+        if let Some(rect) = rect {
+            self.fit_to(&rect);
+        }
+
+        let population = self.engine.borrow().get_board().get_population();
+        self.show_toast(format!("Loaded {}, {} cells", name, population));
+    }
+
+    /// Replaces the board with a demo and starts it, see `presets`
+    pub fn load_preset(&mut self, preset: &Preset) {
+        let loaded = self.engine.borrow_mut().load_preset(preset);
+        if let Err(err) = loaded {
+            return self.show_engine_error(&format!("Unable to start {}", preset.name), &err);
+        }
+
+        let rect = self.engine.borrow().get_board().get_bounding_box();
+        if let Some(rect) = rect {
+            self.fit_to(&rect);
+        }
+
+        // zoom keeps the middle of the pattern in place
+        if let Some(scale) = preset.zoom {
+            self.set_zoom(scale);
+        }
+
+        for window in self.stack.iter_mut() {
+            window.set_speed(preset.speed);
+        }
+
+        self.cur_state.set(States::Working);
+        self.show_toast(format!("Started {}", preset.name));
+    }
+
+    pub fn set_zoom(&mut self, scale: f64) {
+        for window in self.stack.iter_mut() {
+            window.set_zoom(scale);
+        }
+    }
+
+    pub fn set_gpu(&mut self, gpu: bool) {
+        for window in self.stack.iter_mut() {
+            window.set_gpu(gpu);
+        }
+    }
+
+    pub fn set_hud_mode(&mut self, mode: HudMode) {
+        self.hud_mode = mode;
+        for window in self.stack.iter_mut() {
+            window.set_hud_mode(mode);
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.cur_state.set(if paused { States::Paused } else { States::Working });
+    }
+
+    fn show_file_dialog(&mut self, mode: FileDialogMode) {
+        // starts where exported files go
+        let dir = self.config.borrow().get("export.dir").map_or(PathBuf::from("."), PathBuf::from);
+
+        if mode == FileDialogMode::Save {
+            self.cur_state.set(States::Paused);
+        }
+
+        let dialog = Box::new(FileDialogWindow::new(self.get_window(),
+                                                    self.get_resources(),
+                                                    self.get_engine(),
+                                                    self.open_request.clone(),
+                                                    mode,
+                                                    self.selection.get(),
+                                                    &dir));
+        self.push_front(dialog);
+    }
+
+    fn fit_to(&mut self, rect: &Rect) {
+        for window in self.stack.iter_mut() {
+            window.on_pattern_loaded(rect);
+        }
+    }
+
+    /// Sessions, autosaves and exported patterns of big soups take
+    /// megabytes of text, so they may be gzipped
+    fn compress_saves(&self) -> bool {
+        self.config.borrow().get_parsed::<u32>("save.compress").unwrap_or(0) != 0
+    }
+
+    fn get_session_path(&self) -> Option<PathBuf> {
+        match self.config.borrow().get("session.path") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => env::home_dir().map(|home| home.join(SESSION_FILE_NAME))
+        }
+    }
+
+    /// Saves board with its history, rule, camera and whether
+    /// the simulation is running, so that work can be continued later
+    pub fn save_session(&mut self) {
+        let path = match self.get_session_path() {
+            Some(path) => path,
+            None => return self.show_toast("Unable to save session: no home directory".to_string())
+        };
+
+        let mut container = self.engine.borrow().to_container_meta(true);
+        container.set_meta("running", if self.cur_state.get() == States::Working { 1 } else { 0 });
+
+        for window in self.stack.iter() {
+            window.save_session(&mut container);
+        }
+
+        let compressed = self.compress_saves() || compression::is_compressed_path(&path);
+
+        // cells are written straight from the board
+        let saved = container.save_board(self.engine.borrow().get_board(), &path, compressed);
+
+        match saved {
+            Ok(_) => self.show_toast(format!("Session saved to {}", path.display())),
+            Err(err) => self.show_toast(format!("Unable to save session: {}", err))
+        }
+    }
+
+    pub fn load_session(&mut self) {
+        let path = match self.get_session_path() {
+            Some(path) => path,
+            None => return
+        };
+
+        // a broken session file leaves the current board intact
+        let loaded = Container::load(&path).map_err(EngineError::from)
+            .and_then(|container| self.engine.borrow_mut().load_container(&container).map(|_| container));
+
+        let container = match loaded {
+            Ok(container) => container,
+            Err(EngineError::Io(ref err)) if Container::is_corrupted(err) => return self.offer_autosave(&path, err),
+            Err(err) => return self.show_engine_error("Unable to load session", &err)
+        };
+
+        for window in self.stack.iter_mut() {
+            window.restore_session(&container);
+        }
+
+        self.cur_state.set(if container.get_meta("running") == Some("1") { States::Working } else { States::Paused });
+
+        // the file is rewritten in the current format on the next save
+        let (major, minor) = container.version;
+        if (major, minor) < (container::FORMAT_MAJOR, container::FORMAT_MINOR) {
+            self.show_toast(format!("Session loaded from {}, upgraded from format {}.{}",
+                                    path.display(), major, minor));
+        } else {
+            self.show_toast(format!("Session loaded from {}", path.display()));
+        }
+    }
+
+    /// Damaged session file can be replaced with the latest autosave,
+    /// if there is one
+    fn offer_autosave(&mut self, path: &Path, err: &io::Error) {
+        let latest = self.engine.borrow().get_autosave().and_then(|autosave| autosave.find_latest());
+
+        let latest = match latest {
+            Some(latest) => latest,
+            None => return self.show_toast(format!("Unable to load session: {}", err))
+        };
+
+        log::write(&format!("session {} is corrupted: {}", path.display(), err));
+
+        self.create_prompt_window(
+            &format!("Session {} is corrupted, restore the latest autosave instead?", path.display()),
+            move |engine, user_choice| {
+                if user_choice == UserChoice::Ok {
+                    restore_autosave(&engine, &latest);
+                }
+            }
+        );
+    }
+
+    /// Puts the selection, or the whole pattern if nothing is selected,
+    /// on the system clipboard as RLE for other Life programs
+    fn copy_as_rle(&mut self) {
+        let pattern = {
+            let engine = self.engine.borrow();
+            let board = engine.get_board();
+
+            match self.selection.get() {
+                Some(rect) => RleFile {
+                    rule: Some(engine.get_rule_name()),
+                    cells: board.copy_region(&rect),
+                    ..RleFile::default()
+                },
+                // the whole pattern keeps its name, author and comments
+                None if board.get_population() > 0 => engine.to_rle(),
+                None => return
+            }
+        };
+
+        let mut text = Vec::new();
+        let copied = rle::write(&pattern, &mut text)
+            .and_then(|_| clipboard::set_text(&String::from_utf8_lossy(&text)));
+
+        match copied {
+            Ok(_) => self.show_toast(format!("Copied {} cells as RLE", pattern.cells.len())),
+            Err(err) => self.show_toast(format!("Unable to copy to clipboard: {}", err))
+        }
+    }
+
+    /// Saves the visible part of the board, or with `whole_board` all
+    /// the live cells, as PNG into the current directory or `export.dir`
+    fn save_screenshot(&mut self, whole_board: bool) {
+        let canvas = match self.stack.iter().filter_map(|window| window.render_to_image(whole_board)).next() {
+            Some(canvas) => canvas,
+            None => return self.show_toast("Nothing to save, the board is empty".to_string())
+        };
+
+        // screenshots are named after the time they are taken
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+        let dir = self.config.borrow().get("export.dir").unwrap_or(".").to_string();
+        let path = PathBuf::from(dir).join(format!("life-rs-{}.png", timestamp));
+
+        match File::create(&path).and_then(|mut file| canvas.write_png(&mut file)) {
+            Ok(_) => self.show_toast(format!("Screenshot saved to {}", path.display())),
+            Err(err) => self.show_toast(format!("Unable to save screenshot: {}", err))
+        }
+    }
+
+    fn save_svg(&mut self) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+        let dir = self.config.borrow().get("export.dir").unwrap_or(".").to_string();
+        let path = PathBuf::from(dir).join(format!("life-rs-{}.svg", timestamp));
+
+        let mut writer = match File::create(&path) {
+            Ok(file) => BufWriter::new(file),
+            Err(err) => return self.show_toast(format!("Unable to save SVG: {}", err))
+        };
+
+        let written = self.stack.iter().filter_map(|window| window.render_to_svg(&mut writer)).next();
+
+        let written = match written {
+            Some(written) => written.and_then(|_| writer.flush()),
+            None => {
+                let _ = fs::remove_file(&path);
+                return self.show_toast("Nothing to save, the board is empty".to_string());
+            }
+        };
+
+        match written {
+            Ok(_) => self.show_toast(format!("SVG saved to {}", path.display())),
+            Err(err) => self.show_toast(format!("Unable to save SVG: {}", err))
+        }
+    }
+
+    /// Starts autosaving and offers to restore the autosave left
+    /// by a session which didn't exit cleanly
+    fn setup_autosave(&mut self) {
+        let (generations, seconds, journal, dir) = {
+            let config = self.config.borrow();
+
+            (config.get_parsed("autosave.generations").unwrap_or(DEFAULT_AUTOSAVE_GENERATIONS),
+             config.get_parsed("autosave.interval").unwrap_or(DEFAULT_AUTOSAVE_INTERVAL),
+             config.get_parsed("autosave.journal").unwrap_or(DEFAULT_AUTOSAVE_JOURNAL),
+             config.get("autosave.dir").map_or(env::temp_dir(), PathBuf::from))
+        };
+
+        if generations == 0 && seconds <= 0.0 {
+            return;
+        }
+
+        let mut autosave = Autosave::new(&dir, AUTOSAVE_SLOTS, generations, seconds);
+        autosave.set_compressed(self.compress_saves());
+        autosave.set_journal_limit(journal);
+
+        let latest = autosave.find_latest();
+        self.engine.borrow_mut().set_autosave(Some(autosave));
+
+        if let Some(path) = latest {
+            self.create_prompt_window(
+                "Previous session was interrupted, restore its autosave?",
+                move |engine, user_choice| {
+                    if user_choice == UserChoice::Ok {
+                        restore_autosave(&engine, &path);
+                    }
+                }
+            );
+        }
+    }
+
+    /// Writes population, births and deaths of every generation
+    /// since the pattern was loaded as CSV
+    fn save_census(&mut self) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+        let dir = self.config.borrow().get("export.dir").unwrap_or(".").to_string();
+        let path = PathBuf::from(dir).join(format!("life-rs-{}.csv", timestamp));
+
+        let saved = self.engine.borrow().get_census()
+            .map(|census| File::create(&path).and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                census.write_csv(&mut writer).and_then(|_| writer.flush())
+            }));
+
+        match saved {
+            Some(Ok(_)) => self.show_toast(format!("Population history saved to {}", path.display())),
+            Some(Err(err)) => self.show_toast(format!("Unable to save population history: {}", err)),
+            None => self.show_toast("Population is not recorded, see census.record in ~/.life-rs".to_string())
+        }
+    }
+
+    /// Lists all occurrences of the selected cells on the board
+    fn find_selected(&mut self) {
+        let pattern = match self.selection.get() {
+            Some(rect) => self.engine.borrow().get_board().copy_region(&rect),
+            None => Vec::new()
+        };
+
+        if pattern.is_empty() {
+            return self.show_toast("Select cells to search for".to_string());
+        }
+
+        // matches stay where they were found
+        self.cur_state.set(States::Paused);
+
+        let find = Box::new(FindWindow::new(self.get_window(),
+                                            self.get_resources(),
+                                            self.get_engine(),
+                                            &pattern,
+                                            self.get_selection(),
+                                            self.get_look_at()));
+        self.push_front(find);
+    }
+
+    /// Replaces all occurrences of the selected cells with the copied ones
+    fn replace_selected(&mut self) {
+        let (pattern, replacement) = match self.selection.get() {
+            Some(rect) => (self.engine.borrow().get_board().copy_region(&rect), self.clipboard.borrow().get_cells().to_vec()),
+            None => (Vec::new(), Vec::new())
+        };
+
+        if pattern.is_empty() || replacement.is_empty() {
+            return self.show_toast("Copy cells to replace with, then select cells to replace".to_string());
+        }
+
+        let matches = search::find(self.engine.borrow().get_board(), &pattern);
+        if matches.is_empty() {
+            return self.show_toast("No occurrences found".to_string());
+        }
+
+        self.cur_state.set(States::Paused);
+
+        let undo = self.undo.clone();
+        self.create_safety_prompt(
+            "replace",
+            &format!("Replace {} occurrences with the copied cells?", matches.len()),
+            move |engine, user_choice| {
+                if user_choice == UserChoice::Ok {
+                    let mut engine = engine.borrow_mut();
+                    let changed = search::replace(engine.get_board_mut(), &matches, &replacement);
+                    *undo.borrow_mut() = Some((engine.cur_iteration(), changed));
+                }
+            }
+        );
+    }
+
+    fn undo_replace(&mut self) {
+        let undo = self.undo.borrow_mut().take();
+
+        match undo {
+            Some((gen, ref cells)) if gen == self.engine.borrow().cur_iteration() => {
+                search::restore(self.engine.borrow_mut().get_board_mut(), cells);
+                self.show_toast(format!("Replacement undone, {} cells restored", cells.len()));
+            }
+            _ => self.show_toast("Nothing to undo".to_string())
+        }
+    }
+
+    pub fn start_demo(&mut self) {
+        let demo_window = Box::new(DemoWindow::new(self.get_resources(), self.get_engine(), self.get_look_at()));
+        self.push_front(demo_window);
+    }
+
+    /// Shows random soups until any input, which closes the program
+    pub fn start_screensaver(&mut self) {
+        // soups dying out are replaced by the screensaver itself
+        self.engine.borrow_mut().set_auto_stop(false);
+
+        let screensaver = Box::new(ScreensaverWindow::new(self.get_window(),
+                                                          self.get_engine(),
+                                                          self.get_rules(),
+                                                          self.get_look_at()));
+        self.push_front(screensaver);
+        self.set_hud_mode(HudMode::Hidden);
+    }
+
+    fn get_idle_timeout(&self) -> f64 {
+        self.config.borrow().get_parsed("demo.idle_timeout").unwrap_or(DEFAULT_DEMO_IDLE_TIMEOUT)
+    }
+
+    fn check_idle(&mut self, e: &Event) {
+        match e {
+            &Event::Input(_) => self.idle_time = 0.0,
+
+            &Event::Update(args) => {
+                let timeout = self.get_idle_timeout();
+
+                let was_idle = self.idle_time >= timeout;
+                self.idle_time += args.dt;
+
+                // started only once per idle period, the demo ends on user input
+                if timeout > 0.0 && !was_idle && self.idle_time >= timeout {
+                    self.start_demo();
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn check_notices(&mut self, e: &Event) {
+        if let &Event::Update(_) = e {
+            let notices: Vec<String> = self.stack.iter_mut().filter_map(|window| window.take_notice()).collect();
+
+            for notice in notices {
+                self.show_toast(notice);
+            }
+        }
+    }
+
+    fn check_cycles(&mut self, e: &Event) {
+        if let &Event::Update(_) = e {
+            let cycle = self.engine.borrow_mut().take_cycle();
+
+            // nobody is watching the demo or the screensaver to be told
+            let timeout = self.get_idle_timeout();
+            let is_demo = timeout > 0.0 && self.idle_time >= timeout;
+
+            if let Some(cycle) = cycle {
+                if !is_demo && !self.is_modal_open() {
+                    log::write(&cycle.to_string());
+                    self.create_info_window(&cycle.to_string());
+                }
+            }
+        }
+    }
+
+    fn check_stop(&mut self, e: &Event) {
+        if let &Event::Update(_) = e {
+            let stop = self.engine.borrow_mut().take_stop();
+
+            if let Some(stop) = stop {
+                self.cur_state.set(States::Paused);
+
+                let msg = format!("Paused, the {}", stop);
+                log::write(&msg);
+                if !self.is_modal_open() {
+                    self.create_info_window(&msg);
+                }
+            }
+        }
+    }
+
+    fn check_fetch(&mut self, e: &Event) {
+        if let &Event::Update(_) = e {
+            let fetched = match self.fetching {
+                Some(ref fetching) => match fetching.receiver.try_recv() {
+                    Ok(fetched) => fetched,
+                    Err(TryRecvError::Empty) if fetching.started.elapsed() < Duration::from_secs(FETCH_TIMEOUT) => return,
+                    Err(TryRecvError::Empty) => Err(io::Error::new(io::ErrorKind::TimedOut, "download timed out")),
+                    Err(TryRecvError::Disconnected) => Err(io::Error::new(io::ErrorKind::Other, "download failed"))
+                },
+                None => return
+            };
+
+            let source = self.fetching.take().map(|fetching| fetching.source).unwrap_or(String::new());
+
+            let loaded = fetched.map_err(EngineError::from).and_then(|(cells, rule, meta)| {
+                self.engine.borrow_mut().set_loaded_pattern(&cells, rule, meta)
+            });
+
+            // the simulation goes on as it was while the board waited for the pattern
+            let state = self.cur_state.get();
+            self.on_pattern_opened(&source, loaded);
+            self.cur_state.set(state);
+        }
+    }
+
+    fn check_board_error(&mut self, e: &Event) {
+        if let &Event::Update(_) = e {
+            let err = self.engine.borrow_mut().take_board_error();
+
+            if let Some(err) = err {
+                log::write(&err.to_string());
+                self.show_toast(format!("Paged board: {}", err));
+            }
+        }
+    }
+
+    fn check_autosave_error(&mut self, e: &Event) {
+        if let &Event::Update(_) = e {
+            let err = self.engine.borrow_mut().take_autosave_error();
+
+            if let Some(err) = err {
+                log::write(&format!("autosave failed: {}", err));
+                self.show_toast(format!("Autosave failed: {}", err));
+            }
+        }
+    }
+
+    fn check_throttling(&mut self, e: &Event) {
+        let throttle = match e {
+            // wake up immediately, before the input is handled
+            &Event::Input(_) => false,
+
+            &Event::Update(_) => {
+                self.cur_state.get() == States::Paused && self.idle_time >= IDLE_THROTTLE_DELAY &&
+                    !self.stack.iter().any(|window| window.is_animated())
+            }
+
+            _ => return
+        };
+
+        if throttle != self.throttled {
+            self.throttled = throttle;
+
+            let window = match self.window.get_window() {
+                Some(window) => window,
+                None => return
+            };
+            let mut window = window.borrow_mut();
+
+            if throttle {
+                window.set_max_fps(IDLE_FPS);
+                window.set_ups(IDLE_UPS);
+            } else {
+                window.set_max_fps(FPS);
+                window.set_ups(UPS);
+            }
+        }
+    }
+
+    fn handle_resize(&mut self, e: &Event) {
+        match e {
+            &Event::Input(Input::Resize(width, height)) => {
+                let (width, height) = (width as f64, height as f64);
+
+                if self.window.resize(width, height) {
+                    for window in self.stack.iter_mut() {
+                        window.on_resize(width, height);
+                    }
+                }
+            }
+
+            &Event::Render(ref args) => {
+                // drawable size differs from window size on high DPI screens
+                if args.width > 0 {
+                    self.window.set_dpi_factor(args.draw_width as f64 / args.width as f64);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
     fn manage_windows(&mut self, e: &Event) {
 
         let mut to_remove = Vec::new();
+        let mut to_reset = Vec::new();
+        let mut failures = Vec::new();
 
         // update all windows one by one in order
         for (idx, window) in self.stack.iter_mut().enumerate() {
 
-            let post_action = window.event_dispatcher(&e, &self.cur_state);
+            let cur_state = &self.cur_state;
+            let post_action = match boundary::catch(|| window.event_dispatcher(&e, cur_state)) {
+                Ok(post_action) => post_action,
+                Err(msg) => {
+                    // broken window is closed, others still get the event
+                    if window.is_board() {
+                        to_reset.push(idx);
+                        failures.push((msg, true));
+                        PostAction::Transfer
+                    } else {
+                        failures.push((msg, false));
+                        PostAction::Pop
+                    }
+                }
+            };
 
             match post_action {
 
@@ -107,11 +1114,60 @@ impl<'a> UI<'a> {
             }
         }
 
-        // remove windows that scheduled to be removed earlier
-        for window_idx in to_remove {
+        // the broken board is replaced while indices are still valid
+        for window_idx in to_reset {
+            self.stack[window_idx] = self.create_board_window();
+        }
+
+        // remove windows that scheduled to be removed earlier,
+        // the last ones first, so that indices stay valid
+        for window_idx in to_remove.into_iter().rev() {
             self.stack.remove(window_idx);
         }
 
+        for (msg, reset) in failures {
+            self.report_failure(&msg, reset);
+        }
+
+    }
+
+    /// Tells the user that a window has been closed, or the board has
+    /// been recreated, because of a bug, a bug report is written for it
+    fn report_failure(&mut self, msg: &str, reset: bool) {
+        log::write(&format!("window failed: {}", msg));
+
+        let report = match self.write_report(&format!("window failed: {}", msg)) {
+            Ok(path) => format!("bug report is saved to {}", path.display()),
+            Err(err) => format!("unable to save bug report: {}", err)
+        };
+
+        let action = if reset { "Board reset" } else { "Window closed" };
+        self.create_info_window(&format!("{} after an error: {}; {}", action, msg, report));
+    }
+
+    /// Saves the pattern, session, config and recent messages into
+    /// `report.dir` from the config or the temporary directory
+    pub fn write_report(&self, reason: &str) -> io::Result<PathBuf> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+
+        // the engine may be left borrowed by the failed window
+        let (pattern, session) = match self.engine.try_borrow() {
+            Ok(engine) => (engine.to_rle(), engine.to_container(true)),
+            Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "engine is in use"))
+        };
+
+        let config = self.config.borrow();
+        let dir = config.get("report.dir").map_or(env::temp_dir(), PathBuf::from);
+
+        let report = Report {
+            reason: reason.to_string(),
+            pattern: pattern,
+            session: session,
+            config: config.to_string(),
+            log: log::get_recent(),
+        };
+
+        report.write(&dir, timestamp)
     }
 
     pub fn event_dispatcher(&mut self) -> PostAction {
@@ -120,135 +1176,728 @@ impl<'a> UI<'a> {
 
         loop {
 
-            let event = { self.window.get_window().borrow_mut().next() };
+            let event = match self.window.get_window() {
+                Some(window) => window.borrow_mut().next(),
+                None => None
+            };
 
             match event {
 
                 Some(e) => {
+                    self.record(&e);
+                    self.handle_event(e, Some(&mut gl));
+                }
+
+                None => break
+            }
+        }
 
-                    match e {
+        self.save_layouts();
 
-                        // paint all the windows first
-                        Event::Render(args) => {
-                            gl.draw(args.viewport(), |c, g| self.paint_all(c, g));
-                        }
+        // autosaves are needed only after a crash
+        if let Some(autosave) = self.engine.borrow().get_autosave() {
+            autosave.clear();
+        }
 
-                        // process other events
-                        ref some_event => {
+        PostAction::Transfer
 
-                            match some_event {
+    }
 
-                                &Event::Input(Input::Press(Button::Keyboard(Key::C))) => {
+    /// Events of a recording handled one by one as if they came from the
+    /// window, render events paint nothing. Nothing is saved on the way
+    /// out, so replays leave the config as it was
+    pub fn replay<I: IntoIterator<Item=Event>>(&mut self, events: I) {
+        for e in events {
+            self.handle_event(e, None);
+        }
+    }
 
-                                    // clear board and reset counters
+    /// Writes every event coming from the window to the file, see
+    /// `recording`
+    pub fn set_recorder(&mut self, recorder: Option<Recorder>) {
+        self.recorder = recorder;
+    }
 
-                                    self.cur_state.set(States::Paused);
+    fn record(&mut self, e: &Event) {
+        let failed = match self.recorder {
+            Some(ref mut recorder) => recorder.record(e).err(),
+            None => None
+        };
 
-                                    self.create_prompt_window(
-                                        "Are you sure you want to clear the board?",
-                                        |engine, user_choice| {
-                                            if user_choice == UserChoice::Ok {
-                                                engine.borrow_mut().reset();
-                                            }
-                                        }
-                                    );
-                                }
+        // the session goes on without the recording
+        if let Some(err) = failed {
+            self.recorder = None;
+            self.show_toast(format!("Recording stopped: {}", err));
+        }
+    }
 
-                                &Event::Input(Input::Press(Button::Keyboard(Key::P))) => {
-                                    // pause/unpause
-                                    if self.cur_state.get() == States::Working {
-                                        self.cur_state.set(States::Paused);
-                                    } else {
-                                        self.cur_state.set(States::Working);
-                                    }
-                                }
+    fn handle_event(&mut self, e: Event, gl: Option<&mut GlGraphics>) {
 
-                                &Event::Input(Input::Press(Button::Keyboard(Key::S))) => {
-                                    // enter step by step mode
-                                    if self.cur_state.get() == States::Working || self.cur_state.get() == States::Paused {
-                                        self.cur_state.set(States::StepByStep);
-                                    }
+        // whatever the input does, it does to the latest generation
+        if let Event::Input(_) = e {
+            for window in self.stack.iter_mut() {
+                window.catch_up();
+            }
+        }
+
+        self.modifiers.handle(&e);
+        self.check_idle(&e);
+        self.check_throttling(&e);
+        self.check_config(&e);
+        // a still life stops the simulation rather than being announced
+        self.check_stop(&e);
+        self.check_board_error(&e);
+        self.check_autosave_error(&e);
+        self.check_fetch(&e);
+        self.check_cycles(&e);
+        self.check_notices(&e);
+        self.handle_resize(&e);
+
+        match e {
+
+            // paint all the windows first
+            Event::Render(args) => {
+                if let Some(gl) = gl {
+                    gl.draw(args.viewport(), |c, g| self.paint_all(c, g));
+                }
+            }
+
+            // process other events
+            ref some_event => {
+
+                match some_event {
+
+                    // modal windows take all the keys, e.g. for text input,
+                    // focused lists take the keys too
+                    _ if self.is_modal_open() || self.is_keyboard_taken() => {}
+
+                    // the board keeps its own copy of the cells for pasting
+                    &Event::Input(Input::Press(Button::Keyboard(Key::C))) if self.modifiers.ctrl() => {
+                        self.copy_as_rle();
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::C))) => {
+
+                        // clear board and reset counters
+
+                        self.cur_state.set(States::Paused);
+
+                        self.create_safety_prompt(
+                            "clear",
+                            "Are you sure you want to clear the board?",
+                            |engine, user_choice| {
+                                if user_choice == UserChoice::Ok {
+                                    engine.borrow_mut().reset();
                                 }
+                            }
+                        );
+                    }
 
-                                &Event::Input(Input::Press(Button::Keyboard(Key::R))) => {
-                                    if self.cur_state.get() == States::Paused {
-
-                                        let engine = self.get_engine();
-
-                                        if engine.borrow().get_board().is_infinite() {
-                                            self.create_info_window("Can't generate random \
-                                            configuration for infinite board");
-                                        } else {
-                                            self.create_prompt_window(
-                                                "Current position will be lost, ok?",
-                                                |engine, user_choice| {
-                                                    if user_choice == UserChoice::Ok {
-                                                        // generate random board
-                                                        let board = engine.borrow().create_random(0.3);
-                                                        engine.borrow_mut().set_board(board);
-                                                    }
-                                                }
-                                            );
-                                        }
+                    &Event::Input(Input::Press(Button::Keyboard(Key::X))) => {
+
+                        // crop board to selection
+
+                        if let Some(rect) = self.selection.get() {
 
+                            self.cur_state.set(States::Paused);
+
+                            self.create_safety_prompt(
+                                "crop",
+                                "Discard everything outside the selection?",
+                                move |engine, user_choice| {
+                                    if user_choice == UserChoice::Ok {
+                                        engine.borrow_mut().get_board_mut().crop(&rect);
                                     }
                                 }
+                            );
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::B))) if self.modifiers.ctrl() => {
+                        let written = self.write_report("requested by user");
+                        match written {
+                            Ok(path) => self.show_toast(format!("Bug report saved to {}", path.display())),
+                            Err(err) => self.show_toast(format!("Unable to save bug report: {}", err))
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::B))) => {
+
+                        // trim board to live cells and select them
 
-                                // do nothing if nothing matched
-                                _ => {}
+                        let engine = self.get_engine();
+                        let mut engine = engine.borrow_mut();
+
+                        engine.get_board_mut().trim();
+                        self.selection.set(engine.get_board().get_bounding_box());
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::I))) => {
+                        // full HUD, generation only, clean view
+                        let mode = self.hud_mode.next();
+                        self.set_hud_mode(mode);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::J))) => {
+                        let dialog = Box::new(JumpWindow::new(self.get_window(),
+                                                              self.get_resources(),
+                                                              self.get_engine()));
+                        self.push_front(dialog);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::P))) if self.modifiers.ctrl() => {
+                        let menu = Box::new(PresetWindow::new(self.get_window(),
+                                                              self.get_resources(),
+                                                              self.preset_request.clone()));
+                        self.push_front(menu);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::P))) => {
+                        // pause/unpause
+                        if self.cur_state.get() == States::Working {
+                            self.cur_state.set(States::Paused);
+                        } else {
+                            self.cur_state.set(States::Working);
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::S))) if self.modifiers.ctrl() => {
+                        if self.modifiers.shift() {
+                            self.show_file_dialog(FileDialogMode::Save);
+                        } else {
+                            self.save_session();
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::O))) if self.modifiers.ctrl() => {
+                        if self.modifiers.shift() {
+                            self.show_file_dialog(FileDialogMode::Open);
+                        } else {
+                            self.load_session();
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::F))) if self.modifiers.ctrl() => {
+                        self.find_selected();
+                    }
 
+                    &Event::Input(Input::Press(Button::Keyboard(Key::H))) if self.modifiers.ctrl() => {
+                        self.replace_selected();
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::Z))) if self.modifiers.ctrl() => {
+                        self.undo_replace();
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::F2))) => {
+                        let settings = Box::new(PromptSettingsWindow::new(self.get_window(),
+                                                                          self.get_resources(),
+                                                                          self.get_config()));
+                        self.push_front(settings);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::D))) if self.modifiers.ctrl() => {
+                        self.save_census();
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::F12)))
+                        if self.modifiers.ctrl() && self.modifiers.shift() => {
+                        self.save_svg();
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::F12))) if self.modifiers.ctrl() => {
+
+                        // run a copy of the board and save it as animation
+
+                        let export = Box::new(GifExportWindow::new(self.get_window(),
+                                                                   self.get_resources(),
+                                                                   self.get_engine(),
+                                                                   self.get_rules(),
+                                                                   self.get_config()));
+                        self.push_front(export);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::F12))) => {
+                        let whole_board = self.modifiers.shift();
+                        self.save_screenshot(whole_board);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::S))) => {
+                        // enter step by step mode
+                        if self.cur_state.get() == States::Working || self.cur_state.get() == States::Paused {
+                            self.cur_state.set(States::StepByStep);
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::E))) if self.modifiers.ctrl() => {
+
+                        // edit pattern name, author and comments and save it
+
+                        self.cur_state.set(States::Paused);
+
+                        let editor = Box::new(MetaEditorWindow::new(self.get_window(),
+                                                                    self.get_resources(),
+                                                                    self.get_engine(),
+                                                                    self.get_config(),
+                                                                    self.selection.get()));
+                        self.push_front(editor);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::R)))
+                        if self.modifiers.ctrl() && self.modifiers.shift() => {
+
+                        // run the pattern under rules near the current one
+
+                        let explorer = Box::new(RuleExplorerWindow::new(self.get_window(),
+                                                                        self.get_resources(),
+                                                                        self.get_engine()));
+                        self.push_front(explorer);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::U)))
+                        if self.modifiers.ctrl() && self.modifiers.shift() => {
+                        self.engine.borrow_mut().clear_zones();
+                        self.show_toast("Rule zones removed".to_string());
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::U))) if self.modifiers.ctrl() => {
+
+                        // give the selected region a rule of its own
+
+                        match self.selection.get() {
+                            Some(rect) => {
+                                let picker = Box::new(RulePickerWindow::for_zone(self.get_window(),
+                                                                                 self.get_resources(),
+                                                                                 self.get_engine(),
+                                                                                 self.get_rules(),
+                                                                                 rect));
+                                self.push_front(picker);
                             }
+                            None => self.create_info_window("Select the region of the zone first")
+                        }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::R))) if self.modifiers.ctrl() => {
+
+                        // choose rule
+
+                        let picker = Box::new(RulePickerWindow::new(self.get_window(),
+                                                                    self.get_resources(),
+                                                                    self.get_engine(),
+                                                                    self.get_rules()));
+                        self.push_front(picker);
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::L))) if self.modifiers.ctrl() => {
+
+                        // the library opens itself focused, choose built-in
+                        // pattern to place on the board there
 
+                        for window in self.stack.iter_mut() {
+                            window.set_focused(false);
                         }
+                    }
+
+                    &Event::Input(Input::Press(Button::Keyboard(Key::R))) => {
+                        if self.cur_state.get() == States::Paused {
+
+                            // fill visible part of the board, or the whole
+                            // board if it hasn't been painted yet
+                            let bounds = self.engine.borrow().get_board().get_bounds();
+                            let rect = self.viewport.get().unwrap_or(bounds);
+
+                            if rect.intersect(&bounds).is_none() {
+                                self.create_info_window("The board is out of view, \
+                                nothing to fill");
+                            } else {
+                                self.create_safety_prompt(
+                                    "fill",
+                                    "Current position will be lost, ok?",
+                                    move |engine, user_choice| {
+                                        if user_choice == UserChoice::Ok {
+                                            // generate random board
+                                            let board = engine.borrow().create_random(0.3, &rect);
+                                            engine.borrow_mut().set_board(board);
+                                        }
+                                    }
+                                );
+                            }
 
+                        }
                     }
 
-                    self.manage_windows(&e);
+                    // do nothing if nothing matched
+                    _ => {}
 
                 }
 
-                None => break
             }
+
         }
 
-        PostAction::Transfer
+        if !self.is_modal_open() {
+            self.focus.event_dispatcher(&e, &self.modifiers, &mut self.stack);
+        }
+        self.manage_windows(&e);
+
+        let opened = self.open_request.borrow_mut().take();
+        if let Some(path) = opened {
+            self.open_pattern(&path);
+        }
+
+        if let Some(idx) = self.preset_request.get() {
+            self.preset_request.set(None);
+            self.load_preset(&presets::PRESETS[idx]);
+        }
 
     }
 
     pub fn paint_all(&mut self, c: Context, g: &mut GlGraphics) {
 
         // clear background
-        clear([0.0, 0.0, 0.0, 1.0], g);
+        let palette = self.rules.borrow().get_palette(&self.engine.borrow().get_rule());
+        clear(palette.background, g);
 
         // and paint all windows one by one in order
-        for window in &mut self.stack.iter_mut().rev() {
-            window.paint(c, g)
+        let mut failures = Vec::new();
+
+        for (idx, window) in self.stack.iter_mut().enumerate().rev() {
+            if let Err(msg) = boundary::catch(|| window.paint(c, g)) {
+                failures.push((idx, msg, window.is_board()));
+            }
+        }
+
+        // painted from the last one, so indices go down
+        for (idx, msg, reset) in failures {
+            if reset {
+                self.stack[idx] = self.create_board_window();
+            } else {
+                self.stack.remove(idx);
+            }
+            self.report_failure(&msg, reset);
         }
 
     }
 
 }
 
+fn restore_autosave(engine: &RefCell<Engine>, path: &Path) {
+    let restored = Autosave::restore(path).map_err(EngineError::from)
+        .and_then(|container| engine.borrow_mut().load_container(&container));
+
+    if let Err(err) = restored {
+        log::write(&format!("unable to restore autosave {}: {}", path.display(), err));
+    }
+}
+
 pub fn new<'a>(window: Rc<GraphicsWindow>, engine: Rc<RefCell<Engine<'a>>>,
-               resources: Rc<RefCell<Resources>>) -> UI<'a> {
+               resources: Rc<RefCell<Resources>>, config: Rc<RefCell<Config>>) -> UI<'a> {
 
     let mut ui = UI {
                       cur_state: Cell::new(States::Paused),
+                      selection: Rc::new(Cell::new(None)),
+                      viewport: Rc::new(Cell::new(None)),
+                      look_at: Rc::new(Cell::new(None)),
+                      gpu_progress: Rc::new(Cell::new(None)),
+                      clipboard: Rc::new(RefCell::new(Pattern::default())),
+                      undo: Rc::new(RefCell::new(None)),
+                      open_request: Rc::new(RefCell::new(None)),
+                      preset_request: Rc::new(Cell::new(None)),
 
                       stack: Vec::new(),
+                      focus: FocusManager::new(),
+                      modifiers: Modifiers::new(),
+                      idle_time: 0.0,
+                      config_check_time: 0.0,
+                      throttled: false,
+                      hud_mode: HudMode::Full,
+                      recorder: None,
+                      fetching: None,
                       window: window,
                       engine: engine,
                       resources: resources,
+                      config: config,
+                      rules: Rc::new(RefCell::new(RuleRegistry::new())),
                     };
 
-    let board_window = Box::new(GameBoard::new(ui.get_window(),
-                                               ui.get_engine()));
+    for err in ui.load_rules() {
+        log::write(&format!("config error: {}", err));
+    }
+
+    let board_window = ui.create_board_window();
 
     let hud_window = Box::new(HUDWindow::new(ui.get_resources(),
-                                             ui.get_engine()));
+                                             ui.get_engine(),
+                                             ui.get_selection(),
+                                             ui.gpu_progress.clone()));
+
+    let stats_window = Box::new(StatsWindow::new(ui.get_window(),
+                                                 ui.get_resources(),
+                                                 ui.get_engine()));
+
+    let soup_window = Box::new(SoupWindow::new(ui.get_resources(),
+                                               ui.get_engine(),
+                                               ui.get_config()));
+
+    let browser_window = Box::new(PatternBrowserWindow::new(ui.get_window(),
+                                                            ui.get_resources(),
+                                                            ui.get_engine(),
+                                                            ui.get_rules(),
+                                                            ui.get_viewport(),
+                                                            ui.get_config()));
 
     ui.push(board_window);
     ui.push(hud_window);
+    ui.push(soup_window);
+
+    // auxiliary windows go on top of the board to intercept mouse events first
+    ui.push_front(stats_window);
+    ui.push_front(browser_window);
+
+    ui.restore_layouts();
+
+    // config is saved on exit, so the tutorial is shown only once
+    if !ui.config.borrow().existed() {
+        let onboarding_window = Box::new(OnboardingWindow::new(ui.get_window(),
+                                                               ui.get_resources(),
+                                                               ui.get_engine()));
+        ui.push_front(onboarding_window);
+    }
+
+    ui.setup_autosave();
+
+    if let Some(policy) = ui.config.borrow().get_parsed::<EdgePolicy>("edit.edges") {
+        ui.engine.borrow_mut().set_edge_policy(policy);
+    }
+
+    // population of every generation is kept for export unless disabled
+    if ui.config.borrow().get_parsed("census.record").unwrap_or(1) != 0 {
+        ui.engine.borrow_mut().set_census(Some(Census::new()));
+    }
+
+    // patterns settling into still lifes, oscillators or spaceships are
+    // announced unless disabled
+    if ui.config.borrow().get_parsed("cycles.detect").unwrap_or(1) != 0 {
+        ui.engine.borrow_mut().set_cycles(Some(CycleDetector::new()));
+    }
+
+    // the simulation runs on after the pattern dies out or settles
+    // unless asked otherwise
+    if ui.config.borrow().get_parsed("pause.auto").unwrap_or(0) != 0 {
+        ui.engine.borrow_mut().set_auto_stop(true);
+    }
 
     ui
 }
+
+
+#[cfg(test)]
+use piston_window::UpdateArgs;
+
+// offscreen UI over a bounded board, with a config of its own
+#[cfg(test)]
+fn test_ui() -> UI<'static> {
+    let font = GlyphCache::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/Roboto-Regular.ttf")).unwrap();
+    new(Rc::new(GraphicsWindow::headless(640.0, 480.0)),
+        Rc::new(RefCell::new(Engine::new(Some(50), Some(50)))),
+        Rc::new(RefCell::new(Resources { font: font })),
+        Rc::new(RefCell::new(Config::isolated())))
+}
+
+#[test]
+fn test_replay_prompts() {
+    let mut ui = test_ui();
+    // a still life would be announced over the board
+    ui.get_engine().borrow_mut().set_cycles(None);
+    ui.get_engine().borrow_mut().set_pattern(&[Coord { col: 1, row: 1 }]);
+
+    // nothing over the board swallows the first keys, nothing is autosaved
+    assert!(!ui.is_modal_open());
+    assert!(ui.get_engine().borrow().get_autosave().is_none());
+
+    let press = |key| Event::Input(Input::Press(Button::Keyboard(key)));
+    let windows = ui.get_window_count();
+
+    // clearing asks first, keys go to the prompt until it's answered
+    ui.replay(vec![press(Key::C), Event::Update(UpdateArgs { dt: 0.1 }), press(Key::X)]);
+    assert!(ui.is_modal_open());
+    assert_eq!(ui.get_window_count(), windows + 1);
+
+    ui.replay(vec![press(Key::N)]);
+    assert!(!ui.is_modal_open());
+    assert_eq!(ui.get_engine().borrow().get_board().get_population(), 1);
+
+    ui.replay(vec![press(Key::C), press(Key::Y)]);
+    assert_eq!(ui.get_window_count(), windows);
+    assert_eq!(ui.get_engine().borrow().get_board().get_population(), 0);
+}
+
+#[test]
+fn test_demo_exit_key() {
+    let mut ui = test_ui();
+    ui.get_engine().borrow_mut().set_pattern(&[Coord { col: 1, row: 1 }]);
+    let windows = ui.get_window_count();
+
+    let update = || Event::Update(UpdateArgs { dt: 0.1 });
+    ui.start_demo();
+    ui.replay(vec![update(), update()]);
+    assert!(ui.get_engine().borrow().get_board().get_population() > 1);
+
+    // C ends the demo without asking to clear the board just restored
+    ui.replay(vec![Event::Input(Input::Press(Button::Keyboard(Key::C))), update()]);
+    assert_eq!(ui.get_window_count(), windows);
+    assert!(!ui.is_modal_open());
+    assert_eq!(ui.get_engine().borrow().get_board().get_population(), 1);
+}
+
+#[test]
+fn test_corrupted_session() {
+    let dir = env::temp_dir().join("life-rs-test-corrupted-session");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut ui = test_ui();
+    {
+        let config = ui.get_config();
+        let mut config = config.borrow_mut();
+        config.set("autosave.generations", 1);
+        config.set("autosave.dir", dir.display());
+        config.set("session.path", dir.join("session").display());
+    }
+    ui.setup_autosave();
+
+    ui.get_engine().borrow_mut().set_pattern(&[Coord { col: 1, row: 1 }]);
+    {
+        let engine = ui.get_engine();
+        let engine = engine.borrow();
+        Autosave::new(&dir, AUTOSAVE_SLOTS, 1, 0.0).save(&mut engine.to_container(false), engine.get_board(), 0, 0.0).unwrap();
+    }
+    ui.get_engine().borrow_mut().set_pattern(&[Coord { col: 1, row: 1 }, Coord { col: 2, row: 1 }]);
+
+    // the damaged session is not loaded, the autosave is offered instead
+    fs::File::create(dir.join("session")).unwrap()
+        .write_all(b"life-rs 1.3\nchecksum = 0000000000000000\n\n[cells]\no!\n").unwrap();
+    ui.load_session();
+    assert!(ui.is_modal_open());
+    assert_eq!(ui.get_engine().borrow().get_board().get_population(), 2);
+
+    ui.replay(vec![Event::Input(Input::Press(Button::Keyboard(Key::Y)))]);
+    assert!(!ui.is_modal_open());
+    assert_eq!(ui.get_engine().borrow().get_board().get_population(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fetch_in_background() {
+    let mut ui = test_ui();
+    ui.set_paused(false);
+
+    // the block is placed on one of the next updates, the simulation goes on
+    ui.fetch_pattern("xs4_33");
+    for _ in 0..100 {
+        ui.replay(vec![Event::Update(UpdateArgs { dt: 0.1 })]);
+        if ui.fetching.is_none() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(ui.get_engine().borrow().get_board().get_population(), 4);
+    assert!(ui.cur_state.get() == States::Working);
+}
+
+#[test]
+fn test_tiny_layout() {
+    let layout = |name: &'static str, value: &str| -> Option<WindowLayout> {
+        let mut ui = test_ui();
+        ui.get_config().borrow_mut().set(&format!("window.{}", name), value);
+        ui.restore_layouts();
+
+        let layout = ui.stack.iter().find(|window| window.get_name() == Some(name))
+            .and_then(|window| window.get_layout());
+        layout
+    };
+
+    // a tiny window grows to the smallest size its title bar and handle fit into
+    for &name in &["stats", "library"] {
+        assert_eq!(layout(name, "10,10,0.5,0.5,1"), Some(WindowLayout::new(10.0, 10.0, 100.0, 50.0, true)));
+
+        // sizes which aren't numbers are ignored
+        assert_eq!(layout(name, "10,10,inf,NaN,1"), layout(name, ""));
+    }
+}
+
+#[test]
+fn test_library_window() {
+    let mut ui = test_ui();
+    ui.get_engine().borrow_mut().set_pattern(&[Coord { col: 1, row: 1 }]);
+
+    let press = |key| Event::Input(Input::Press(Button::Keyboard(key)));
+    let release = |key| Event::Input(Input::Release(Button::Keyboard(key)));
+    let library = |ui: &UI| ui.stack.iter().find(|window| window.get_name() == Some("library"))
+        .and_then(|window| window.get_layout()).unwrap();
+
+    // the library opens focused without blocking the board
+    ui.replay(vec![press(Key::LCtrl), press(Key::L), release(Key::L), release(Key::LCtrl)]);
+    assert!(library(&ui).open);
+    assert!(!ui.is_modal_open());
+    assert!(ui.is_keyboard_taken());
+
+    // keys go to the list, C doesn't ask to clear the board
+    ui.replay(vec![press(Key::C), press(Key::Down)]);
+    assert!(!ui.is_modal_open());
+
+    // closing it from the keyboard only hides it, the placement is saved
+    ui.replay(vec![press(Key::LCtrl), press(Key::W), release(Key::W), release(Key::LCtrl)]);
+    assert!(!library(&ui).open);
+    assert!(!ui.is_keyboard_taken());
+    ui.save_layouts();
+    assert_eq!(WindowLayout::load(&ui.get_config().borrow(), "library"), Some(library(&ui)));
+
+    // reopened from the config, but the keys stay with the board
+    ui.get_config().borrow_mut().set("window.library", "20,30,200,150,1");
+    ui.restore_layouts();
+    assert_eq!(library(&ui), WindowLayout::new(20.0, 30.0, 200.0, 150.0, true));
+    assert!(!ui.is_keyboard_taken());
+}
+
+#[cfg(test)]
+struct BrokenWindow {
+    board: bool
+}
+
+#[cfg(test)]
+impl WindowBase for BrokenWindow {
+    fn paint(&mut self, _c: Context, _g: &mut GlGraphics) {}
+
+    fn event_dispatcher(&mut self, _event: &Event, _cur_state: &Cell<States>) -> PostAction {
+        panic!("broken");
+    }
+
+    fn is_board(&self) -> bool {
+        self.board
+    }
+}
+
+#[test]
+fn test_broken_board_reset() {
+    let mut ui = test_ui();
+    let windows = ui.get_window_count();
+    let update = || Event::Update(UpdateArgs { dt: 0.1 });
+
+    // a broken window is closed, the message about it is shown instead
+    ui.push_front(Box::new(BrokenWindow { board: false }));
+    ui.replay(vec![update()]);
+    assert_eq!(ui.get_window_count(), windows + 1);
+    assert!(!ui.stack[0].is_board());
+    ui.stack.remove(0);
+
+    // a broken board is recreated in its place
+    ui.push_front(Box::new(BrokenWindow { board: true }));
+    ui.replay(vec![update()]);
+    assert_eq!(ui.get_window_count(), windows + 2);
+    assert!(ui.stack[1].is_board());
+    ui.replay(vec![update()]);
+    assert_eq!(ui.get_window_count(), windows + 2);
+}
@@ -0,0 +1,340 @@
+// Classic patterns built into the program, so they are available
+// without any files around, and patterns from the directories listed
+// in `library.dirs` of the config, zip archives in them included. Tags, favorites and the time a
+// pattern was last used are stored as `library.<id>.*` in config
+extern crate engine;
+
+use self::engine::io::rle;
+use self::engine::io::rle::RleFile;
+use self::engine::io::read_pattern;
+use self::engine::io::archive;
+use self::engine::io::archive::Archive;
+
+use super::config::Config;
+use super::files;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// directories nested deeper are not indexed, this also stops link loops
+const MAX_DEPTH: usize = 8;
+
+
+const PATTERNS: [&'static str; 8] = [
+    include_str!("../../assets/patterns/glider.rle"),
+    include_str!("../../assets/patterns/lwss.rle"),
+    include_str!("../../assets/patterns/gosper-gun.rle"),
+    include_str!("../../assets/patterns/pulsar.rle"),
+    include_str!("../../assets/patterns/pentadecathlon.rle"),
+    include_str!("../../assets/patterns/r-pentomino.rle"),
+    include_str!("../../assets/patterns/acorn.rle"),
+    include_str!("../../assets/patterns/diehard.rle"),
+];
+
+
+pub struct Item {
+    pub name: String,
+    // file the pattern comes from, built-in ones have none, entries
+    // of archives are read from memory, see `archive`
+    pub path: Option<PathBuf>,
+    // files are read when the pattern is first shown or placed
+    pattern: Option<Result<RleFile, String>>,
+}
+
+impl Item {
+
+    /// Config key part for the pattern, its name in lowercase with dashes
+    pub fn get_id(&self) -> String {
+        self.name.to_lowercase()
+            .split(|ch: char| !ch.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// The pattern if it has been read already
+    pub fn get_loaded(&self) -> Option<&RleFile> {
+        match self.pattern {
+            Some(Ok(ref pattern)) => Some(pattern),
+            _ => None
+        }
+    }
+
+    /// Cells start at (0, 0), whatever the file has
+    pub fn get_pattern(&mut self) -> Result<&RleFile, String> {
+        if self.pattern.is_none() {
+            let path = self.path.clone().unwrap_or(PathBuf::new());
+            self.pattern = Some(read_item(&path, &self.name));
+        }
+
+        match self.pattern {
+            Some(Ok(ref pattern)) => Ok(pattern),
+            Some(Err(ref err)) => Err(err.clone()),
+            None => unreachable!()
+        }
+    }
+
+}
+
+fn read_item(path: &Path, name: &str) -> Result<RleFile, String> {
+    let (mut cells, rule, meta) = read_pattern(path).map_err(|err| format!("unable to read {}: {}", name, err))?;
+
+    let left = cells.iter().map(|c| c.col).min().unwrap_or(0);
+    let top = cells.iter().map(|c| c.row).min().unwrap_or(0);
+    for cell in cells.iter_mut() {
+        cell.col -= left;
+        cell.row -= top;
+    }
+
+    Ok(RleFile { name: meta.name, author: meta.author, comments: meta.comments, rule: rule, cells: cells })
+}
+
+pub fn load() -> Vec<Item> {
+    PATTERNS.iter()
+        .map(|data| {
+            let pattern = rle::read(&mut data.as_bytes()).expect("malformed built-in pattern");
+            Item {
+                name: pattern.name.clone().unwrap_or(String::new()),
+                path: None,
+                pattern: Some(Ok(pattern)),
+            }
+        })
+        .collect()
+}
+
+/// Directories of `library.dirs`, separated like in `PATH`
+pub fn get_dirs(config: &Config) -> Vec<PathBuf> {
+    config.get("library.dirs").map_or(Vec::new(), |dirs| env::split_paths(dirs).collect())
+}
+
+/// Pattern files in the directory and all the directories inside it,
+/// named by their path in it without the extension, e.g. `guns/gosper`,
+/// patterns in archives are named as if the archive was a directory
+pub fn index_dir(dir: &Path) -> Vec<Item> {
+    let mut res = Vec::new();
+    index_dir_into(dir, "", 0, &mut res);
+    res
+}
+
+fn index_dir_into(dir: &Path, prefix: &str, depth: usize, res: &mut Vec<Item>) {
+    // unreadable directories are skipped, like hidden files
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => return
+    };
+    entries.sort();
+
+    for path in entries {
+        let file_name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                index_dir_into(&path, &format!("{}{}/", prefix, file_name), depth + 1, res);
+            }
+        } else if archive::is_archive(&path) {
+            index_archive_into(&path, &format!("{}{}/", prefix, get_stem(&file_name)), res);
+        } else if files::is_pattern_file(&path) && !files::is_rule_file(&path) {
+            res.push(Item { name: format!("{}{}", prefix, get_stem(&file_name)), path: Some(path.clone()), pattern: None });
+        }
+    }
+}
+
+fn index_archive_into(path: &Path, prefix: &str, res: &mut Vec<Item>) {
+    // broken archives are skipped, like unreadable directories
+    let entries = match Archive::open(path) {
+        Ok(mut archive) => archive.get_entries(),
+        Err(_) => return
+    };
+
+    for entry in entries {
+        // Oscillators/pulsar.rle is shown as Oscillators/pulsar
+        let (dir, file_name) = match entry.rfind('/') {
+            Some(pos) => entry.split_at(pos + 1),
+            None => ("", &entry[..])
+        };
+        res.push(Item { name: format!("{}{}{}", prefix, dir, get_stem(file_name)), path: Some(path.join(&entry)), pattern: None });
+    }
+}
+
+// soup.rle.gz is shown as soup
+fn get_stem(file_name: &str) -> &str {
+    file_name.split('.').next().unwrap_or("")
+}
+
+/// Built-in patterns followed by those of the configured directories
+pub fn load_all(config: &Config) -> Vec<Item> {
+    let mut res = load();
+    for dir in get_dirs(config) {
+        res.extend(index_dir(&dir));
+    }
+    res
+}
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Filter {
+    All,
+    Favorites,
+    Tag(String),
+}
+
+fn get_key(id: &str, field: &str) -> String {
+    format!("library.{}.{}", id, field)
+}
+
+pub fn get_tags(config: &Config, id: &str) -> Vec<String> {
+    config.get(&get_key(id, "tags")).map_or(Vec::new(), parse_tags)
+}
+
+pub fn set_tags(config: &mut Config, id: &str, tags: &[String]) {
+    config.set(&get_key(id, "tags"), tags.join(", "));
+}
+
+/// Tags are separated by commas, case doesn't matter
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+
+    for tag in tags.split(',').map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty()) {
+        if !res.contains(&tag) {
+            res.push(tag);
+        }
+    }
+    res
+}
+
+pub fn is_favorite(config: &Config, id: &str) -> bool {
+    config.get_parsed::<u32>(&get_key(id, "favorite")).unwrap_or(0) != 0
+}
+
+pub fn set_favorite(config: &mut Config, id: &str, favorite: bool) {
+    config.set(&get_key(id, "favorite"), if favorite { 1 } else { 0 });
+}
+
+/// Unix time the pattern was last placed on the board, 0 if never
+pub fn get_last_used(config: &Config, id: &str) -> u64 {
+    config.get_parsed(&get_key(id, "used")).unwrap_or(0)
+}
+
+pub fn set_last_used(config: &mut Config, id: &str, timestamp: u64) {
+    config.set(&get_key(id, "used"), timestamp);
+}
+
+/// All tags of the given patterns, sorted
+pub fn get_all_tags(items: &[Item], config: &Config) -> Vec<String> {
+    let mut res: Vec<String> = items.iter().flat_map(|item| get_tags(config, &item.get_id())).collect();
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// Indices of patterns passing the filter, the most recently used
+/// first, patterns never used keep the library order
+pub fn select(items: &[Item], config: &Config, filter: &Filter) -> Vec<usize> {
+    let mut res: Vec<(u64, usize)> = items.iter()
+        .enumerate()
+        .filter(|&(_, item)| {
+            let id = item.get_id();
+            match *filter {
+                Filter::All => true,
+                Filter::Favorites => is_favorite(config, &id),
+                Filter::Tag(ref tag) => get_tags(config, &id).contains(tag),
+            }
+        })
+        .map(|(idx, item)| (get_last_used(config, &item.get_id()), idx))
+        .collect();
+
+    res.sort_by(|a, b| (b.0, a.1).cmp(&(a.0, b.1)));
+    res.into_iter().map(|(_, idx)| idx).collect()
+}
+
+
+#[test]
+fn test_library() {
+    let items = load();
+    let patterns: Vec<&RleFile> = items.iter().filter_map(|item| item.get_loaded()).collect();
+
+    assert_eq!(patterns.len(), PATTERNS.len());
+    assert!(patterns.iter().all(|pattern| pattern.name.is_some() && !pattern.cells.is_empty()));
+
+    assert_eq!(items[0].name, "Glider");
+    assert_eq!(patterns[0].cells.len(), 5);
+    assert_eq!(patterns[2].cells.len(), 36);
+    assert_eq!(patterns[3].cells.len(), 48);
+}
+
+#[cfg(test)]
+use std::fs::File;
+#[cfg(test)]
+use std::io::Write;
+
+#[test]
+fn test_library_dirs() {
+    let dir = env::temp_dir().join("life-rs-library-test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("guns/.cache")).unwrap();
+
+    let write = |name: &str, contents: &str| File::create(dir.join(name)).unwrap().write_all(contents.as_bytes()).unwrap();
+    write("guns/gosper.rle", "#N Gosper gun\nx = 3, y = 1\n3o!\n");
+    write("guns/.cache/hidden.rle", "3o!\n");
+    write("blinker.cells", "!Name: Blinker\n.O\n.O\n.O\n");
+    write("broken.lif", "#Life 1.06\nnot a cell\n");
+    write("Wire.rule", "@RULE Wire\n");
+    write("notes.txt", "3o!\n");
+    // not a zip archive inside, nothing is indexed
+    write("broken.zip", "3o!\n");
+
+    let config = Config::parse(&format!("library.dirs = {}", env::join_paths(&[&dir]).unwrap().to_string_lossy()));
+    let mut items = load_all(&config);
+    assert_eq!(items.len(), PATTERNS.len() + 3);
+
+    // sorted by path, read only when needed
+    let items = &mut items[PATTERNS.len()..];
+    let names: Vec<&str> = items.iter().map(|item| &item.name[..]).collect();
+    assert_eq!(names, vec!["blinker", "broken", "guns/gosper"]);
+    assert_eq!(items[2].get_id(), "guns-gosper");
+    assert!(items.iter().all(|item| item.get_loaded().is_none()));
+
+    assert_eq!(items[2].get_pattern().unwrap().name, Some("Gosper gun".to_string()));
+    assert_eq!(items[0].get_pattern().unwrap().cells.len(), 3);
+    assert!(items[1].get_pattern().is_err());
+    assert!(items[0].get_loaded().is_some());
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    // missing directories have no patterns
+    assert!(index_dir(&dir).is_empty());
+}
+
+#[test]
+fn test_library_tags() {
+    let items = load();
+    let mut config = Config::parse("library.glider.tags = Spaceship, small\n\
+                                    library.lightweight-spaceship.tags = spaceship\n\
+                                    library.pulsar.favorite = 1");
+
+    assert_eq!(items[0].get_id(), "glider");
+    assert_eq!(items[2].get_id(), "gosper-glider-gun");
+
+    assert_eq!(get_tags(&config, "glider"), vec!["spaceship", "small"]);
+    assert_eq!(get_all_tags(&items, &config), vec!["small", "spaceship"]);
+    assert_eq!(parse_tags(" a,, B ,a"), vec!["a", "b"]);
+
+    assert_eq!(select(&items, &config, &Filter::Tag("spaceship".to_string())), vec![0, 1]);
+    assert_eq!(select(&items, &config, &Filter::Favorites), vec![3]);
+
+    set_favorite(&mut config, "pulsar", false);
+    set_favorite(&mut config, "glider", true);
+    assert_eq!(select(&items, &config, &Filter::Favorites), vec![0]);
+
+    // recently used go first
+    set_last_used(&mut config, "acorn", 100);
+    set_last_used(&mut config, "lightweight-spaceship", 200);
+    assert_eq!(select(&items, &config, &Filter::All), vec![1, 6, 0, 2, 3, 4, 5, 7]);
+
+    set_tags(&mut config, "acorn", &parse_tags("methuselah"));
+    assert_eq!(get_tags(&config, "acorn"), vec!["methuselah"]);
+}
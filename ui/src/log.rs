@@ -0,0 +1,44 @@
+// Messages printed to the console, the most recent of them are also
+// kept in memory to be put into bug reports
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_LINES: usize = 200;
+
+thread_local! {
+    static RECENT: RefCell<VecDeque<String>> = RefCell::new(VecDeque::new());
+}
+
+
+pub fn write(msg: &str) {
+    println!("{}", msg);
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+
+    RECENT.with(|recent| {
+        let mut recent = recent.borrow_mut();
+        if recent.len() == MAX_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(format!("{} {}", timestamp, msg));
+    });
+}
+
+/// Oldest messages first
+pub fn get_recent() -> Vec<String> {
+    RECENT.with(|recent| recent.borrow().iter().cloned().collect())
+}
+
+
+#[test]
+fn test_log_recent() {
+    for i in 0..MAX_LINES + 5 {
+        write(&format!("message {}", i));
+    }
+
+    let recent = get_recent();
+    assert_eq!(recent.len(), MAX_LINES);
+    assert!(recent[0].ends_with(" message 5"));
+    assert!(recent[MAX_LINES - 1].ends_with(&format!(" message {}", MAX_LINES + 4)));
+}
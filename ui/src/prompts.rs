@@ -0,0 +1,45 @@
+// Confirmations of destructive actions, each of them can be turned off
+// with "don't ask again", which is stored as `confirm.<id> = 0` in config
+use super::config::Config;
+
+
+pub struct PromptInfo {
+    pub id: &'static str,
+    pub description: &'static str,
+}
+
+pub const PROMPTS: [PromptInfo; 4] = [
+    PromptInfo { id: "clear", description: "Clearing the board" },
+    PromptInfo { id: "crop", description: "Cropping the board to selection" },
+    PromptInfo { id: "fill", description: "Filling the board with random cells" },
+    PromptInfo { id: "replace", description: "Replacing found patterns" },
+];
+
+
+pub fn get_key(id: &str) -> String {
+    format!("confirm.{}", id)
+}
+
+pub fn is_enabled(config: &Config, id: &str) -> bool {
+    config.get_parsed::<u32>(&get_key(id)).unwrap_or(1) != 0
+}
+
+pub fn set_enabled(config: &mut Config, id: &str, enabled: bool) {
+    config.set(&get_key(id), if enabled { 1 } else { 0 });
+}
+
+
+#[test]
+fn test_prompts_enabled() {
+    let mut config = Config::parse("confirm.crop = 0\nconfirm.fill = nope");
+
+    // missing and malformed values keep asking
+    assert!(is_enabled(&config, "clear"));
+    assert!(!is_enabled(&config, "crop"));
+    assert!(is_enabled(&config, "fill"));
+
+    set_enabled(&mut config, "clear", false);
+    set_enabled(&mut config, "crop", true);
+    assert!(!is_enabled(&config, "clear"));
+    assert!(is_enabled(&config, "crop"));
+}
@@ -0,0 +1,204 @@
+// Raw events of a session written to a text file, one event per line,
+// and read back to be replayed without a window, see `UI::replay`.
+// Lines are like `press key 97` or `move 10.5 20`; idle and after
+// render events, as well as input of game controllers, are left out
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use piston_window::{Event, Input, Button, Key, MouseButton, Motion, RenderArgs, UpdateArgs};
+
+
+pub struct Recorder {
+    file: BufWriter<File>,
+}
+
+impl Recorder {
+
+    pub fn new(path: &Path) -> io::Result<Self> {
+        Ok(Recorder { file: BufWriter::new(File::create(path)?) })
+    }
+
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        if let Some(line) = encode(event) {
+            writeln!(self.file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+}
+
+fn encode_button(button: &Button) -> Option<String> {
+    match *button {
+        Button::Keyboard(key) => Some(format!("key {}", key as u32)),
+        Button::Mouse(button) => Some(format!("mouse {}", button as u32)),
+        #[allow(unreachable_patterns)]
+        _ => None
+    }
+}
+
+// text may contain anything, but has to stay on its line
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::new();
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            res.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some(other) => res.push(other),
+            None => res.push('\\')
+        }
+    }
+
+    res
+}
+
+/// Line of the recording for the event, None if it isn't recorded
+pub fn encode(event: &Event) -> Option<String> {
+    match *event {
+        Event::Update(args) => Some(format!("update {}", args.dt)),
+        Event::Render(args) => Some(format!("render {} {} {} {}", args.width, args.height,
+                                            args.draw_width, args.draw_height)),
+
+        Event::Input(Input::Press(ref button)) => encode_button(button).map(|button| format!("press {}", button)),
+        Event::Input(Input::Release(ref button)) => encode_button(button).map(|button| format!("release {}", button)),
+
+        Event::Input(Input::Move(Motion::MouseCursor(x, y))) => Some(format!("move {} {}", x, y)),
+        Event::Input(Input::Move(Motion::MouseRelative(x, y))) => Some(format!("relative {} {}", x, y)),
+        Event::Input(Input::Move(Motion::MouseScroll(x, y))) => Some(format!("scroll {} {}", x, y)),
+
+        Event::Input(Input::Text(ref text)) => Some(format!("text {}", escape(text))),
+        Event::Input(Input::Resize(width, height)) => Some(format!("resize {} {}", width, height)),
+        Event::Input(Input::Focus(focused)) => Some(format!("focus {}", focused as u8)),
+        Event::Input(Input::Cursor(inside)) => Some(format!("cursor {}", inside as u8)),
+
+        _ => None
+    }
+}
+
+fn decode_button(text: &str) -> Option<Button> {
+    let mut words = text.split_whitespace();
+    let kind = words.next();
+    let code = match words.next().and_then(|code| code.parse::<u32>().ok()) {
+        Some(code) => code,
+        None => return None
+    };
+
+    match kind {
+        Some("key") => Some(Button::Keyboard(Key::from(code))),
+        Some("mouse") => Some(Button::Mouse(MouseButton::from(code))),
+        _ => None
+    }
+}
+
+fn malformed(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("malformed event '{}'", line))
+}
+
+/// Event of a line written by `encode`
+pub fn decode(line: &str) -> io::Result<Event> {
+    let mut words = line.splitn(2, ' ');
+    let kind = words.next().unwrap_or("");
+    let rest = words.next().unwrap_or("");
+
+    match kind {
+        "text" => return Ok(Event::Input(Input::Text(unescape(rest)))),
+        "press" => return decode_button(rest).map(|button| Event::Input(Input::Press(button))).ok_or_else(|| malformed(line)),
+        "release" => return decode_button(rest).map(|button| Event::Input(Input::Release(button))).ok_or_else(|| malformed(line)),
+        _ => {}
+    }
+
+    let numbers: Vec<f64> = rest.split_whitespace().map(|word| word.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| malformed(line))?;
+
+    let event = match (kind, numbers.len()) {
+        ("update", 1) => Event::Update(UpdateArgs { dt: numbers[0] }),
+        ("render", 4) => Event::Render(RenderArgs { ext_dt: 0.0,
+                                                    width: numbers[0] as u32,
+                                                    height: numbers[1] as u32,
+                                                    draw_width: numbers[2] as u32,
+                                                    draw_height: numbers[3] as u32 }),
+
+        ("move", 2) => Event::Input(Input::Move(Motion::MouseCursor(numbers[0], numbers[1]))),
+        ("relative", 2) => Event::Input(Input::Move(Motion::MouseRelative(numbers[0], numbers[1]))),
+        ("scroll", 2) => Event::Input(Input::Move(Motion::MouseScroll(numbers[0], numbers[1]))),
+
+        ("resize", 2) => Event::Input(Input::Resize(numbers[0] as u32, numbers[1] as u32)),
+        ("focus", 1) => Event::Input(Input::Focus(numbers[0] != 0.0)),
+        ("cursor", 1) => Event::Input(Input::Cursor(numbers[0] != 0.0)),
+
+        _ => return Err(malformed(line))
+    };
+
+    Ok(event)
+}
+
+/// Events of a recording in the order they happened, empty lines and
+/// lines starting with `#` are skipped
+pub fn read(path: &Path) -> io::Result<Vec<Event>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut res = Vec::new();
+
+    for line in file.lines() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        res.push(decode(line.trim_right_matches('\r'))?);
+    }
+
+    Ok(res)
+}
+
+
+#[cfg(test)]
+use std::env;
+#[cfg(test)]
+use std::fs;
+
+#[test]
+fn test_recording_round_trip() {
+    let key = format!("press key {}", Key::A as u32);
+    let lines = ["update 0.016666", "render 1024 768 2048 1536",
+                 &key[..], "release key 0", "press mouse 1",
+                 "move 10.5 -3", "relative 1 2", "scroll 0 -1",
+                 "text a\\\\b\\nc", "resize 800 600", "focus 0", "cursor 1"];
+
+    for line in lines.iter() {
+        assert_eq!(encode(&decode(line).unwrap()), Some(line.to_string()));
+    }
+
+    assert!(decode("press joystick 1").is_err());
+    assert!(decode("move 1").is_err());
+    assert!(decode("update soon").is_err());
+    assert!(encode(&Event::Input(Input::Text("two\nlines".to_string()))).unwrap().lines().count() == 1);
+}
+
+#[test]
+fn test_recording_file() {
+    let path = env::temp_dir().join("life-rs-recording-test");
+
+    {
+        let mut recorder = Recorder::new(&path).unwrap();
+        recorder.record(&Event::Input(Input::Press(Button::Keyboard(Key::Space)))).unwrap();
+        recorder.record(&Event::Update(UpdateArgs { dt: 0.5 })).unwrap();
+    }
+
+    let events = read(&path).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(encode(&events[0]), Some(format!("press key {}", Key::Space as u32)));
+
+    let _ = fs::remove_file(&path);
+}
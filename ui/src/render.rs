@@ -0,0 +1,172 @@
+// Turns board contents into a list of colored rectangles, kept apart
+// from the drawing itself so that it can be checked without a GPU
+extern crate engine;
+
+use self::engine::board::CellDesc;
+use self::engine::cam::Transform;
+
+use super::rules::Palette;
+
+use std::collections::HashMap;
+
+// cells smaller than that many pixels are drawn by pixel coverage
+pub const MIN_CELL_SIZE: f64 = 3.0;
+
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quad {
+    pub color: [f32; 4],
+    pub rect: [f64; 4],
+}
+
+pub fn is_small_cells(transform: &Transform) -> bool {
+    transform.get_cell_width() < MIN_CELL_SIZE || transform.get_cell_height() < MIN_CELL_SIZE
+}
+
+pub fn render_cells(cells: &[CellDesc], transform: &Transform, palette: &Palette,
+                    width: f64, height: f64) -> Vec<Quad> {
+    if is_small_cells(transform) {
+        render_cells_coverage(cells, transform, palette, width, height)
+    } else {
+        render_cells_full(cells, transform, palette, width, height)
+    }
+}
+
+fn render_cells_full(cells: &[CellDesc], transform: &Transform, palette: &Palette,
+                     width: f64, height: f64) -> Vec<Quad> {
+    let (cell_width, cell_height) = (transform.get_cell_width(), transform.get_cell_height());
+
+    cells.iter()
+        .filter(|cell| cell.is_alive)
+        .filter_map(|&CellDesc { coord, gen, .. }| {
+            let (x, y) = transform.to_screen(coord.col, coord.row);
+
+            // cells out of the screen are culled
+            if x + cell_width <= 0.0 || y + cell_height <= 0.0 || x >= width || y >= height {
+                None
+            } else {
+                Some(Quad { color: palette.get_cell_color(gen), rect: [x, y, cell_width, cell_height] })
+            }
+        })
+        .collect()
+}
+
+fn render_cells_coverage(cells: &[CellDesc], transform: &Transform, palette: &Palette,
+                         width: f64, height: f64) -> Vec<Quad> {
+    // several cells fall into one screen pixel, so instead of overdrawing
+    // full size rectangles accumulate the share of each pixel covered by
+    // live cells and use it as pixel alpha, this removes shimmering when zoomed out
+
+    let cell_area = transform.get_cell_width() * transform.get_cell_height();
+
+    // pixel -> (covered area, sum of cell colors weighted by area)
+    let mut pixels: HashMap<(i64, i64), (f64, [f64; 4])> = HashMap::new();
+
+    for &CellDesc { coord, gen, is_alive, .. } in cells {
+        if !is_alive {
+            continue;
+        }
+
+        let (x, y) = transform.to_screen(coord.col, coord.row);
+        if x < 0.0 || y < 0.0 || x >= width || y >= height {
+            continue;
+        }
+
+        let color = palette.get_cell_color(gen);
+        let pixel = pixels.entry((x as i64, y as i64)).or_insert((0.0, [0.0; 4]));
+
+        pixel.0 += cell_area;
+        for i in 0..4 {
+            pixel.1[i] += color[i] as f64 * cell_area;
+        }
+    }
+
+    let mut res: Vec<Quad> = pixels.iter().map(|(&(x, y), &(covered, color))| {
+        // average color of the cells, faded by the uncovered part of the pixel
+        let alpha = color[3] / covered * covered.min(1.0);
+        Quad {
+            color: [(color[0] / covered) as f32, (color[1] / covered) as f32,
+                    (color[2] / covered) as f32, alpha as f32],
+            rect: [x as f64, y as f64, 1.0, 1.0],
+        }
+    }).collect();
+
+    // hash map order is random, keep output stable
+    res.sort_by(|a, b| (a.rect[1], a.rect[0]).partial_cmp(&(b.rect[1], b.rect[0])).unwrap());
+    res
+}
+
+
+#[cfg(test)]
+use self::engine::engine::Engine;
+#[cfg(test)]
+use self::engine::board::Coord;
+#[cfg(test)]
+use self::engine::cam::Cam;
+#[cfg(test)]
+use self::engine::structs::CellProp;
+#[cfg(test)]
+use super::golden::check_golden;
+#[cfg(test)]
+use super::image::Canvas;
+
+#[cfg(test)]
+fn render_frame(engine: &Engine, cam: &Cam, width: usize, height: usize) -> Canvas {
+    let transform = Transform::new(cam, &CellProp::new(10.0, 10.0), width as f64, height as f64);
+
+    let palette = Palette::default();
+    let mut canvas = Canvas::new(width, height, palette.background);
+    for quad in render_cells(&engine.snapshot().cells, &transform, &palette, width as f64, height as f64) {
+        canvas.fill(&quad);
+    }
+    canvas
+}
+
+#[cfg(test)]
+fn glider_engine(generations: usize) -> Engine<'static> {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&[Coord { col: 1, row: 0 }, Coord { col: 2, row: 1 }, Coord { col: 0, row: 2 },
+                         Coord { col: 1, row: 2 }, Coord { col: 2, row: 2 }]);
+    for _ in 0..generations {
+        engine.one_iteration();
+    }
+    engine
+}
+
+#[test]
+fn test_golden_glider() {
+    // replay is deterministic, so is the frame, colors depend on cells age
+    let engine = glider_engine(3);
+    check_golden("glider", &render_frame(&engine, &Cam::new(0.0, 0.0), 64, 48));
+}
+
+#[test]
+fn test_golden_camera_offset() {
+    // partially visible cells at the screen edges
+    let engine = glider_engine(0);
+    check_golden("camera_offset", &render_frame(&engine, &Cam::new(-27.0, 19.0), 64, 48));
+}
+
+#[test]
+fn test_golden_zoomed_out() {
+    let mut engine = Engine::new(None, None);
+    let cells: Vec<Coord> = (0..40).flat_map(|row| (0..40).map(move |col| Coord { col: col, row: row }))
+        .filter(|c| (c.col * 7 + c.row * 3) % 5 < 2)
+        .collect();
+    engine.set_pattern(&cells);
+
+    let mut cam = Cam::new(0.0, 0.0);
+    for _ in 0..20 {
+        cam.zoom_out();
+    }
+
+    check_golden("zoomed_out", &render_frame(&engine, &cam, 64, 48));
+}
+
+#[test]
+fn test_culling() {
+    let engine = glider_engine(0);
+    let transform = Transform::new(&Cam::new(1000.0, 0.0), &CellProp::new(10.0, 10.0), 64.0, 48.0);
+
+    assert!(render_cells(&engine.snapshot().cells, &transform, &Palette::default(), 64.0, 48.0).is_empty());
+}
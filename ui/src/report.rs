@@ -0,0 +1,97 @@
+// Bug report bundle: a directory with everything needed to reproduce
+// a problem, the pattern, the whole session, config, recent messages
+// and the version of the program
+extern crate engine;
+
+use self::engine::io::container::Container;
+use self::engine::io::rle;
+use self::engine::io::rle::RleFile;
+
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+
+pub struct Report {
+    // what happened, e.g. the message of a caught panic
+    pub reason: String,
+    pub pattern: RleFile,
+    pub session: Container,
+    pub config: String,
+    pub log: Vec<String>,
+}
+
+impl Report {
+
+    /// Creates `life-rs-report-<timestamp>` directory inside the given one
+    pub fn write(&self, dir: &Path, timestamp: u64) -> io::Result<PathBuf> {
+        let path = dir.join(format!("life-rs-report-{}", timestamp));
+        fs::create_dir_all(&path)?;
+
+        let mut info = File::create(path.join("version.txt"))?;
+        writeln!(info, "life-rs {}", VERSION)?;
+        writeln!(info, "os: {} {}", env::consts::OS, env::consts::ARCH)?;
+        writeln!(info, "reason: {}", self.reason)?;
+
+        rle::save(&self.pattern, &path.join("pattern.rle"))?;
+        self.session.save(&path.join("session.life-rs"), false)?;
+
+        File::create(path.join("config.txt"))?.write_all(self.config.as_bytes())?;
+
+        let mut log = File::create(path.join("log.txt"))?;
+        for line in &self.log {
+            writeln!(log, "{}", line)?;
+        }
+
+        Ok(path)
+    }
+
+}
+
+
+#[cfg(test)]
+use self::engine::engine::Engine;
+#[cfg(test)]
+use self::engine::io::read_pattern;
+#[cfg(test)]
+use std::io::Read;
+
+#[test]
+fn test_report_write() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&rle::decode_cells("bo$2bo$3o!").unwrap());
+
+    let report = Report {
+        reason: "window failed".to_string(),
+        pattern: engine.to_rle(),
+        session: engine.to_container(true),
+        config: "speed = 10\n".to_string(),
+        log: vec!["1 first".to_string(), "2 second".to_string()],
+    };
+
+    let dir = env::temp_dir().join("life-rs-report-test");
+    let _ = fs::remove_dir_all(&dir);
+
+    let path = report.write(&dir, 42).unwrap();
+    assert_eq!(path, dir.join("life-rs-report-42"));
+
+    let read = |name: &str| {
+        let mut contents = String::new();
+        File::open(path.join(name)).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    };
+
+    assert!(read("version.txt").contains("reason: window failed"));
+    assert_eq!(read("config.txt"), "speed = 10\n");
+    assert_eq!(read("log.txt"), "1 first\n2 second\n");
+
+    assert_eq!(read_pattern(&path.join("pattern.rle")).unwrap().0.len(), 5);
+    assert_eq!(Container::load(&path.join("session.life-rs")).unwrap().cells.len(), 5);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,370 @@
+/// Known rules with their display names, descriptions and colors.
+/// Besides the built-in ones, rules can be added or redefined in the
+/// config, every rule takes several keys with a common id:
+/// ```
+/// # use ui::config::Config;
+/// # use ui::rules::RuleRegistry;
+/// let config = Config::parse("rules.maze.rule = B3/S12345\n\
+///                             rules.maze.name = Maze\n\
+///                             rules.maze.palette = #000000, #ffff00, #0000ff");
+///
+/// let mut registry = RuleRegistry::new();
+/// assert!(registry.add_from_config(&config).is_empty());
+/// assert_eq!(registry.find(&"B3/S12345".parse().unwrap()).unwrap().name, "Maze");
+/// ```
+///
+/// Palette is the background color, the color of newborn cells and the
+/// color old cells fade to, `rule` is the only mandatory key. Golly `.rule`
+/// files are given by `file` instead of `rule`, named by their `@RULE`
+/// line unless `name` says otherwise.
+///
+extern crate engine;
+
+use self::engine::rule::Rule;
+use self::engine::io::compression;
+use self::engine::io::ruletable::RuleTable;
+
+use super::config::Config;
+
+use std::io;
+use std::path::Path;
+
+
+// newborn cells turn into old ones in that many generations
+const PALETTE_GENERATIONS: f64 = 256.0 / 50.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Palette {
+    pub background: [f32; 4],
+    pub young: [f32; 4],
+    pub old: [f32; 4],
+}
+
+impl Palette {
+
+    pub fn new(background: [f32; 4], young: [f32; 4], old: [f32; 4]) -> Self {
+        Palette { background: background, young: young, old: old }
+    }
+
+    pub fn get_cell_color(&self, gen: usize) -> [f32; 4] {
+        let r = 1.0_f64.min(gen as f64 / PALETTE_GENERATIONS);
+
+        let mut res = [0.0; 4];
+        for i in 0..4 {
+            res[i] = (self.young[i] as f64 * (1.0 - r) + self.old[i] as f64 * r) as f32;
+        }
+        res
+    }
+
+    fn parse_color(color: &str) -> Option<[f32; 4]> {
+        // #rrggbb or #rrggbbaa
+        let color = color.trim();
+        if !color.starts_with('#') || (color.len() != 7 && color.len() != 9) {
+            return None;
+        }
+
+        let mut res = [1.0; 4];
+        for i in 0..(color.len() - 1) / 2 {
+            match u8::from_str_radix(&color[1 + 2 * i..3 + 2 * i], 16) {
+                Ok(value) => res[i] = value as f32 / 255.0,
+                Err(_) => return None
+            }
+        }
+        Some(res)
+    }
+
+    pub fn parse(palette: &str) -> Option<Palette> {
+        let colors: Vec<[f32; 4]> = palette.split(',').filter_map(Palette::parse_color).collect();
+
+        if colors.len() == 3 && palette.split(',').count() == 3 {
+            Some(Palette::new(colors[0], colors[1], colors[2]))
+        } else {
+            None
+        }
+    }
+
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        // green youngsters turning red with age on black
+        Palette::new([0.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 0.5], [1.0, 0.0, 0.0, 0.5])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleInfo {
+    pub rule: Rule,
+    pub name: String,
+    pub description: String,
+    pub palette: Palette,
+}
+
+impl RuleInfo {
+
+    pub fn new(rule: &str, name: &str, description: &str, palette: Palette) -> Self {
+        RuleInfo {
+            rule: rule.parse().expect("malformed built-in rule"),
+            name: name.to_string(),
+            description: description.to_string(),
+            palette: palette,
+        }
+    }
+
+}
+
+// rules given by a table of transitions rather than B/S notation
+#[derive(Clone)]
+pub struct TableInfo {
+    pub table: RuleTable,
+    pub name: String,
+    pub description: String,
+    pub palette: Palette,
+    // opened from a file during the session, not named in the config
+    pub opened: bool,
+}
+
+pub struct RuleRegistry {
+    rules: Vec<RuleInfo>,
+    tables: Vec<TableInfo>,
+}
+
+impl RuleRegistry {
+
+    pub fn new() -> Self {
+        let night = |young: [f32; 4], old: [f32; 4]| Palette::new([0.0, 0.0, 0.1, 1.0], young, old);
+
+        RuleRegistry {
+            rules: vec![
+                RuleInfo::new("B3/S23", "Life", "Conway's Game of Life", Palette::default()),
+                RuleInfo::new("B36/S23", "HighLife", "Life with a small replicator",
+                              night([0.3, 0.8, 1.0, 0.6], [0.8, 0.2, 1.0, 0.6])),
+                RuleInfo::new("B3678/S34678", "Day & Night", "Live and dead cells behave symmetrically",
+                              night([1.0, 0.9, 0.3, 0.7], [0.3, 0.3, 0.9, 0.7])),
+                RuleInfo::new("B2/S", "Seeds", "Every cell dies, explosive growth",
+                              night([1.0, 0.6, 0.2, 0.8], [1.0, 0.6, 0.2, 0.8])),
+                RuleInfo::new("B3/S012345678", "Life without death", "Cells never die, ladders grow",
+                              night([0.4, 1.0, 0.4, 0.6], [0.1, 0.4, 0.1, 0.6])),
+                RuleInfo::new("B1357/S1357", "Replicator", "Every pattern replicates itself",
+                              night([1.0, 0.3, 0.3, 0.6], [1.0, 1.0, 0.3, 0.6])),
+                RuleInfo::new("B36/S125", "2x2", "Patterns made of 2x2 blocks",
+                              night([0.2, 0.9, 0.9, 0.6], [0.2, 0.4, 0.9, 0.6])),
+                RuleInfo::new("B3/S12345", "Maze", "Grows maze-like structures",
+                              night([1.0, 1.0, 0.4, 0.6], [0.5, 0.5, 1.0, 0.6])),
+                RuleInfo::new("B35678/S5678", "Diamoeba", "Large diamond-shaped amoebas",
+                              night([0.9, 0.5, 1.0, 0.6], [0.4, 0.2, 0.8, 0.6])),
+                RuleInfo::new("B368/S245", "Morley", "Rich in spaceships and puffers",
+                              night([0.4, 1.0, 0.8, 0.6], [1.0, 0.4, 0.6, 0.6])),
+            ],
+            tables: Vec::new(),
+        }
+    }
+
+    pub fn get_rules(&self) -> &[RuleInfo] {
+        &self.rules
+    }
+
+    pub fn find(&self, rule: &Rule) -> Option<&RuleInfo> {
+        self.rules.iter().find(|info| info.rule == *rule)
+    }
+
+    pub fn get_palette(&self, rule: &Rule) -> Palette {
+        self.find(rule).map_or(Palette::default(), |info| info.palette)
+    }
+
+    pub fn get_tables(&self) -> &[TableInfo] {
+        &self.tables
+    }
+
+    pub fn add_table(&mut self, info: TableInfo) {
+        // tables are told apart by their names
+        match self.tables.iter().position(|t| t.name == info.name) {
+            Some(idx) => self.tables[idx] = info,
+            None => self.tables.push(info)
+        }
+    }
+
+    pub fn add(&mut self, info: RuleInfo) {
+        // user definitions replace built-in ones
+        match self.rules.iter().position(|r| r.rule == info.rule) {
+            Some(idx) => self.rules[idx] = info,
+            None => self.rules.push(info)
+        }
+    }
+
+    /// Adds rules defined in the config, returns errors in the definitions,
+    /// malformed definitions are skipped
+    pub fn add_from_config(&mut self, config: &Config) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let ids: Vec<String> = config.get_keys()
+            .filter(|key| key.starts_with("rules.") && key.ends_with(".rule"))
+            .map(|key| key["rules.".len()..key.len() - ".rule".len()].to_string())
+            .collect();
+
+        for id in ids {
+            let get = |field: &str| config.get(&format!("rules.{}.{}", id, field));
+
+            let rule: Rule = match get("rule").unwrap_or("").parse() {
+                Ok(rule) => rule,
+                Err(err) => {
+                    errors.push(format!("rule '{}': {}", id, err));
+                    continue;
+                }
+            };
+
+            let palette = match get("palette") {
+                Some(palette) => match Palette::parse(palette) {
+                    Some(palette) => palette,
+                    None => {
+                        errors.push(format!("rule '{}': malformed palette", id));
+                        continue;
+                    }
+                },
+                None => self.get_palette(&rule)
+            };
+
+            self.add(RuleInfo {
+                rule: rule,
+                name: get("name").unwrap_or(&id).to_string(),
+                description: get("description").unwrap_or("").to_string(),
+                palette: palette,
+            });
+        }
+
+        errors.extend(self.add_tables_from_config(config));
+        errors
+    }
+
+    fn add_tables_from_config(&mut self, config: &Config) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let ids: Vec<String> = config.get_keys()
+            .filter(|key| key.starts_with("rules.") && key.ends_with(".file"))
+            .map(|key| key["rules.".len()..key.len() - ".file".len()].to_string())
+            .collect();
+
+        for id in ids {
+            let get = |field: &str| config.get(&format!("rules.{}.{}", id, field));
+
+            let table = match read_table(Path::new(get("file").unwrap_or(""))) {
+                Ok(table) => table,
+                Err(err) => {
+                    errors.push(format!("rule '{}': {}", id, err));
+                    continue;
+                }
+            };
+
+            let palette = match get("palette").map(Palette::parse) {
+                Some(Some(palette)) => palette,
+                Some(None) => {
+                    errors.push(format!("rule '{}': malformed palette", id));
+                    continue;
+                }
+                None => Palette::default()
+            };
+
+            let name = match get("name") {
+                Some(name) => name.to_string(),
+                None if !table.get_name().is_empty() => table.get_name().to_string(),
+                None => id.clone()
+            };
+
+            self.add_table(TableInfo {
+                table: table,
+                name: name,
+                description: get("description").unwrap_or("").to_string(),
+                palette: palette,
+                opened: false,
+            });
+        }
+
+        errors
+    }
+
+}
+
+fn read_table(path: &Path) -> io::Result<RuleTable> {
+    RuleTable::read(&mut compression::open(path)?)
+}
+
+
+#[test]
+fn test_default_palette() {
+    // newborn cells are green, old ones are red
+    let palette = Palette::default();
+
+    assert_eq!(palette.get_cell_color(0), [0.0, 1.0, 0.0, 0.5]);
+    assert_eq!(palette.get_cell_color(1000), [1.0, 0.0, 0.0, 0.5]);
+}
+
+#[test]
+fn test_rules_from_config() {
+    let config = Config::parse("rules.life.rule = B3/S23\n\
+                                rules.life.palette = #000000, #ffffff, #80808080\n\
+                                rules.bad.rule = B3/S9\n\
+                                rules.ugly.rule = B2/S2\n\
+                                rules.ugly.palette = #0000\n\
+                                rules.custom.rule = 245/36\n\
+                                rules.custom.description = Morley, sort of");
+
+    let mut registry = RuleRegistry::new();
+    let count = registry.get_rules().len();
+
+    assert_eq!(registry.add_from_config(&config).len(), 2);
+    assert_eq!(registry.get_rules().len(), count + 1);
+
+    let life = registry.find(&"B3/S23".parse().unwrap()).unwrap();
+    assert_eq!(life.name, "life");
+    assert_eq!(life.palette.young, [1.0, 1.0, 1.0, 1.0]);
+    assert_eq!(life.palette.old, [128.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0]);
+
+    let custom = registry.find(&"B36/S245".parse().unwrap()).unwrap();
+    assert_eq!(custom.description, "Morley, sort of");
+
+    // unknown rules get the default colors
+    assert_eq!(registry.get_palette(&"B1/S1".parse().unwrap()), Palette::default());
+}
+
+#[cfg(test)]
+use std::{env, fs};
+#[cfg(test)]
+use std::fs::File;
+#[cfg(test)]
+use std::io::Write;
+
+#[test]
+fn test_tables_from_config() {
+    let dir = env::temp_dir().join("life-rs-test-rule-tables");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    File::create(dir.join("Cross.rule")).unwrap()
+        .write_all(b"@RULE Cross\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:rotate4\n0,1,0,0,0,1\n")
+        .unwrap();
+
+    let config = Config::parse(&format!("rules.cross.file = {}\n\
+                                         rules.cross.description = Grows a cross\n\
+                                         rules.renamed.file = {0}\n\
+                                         rules.renamed.name = Plus\n\
+                                         rules.missing.file = {}",
+                                        dir.join("Cross.rule").display(), dir.join("none.rule").display()));
+
+    let mut registry = RuleRegistry::new();
+    let count = registry.get_rules().len();
+
+    // the missing file is reported, B/S rules are left alone
+    assert_eq!(registry.add_from_config(&config).len(), 1);
+    assert_eq!(registry.get_rules().len(), count);
+
+    let mut names: Vec<&str> = registry.get_tables().iter().map(|info| &info.name[..]).collect();
+    names.sort();
+    assert_eq!(names, vec!["Cross", "Plus"]);
+
+    let cross = registry.get_tables().iter().find(|info| info.name == "Cross").unwrap();
+    assert_eq!(cross.description, "Grows a cross");
+    assert_eq!(cross.table.get_name(), "Cross");
+    assert!(!cross.opened);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,116 @@
+// Vector image of the board, one square per live cell, which stays
+// crisp at any size when put into documents
+extern crate engine;
+
+use self::engine::board::{CellDesc, Rect};
+
+use super::rules::Palette;
+
+use std::io;
+use std::io::Write;
+
+// size of a cell in SVG units, the picture is scaled by whoever shows it
+const CELL_SIZE: i64 = 10;
+
+const GRID_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const GRID_WIDTH: f64 = 0.5;
+
+
+/// `#rrggbb` and the opacity, which SVG takes as a separate attribute
+fn to_svg_color(color: [f32; 4]) -> (String, f32) {
+    let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0).round() as u8;
+    (format!("#{:02x}{:02x}{:02x}", channel(color[0]), channel(color[1]), channel(color[2])), color[3])
+}
+
+fn write_fill(writer: &mut Write, color: [f32; 4]) -> io::Result<()> {
+    let (color, opacity) = to_svg_color(color);
+    write!(writer, "fill=\"{}\"", color)?;
+
+    if opacity < 1.0 {
+        write!(writer, " fill-opacity=\"{:.3}\"", opacity)?;
+    }
+    Ok(())
+}
+
+/// Live cells inside the given region, colored by age like on the screen,
+/// the grid is a single path with a line between every two cells
+pub fn write_svg(cells: &[CellDesc], rect: &Rect, palette: &Palette, grid: bool,
+                 writer: &mut Write) -> io::Result<()> {
+    let width = rect.get_width() as i64 * CELL_SIZE;
+    let height = rect.get_height() as i64 * CELL_SIZE;
+
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(writer, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                      viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">", width, height, width, height)?;
+
+    write!(writer, "<rect width=\"{}\" height=\"{}\" ", width, height)?;
+    write_fill(writer, palette.background)?;
+    writeln!(writer, "/>")?;
+
+    for cell in cells.iter().filter(|cell| cell.is_alive && rect.contains(cell.coord.col, cell.coord.row)) {
+        let (x, y) = ((cell.coord.col - rect.left) * CELL_SIZE, (cell.coord.row - rect.top) * CELL_SIZE);
+
+        write!(writer, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" ", x, y, CELL_SIZE, CELL_SIZE)?;
+        write_fill(writer, palette.get_cell_color(cell.gen))?;
+        writeln!(writer, "/>")?;
+    }
+
+    if grid {
+        let (color, opacity) = to_svg_color(GRID_COLOR);
+        write!(writer, "<path stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\" d=\"",
+               color, opacity, GRID_WIDTH)?;
+
+        for x in 1..rect.get_width() as i64 {
+            write!(writer, "M{} 0V{}", x * CELL_SIZE, height)?;
+        }
+        for y in 1..rect.get_height() as i64 {
+            write!(writer, "M0 {}H{}", y * CELL_SIZE, width)?;
+        }
+
+        writeln!(writer, "\"/>")?;
+    }
+
+    writeln!(writer, "</svg>")
+}
+
+
+#[cfg(test)]
+use self::engine::engine::Engine;
+#[cfg(test)]
+use self::engine::io::rle::decode_cells;
+
+#[test]
+fn test_svg_colors() {
+    assert_eq!(to_svg_color([1.0, 0.0, 0.5, 1.0]), ("#ff0080".to_string(), 1.0));
+    assert_eq!(to_svg_color([0.0, 0.0, 0.0, 0.5]).1, 0.5);
+}
+
+#[test]
+fn test_write_svg() {
+    let mut engine = Engine::new(None, None);
+    engine.set_pattern(&decode_cells("bo$2bo$3o!").unwrap());
+
+    let rect = engine.get_board().get_bounding_box().unwrap();
+    let cells = engine.snapshot().cells;
+    let palette = Palette::default();
+
+    let write = |rect: &Rect, grid: bool| {
+        let mut data = Vec::new();
+        write_svg(&cells, rect, &palette, grid, &mut data).unwrap();
+        String::from_utf8(data).unwrap()
+    };
+
+    // background and the glider
+    let svg = write(&rect, false);
+    assert!(svg.contains("width=\"30\" height=\"30\""));
+    assert_eq!(svg.matches("<rect").count(), 6);
+    assert!(!svg.contains("<path"));
+    assert!(svg.trim_right().ends_with("</svg>"));
+
+    // cells are relative to the region, the ones outside it are left out
+    let top = Rect::new(rect.left, rect.top, rect.right, rect.top);
+    let svg = write(&top, true);
+    assert_eq!(svg.matches("<rect").count(), 2);
+    assert!(svg.contains(&format!("<rect x=\"10\" y=\"0\" width=\"{0}\" height=\"{0}\"", CELL_SIZE)));
+    assert!(svg.contains("d=\"M10 0V10M20 0V10\""));
+}
@@ -4,53 +4,183 @@ extern crate engine;
 use piston_window::{Context, Event, Input, Button, Key,
                     MouseButton, Motion, line, rectangle};
 
-use super::{WindowBase, PostAction};
+use super::{WindowBase, PostAction, Modifiers};
 use super::super::States;
+use super::super::render;
+use super::super::rules::RuleRegistry;
+use super::super::image::Canvas;
+use super::super::svg;
+use super::super::log;
+#[cfg(feature = "gpu")]
+use super::super::gpu::GpuStepper;
 
 use self::engine::structs::{CellProp, GraphicsWindow};
-use self::engine::board::CellDesc;
-use self::engine::cam::Cam;
+use self::engine::board::{Rect, CellDesc, CombineMode};
+use self::engine::cam::{Cam, Transform};
 use self::engine::engine::Engine;
+use self::engine::history::EventKind;
+use self::engine::snapshot::Snapshot;
+use self::engine::pattern::Pattern;
+use self::engine::io::container::Container;
+#[cfg(feature = "gpu")]
+use self::engine::kernel::Stats;
 
 use opengl_graphics::GlGraphics;
 
 use std::rc::Rc;
 use std::cell::{RefCell, Cell};
 use std::time::{Instant, Duration};
-
+use std::io;
+use std::io::Write;
+
+const DOUBLE_CLICK_INTERVAL_MS: u64 = 400;
+
+// share of the screen left empty on each side of a loaded pattern
+const FIT_MARGIN: f64 = 0.1;
+
+// larger side of the whole board image in pixels, cells are shrunk to fit it
+const MAX_IMAGE_SIZE: f64 = 4096.0;
+
+// dragging closer than that many pixels to the window edge scrolls the board,
+// the faster the closer to the edge, up to the given speed in pixels per second
+const EDGE_SCROLL_ZONE: f64 = 40.0;
+const EDGE_SCROLL_SPEED: f64 = 800.0;
+
+// share of every update interval spent on generations while rendering is off,
+// the rest is left to input and to the event loop sleeping until the next update
+const UNRENDERED_LOAD: f64 = 0.5;
+
+// simulation ticks, each running `speed` generations, are counted by the
+// clock at the rate set in the engine rather than by updates, so slow frames
+// don't slow the simulation; after a longer stall or a pause only one tick
+// is run instead of catching up
+const MAX_CATCH_UP_TICKS: u64 = 4;
+
+// while the GPU computes generations, only the visible cells are read back
+// on every paint, the engine catches up with the whole board this often,
+// e.g. for the autosave and the saved sessions
+#[cfg(feature = "gpu")]
+const GPU_SYNC_INTERVAL_MS: u64 = 1000;
+
+// generations computed on the GPU and not yet in the engine
+#[cfg(feature = "gpu")]
+struct GpuRun {
+    stepper: GpuStepper,
+    // engine version the textures were uploaded at or caught up with
+    version: Option<usize>,
+    // generations since then, see `catch_up`
+    stats: Vec<Stats>,
+    // live cells visible at the last of them, drawn instead of the engine's
+    cells: Vec<CellDesc>,
+    synced: Instant,
+}
 
 pub struct GameBoard<'a> {
 
     window: Rc<GraphicsWindow>,
     engine: Rc<RefCell<Engine<'a>>>,
+    rules: Rc<RefCell<RuleRegistry>>,
 
     cell: CellProp,
     cam: Cam,
 
     show_grid: bool,
     render: bool,
+    // generations per tick, single steps are always one generation
+    speed: u64,
 
     last_iter_time: Instant,
     last_pos: Option<[f64; 2]>,
+    last_click: Option<(Instant, i64, i64)>,
+
+    modifiers: Modifiers,
+    selection: Rc<Cell<Option<Rect>>>,
+    // cells currently visible on the screen, updated on every paint
+    viewport: Rc<Cell<Option<Rect>>>,
+    // cell where the selection being dragged started
+    selection_start: Option<(i64, i64)>,
+    // region other windows ask to be shown, camera moves there on next update
+    look_at: Rc<Cell<Option<Rect>>>,
+
+    // cells copied with Ctrl+C, relative to the top left corner of copied region,
+    // shared with windows using them, e.g. for replacing found patterns
+    clipboard: Rc<RefCell<Pattern>>,
+    // clipboard contents follow the mouse until placed, if set
+    paste_mode: Option<CombineMode>,
+    // refuse to paste over existing live cells
+    safe_paste: bool,
+
+    // drawing only kills cells while E or the right mouse button is held
+    erase_key: bool,
+    erase_drag: bool,
+
+    // cells drawn on the last paint, retaken only when the engine has changed,
+    // so the engine isn't borrowed while drawing
+    snapshot: Option<Snapshot>,
+
+    gpu_enabled: bool,
+    // generations left for the GPU, computed on the next paint
+    gpu_pending: u64,
+    #[cfg(feature = "gpu")]
+    gpu: Option<GpuRun>,
+    // generation and population the GPU is at while the engine is behind,
+    // shared with the HUD
+    gpu_progress: Rc<Cell<Option<(usize, usize)>>>,
+    // reason of the GPU not being used told the last time, see `report_gpu_obstacle`
+    gpu_obstacle: Option<&'static str>,
+    // message for the user, see `take_notice`
+    notice: Option<String>,
 
 }
 
 impl<'a> GameBoard<'a> {
 
-    pub fn new(window: Rc<GraphicsWindow>, engine: Rc<RefCell<Engine<'a>>>) -> GameBoard<'a> {
+    pub fn new(window: Rc<GraphicsWindow>, engine: Rc<RefCell<Engine<'a>>>,
+               rules: Rc<RefCell<RuleRegistry>>,
+               selection: Rc<Cell<Option<Rect>>>,
+               viewport: Rc<Cell<Option<Rect>>>,
+               look_at: Rc<Cell<Option<Rect>>>,
+               clipboard: Rc<RefCell<Pattern>>,
+               gpu_progress: Rc<Cell<Option<(usize, usize)>>>) -> GameBoard<'a> {
 
         GameBoard {
             window: window,
             engine: engine,
+            rules: rules,
 
             cell: CellProp::new(10.0, 10.0),
             cam: Cam::new(0.0, 0.0),
 
             show_grid: true,
             render: true,
+            speed: 1,
 
             last_iter_time: Instant::now(),
             last_pos: None,
+            last_click: None,
+
+            modifiers: Modifiers::new(),
+            selection: selection,
+            viewport: viewport,
+            selection_start: None,
+            look_at: look_at,
+
+            clipboard: clipboard,
+            paste_mode: None,
+            safe_paste: false,
+
+            erase_key: false,
+            erase_drag: false,
+
+            snapshot: None,
+
+            gpu_enabled: false,
+            gpu_pending: 0,
+            #[cfg(feature = "gpu")]
+            gpu: None,
+            gpu_progress: gpu_progress,
+            gpu_obstacle: None,
+            notice: None,
 
         }
 
@@ -60,49 +190,99 @@ impl<'a> GameBoard<'a> {
 
 impl<'a> WindowBase for GameBoard<'a> {
 
+    fn is_board(&self) -> bool {
+        true
+    }
+
     fn paint(&mut self, c: Context, g: &mut GlGraphics) {
 
+        let (left, top) = self.to_logical(0.0, 0.0);
+        let (right, bottom) = self.to_logical(self.window.get_width(), self.window.get_height());
+        let viewport = Rect::new(left, top, right, bottom);
+
+        // paged out chunks where the camera is heading are read in advance
+        if let Some(prev) = self.viewport.get() {
+            let (dx, dy) = (viewport.left - prev.left, viewport.top - prev.top);
+            if dx != 0 || dy != 0 {
+                let ahead = Rect::new(left + dx, top + dy, right + dx, bottom + dy);
+                self.engine.borrow().get_board().prefetch(&ahead);
+            }
+        }
+        self.viewport.set(Some(viewport));
+
+        self.run_gpu(g);
+
+        let transform = self.get_transform();
+        let small_cells = render::is_small_cells(&transform);
+
         if self.render {
-            {
-                let engine = self.engine.borrow();
+            self.refresh_snapshot();
 
-                for CellDesc { coord, gen, is_alive, .. } in engine.get_board().into_iter() {
-                    if is_alive {
-                        let (x, y) = self.to_screen(coord.col, coord.row);
-                        rectangle(GameBoard::get_color(gen), [x, y,
-                            self.cell.get_width(&self.cam),
-                            self.cell.get_height(&self.cam)],
-                                  c.transform, g);
-                    }
+            if let Some(ref snapshot) = self.snapshot {
+                let palette = self.rules.borrow().get_palette(&snapshot.rule);
+                // the engine is behind the GPU, which read back just the visible cells
+                let cells = self.get_gpu_cells().unwrap_or(&snapshot.cells);
+
+                for quad in render::render_cells(cells, &transform, &palette,
+                                                 self.window.get_width(), self.window.get_height()) {
+                    rectangle(quad.color, quad.rect, c.transform, g);
                 }
             }
         }
 
-        if self.show_grid {
+        // grid of tiny cells is just noise
+        if self.show_grid && !small_cells {
             self.draw_grid(&c, g);
         }
 
         self.draw_borders(&c, g);
+        self.draw_zones(&c, g);
+        self.draw_selection(&c, g);
+        self.draw_paste_preview(&c, g);
+        self.draw_erase_cursor(&c, g);
     }
 
     fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
 
+        self.modifiers.handle(event);
+
         match event {
 
-            &Event::Update(_) => {
+            &Event::Update(args) => {
 
-                if cur_state.get() == States::Working || cur_state.get() == States::StepByStep {
-                    if !self.render ||
-                        Instant::now() - self.last_iter_time >= Duration::from_millis(3) ||
-                        cur_state.get() == States::StepByStep {
+                // the engine gets the generations computed on the GPU once it isn't used any more
+                if cur_state.get() != States::Working || !self.can_use_gpu() {
+                    self.catch_up();
+                }
 
-                        self.engine.borrow_mut().iterations(1);
-                        self.last_iter_time = Instant::now();
+                if let Some(rect) = self.look_at.get() {
+                    self.look_at.set(None);
+                    self.move_to_rect(&rect);
+                }
 
-                        if cur_state.get() == States::StepByStep {
-                            cur_state.set(States::Paused);
-                        }
+                self.cam.update(args.dt);
+                self.edge_scroll(args.dt);
 
+                if cur_state.get() == States::Working && !self.render {
+                    self.run_for(args.dt * UNRENDERED_LOAD);
+                } else if cur_state.get() == States::StepByStep {
+                    self.engine.borrow_mut().iterations(1);
+                    cur_state.set(States::Paused);
+                    self.last_iter_time = Instant::now();
+                } else if cur_state.get() == States::Working && self.engine.borrow().get_ups().is_none() {
+                    // as fast as possible, leaving time to draw
+                    self.run_for(args.dt * UNRENDERED_LOAD);
+                } else if cur_state.get() == States::Working {
+                    let generations = self.take_ticks() * self.speed;
+
+                    if generations > 0 && self.gpu_enabled {
+                        self.report_gpu_obstacle();
+                    }
+
+                    if generations > 0 && self.can_use_gpu() {
+                        self.gpu_pending += generations;
+                    } else if generations > 0 {
+                        self.engine.borrow_mut().iterations(generations);
                     }
                 }
 
@@ -118,6 +298,67 @@ impl<'a> WindowBase for GameBoard<'a> {
                 self.render = true;
             }
 
+            &Event::Input(Input::Press(Button::Keyboard(Key::C))) if self.modifiers.ctrl() => {
+                // copy selection, or the whole pattern if nothing is selected
+                let engine = self.engine.borrow();
+                let board = engine.get_board();
+
+                if let Some(rect) = self.selection.get().or(board.get_bounding_box()) {
+                    *self.clipboard.borrow_mut() = Pattern::from_selection(board, &rect);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::V))) if self.modifiers.ctrl() => {
+                // start placing copied cells
+                if !self.clipboard.borrow().is_empty() {
+                    self.paste_mode = Some(CombineMode::Or);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::M))) => {
+                // switch the way pasted cells are combined with existing ones
+                self.paste_mode = self.paste_mode.map(|mode| mode.next());
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::K))) => {
+                // enable/disable safe paste mode
+                self.safe_paste = !self.safe_paste;
+            }
+
+            &Event::Input(Input::Press(Button::Mouse(MouseButton::Right))) => {
+                if self.paste_mode.is_some() {
+                    // cancel pasting
+                    self.paste_mode = None;
+                } else if let Some(pos) = self.last_pos {
+                    // right drag erases cells
+                    self.erase_drag = true;
+                    self.erase_at(pos[0], pos[1]);
+                }
+            }
+
+            &Event::Input(Input::Release(Button::Mouse(MouseButton::Right))) => {
+                self.erase_drag = false;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::E))) if !self.modifiers.ctrl() => {
+                self.erase_key = true;
+            }
+
+            &Event::Input(Input::Release(Button::Keyboard(Key::E))) => {
+                self.erase_key = false;
+            }
+
+            &Event::Input(Input::Cursor(false)) if self.selection_start.is_none() => {
+                // paste preview is hidden and doesn't scroll the board while the mouse is away
+                self.last_pos = None;
+            }
+
+            &Event::Input(Input::Focus(false)) => {
+                // releases are not seen by unfocused window
+                self.erase_key = false;
+                self.erase_drag = false;
+            }
+
             &Event::Input(Input::Press(Button::Keyboard(Key::G))) => {
                 // show/hide grid
                 self.show_grid = !self.show_grid;
@@ -125,21 +366,67 @@ impl<'a> WindowBase for GameBoard<'a> {
 
             // mouse controls ->
             &Event::Input(Input::Press(Button::Mouse(MouseButton::Left))) => {
-                cur_state.set(States::Draw);
+                if let Some(mode) = self.paste_mode {
+                    // place pattern under the mouse cursor
+                    if let Some(pos) = self.last_pos {
+                        let (col, row) = self.to_logical(pos[0], pos[1]);
+
+                        let mut engine = self.engine.borrow_mut();
+                        let policy = engine.get_edge_policy();
+                        let board = engine.get_board_mut();
+                        let clipboard = self.clipboard.borrow();
+
+                        // in safe mode conflicting paste is just ignored,
+                        // conflicts are highlighted in preview anyway; so is
+                        // a paste reaching outside of the board if it's rejected
+                        if !self.safe_paste ||
+                            board.get_paste_conflicts(clipboard.get_cells(), col, row, mode).is_empty() {
+                            match board.try_paste(clipboard.get_cells(), col, row, mode, policy) {
+                                Ok(_) => self.paste_mode = None,
+                                Err(err) => log::write(&format!("paste is rejected: {}", err))
+                            }
+                        }
+                    }
+                } else if self.modifiers.shift() {
+                    // shift + drag selects a region
+                    if let Some(pos) = self.last_pos {
+                        let (col, row) = self.to_logical(pos[0], pos[1]);
+                        self.selection_start = Some((col, row));
+                        self.selection.set(Some(Rect::new(col, row, col, row)));
+                    }
+                } else {
+                    self.selection.set(None);
+                    cur_state.set(States::Draw);
+                }
             }
 
             &Event::Input(Input::Release(Button::Mouse(MouseButton::Left))) => {
-                if self.last_pos.is_some() {
+                if self.selection_start.is_some() {
+                    self.selection_start = None;
+                } else if cur_state.get() == States::Draw && self.last_pos.is_some() {
                     let pos = self.last_pos.unwrap();
-                    self.born_or_kill(true, pos[0], pos[1]);
+
+                    if self.is_erasing() {
+                        self.erase_at(pos[0], pos[1]);
+                    } else if self.is_double_click(pos[0], pos[1]) {
+                        // the second click only centers, the cell isn't edited again
+                        self.center_on(pos[0], pos[1]);
+                    } else {
+                        self.born_or_kill(true, pos[0], pos[1]);
+                    }
 
                     cur_state.set(States::Paused);
                 }
             }
 
             &Event::Input(Input::Move(Motion::MouseCursor(x, y))) => {
-                if cur_state.get() == States::Draw {
+                if let Some((start_col, start_row)) = self.selection_start {
+                    let (col, row) = self.to_logical(x, y);
+                    self.selection.set(Some(Rect::new(start_col, start_row, col, row)));
+                } else if cur_state.get() == States::Draw && !self.is_erasing() {
                     self.born_or_kill(false, x, y);
+                } else if cur_state.get() == States::Draw || self.erase_drag {
+                    self.erase_at(x, y);
                 }
                 self.last_pos = Some([x, y]);
             }
@@ -184,6 +471,11 @@ impl<'a> WindowBase for GameBoard<'a> {
                 self.cam.zoom_out();
             }
 
+            // with shift + and - change the simulation rate instead
+            &Event::Input(Input::Press(Button::Keyboard(Key::Minus))) if self.modifiers.shift() => {
+                self.engine.borrow_mut().slower_ups();
+            }
+
             &Event::Input(Input::Press(Button::Keyboard(Key::Minus))) => {
                 self.cam.zoom_out();
             }
@@ -194,6 +486,10 @@ impl<'a> WindowBase for GameBoard<'a> {
                 self.cam.zoom_in();
             }
 
+            &Event::Input(Input::Press(Button::Keyboard(Key::Equals))) if self.modifiers.shift() => {
+                self.engine.borrow_mut().faster_ups();
+            }
+
             // use "Equals" instead of "Plus" to avoid holding shift key requirement
             &Event::Input(Input::Press(Button::Keyboard(Key::Equals))) => {
                 self.cam.zoom_in();
@@ -209,7 +505,7 @@ impl<'a> WindowBase for GameBoard<'a> {
                 }
             }
 
-            &Event::Input(Input::Press(Button::Keyboard(Key::F))) => {
+            &Event::Input(Input::Press(Button::Keyboard(Key::F))) if !self.modifiers.ctrl() => {
                 // reset camera coordinates to defaults
                 self.cam.reset();
             }
@@ -223,6 +519,116 @@ impl<'a> WindowBase for GameBoard<'a> {
 
     }
 
+    fn save_session(&self, container: &mut Container) {
+        let (x, y) = self.cam.get_position();
+
+        container.set_meta("cam.x", x);
+        container.set_meta("cam.y", y);
+        container.set_meta("cam.scale", self.cam.get_scale());
+    }
+
+    fn restore_session(&mut self, container: &Container) {
+        let get = |key: &str| container.get_meta(key).and_then(|v| v.parse::<f64>().ok());
+
+        if let (Some(x), Some(y)) = (get("cam.x"), get("cam.y")) {
+            self.cam.set_position(x, y);
+        }
+        if let Some(scale) = get("cam.scale") {
+            self.cam.set_scale(scale);
+        }
+
+        self.selection.set(None);
+        self.paste_mode = None;
+    }
+
+    fn on_pattern_loaded(&mut self, rect: &Rect) {
+        self.cam.fit(&self.cell, rect, self.window.get_width(), self.window.get_height(), FIT_MARGIN);
+    }
+
+    fn set_gpu(&mut self, gpu: bool) {
+        if gpu && cfg!(not(feature = "gpu")) {
+            log::write("GPU stepping needs the program built with --features gpu");
+        }
+        self.gpu_enabled = gpu && cfg!(feature = "gpu");
+        self.gpu_obstacle = None;
+        if !self.gpu_enabled {
+            self.catch_up();
+        }
+    }
+
+    fn catch_up(&mut self) {
+        self.sync_gpu();
+        self.gpu_progress.set(None);
+    }
+
+    fn take_notice(&mut self) -> Option<String> {
+        self.notice.take()
+    }
+
+    fn set_zoom(&mut self, scale: f64) {
+        // cell in the middle of the screen stays there
+        let (col, row) = self.to_logical(self.window.get_half_width(), self.window.get_half_height());
+
+        self.cam.set_scale(scale);
+        self.cam.center(&self.cell, &Rect::new(col, row, col, row));
+    }
+
+    fn set_speed(&mut self, generations: u64) {
+        if generations != self.speed {
+            self.engine.borrow_mut().record_event(EventKind::SpeedChanged);
+        }
+        self.speed = generations;
+    }
+
+    fn render_to_image(&self, whole_board: bool) -> Option<Canvas> {
+        let engine = self.engine.borrow();
+
+        let (transform, width, height) = if whole_board {
+            // the selection if there is one, all live cells otherwise
+            let rect = match self.selection.get().or(engine.get_board().get_bounding_box()) {
+                Some(rect) => rect,
+                None => return None
+            };
+
+            // cells are as large as on the screen unless the image gets too big
+            let cell_size = self.get_transform().get_cell_width();
+            let shrink = (MAX_IMAGE_SIZE / (rect.get_width().max(rect.get_height()) as f64 * cell_size)).min(1.0);
+
+            let mut cam = Cam::new(0.0, 0.0);
+            cam.set_scale(self.cam.get_scale() * shrink);
+            cam.center(&self.cell, &rect);
+
+            let width = (rect.get_width() as f64 * self.cell.get_width(&cam)).ceil().max(1.0);
+            let height = (rect.get_height() as f64 * self.cell.get_height(&cam)).ceil().max(1.0);
+
+            (Transform::new(&cam, &self.cell, width, height), width, height)
+        } else {
+            (self.get_transform(), self.window.get_width(), self.window.get_height())
+        };
+
+        let palette = self.rules.borrow().get_palette(&engine.get_rule());
+        let mut canvas = Canvas::new(width as usize, height as usize, palette.background);
+
+        for quad in render::render_cells(&engine.snapshot().cells, &transform, &palette, width, height) {
+            canvas.fill(&quad);
+        }
+
+        Some(canvas)
+    }
+
+    fn render_to_svg(&self, writer: &mut Write) -> Option<io::Result<()>> {
+        let engine = self.engine.borrow();
+
+        let rect = match self.selection.get().or(engine.get_board().get_bounding_box()) {
+            Some(rect) => rect,
+            None => return None
+        };
+
+        // grid is there if it is shown on the screen
+        let palette = self.rules.borrow().get_palette(&engine.get_rule());
+        Some(svg::write_svg(&engine.snapshot().cells, &rect, &palette, self.show_grid, writer))
+    }
+
 }
 
 impl<'a> GameBoard<'a> {
@@ -271,71 +677,321 @@ impl<'a> GameBoard<'a> {
         } else { self.window.get_height() }
     }
 
-    fn to_logical(&self, x: f64, y: f64) -> (isize, isize) {
-        let (x, y) = self.cam.translate_inv(x, y);
-
-        let mut offset_x = x - self.window.get_half_width();
-        let mut offset_y = y - self.window.get_half_height();
-
-        // TODO: Ensure this needed
+    fn refresh_snapshot(&mut self) {
+        let engine = self.engine.borrow();
 
-        if offset_x < 0.0 {
-            offset_x -= self.cell.get_half_width(&self.cam);
-        } else if offset_x > 0.0 {
-            offset_x += self.cell.get_half_width(&self.cam);
-        }
-
-        if offset_y < 0.0 {
-            offset_y -= self.cell.get_half_height(&self.cam);
-        } else if offset_y > 0.0 {
-            offset_y += self.cell.get_half_height(&self.cam);
+        if self.snapshot.as_ref().map_or(true, |snapshot| snapshot.is_outdated(&engine)) {
+            self.snapshot = Some(engine.snapshot());
         }
+    }
 
-        let col = (offset_x / self.cell.get_width(&self.cam)) as isize;
-        let row = (offset_y / self.cell.get_height(&self.cam)) as isize;
+    fn get_transform(&self) -> Transform {
+        Transform::new(&self.cam, &self.cell, self.window.get_width(), self.window.get_height())
+    }
 
-        (col, row)
+    fn to_logical(&self, x: f64, y: f64) -> (i64, i64) {
+        self.get_transform().to_logical(x, y)
     }
 
-    fn to_screen(&self, col: isize, row: isize) -> (f64, f64) {
+    fn to_screen(&self, col: i64, row: i64) -> (f64, f64) {
         // converts from logical board coordinates into screen coordinates
         // taking into account current camera position and scale
+        self.get_transform().to_screen(col, row)
+    }
+
+    fn is_double_click(&mut self, x: f64, y: f64) -> bool {
+        // remembers the click and checks whether it was the second one
+        // on the same cell within a short time interval
+        let (col, row) = self.to_logical(x, y);
+        let now = Instant::now();
+
+        let is_double = match self.last_click {
+            Some((time, last_col, last_row)) => {
+                last_col == col && last_row == row &&
+                    now - time <= Duration::from_millis(DOUBLE_CLICK_INTERVAL_MS)
+            }
+            None => false
+        };
 
-        // suppose that screen center goes through the center of a cell
-        // with coordinates (0, 0)
-        //
-        //               ^
-        //               |
-        //               |
-        //              [|] - - - >
+        self.last_click = if is_double { None } else { Some((now, col, row)) };
 
-        let x = col as f64 * self.cell.get_width(&self.cam) + self.window.get_half_width() -
-            self.cell.get_half_width(&self.cam);
+        is_double
+    }
+
+    fn center_on(&mut self, x: f64, y: f64) {
+        // smoothly move camera so that the cell under the given
+        // screen coordinates appears in the center of the screen
+        let (col, row) = self.to_logical(x, y);
+
+        self.cam.move_to(-(col as f64) * self.cell.get_width(&self.cam),
+                         -(row as f64) * self.cell.get_height(&self.cam));
+    }
 
-        let y = row as f64 * self.cell.get_height(&self.cam) + self.window.get_half_height() -
-            self.cell.get_half_height(&self.cam);
+    fn move_to_rect(&mut self, rect: &Rect) {
+        // smoothly move camera to the middle of the region, keeping the scale
+        let (mid_col, mid_row) = (0.5 * (rect.left + rect.right) as f64, 0.5 * (rect.top + rect.bottom) as f64);
 
-        self.cam.translate(x, y)
+        self.cam.move_to(-mid_col * self.cell.get_width(&self.cam),
+                         -mid_row * self.cell.get_height(&self.cam));
     }
 
     fn born_or_kill(&mut self, kill_alive: bool, x: f64, y: f64) {
         let (col, row) = self.to_logical(x, y);
         let mut engine = self.engine.borrow_mut();
 
+        let policy = engine.get_edge_policy();
         let board = engine.get_board_mut();
 
-        if kill_alive && board.is_alive(col, row) {
-            board.kill_at(col, row);
+        // drawing past a rejecting edge just does nothing
+        let _ = if kill_alive && board.is_alive(col, row) {
+            board.try_kill_at(col, row, policy)
         } else {
-            board.born_at(col, row);
+            board.try_born_at(col, row, policy)
+        };
+    }
+
+    fn take_ticks(&mut self) -> u64 {
+        let ticks_per_second = match self.engine.borrow().get_ups() {
+            Some(ups) => ups,
+            None => return 1
+        };
+
+        let now = Instant::now();
+        // a tick of one update per second is a whole second, not nanoseconds
+        let tick_nanos = 1_000_000_000 / ticks_per_second as u64;
+        let tick = Duration::new(tick_nanos / 1_000_000_000, (tick_nanos % 1_000_000_000) as u32);
+
+        let elapsed = now - self.last_iter_time;
+        let ticks = (elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64) / tick_nanos;
+
+        if ticks > MAX_CATCH_UP_TICKS {
+            self.last_iter_time = now;
+            1
+        } else {
+            // the part of a tick left over counts towards the next one
+            self.last_iter_time += tick * ticks as u32;
+            ticks
+        }
+    }
+
+    // why the generations can't be computed on the GPU, if they can't
+    fn get_gpu_obstacle(&self) -> Option<&'static str> {
+        let engine = self.engine.borrow();
+        let board = engine.get_board();
+
+        // the shader knows only single B/S rules on wrapping boards, the timeline
+        // needs every generation on the board, not just the last one of a paint
+        if board.get_cols().is_none() || board.get_rows().is_none() {
+            Some("the board is unbounded")
+        } else if engine.get_rule_table().is_some() {
+            Some("the rule is a rule table")
+        } else if !engine.get_zones().is_empty() {
+            Some("the board has rule zones")
+        } else if engine.get_timeline().is_some() {
+            Some("the timeline is recorded")
+        } else {
+            None
+        }
+    }
+
+    fn can_use_gpu(&self) -> bool {
+        self.gpu_enabled && self.render && self.get_gpu_obstacle().is_none()
+    }
+
+    // the GPU was asked for, so whatever keeps it from being used is told, once
+    fn report_gpu_obstacle(&mut self) {
+        let obstacle = self.get_gpu_obstacle();
+        if obstacle.is_some() && obstacle != self.gpu_obstacle {
+            self.notice = obstacle.map(|obstacle| format!("Generations are computed on the CPU: {}", obstacle));
+        }
+        self.gpu_obstacle = obstacle;
+    }
+
+    #[cfg(feature = "gpu")]
+    fn run_gpu(&mut self, g: &mut GlGraphics) {
+        if self.gpu_pending == 0 {
+            return;
+        }
+
+        let steps = self.gpu_pending;
+        self.gpu_pending = 0;
+
+        let bounds = self.engine.borrow().get_board().get_bounds();
+
+        // board has been resized
+        if self.gpu.as_ref().map_or(false, |run| *run.stepper.get_bounds() != bounds) {
+            self.gpu = None;
+        }
+
+        if self.gpu.is_none() {
+            match GpuStepper::new(&bounds) {
+                Ok(stepper) => self.gpu = Some(GpuRun {
+                    stepper: stepper,
+                    version: None,
+                    stats: Vec::new(),
+                    cells: Vec::new(),
+                    synced: Instant::now(),
+                }),
+                Err(err) => {
+                    self.notice = Some(format!("GPU stepping is turned off: {}", err));
+                    self.gpu_enabled = false;
+                    self.engine.borrow_mut().iterations(steps);
+                    return;
+                }
+            }
+        }
+
+        let due = match self.gpu {
+            Some(ref mut run) => {
+                let engine = self.engine.borrow();
+
+                // cells were edited, loaded or stepped on the CPU since the last upload
+                if run.version != Some(engine.get_version()) {
+                    run.stepper.upload(engine.get_board());
+                    run.version = Some(engine.get_version());
+                    run.stats.clear();
+                }
+
+                let stats = run.stepper.step(&engine.get_rule(), steps as usize);
+                g.clear_program();
+                run.stats.extend(stats);
+
+                let viewport = self.viewport.get().unwrap_or(bounds);
+                run.cells = run.stepper.read_region(&viewport).into_iter()
+                    .map(|(coord, gen)| CellDesc { coord: coord, gen: gen, is_alive: true, new_line: false })
+                    .collect();
+
+                let population = run.stats.last().map_or(0, |stat| stat.population);
+                self.gpu_progress.set(Some((engine.cur_iteration() + run.stats.len(), population)));
+
+                run.synced.elapsed() >= Duration::from_millis(GPU_SYNC_INTERVAL_MS)
+            },
+            None => false
+        };
+
+        if due {
+            self.catch_up();
         }
     }
 
-    fn get_color(gen: usize) -> [f32; 4] {
-        let r = 1.0_f64.min(50.0*gen as f64/256.0);
-        [r as f32, 1.0 - r as f32, 0.0, 0.5]
+    #[cfg(feature = "gpu")]
+    fn sync_gpu(&mut self) {
+        if let Some(ref mut run) = self.gpu {
+            if !run.stats.is_empty() {
+                let mut engine = self.engine.borrow_mut();
+
+                // otherwise the engine has moved on without the GPU, e.g. to a loaded pattern
+                if run.version == Some(engine.get_version()) {
+                    let cells = run.stepper.read_region(run.stepper.get_bounds());
+                    engine.apply_generations(&cells, &run.stats);
+                    run.version = Some(engine.get_version());
+                }
+
+                run.stats.clear();
+            }
+            run.synced = Instant::now();
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    fn get_gpu_cells(&self) -> Option<&[CellDesc]> {
+        match self.gpu {
+            Some(ref run) if !run.stats.is_empty() => Some(&run.cells[..]),
+            _ => None
+        }
     }
 
+    #[cfg(not(feature = "gpu"))]
+    fn run_gpu(&mut self, _g: &mut GlGraphics) {}
+
+    #[cfg(not(feature = "gpu"))]
+    fn sync_gpu(&mut self) {}
+
+    #[cfg(not(feature = "gpu"))]
+    fn get_gpu_cells(&self) -> Option<&[CellDesc]> {
+        None
+    }
+
+    fn run_for(&mut self, seconds: f64) {
+        // at least one generation, even if it takes longer than the budget
+        let mut engine = self.engine.borrow_mut();
+        let mut spent = 0.0;
+
+        loop {
+            spent += engine.iterations(1);
+            if spent >= seconds {
+                break;
+            }
+        }
+
+        self.last_iter_time = Instant::now();
+    }
+
+    fn get_edge_scroll_speed(pos: f64, size: f64) -> f64 {
+        // negative near the start of the axis, positive near its end
+        if pos < EDGE_SCROLL_ZONE {
+            -EDGE_SCROLL_SPEED * (1.0 - pos.max(0.0) / EDGE_SCROLL_ZONE)
+        } else if pos > size - EDGE_SCROLL_ZONE {
+            EDGE_SCROLL_SPEED * (1.0 - (size - pos).max(0.0) / EDGE_SCROLL_ZONE)
+        } else {
+            0.0
+        }
+    }
+
+    fn edge_scroll(&mut self, dt: f64) {
+        // only selecting and pasting may need more room than the screen has
+        if self.selection_start.is_none() && self.paste_mode.is_none() {
+            return;
+        }
+
+        let pos = match self.last_pos {
+            Some(pos) => pos,
+            None => return
+        };
+
+        let dx = GameBoard::get_edge_scroll_speed(pos[0], self.window.get_width()) * dt;
+        let dy = GameBoard::get_edge_scroll_speed(pos[1], self.window.get_height()) * dt;
+
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+
+        self.cam.pan(dx, dy);
+
+        // the mouse stays still, but the cell under it changes
+        if let Some((start_col, start_row)) = self.selection_start {
+            let (col, row) = self.to_logical(pos[0], pos[1]);
+            self.selection.set(Some(Rect::new(start_col, start_row, col, row)));
+        }
+    }
+
+    fn is_erasing(&self) -> bool {
+        self.erase_key || self.erase_drag
+    }
+
+    fn erase_at(&mut self, x: f64, y: f64) {
+        let (col, row) = self.to_logical(x, y);
+        let mut engine = self.engine.borrow_mut();
+
+        let policy = engine.get_edge_policy();
+        let _ = engine.get_board_mut().try_kill_at(col, row, policy);
+    }
+
+   fn draw_erase_cursor(&self, c: &Context, g: &mut GlGraphics) {
+
+       let pos = match self.last_pos {
+           Some(pos) if self.is_erasing() => pos,
+           _ => return
+       };
+
+       // the cell under the mouse cursor is marked red
+       let (col, row) = self.to_logical(pos[0], pos[1]);
+       let (x, y) = self.to_screen(col, row);
+
+       rectangle([1.0, 0.0, 0.0, 0.4], [x, y, self.cell.get_width(&self.cam), self.cell.get_height(&self.cam)],
+                 c.transform, g);
+   }
+
    fn draw_borders(&self, c: &Context, g: &mut GlGraphics) {
 
         // draw borders
@@ -374,6 +1030,80 @@ impl<'a> GameBoard<'a> {
         }
    }
 
+   fn draw_zones(&self, c: &Context, g: &mut GlGraphics) {
+
+       // outlined in the colors of their rules
+       let engine = self.engine.borrow();
+       let rules = self.rules.borrow();
+
+       for zone in engine.get_zones().get_zones() {
+           let color = rules.get_palette(&zone.rule).get_cell_color(1);
+           let (left, top) = self.to_screen(zone.rect.left, zone.rect.top);
+           let (right, bottom) = self.to_screen(zone.rect.right + 1, zone.rect.bottom + 1);
+
+           for border in &[[left, top, right, top], [right, top, right, bottom],
+                           [right, bottom, left, bottom], [left, bottom, left, top]] {
+               line(color, 0.5, *border, c.transform, g);
+           }
+       }
+   }
+
+   fn draw_selection(&self, c: &Context, g: &mut GlGraphics) {
+
+       if let Some(rect) = self.selection.get() {
+           let (left, top) = self.to_screen(rect.left, rect.top);
+           let (right, bottom) = self.to_screen(rect.right + 1, rect.bottom + 1);
+
+           for border in &[[left, top, right, top], [right, top, right, bottom],
+                           [right, bottom, left, bottom], [left, bottom, left, top]] {
+               line(super::BLUE, 0.5, *border, c.transform, g);
+           }
+       }
+   }
+
+   fn draw_paste_preview(&self, c: &Context, g: &mut GlGraphics) {
+
+       let (mode, pos) = match (self.paste_mode, self.last_pos) {
+           (Some(mode), Some(pos)) => (mode, pos),
+           _ => return
+       };
+
+       let (col, row) = self.to_logical(pos[0], pos[1]);
+       let clipboard = self.clipboard.borrow();
+
+       let color = match mode {
+           CombineMode::Or => [0.0, 1.0, 1.0, 0.5],
+           CombineMode::Xor => [1.0, 1.0, 0.0, 0.5],
+           CombineMode::Overwrite => [1.0, 0.0, 1.0, 0.5],
+       };
+
+       if mode == CombineMode::Overwrite {
+           // show the region which is going to be cleared
+           let (right, bottom) = clipboard.get_bounding_box().map_or((0, 0), |rect| (rect.right, rect.bottom));
+
+           let (left, top) = self.to_screen(col, row);
+           let (right, bottom) = self.to_screen(col + right + 1, row + bottom + 1);
+
+           rectangle([1.0, 0.0, 1.0, 0.15], [left, top, right - left, bottom - top], c.transform, g);
+       }
+
+       for cell in clipboard.get_cells() {
+           let (x, y) = self.to_screen(col + cell.col, row + cell.row);
+           rectangle(color, [x, y, self.cell.get_width(&self.cam), self.cell.get_height(&self.cam)],
+                     c.transform, g);
+       }
+
+       // highlight existing cells which are going to be affected
+       let conflicts = self.engine.borrow().get_board()
+           .get_paste_conflicts(clipboard.get_cells(), col, row, mode);
+
+       for cell in conflicts {
+           let (x, y) = self.to_screen(cell.col, cell.row);
+           rectangle(super::RED, [x, y, self.cell.get_width(&self.cam), self.cell.get_height(&self.cam)],
+                     c.transform, g);
+       }
+   }
+
    fn draw_grid(&self, c: &Context, g: &mut GlGraphics) {
 
        let right_offset_x = self.get_right_border();
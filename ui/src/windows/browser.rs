@@ -0,0 +1,472 @@
+// Pattern browser, list of built-in patterns and the ones found in the
+// pattern folders to stamp onto the board, recently used first, filtered
+// by favorites or tags
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, WindowLayout, PostAction, States, Modifiers};
+use super::chrome::Chrome;
+use super::Resources;
+use super::super::rules::RuleRegistry;
+use super::super::library;
+use super::super::library::Filter;
+use super::super::config::Config;
+
+use self::engine::engine::Engine;
+use self::engine::board::{Rect, CombineMode};
+use self::engine::io::rle::RleFile;
+use self::engine::pattern::Pattern;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+const WIDTH: f64 = 480.0;
+const HEIGHT: f64 = 300.0;
+
+const ROW_HEIGHT: f64 = 20.0;
+
+const PREVIEW_SIZE: f64 = 144.0;
+const MAX_PREVIEW_CELL: f64 = 12.0;
+
+
+pub struct PatternBrowserWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    rules: Rc<RefCell<RuleRegistry>>,
+    resources: Rc<RefCell<Resources>>,
+    viewport: Rc<Cell<Option<Rect>>>,
+    config: Rc<RefCell<Config>>,
+
+    chrome: Chrome,
+    modifiers: Modifiers,
+
+    items: Vec<library::Item>,
+    filter: Filter,
+    // indices of patterns passing the filter in the order they are listed
+    visible: Vec<usize>,
+    // position in the visible list
+    selected: usize,
+
+    // tags of the selected pattern being typed
+    tags_edit: Option<String>,
+    error: Option<String>,
+}
+
+impl<'a> PatternBrowserWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>, rules: Rc<RefCell<RuleRegistry>>,
+               viewport: Rc<Cell<Option<Rect>>>,
+               config: Rc<RefCell<Config>>) -> PatternBrowserWindow<'a> {
+
+        PatternBrowserWindow {
+            engine: engine,
+            rules: rules,
+            resources: resources,
+            viewport: viewport,
+            config: config,
+
+            chrome: Chrome::new("library",
+                                WindowLayout::new(0.5 * (window.get_width() - WIDTH),
+                                                  0.5 * (window.get_height() - HEIGHT),
+                                                  WIDTH, HEIGHT, false)),
+            modifiers: Modifiers::new(),
+
+            // the folders are searched when the window is opened
+            items: Vec::new(),
+            filter: Filter::All,
+            visible: Vec::new(),
+            selected: 0,
+
+            tags_edit: None,
+            error: None,
+        }
+    }
+
+    fn set_open(&mut self, open: bool) {
+        if open {
+            self.items = library::load_all(&self.config.borrow());
+            self.set_filter(Filter::All);
+        }
+
+        self.chrome.set_open(open);
+        self.tags_edit = None;
+        self.error = None;
+    }
+
+    // as many rows as fit between the title and the info lines
+    fn get_list_rows(&self) -> usize {
+        let height = self.chrome.get_content_rect()[3];
+        ((height - 100.0) / ROW_HEIGHT).max(1.0) as usize
+    }
+
+    fn get_selected(&self) -> Option<&library::Item> {
+        self.visible.get(self.selected).map(|&idx| &self.items[idx])
+    }
+
+    /// Pattern files are read only when they are shown or placed
+    fn load_selected(&mut self) -> Option<Result<&RleFile, String>> {
+        match self.visible.get(self.selected) {
+            Some(&idx) => Some(self.items[idx].get_pattern()),
+            None => None
+        }
+    }
+
+    fn get_filters(&self) -> Vec<Filter> {
+        let mut res = vec![Filter::All, Filter::Favorites];
+        res.extend(library::get_all_tags(&self.items, &self.config.borrow()).into_iter().map(Filter::Tag));
+        res
+    }
+
+    fn set_filter(&mut self, filter: Filter) {
+        self.visible = library::select(&self.items, &self.config.borrow(), &filter);
+        self.filter = filter;
+        self.selected = 0;
+    }
+
+    fn next_filter(&mut self, forward: bool) {
+        let filters = self.get_filters();
+        let pos = filters.iter().position(|filter| *filter == self.filter).unwrap_or(0);
+
+        let next = if forward { (pos + 1) % filters.len() } else { (pos + filters.len() - 1) % filters.len() };
+        self.set_filter(filters[next].clone());
+    }
+
+    /// Changes the config and saves it, the list keeps its order
+    /// until the filter changes, so the selection doesn't jump
+    fn update_config<F: FnOnce(&mut Config, &str)>(&mut self, change: F) {
+        let id = match self.get_selected() {
+            Some(item) => item.get_id(),
+            None => return
+        };
+
+        let mut config = self.config.borrow_mut();
+        change(&mut config, &id);
+
+        self.error = config.save().err().map(|err| format!("unable to save config: {}", err));
+    }
+
+    fn toggle_favorite(&mut self) {
+        self.update_config(|config, id| {
+            let favorite = library::is_favorite(config, id);
+            library::set_favorite(config, id, !favorite);
+        });
+    }
+
+    fn edit_tags(&mut self) {
+        let tags = match self.get_selected() {
+            Some(item) => library::get_tags(&self.config.borrow(), &item.get_id()).join(", "),
+            None => return
+        };
+        self.tags_edit = Some(tags);
+    }
+
+    fn save_tags(&mut self) {
+        if let Some(tags) = self.tags_edit.take() {
+            self.update_config(|config, id| library::set_tags(config, id, &library::parse_tags(&tags)));
+        }
+    }
+
+    fn get_title(&self) -> String {
+        match self.filter {
+            Filter::All => "Pattern library".to_string(),
+            Filter::Favorites => "Pattern library: favorites".to_string(),
+            Filter::Tag(ref tag) => format!("Pattern library: {}", tag),
+        }
+    }
+
+    fn get_size(pattern: &RleFile) -> (i64, i64) {
+        let pattern = Pattern::new(&pattern.cells);
+        (pattern.get_width() as i64, pattern.get_height() as i64)
+    }
+
+    /// Returns false if there is nothing to place or it's rejected at the board edge
+    fn stamp(&mut self) -> bool {
+        let pattern = match self.load_selected() {
+            Some(Ok(pattern)) => Pattern::new(&pattern.cells),
+            _ => return false
+        };
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+        self.update_config(|config, id| library::set_last_used(config, id, timestamp));
+
+        let (width, height) = (pattern.get_width() as i64, pattern.get_height() as i64);
+
+        // pattern is centered on the visible part of the board
+        let (col, row) = match self.viewport.get() {
+            Some(rect) => (rect.left + (rect.right - rect.left) / 2, rect.top + (rect.bottom - rect.top) / 2),
+            None => (0, 0)
+        };
+
+        let mut engine = self.engine.borrow_mut();
+        let policy = engine.get_edge_policy();
+        engine.get_board_mut()
+            .try_paste(pattern.get_cells(), col - width / 2, row - height / 2, CombineMode::Or, policy)
+            .is_ok()
+    }
+
+    fn paint_preview(&self, c: &Context, g: &mut GlGraphics, x: f64, y: f64) {
+        let palette = self.rules.borrow().get_palette(&self.engine.borrow().get_rule());
+        rectangle(palette.background, [x, y, PREVIEW_SIZE, PREVIEW_SIZE], c.transform, g);
+
+        let pattern = match self.get_selected().and_then(|item| item.get_loaded()) {
+            Some(pattern) => pattern,
+            None => return
+        };
+        let (width, height) = PatternBrowserWindow::get_size(pattern);
+
+        let cell_size = (PREVIEW_SIZE / width.max(height) as f64).min(MAX_PREVIEW_CELL);
+        let (x, y) = (x + 0.5 * (PREVIEW_SIZE - width as f64 * cell_size),
+                      y + 0.5 * (PREVIEW_SIZE - height as f64 * cell_size));
+
+        for cell in pattern.cells.iter() {
+            rectangle(palette.young,
+                      [x + cell.col as f64 * cell_size, y + cell.row as f64 * cell_size, cell_size, cell_size],
+                      c.transform, g);
+        }
+    }
+
+}
+
+impl<'a> WindowBase for PatternBrowserWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        if !self.chrome.is_open() {
+            return;
+        }
+
+        let load_error = match self.load_selected() {
+            Some(Err(err)) => Some(err),
+            _ => None
+        };
+
+        self.chrome.paint(&c, g, &self.resources);
+
+        let rect = self.chrome.get_content_rect();
+        let (x, y, width, height) = (rect[0], rect[1], rect[2], rect[3]);
+        let rows = self.get_list_rows();
+
+        text(super::WHITE, 15, &self.get_title(),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        // list is scrolled to keep the highlighted pattern visible
+        let first = if self.selected >= rows { self.selected + 1 - rows } else { 0 };
+        let list_y = y + 45.0;
+
+        for (row, &idx) in self.visible.iter().enumerate().skip(first).take(rows) {
+            let item = &self.items[idx];
+            let row_y = list_y + (row - first) as f64 * ROW_HEIGHT;
+
+            if row == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, (width - PREVIEW_SIZE - 35.0).max(0.0), ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            let star = if library::is_favorite(&self.config.borrow(), &item.get_id()) { "* " } else { "" };
+            text(super::WHITE, 13, &format!("{}{}", star, item.name),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 15.0).transform, g);
+
+            // size is known once the file has been read
+            if let Some(pattern) = item.get_loaded() {
+                let (width, height) = PatternBrowserWindow::get_size(pattern);
+                text(super::GRAY, 13, &format!("{}x{}", width, height),
+                     &mut self.resources.borrow_mut().font,
+                     c.trans(x + 230.0, row_y + 15.0).transform, g);
+            }
+        }
+
+        // typed tags, an error, the tags or the comment of the selected pattern
+        let error = self.error.as_ref().or(load_error.as_ref());
+        let (color, info) = match (&self.tags_edit, error, self.get_selected()) {
+            (&Some(ref tags), _, _) => (super::WHITE, format!("Tags: {}_", tags)),
+            (_, Some(error), _) => (super::RED, error.clone()),
+            (_, _, Some(item)) => {
+                let tags = library::get_tags(&self.config.borrow(), &item.get_id());
+                if tags.is_empty() {
+                    let comment = item.get_loaded().and_then(|pattern| pattern.comments.first().cloned());
+                    (super::GRAY, comment.unwrap_or(String::new()))
+                } else {
+                    (super::GRAY, format!("Tags: {}", tags.join(", ")))
+                }
+            }
+            _ => (super::GRAY, "No patterns here, Left/Right to show others".to_string())
+        };
+
+        text(color, 12, &info,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + height - 35.0).transform, g);
+
+        let help = if self.tags_edit.is_some() {
+            "Type tags separated by commas, Enter to save, Escape to cancel"
+        } else {
+            "Enter to place, F favorite, Ctrl+T tags, Left/Right filter, Ctrl+L to close"
+        };
+
+        text(super::GREEN, 12, help,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + height - 15.0).transform, g);
+
+        self.paint_preview(&c, g, x + width - PREVIEW_SIZE - 15.0, list_y);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        if self.chrome.event_dispatcher(event) {
+            return PostAction::Stop;
+        }
+
+        if let &Event::Input(Input::Press(Button::Keyboard(Key::L))) = event {
+            if self.modifiers.ctrl() {
+                // the opened list takes the keys at once
+                let open = !self.chrome.is_open();
+                self.set_open(open);
+                self.chrome.set_focused(open);
+                return PostAction::Stop;
+            }
+        }
+
+        // the keys go to the board and the other windows unless the list is focused,
+        // the simulation goes on behind the library
+        if !self.takes_keys() {
+            return PostAction::Transfer;
+        }
+
+        if self.tags_edit.is_some() {
+            return self.edit_dispatcher(event);
+        }
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                // stamped pattern is shown before it starts evolving
+                if self.stamp() {
+                    cur_state.set(States::Paused);
+                    self.set_open(false);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    self.selected -= 1;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < self.visible.len() {
+                    self.selected += 1;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Left))) => self.next_filter(false),
+            &Event::Input(Input::Press(Button::Keyboard(Key::Right))) => self.next_filter(true),
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::F))) => self.toggle_favorite(),
+
+            // Tab moves the focus, plain letters would be typed into the tags
+            &Event::Input(Input::Press(Button::Keyboard(Key::T))) if self.modifiers.ctrl() => self.edit_tags(),
+
+            &Event::Input(Input::Press(_)) | &Event::Input(Input::Release(_)) |
+            &Event::Input(Input::Text(_)) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn takes_keys(&self) -> bool {
+        self.chrome.is_focused()
+    }
+
+    fn is_focusable(&self) -> bool {
+        self.chrome.is_open()
+    }
+
+    fn is_focused(&self) -> bool {
+        self.chrome.is_focused()
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.chrome.set_focused(focused);
+        if !focused {
+            self.tags_edit = None;
+        }
+    }
+
+    fn close(&mut self) -> PostAction {
+        // the window lives in the stack for the whole session, just hide it
+        self.set_open(false);
+        PostAction::Transfer
+    }
+
+    fn get_name(&self) -> Option<&'static str> {
+        Some("library")
+    }
+
+    fn get_layout(&self) -> Option<WindowLayout> {
+        Some(self.chrome.get_layout())
+    }
+
+    fn set_layout(&mut self, layout: WindowLayout) {
+        let was_open = self.chrome.is_open();
+        self.chrome.set_layout(layout);
+
+        // the folders are searched when the config reopens the window
+        if layout.open != was_open {
+            self.set_open(layout.open);
+        }
+    }
+
+    fn on_resize(&mut self, width: f64, height: f64) {
+        self.chrome.fit_into(width, height);
+    }
+
+}
+
+impl<'a> PatternBrowserWindow<'a> {
+
+    fn edit_dispatcher(&mut self, event: &Event) -> PostAction {
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => self.save_tags(),
+            &Event::Input(Input::Press(Button::Keyboard(Key::Escape))) => self.tags_edit = None,
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Backspace))) => {
+                if let Some(ref mut tags) = self.tags_edit {
+                    tags.pop();
+                }
+            }
+
+            // control keys come as text too
+            &Event::Input(Input::Text(ref s)) => {
+                if let Some(ref mut tags) = self.tags_edit {
+                    tags.extend(s.chars().filter(|ch| !ch.is_control()));
+                }
+            }
+
+            &Event::Input(Input::Press(_)) | &Event::Input(Input::Release(_)) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+}
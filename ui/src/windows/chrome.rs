@@ -0,0 +1,199 @@
+// Title bar and borders of auxiliary windows, allows
+// to move windows around and resize them with the mouse
+use piston_window::{Context, Event, Input, Button, MouseButton, Motion,
+                    Transformed, line, rectangle, text};
+
+use opengl_graphics::GlGraphics;
+
+use super::{WindowLayout, Resources};
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+
+const TITLE_HEIGHT: f64 = 18.0;
+const RESIZE_HANDLE_SIZE: f64 = 10.0;
+const MIN_WIDTH: f64 = 100.0;
+const MIN_HEIGHT: f64 = 50.0;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Drag {
+    Move,
+    Resize,
+    // mouse was pressed inside window contents
+    Content,
+}
+
+pub struct Chrome {
+    title: &'static str,
+
+    layout: WindowLayout,
+    focused: bool,
+
+    drag: Option<Drag>,
+    last_pos: [f64; 2],
+}
+
+impl Chrome {
+
+    pub fn new(title: &'static str, layout: WindowLayout) -> Self {
+        Chrome {
+            title: title,
+
+            layout: layout,
+            focused: false,
+
+            drag: None,
+            last_pos: [0.0, 0.0],
+        }
+    }
+
+    #[inline]
+    pub fn get_layout(&self) -> WindowLayout {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: WindowLayout) {
+        self.layout = WindowLayout {
+            width: layout.width.max(MIN_WIDTH),
+            height: layout.height.max(MIN_HEIGHT),
+            ..layout
+        };
+    }
+
+    pub fn fit_into(&mut self, width: f64, height: f64) {
+        // keep the window on the screen after it has shrunk,
+        // at least the title bar must stay reachable
+        self.layout.x = self.layout.x.min(width - MIN_WIDTH).max(0.0);
+        self.layout.y = self.layout.y.min(height - TITLE_HEIGHT).max(0.0);
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.layout.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.layout.open = open;
+        self.focused = self.focused && open;
+        if !open {
+            self.drag = None;
+        }
+    }
+
+    #[inline]
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn get_content_rect(&self) -> [f64; 4] {
+        // area available for window contents, below the title bar
+        [self.layout.x, self.layout.y + TITLE_HEIGHT,
+         self.layout.width, self.layout.height - TITLE_HEIGHT]
+    }
+
+    fn is_inside(&self, x: f64, y: f64) -> bool {
+        x >= self.layout.x && x < self.layout.x + self.layout.width &&
+            y >= self.layout.y && y < self.layout.y + self.layout.height
+    }
+
+    fn is_title(&self, x: f64, y: f64) -> bool {
+        self.is_inside(x, y) && y < self.layout.y + TITLE_HEIGHT
+    }
+
+    fn is_resize_handle(&self, x: f64, y: f64) -> bool {
+        self.is_inside(x, y) &&
+            x >= self.layout.x + self.layout.width - RESIZE_HANDLE_SIZE &&
+            y >= self.layout.y + self.layout.height - RESIZE_HANDLE_SIZE
+    }
+
+    pub fn paint(&self, c: &Context, g: &mut GlGraphics, resources: &Rc<RefCell<Resources>>) {
+
+        let WindowLayout { x, y, width, height, .. } = self.layout;
+
+        // background
+        rectangle([0.0, 0.0, 0.3, 0.7], [x, y, width, height], c.transform, g);
+
+        // title bar
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, width, TITLE_HEIGHT], c.transform, g);
+
+        text(super::WHITE, 12, self.title,
+             &mut resources.borrow_mut().font,
+             c.trans(x + 5.0, y + TITLE_HEIGHT - 5.0).transform, g);
+
+        // resize handle
+        let (right, bottom) = (x + width, y + height);
+
+        line(super::GRAY, 0.5,
+             [right - RESIZE_HANDLE_SIZE, bottom, right, bottom - RESIZE_HANDLE_SIZE],
+             c.transform, g);
+
+        if self.focused {
+            for border in &[[x, y, right, y], [right, y, right, bottom],
+                            [right, bottom, x, bottom], [x, bottom, x, y]] {
+                line(super::WHITE, 0.5, *border, c.transform, g);
+            }
+        }
+    }
+
+    pub fn event_dispatcher(&mut self, event: &Event) -> bool {
+        // returns true if event was consumed by the window chrome and
+        // thus shouldn't be passed to the windows below
+
+        if !self.layout.open {
+            return false;
+        }
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Mouse(MouseButton::Left))) => {
+                let (x, y) = (self.last_pos[0], self.last_pos[1]);
+
+                if self.is_resize_handle(x, y) {
+                    self.drag = Some(Drag::Resize);
+                } else if self.is_title(x, y) {
+                    self.drag = Some(Drag::Move);
+                } else if self.is_inside(x, y) {
+                    self.drag = Some(Drag::Content);
+                }
+
+                // clicks inside the window never reach the board
+                self.drag.is_some()
+            }
+
+            &Event::Input(Input::Release(Button::Mouse(MouseButton::Left))) => {
+                // release belongs to the window only if it was pressed here
+                let consumed = self.drag.is_some();
+                self.drag = None;
+                consumed
+            }
+
+            &Event::Input(Input::Move(Motion::MouseCursor(x, y))) => {
+                let (dx, dy) = (x - self.last_pos[0], y - self.last_pos[1]);
+                self.last_pos = [x, y];
+
+                match self.drag {
+                    Some(Drag::Move) => {
+                        self.layout.x += dx;
+                        self.layout.y += dy;
+                        true
+                    }
+                    Some(Drag::Resize) => {
+                        self.layout.width = (self.layout.width + dx).max(MIN_WIDTH);
+                        self.layout.height = (self.layout.height + dy).max(MIN_HEIGHT);
+                        true
+                    }
+                    Some(Drag::Content) | None => false
+                }
+            }
+
+            _ => false
+
+        }
+    }
+
+}
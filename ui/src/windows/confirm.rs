@@ -8,6 +8,9 @@ use opengl_graphics::GlGraphics;
 
 use self::engine::engine::Engine;
 use super::Resources;
+use super::super::log;
+use super::super::config::Config;
+use super::super::prompts;
 
 use std::rc::Rc;
 use std::cell::{RefCell, Cell};
@@ -21,7 +24,7 @@ pub enum UserChoice {
 pub struct ConfirmationWindow<'a, F>
     where F: FnMut(Rc<RefCell<Engine<'a>>>, UserChoice) {
 
-    msg: &'a str,
+    msg: String,
 
     scr_width: f64,
     scr_height: f64,
@@ -30,16 +33,20 @@ pub struct ConfirmationWindow<'a, F>
     resources: Rc<RefCell<Resources>>,
 
     callback: F,
+
+    // prompt which can be turned off and whether the user wants to
+    dont_ask: Option<(&'static str, Rc<RefCell<Config>>)>,
+    dont_ask_checked: bool,
 }
 
 impl<'a, F> ConfirmationWindow<'a, F>
     where F: FnMut(Rc<RefCell<Engine<'a>>>, UserChoice)  {
 
     pub fn new(resources: Rc<RefCell<Resources>>, engine: Rc<RefCell<Engine<'a>>>,
-               callback: F, msg: &'a str, width: f64, height: f64) -> Self {
+               callback: F, msg: &str, width: f64, height: f64) -> Self {
 
         ConfirmationWindow {
-            msg: msg,
+            msg: msg.to_string(),
 
             scr_width: width,
             scr_height: height,
@@ -47,8 +54,40 @@ impl<'a, F> ConfirmationWindow<'a, F>
             engine: engine,
             resources: resources,
 
-            callback: callback
+            callback: callback,
+
+            dont_ask: None,
+            dont_ask_checked: false,
+        }
+    }
+
+    /// Offers to turn the prompt off, see `prompts`
+    pub fn with_dont_ask(mut self, id: &'static str, config: Rc<RefCell<Config>>) -> Self {
+        self.dont_ask = Some((id, config));
+        self
+    }
+
+    fn get_prompt(&self) -> &'static str {
+        match (&self.dont_ask, self.dont_ask_checked) {
+            (&None, _) => "(Y/N)",
+            (&Some(_), false) => "(Y/N)   [ ] don't ask again (D)",
+            (&Some(_), true) => "(Y/N)   [x] don't ask again (D)",
+        }
+    }
+
+    fn confirm(&mut self) {
+        if let Some((id, ref config)) = self.dont_ask {
+            if self.dont_ask_checked {
+                let mut config = config.borrow_mut();
+                prompts::set_enabled(&mut config, id, false);
+
+                if let Err(err) = config.save() {
+                    log::write(&format!("unable to save config: {}", err));
+                }
+            }
         }
+
+        (self.callback)(self.engine.clone(), UserChoice::Ok);
     }
 
 }
@@ -65,9 +104,11 @@ impl<'a, F> WindowBase for ConfirmationWindow<'a, F> where F: FnMut(Rc<RefCell<E
 
         let (scr_width, scr_height) = (self.scr_width, self.scr_height);
         let resources = self.resources.clone();
+        let msg = self.msg.clone();
+        let prompt = self.get_prompt();
 
         self.paint_info_window(c, g, scr_width, scr_height,
-                               resources, self.msg, "(Y/N)");
+                               resources, &msg, prompt);
 
     }
 
@@ -76,10 +117,15 @@ impl<'a, F> WindowBase for ConfirmationWindow<'a, F> where F: FnMut(Rc<RefCell<E
         match event {
 
              &Event::Input(Input::Press(Button::Keyboard(Key::Y))) => {
-                 (self.callback)(self.engine.clone(), UserChoice::Ok);
+                 self.confirm();
                  PostAction::Pop
              }
 
+             &Event::Input(Input::Press(Button::Keyboard(Key::D))) if self.dont_ask.is_some() => {
+                 self.dont_ask_checked = !self.dont_ask_checked;
+                 PostAction::Stop
+             }
+
              &Event::Input(Input::Press(Button::Keyboard(Key::N))) => {
                  (self.callback)(self.engine.clone(), UserChoice::Cancel);
                  PostAction::Pop
@@ -95,4 +141,9 @@ impl<'a, F> WindowBase for ConfirmationWindow<'a, F> where F: FnMut(Rc<RefCell<E
         true
     }
 
+    fn on_resize(&mut self, width: f64, height: f64) {
+        self.scr_width = width;
+        self.scr_height = height;
+    }
+
 }
@@ -0,0 +1,165 @@
+// Demo mode, cycles through showcase patterns until interrupted, the
+// camera follows each of them as it moves and grows
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, text};
+
+use super::{WindowBase, PostAction, States};
+use super::Resources;
+use super::super::log;
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::io::container::Container;
+use self::engine::io::rle::decode_cells;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+// seconds each pattern is shown for
+const PATTERN_TIME: f64 = 20.0;
+
+const RULE: &'static str = "B3/S23";
+
+const PATTERNS: [(&'static str, &'static str); 5] = [
+    ("Gosper glider gun", "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+                           2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"),
+    ("R-pentomino", "b2o$2o$bo!"),
+    ("Acorn", "bo$3bo$2o2b3o!"),
+    ("Pulsar", "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$\
+                o4bobo4bo$o4bobo4bo2$2b3o3b3o!"),
+    ("Lightweight spaceship", "bo2bo$o$o3bo$4o!"),
+];
+
+pub struct DemoWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+    look_at: Rc<Cell<Option<Rect>>>,
+
+    // user's board, restored when the demo ends
+    saved: Container,
+    // the demo moves on to the next pattern by itself instead
+    auto_stop: bool,
+
+    pattern: Option<usize>,
+    elapsed: f64,
+    // the key which ended the demo is taken, the window goes on the next event
+    finished: bool,
+}
+
+impl<'a> DemoWindow<'a> {
+
+    pub fn new(resources: Rc<RefCell<Resources>>, engine: Rc<RefCell<Engine<'a>>>,
+               look_at: Rc<Cell<Option<Rect>>>) -> DemoWindow<'a> {
+
+        let saved = engine.borrow().to_container(true);
+        let auto_stop = engine.borrow().is_auto_stopping();
+        engine.borrow_mut().set_auto_stop(false);
+
+        DemoWindow {
+            engine: engine,
+            resources: resources,
+            look_at: look_at,
+
+            saved: saved,
+            auto_stop: auto_stop,
+
+            pattern: None,
+            elapsed: 0.0,
+            finished: false,
+        }
+
+    }
+
+    fn show_next(&mut self) {
+        let next = self.pattern.map_or(0, |idx| (idx + 1) % PATTERNS.len());
+
+        // patterns are centered at the origin, where the camera looks by default
+        let cells = decode_cells(PATTERNS[next].1).expect("malformed demo pattern");
+        self.engine.borrow_mut().set_pattern(&cells);
+
+        self.pattern = Some(next);
+        self.elapsed = 0.0;
+    }
+
+    fn restore(&mut self) {
+        let mut engine = self.engine.borrow_mut();
+        if let Err(err) = engine.load_container(&self.saved) {
+            log::write(&format!("unable to restore board after demo: {}", err));
+        }
+        engine.set_auto_stop(self.auto_stop);
+    }
+
+    fn follow(&mut self) {
+        // the board camera moves there smoothly, e.g. along with a spaceship
+        if let Some(rect) = self.engine.borrow().get_board().get_bounding_box() {
+            self.look_at.set(Some(rect));
+        }
+    }
+
+}
+
+impl<'a> WindowBase for DemoWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        if let (Some(idx), false) = (self.pattern, self.finished) {
+            text(super::WHITE, 20,
+                 &format!("{}, rule {}", PATTERNS[idx].0, RULE),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(10.0, 70.0).transform, g);
+
+            text(super::GRAY, 12, "demo mode, press any key to exit",
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(10.0, 90.0).transform, g);
+        }
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
+
+        if self.finished {
+            return PostAction::Pop;
+        }
+
+        match event {
+
+            &Event::Update(args) => {
+                self.elapsed += args.dt;
+
+                let extinct = self.engine.borrow().get_board().get_population() == 0;
+
+                if self.pattern.is_none() || self.elapsed >= PATTERN_TIME || extinct {
+                    self.show_next();
+                }
+
+                self.follow();
+                cur_state.set(States::Working);
+            }
+
+            // the key only ends the demo, it doesn't clear or randomize
+            // the board just restored
+            &Event::Input(Input::Press(_)) => {
+                self.restore();
+                cur_state.set(States::Paused);
+                self.finished = true;
+                return PostAction::Stop;
+            }
+
+            _ => {}
+
+        }
+
+        PostAction::Transfer
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
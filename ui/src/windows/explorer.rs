@@ -0,0 +1,228 @@
+// Rule explorer, the current pattern run under the current rule and every
+// rule one condition away from it, shown as thumbnails as the background
+// runs finish; a thumbnail can be explored further or its rule applied
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+
+use self::engine::engine::Engine;
+use self::engine::board::Coord;
+use self::engine::explore::{Explorer, Outcome, THUMBNAIL_SIZE};
+use self::engine::rule::Rule;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const COLUMNS: usize = 6;
+const THUMBNAIL: f64 = 96.0;
+// thumbnail with the labels under it
+const TILE_WIDTH: f64 = THUMBNAIL + 16.0;
+const TILE_HEIGHT: f64 = THUMBNAIL + 44.0;
+
+// generations every rule is run for
+const GENERATIONS: usize = 200;
+
+
+pub struct RuleExplorerWindow<'a> {
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    engine: Rc<RefCell<Engine<'a>>>,
+
+    modifiers: Modifiers,
+
+    // live cells when the explorer was opened
+    cells: Vec<Coord>,
+    // rule the others are mutations of
+    rule: Rule,
+    explorer: Explorer,
+    selected: usize,
+}
+
+fn describe(outcome: &Outcome) -> String {
+    if outcome.is_extinct() {
+        format!("dies at {}", outcome.generations)
+    } else if outcome.is_exploded() {
+        format!("explodes at {}", outcome.generations)
+    } else {
+        format!("{} cells", outcome.population)
+    }
+}
+
+impl<'a> RuleExplorerWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>) -> RuleExplorerWindow<'a> {
+
+        let (cells, rule) = {
+            let engine = engine.borrow();
+            (engine.get_board().get_occupied().iter().cloned().collect::<Vec<Coord>>(), engine.get_rule())
+        };
+
+        RuleExplorerWindow {
+            resources: resources,
+            window: window,
+            engine: engine,
+
+            modifiers: Modifiers::new(),
+
+            explorer: Explorer::start(&cells, &rule, GENERATIONS),
+            cells: cells,
+            rule: rule,
+            selected: 0,
+        }
+    }
+
+    fn get_count(&self) -> usize {
+        self.explorer.get_outcomes().len()
+    }
+
+    fn get_size(&self) -> (f64, f64) {
+        let rows = (self.get_count() + COLUMNS - 1) / COLUMNS;
+        (COLUMNS as f64 * TILE_WIDTH + 20.0, rows as f64 * TILE_HEIGHT + 70.0)
+    }
+
+    fn get_selected_rule(&self) -> Option<Rule> {
+        self.explorer.get_outcomes()[self.selected].as_ref().map(|outcome| outcome.rule)
+    }
+
+    fn explore_selected(&mut self) {
+        // the selected rule becomes the middle of the next exploration
+        if let Some(rule) = self.get_selected_rule() {
+            self.explorer = Explorer::start(&self.cells, &rule, GENERATIONS);
+            self.rule = rule;
+            self.selected = 0;
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let pos = self.selected as isize + delta;
+        if pos >= 0 && (pos as usize) < self.get_count() {
+            self.selected = pos as usize;
+        }
+    }
+
+    fn paint_tile(&self, c: &Context, g: &mut GlGraphics, idx: usize, x: f64, y: f64) {
+        let outcome = match self.explorer.get_outcomes()[idx] {
+            Some(ref outcome) => outcome,
+            None => {
+                text(super::GRAY, 12, "running...",
+                     &mut self.resources.borrow_mut().font,
+                     c.trans(x + 8.0, y + 0.5 * THUMBNAIL).transform, g);
+                return;
+            }
+        };
+
+        rectangle([0.0, 0.0, 0.0, 1.0], [x, y, THUMBNAIL, THUMBNAIL], c.transform, g);
+
+        let cell_size = THUMBNAIL / THUMBNAIL_SIZE as f64;
+        for (pos, _) in outcome.thumbnail.iter().enumerate().filter(|&(_, &alive)| alive) {
+            let (col, row) = (pos % THUMBNAIL_SIZE, pos / THUMBNAIL_SIZE);
+            rectangle(super::WHITE,
+                      [x + col as f64 * cell_size, y + row as f64 * cell_size, cell_size, cell_size],
+                      c.transform, g);
+        }
+
+        text(super::WHITE, 12, &outcome.rule.to_string(),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x, y + THUMBNAIL + 15.0).transform, g);
+
+        text(super::GRAY, 11, &describe(outcome),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x, y + THUMBNAIL + 29.0).transform, g);
+    }
+
+}
+
+impl<'a> WindowBase for RuleExplorerWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let (width, height) = self.get_size();
+        let (x, y) = (0.5 * (self.window.get_width() - width), 0.5 * (self.window.get_height() - height));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, width, height], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, width - 10.0, height - 10.0], c.transform, g);
+
+        text(super::WHITE, 15, &format!("Rules near {}, {} generations", self.rule, GENERATIONS),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        for idx in 0..self.get_count() {
+            let tile_x = x + 10.0 + (idx % COLUMNS) as f64 * TILE_WIDTH;
+            let tile_y = y + 40.0 + (idx / COLUMNS) as f64 * TILE_HEIGHT;
+
+            // the rule the others are mutations of is framed in green
+            if idx == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [tile_x, tile_y, TILE_WIDTH - 4.0, TILE_HEIGHT - 4.0], c.transform, g);
+            } else if idx == 0 {
+                rectangle([0.1, 0.3, 0.1, 1.0], [tile_x, tile_y, TILE_WIDTH - 4.0, TILE_HEIGHT - 4.0], c.transform, g);
+            }
+
+            self.paint_tile(&c, g, idx, tile_x + 6.0, tile_y + 6.0);
+        }
+
+        text(super::GREEN, 12, "arrows to choose, Space to explore around it, Enter to apply, Ctrl+Shift+R to cancel",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + height - 15.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            &Event::Update(_) => {
+                self.explorer.poll();
+                return PostAction::Transfer;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                if let Some(rule) = self.get_selected_rule() {
+                    self.engine.borrow_mut().set_rule(rule);
+                    return PostAction::Pop;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Space))) => self.explore_selected(),
+
+            // the explorer sees the Ctrl+Shift+R which opened it, but not
+            // the modifier presses before it, so it is closed only by the next one
+            &Event::Input(Input::Press(Button::Keyboard(Key::R))) if self.modifiers.ctrl() && self.modifiers.shift() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Left))) => self.move_selection(-1),
+            &Event::Input(Input::Press(Button::Keyboard(Key::Right))) => self.move_selection(1),
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => self.move_selection(-(COLUMNS as isize)),
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => self.move_selection(COLUMNS as isize),
+
+            &Event::Input(_) => {}
+
+            // the simulation goes on behind the explorer
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+    fn is_animated(&self) -> bool {
+        !self.explorer.is_finished()
+    }
+
+}
@@ -0,0 +1,313 @@
+// Choosing a pattern file to open or a file name to save the pattern to,
+// a file name can be typed or picked from the directory listing
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+use super::super::log;
+use super::super::files;
+use super::super::files::Entry;
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+const WIDTH: f64 = 520.0;
+const HEIGHT: f64 = 380.0;
+
+const LIST_ROWS: usize = 11;
+const ROW_HEIGHT: f64 = 20.0;
+
+const DEFAULT_SAVE_NAME: &'static str = "pattern.rle";
+
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum FileDialogMode {
+    Open,
+    Save,
+}
+
+pub struct FileDialogWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    // chosen file is loaded by the UI, as the whole view is fit to it
+    open_request: Rc<RefCell<Option<PathBuf>>>,
+
+    mode: FileDialogMode,
+    // only the selected cells are saved if there is a selection
+    selection: Option<Rect>,
+    modifiers: Modifiers,
+
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    // typed file name, relative to dir or absolute
+    name: String,
+
+    error: Option<String>,
+}
+
+impl<'a> FileDialogWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>, open_request: Rc<RefCell<Option<PathBuf>>>,
+               mode: FileDialogMode, selection: Option<Rect>, dir: &Path) -> FileDialogWindow<'a> {
+
+        // saved file is named after the pattern by default
+        let name = match mode {
+            FileDialogMode::Open => String::new(),
+            FileDialogMode::Save => engine.borrow().get_pattern_meta().name.as_ref()
+                .map_or(DEFAULT_SAVE_NAME.to_string(), |name| format!("{}.rle", name.replace('/', "_")))
+        };
+
+        let mut dialog = FileDialogWindow {
+            engine: engine,
+            resources: resources,
+            window: window,
+            open_request: open_request,
+
+            mode: mode,
+            selection: selection,
+            modifiers: Modifiers::new(),
+
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            selected: 0,
+            name: name,
+
+            error: None,
+        };
+
+        dialog.change_dir(dir);
+        dialog
+    }
+
+    fn change_dir(&mut self, dir: &Path) {
+        // ".." entries are resolved, so the shown path stays readable
+        let dir = fs::canonicalize(dir).unwrap_or(dir.to_path_buf());
+
+        match files::list_dir(&dir) {
+            Ok(entries) => {
+                self.dir = dir;
+                self.entries = entries;
+                self.selected = 0;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(format!("unable to open {}: {}", dir.display(), err))
+        }
+    }
+
+    fn select(&mut self, pos: usize) {
+        self.selected = pos;
+
+        // Enter goes into the highlighted directory unless a name is typed again
+        if let Some(entry) = self.entries.get(pos) {
+            self.name = if entry.is_dir { String::new() } else { entry.name.clone() };
+        }
+    }
+
+    fn get_target(&self) -> Option<PathBuf> {
+        let name = self.name.trim();
+
+        if name.is_empty() {
+            self.entries.get(self.selected).map(|entry| entry.path.clone())
+        } else {
+            Some(self.dir.join(name))
+        }
+    }
+
+    /// Opens a directory or takes the file, returns whether the dialog is done
+    fn accept(&mut self) -> bool {
+        let path = match self.get_target() {
+            Some(path) => path,
+            None => return false
+        };
+
+        if files::is_dir(&path) {
+            self.name.clear();
+            self.change_dir(&path);
+            return false;
+        }
+
+        match self.mode {
+            FileDialogMode::Open => {
+                if !files::is_file(&path) {
+                    self.error = Some(format!("{} doesn't exist", path.display()));
+                    return false;
+                }
+
+                *self.open_request.borrow_mut() = Some(path);
+                true
+            }
+
+            FileDialogMode::Save => {
+                // file name decides whether it's compressed
+                let saved = self.engine.borrow().save_rle_region(self.selection.as_ref(), &path);
+
+                match saved {
+                    Ok(_) => {
+                        log::write(&format!("pattern saved to {}", path.display()));
+                        true
+                    }
+                    Err(err) => {
+                        self.error = Some(format!("unable to save {}: {}", path.display(), err));
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_close_key(&self) -> Key {
+        match self.mode {
+            FileDialogMode::Open => Key::O,
+            FileDialogMode::Save => Key::S,
+        }
+    }
+
+}
+
+impl<'a> WindowBase for FileDialogWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - HEIGHT));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, HEIGHT], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, HEIGHT - 10.0], c.transform, g);
+
+        let title = match (self.mode, self.selection) {
+            (FileDialogMode::Open, _) => "Open pattern",
+            (FileDialogMode::Save, Some(_)) => "Save selection as",
+            (FileDialogMode::Save, None) => "Save whole pattern as",
+        };
+
+        text(super::WHITE, 15, title,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        text(super::GRAY, 12, &self.dir.display().to_string(),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 45.0).transform, g);
+
+        rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, y + 52.0, WIDTH - 20.0, ROW_HEIGHT], c.transform, g);
+        text(super::WHITE, 13, &format!("{}_", self.name),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 67.0).transform, g);
+
+        // list is scrolled to keep the highlighted entry visible
+        let first = if self.selected >= LIST_ROWS { self.selected + 1 - LIST_ROWS } else { 0 };
+        let list_y = y + 80.0;
+
+        for (row, entry) in self.entries.iter().enumerate().skip(first).take(LIST_ROWS) {
+            let row_y = list_y + (row - first) as f64 * ROW_HEIGHT;
+
+            if row == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, WIDTH - 20.0, ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            let (color, label) = if entry.is_dir {
+                (super::GRAY, format!("{}/", entry.name))
+            } else {
+                (super::WHITE, entry.name.clone())
+            };
+
+            text(color, 13, &label,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 15.0).transform, g);
+        }
+
+        if let Some(ref error) = self.error {
+            text(super::RED, 12, error,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, y + HEIGHT - 30.0).transform, g);
+        }
+
+        let help = match self.mode {
+            FileDialogMode::Open => "Type a name or pick a file with Up/Down, Enter to open, Ctrl+O to cancel",
+            FileDialogMode::Save => "Type a name or pick a file with Up/Down, Enter to save, Ctrl+S to cancel",
+        };
+
+        text(super::GREEN, 12, help,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + HEIGHT - 15.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            // the window sees the key which opened it, but not the Ctrl press
+            // before it, so it is closed only by the next Ctrl+O or Ctrl+S
+            &Event::Input(Input::Press(Button::Keyboard(key))) if self.modifiers.ctrl() &&
+                key == self.get_close_key() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                if self.accept() {
+                    return PostAction::Pop;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    let pos = self.selected - 1;
+                    self.select(pos);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < self.entries.len() {
+                    let pos = self.selected + 1;
+                    self.select(pos);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Backspace))) => {
+                // backspace with nothing typed goes up a directory
+                if self.name.is_empty() {
+                    if let Some(parent) = self.dir.parent().map(|parent| parent.to_path_buf()) {
+                        self.change_dir(&parent);
+                    }
+                } else {
+                    self.name.pop();
+                }
+            }
+
+            // control keys come as text too
+            &Event::Input(Input::Text(ref s)) if !self.modifiers.ctrl() => {
+                self.name.extend(s.chars().filter(|ch| !ch.is_control()));
+            }
+
+            &Event::Input(_) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
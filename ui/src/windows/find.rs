@@ -0,0 +1,169 @@
+// Occurrences of the selected pattern all over the board, the camera
+// is moved to the one chosen in the list
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+
+use self::engine::engine::Engine;
+use self::engine::board::{Coord, Rect};
+use self::engine::search;
+use self::engine::search::Match;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const WIDTH: f64 = 360.0;
+const HEIGHT: f64 = 300.0;
+
+const LIST_ROWS: usize = 10;
+const ROW_HEIGHT: f64 = 20.0;
+
+const ORIENTATIONS: [&'static str; 8] = [
+    "as selected", "turned right", "upside down", "turned left",
+    "mirrored", "turned right, mirrored", "upside down, mirrored", "turned left, mirrored",
+];
+
+
+pub struct FindWindow {
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    selection: Rc<Cell<Option<Rect>>>,
+    look_at: Rc<Cell<Option<Rect>>>,
+
+    modifiers: Modifiers,
+
+    matches: Vec<Match>,
+    selected: usize,
+}
+
+impl FindWindow {
+
+    pub fn new<'a>(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+                   engine: Rc<RefCell<Engine<'a>>>, pattern: &[Coord],
+                   selection: Rc<Cell<Option<Rect>>>, look_at: Rc<Cell<Option<Rect>>>) -> FindWindow {
+
+        let matches = search::find(engine.borrow().get_board(), pattern);
+
+        let mut find = FindWindow {
+            resources: resources,
+            window: window,
+            selection: selection,
+            look_at: look_at,
+
+            modifiers: Modifiers::new(),
+
+            matches: matches,
+            selected: 0,
+        };
+
+        find.select(0);
+        find
+    }
+
+    fn select(&mut self, pos: usize) {
+        if let Some(found) = self.matches.get(pos) {
+            // found cells are selected, so they can be copied or cropped to
+            self.selection.set(Some(found.rect));
+            self.look_at.set(Some(found.rect));
+        }
+
+        self.selected = pos;
+    }
+
+}
+
+impl WindowBase for FindWindow {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        // the list stays at the side, so the found cells are seen in the middle
+        let (x, y) = (self.window.get_width() - WIDTH - 10.0, 0.5 * (self.window.get_height() - HEIGHT));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, HEIGHT], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, HEIGHT - 10.0], c.transform, g);
+
+        text(super::WHITE, 15, &format!("Found {} occurrences", self.matches.len()),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        // list is scrolled to keep the highlighted match visible
+        let first = if self.selected >= LIST_ROWS { self.selected + 1 - LIST_ROWS } else { 0 };
+        let list_y = y + 45.0;
+
+        for (row, found) in self.matches.iter().enumerate().skip(first).take(LIST_ROWS) {
+            let row_y = list_y + (row - first) as f64 * ROW_HEIGHT;
+
+            if row == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, WIDTH - 20.0, ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            text(super::WHITE, 13, &format!("{}, {}", found.rect.left, found.rect.top),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 15.0).transform, g);
+
+            text(super::GRAY, 13, ORIENTATIONS[found.orientation],
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 130.0, row_y + 15.0).transform, g);
+        }
+
+        text(super::GREEN, 12, "Up/Down to go to a match, Enter or Ctrl+F to close",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + HEIGHT - 15.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                return PostAction::Pop;
+            }
+
+            // the window sees the Ctrl+F which opened it, but not the Ctrl press
+            // before it, so it is closed only by the next Ctrl+F
+            &Event::Input(Input::Press(Button::Keyboard(Key::F))) if self.modifiers.ctrl() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    let pos = self.selected - 1;
+                    self.select(pos);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < self.matches.len() {
+                    let pos = self.selected + 1;
+                    self.select(pos);
+                }
+            }
+
+            &Event::Input(_) => {}
+
+            // the board keeps moving the camera and the simulation goes on
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
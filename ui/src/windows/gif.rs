@@ -0,0 +1,265 @@
+// Animated GIF export: a copy of the board is run for the chosen number
+// of generations and every generation becomes a frame
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+use super::super::log;
+use super::super::config::Config;
+use super::super::rules::RuleRegistry;
+use super::super::render;
+use super::super::image::{Canvas, GifWriter};
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::cam::{Cam, Transform};
+use self::engine::io::invalid_data;
+use self::engine::structs::{CellProp, GraphicsWindow};
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+const WIDTH: f64 = 420.0;
+const HEIGHT: f64 = 150.0;
+const ROW_HEIGHT: f64 = 22.0;
+
+const FRAMES: usize = 0;
+const CELL_SIZE: usize = 1;
+
+const DEFAULT_FRAMES: usize = 100;
+const DEFAULT_CELL_SIZE: usize = 4;
+const MAX_FRAMES: usize = 10000;
+const MAX_CELL_SIZE: usize = 20;
+
+// larger side of the animation in pixels, cells are shrunk to fit it
+const MAX_IMAGE_SIZE: f64 = 1024.0;
+// hundredths of a second per frame
+const FRAME_DELAY: u16 = 5;
+
+
+pub struct GifExportWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    rules: Rc<RefCell<RuleRegistry>>,
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    config: Rc<RefCell<Config>>,
+
+    modifiers: Modifiers,
+
+    // frame count and cell size
+    fields: [String; 2],
+    focused: usize,
+
+    error: Option<String>,
+}
+
+impl<'a> GifExportWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>, rules: Rc<RefCell<RuleRegistry>>,
+               config: Rc<RefCell<Config>>) -> GifExportWindow<'a> {
+
+        GifExportWindow {
+            engine: engine,
+            rules: rules,
+            resources: resources,
+            window: window,
+            config: config,
+
+            modifiers: Modifiers::new(),
+
+            fields: [DEFAULT_FRAMES.to_string(), DEFAULT_CELL_SIZE.to_string()],
+            focused: FRAMES,
+
+            error: None,
+        }
+    }
+
+    fn get_value(&self, idx: usize, max: usize) -> io::Result<usize> {
+        match self.fields[idx].parse() {
+            Ok(value) if value >= 1 && value <= max => Ok(value),
+            _ => invalid_data(&format!("{} should be from 1 to {}", self.get_label(idx), max))
+        }
+    }
+
+    fn copy_engine(&self) -> io::Result<Engine<'a>> {
+        // the simulation itself is not advanced by export
+        let mut engine = Engine::new(None, None);
+        engine.load_container(&self.engine.borrow().to_container(false))?;
+        Ok(engine)
+    }
+
+    fn export(&self) -> io::Result<PathBuf> {
+        let frames = self.get_value(FRAMES, MAX_FRAMES)?;
+        let cell_size = self.get_value(CELL_SIZE, MAX_CELL_SIZE)? as f64;
+
+        // first run finds the area the pattern covers during the whole animation
+        let mut engine = self.copy_engine()?;
+        let mut rect = match engine.get_board().get_bounding_box() {
+            Some(rect) => rect,
+            None => return invalid_data("the board is empty")
+        };
+
+        for _ in 1..frames {
+            engine.one_iteration();
+
+            if let Some(bounds) = engine.get_board().get_bounding_box() {
+                rect = Rect::new(rect.left.min(bounds.left), rect.top.min(bounds.top),
+                                 rect.right.max(bounds.right), rect.bottom.max(bounds.bottom));
+            }
+        }
+
+        let cell_size = cell_size.min(MAX_IMAGE_SIZE / rect.get_width().max(rect.get_height()) as f64);
+        let (width, height) = ((rect.get_width() as f64 * cell_size).ceil(), (rect.get_height() as f64 * cell_size).ceil());
+
+        let cell = CellProp::new(1.0, 1.0);
+        let mut cam = Cam::new(0.0, 0.0);
+        cam.set_scale(cell_size);
+        cam.center(&cell, &rect);
+        let transform = Transform::new(&cam, &cell, width, height);
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0);
+        let dir = self.config.borrow().get("export.dir").unwrap_or(".").to_string();
+        let path = PathBuf::from(dir).join(format!("life-rs-{}.gif", timestamp));
+
+        let mut gif = GifWriter::new(BufWriter::new(File::create(&path)?), width as usize, height as usize)?;
+
+        // second run renders the frames
+        let mut engine = self.copy_engine()?;
+        let palette = self.rules.borrow().get_palette(&engine.get_rule());
+
+        for frame in 0..frames {
+            if frame > 0 {
+                engine.one_iteration();
+            }
+
+            let mut canvas = Canvas::new(width as usize, height as usize, palette.background);
+            for quad in render::render_cells(&engine.snapshot().cells, &transform, &palette, width, height) {
+                canvas.fill(&quad);
+            }
+            gif.add_frame(&canvas, FRAME_DELAY)?;
+        }
+
+        gif.finish()?;
+        Ok(path)
+    }
+
+    fn get_label(&self, idx: usize) -> &'static str {
+        match idx {
+            FRAMES => "Frames",
+            _ => "Cell size"
+        }
+    }
+
+}
+
+impl<'a> WindowBase for GifExportWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - HEIGHT));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, HEIGHT], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, HEIGHT - 10.0], c.transform, g);
+
+        text(super::WHITE, 15, "Export animated GIF",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        for (idx, value) in self.fields.iter().enumerate() {
+            let row_y = y + 15.0 + (idx + 1) as f64 * ROW_HEIGHT;
+
+            if idx == self.focused {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 95.0, row_y + 4.0, WIDTH - 110.0, ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            text(super::GRAY, 13, self.get_label(idx),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 19.0).transform, g);
+
+            let cursor = if idx == self.focused { "_" } else { "" };
+            text(super::WHITE, 13, &format!("{}{}", value, cursor),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 100.0, row_y + 19.0).transform, g);
+        }
+
+        let status_y = y + HEIGHT - 30.0;
+
+        if let Some(ref error) = self.error {
+            text(super::RED, 12, error,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, status_y).transform, g);
+        }
+
+        text(super::GREEN, 12, "Up/Down to move, Enter to export, Ctrl+F12 to cancel",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, status_y + 16.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                match self.export() {
+                    Ok(path) => {
+                        log::write(&format!("animation saved to {}", path.display()));
+                        return PostAction::Pop;
+                    }
+                    Err(err) => self.error = Some(format!("unable to export: {}", err))
+                }
+            }
+
+            // the window sees the Ctrl+F12 which opened it, but not the Ctrl press
+            // before it, so it is closed only by the next Ctrl+F12
+            &Event::Input(Input::Press(Button::Keyboard(Key::F12))) if self.modifiers.ctrl() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                self.focused = FRAMES;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) |
+            &Event::Input(Input::Press(Button::Keyboard(Key::Tab))) => {
+                self.focused = CELL_SIZE;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Backspace))) => {
+                self.fields[self.focused].pop();
+            }
+
+            &Event::Input(Input::Text(ref s)) if !self.modifiers.ctrl() => {
+                self.fields[self.focused].extend(s.chars().filter(|ch| ch.is_digit(10)));
+            }
+
+            &Event::Input(_) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
@@ -4,10 +4,11 @@ extern crate engine;
 
 use piston_window::{Context, Transformed, text, Event};
 
-use super::{WindowBase, PostAction, States};
+use super::{WindowBase, PostAction, States, HudMode};
 use super::Resources;
 
 use self::engine::engine::Engine;
+use self::engine::board::Rect;
 
 use opengl_graphics::GlGraphics;
 
@@ -17,16 +18,24 @@ use std::cell::{RefCell, Cell};
 
 pub struct HUDWindow<'a> {
     engine: Rc<RefCell<Engine<'a>>>,
-    resources: Rc<RefCell<Resources>>
-    //state: isize,
+    resources: Rc<RefCell<Resources>>,
+    selection: Rc<Cell<Option<Rect>>>,
+    // shown instead of the engine's while the GPU is ahead of it
+    gpu_progress: Rc<Cell<Option<(usize, usize)>>>,
+    mode: HudMode,
 }
 
 impl<'a> HUDWindow<'a> {
-    pub fn new(resources: Rc<RefCell<Resources>>, engine: Rc<RefCell<Engine<'a>>>) -> HUDWindow<'a> {
+    pub fn new(resources: Rc<RefCell<Resources>>, engine: Rc<RefCell<Engine<'a>>>,
+               selection: Rc<Cell<Option<Rect>>>,
+               gpu_progress: Rc<Cell<Option<(usize, usize)>>>) -> HUDWindow<'a> {
 
         HUDWindow {
             resources: resources,
-            engine: engine
+            engine: engine,
+            selection: selection,
+            gpu_progress: gpu_progress,
+            mode: HudMode::Full,
         }
 
     }
@@ -36,13 +45,29 @@ impl<'a> WindowBase for HUDWindow<'a> {
 
     fn paint(&mut self, c: Context, g: &mut GlGraphics) {
 
+        if self.mode == HudMode::Hidden {
+            return;
+        }
+
+        let (generation, population) = match self.gpu_progress.get() {
+            Some(progress) => progress,
+            None => {
+                let engine = self.engine.borrow();
+                (engine.cur_iteration(), engine.get_board().get_population())
+            }
+        };
+
         text(super::GREEN, 15,
-             &format!("generation {}", self.engine.borrow().cur_iteration()),
+             &format!("generation {}", generation),
              &mut self.resources.borrow_mut().font,
              c.trans(10.0, 20.0).transform, g);
 
+        if self.mode == HudMode::Minimal {
+            return;
+        }
+
         text(super::GREEN, 15,
-             &format!("population {}", self.engine.borrow().get_board().get_population()),
+             &format!("population {}", population),
              &mut self.resources.borrow_mut().font,
              c.trans(150.0, 20.0).transform, g);
 
@@ -51,6 +76,43 @@ impl<'a> WindowBase for HUDWindow<'a> {
              &mut self.resources.borrow_mut().font,
              c.trans(320.0, 20.0).transform, g);
 
+        let title = {
+            let engine = self.engine.borrow();
+            let meta = engine.get_pattern_meta();
+
+            match (meta.name.as_ref(), meta.author.as_ref()) {
+                (Some(name), Some(author)) => Some(format!("{} by {}", name, author)),
+                (Some(name), None) => Some(name.clone()),
+                _ => None
+            }
+        };
+
+        if let Some(title) = title {
+            text(super::GREEN, 15, &title,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(500.0, 20.0).transform, g);
+        }
+
+        let ups = match self.engine.borrow().get_ups() {
+            Some(ups) => format!("{} updates per second", ups),
+            None => "as fast as possible".to_string()
+        };
+
+        text(super::GREEN, 15, &ups,
+             &mut self.resources.borrow_mut().font,
+             c.trans(10.0, 40.0).transform, g);
+
+        if let Some(rect) = self.selection.get() {
+            let population = self.engine.borrow().get_board().population_in(&rect);
+
+            text(super::GREEN, 15,
+                 &format!("selection {}x{}, {} cells, density {:.*}",
+                          rect.get_width(), rect.get_height(), population,
+                          3, population as f64 / rect.get_area() as f64),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(10.0, 60.0).transform, g);
+        }
+
     }
 
     fn event_dispatcher(&mut self, _event: &Event, _cur_state: &Cell<States>) -> PostAction {
@@ -59,4 +121,8 @@ impl<'a> WindowBase for HUDWindow<'a> {
 
     }
 
+    fn set_hud_mode(&mut self, mode: HudMode) {
+        self.mode = mode;
+    }
+
 }
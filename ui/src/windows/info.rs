@@ -16,7 +16,7 @@ use std::cell::{RefCell, Cell};
 
 pub struct InfoWindow<'a> {
 
-    msg: &'a str,
+    msg: String,
 
     scr_width: f64,
     scr_height: f64,
@@ -29,10 +29,10 @@ pub struct InfoWindow<'a> {
 impl<'a> InfoWindow<'a> {
 
     pub fn new(resources: Rc<RefCell<Resources>>, _engine: Rc<RefCell<Engine<'a>>>,
-               msg: &'a str, width: f64, height: f64) -> Self {
+               msg: &str, width: f64, height: f64) -> Self {
 
         InfoWindow {
-            msg: msg,
+            msg: msg.to_string(),
 
             scr_width: width,
             scr_height: height,
@@ -55,9 +55,10 @@ impl<'a> WindowBase for InfoWindow<'a> {
 
         let (scr_width, scr_height) = (self.scr_width, self.scr_height);
         let resources = self.resources.clone();
+        let msg = self.msg.clone();
 
         self.paint_info_window(c, g, scr_width, scr_height,
-                               resources, self.msg, "press Enter to continue");
+                               resources, &msg, "press Enter to continue");
     }
 
     fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
@@ -78,4 +79,9 @@ impl<'a> WindowBase for InfoWindow<'a> {
         true
     }
 
+    fn on_resize(&mut self, width: f64, height: f64) {
+        self.scr_width = width;
+        self.scr_height = height;
+    }
+
 }
@@ -0,0 +1,223 @@
+// Jump ahead by a number of generations at once, short jumps are done
+// right away and the board is rendered only when the last generation is
+// computed, long ones run a bit on every update and show their progress
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States};
+use super::Resources;
+
+use self::engine::engine::Engine;
+use self::engine::progress::Progress;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::cmp;
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+use std::time::Instant;
+
+
+const WIDTH: f64 = 360.0;
+const ROW_HEIGHT: f64 = 20.0;
+
+const STEPS: [u64; 5] = [10, 100, 1000, 10000, 100000];
+
+// longer jumps are split between updates
+const MAX_INSTANT_STEPS: u64 = 1000;
+// seconds spent computing generations on every update
+const CHUNK_TIME: f64 = 0.05;
+
+
+pub struct JumpWindow<'a> {
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    engine: Rc<RefCell<Engine<'a>>>,
+
+    selected: usize,
+    // set by the J press which opened the window
+    opened: bool,
+
+    // long jump being run, with the time it started at
+    running: Option<(Progress, Instant)>,
+}
+
+fn get_secs(since: Instant) -> f64 {
+    let elapsed = since.elapsed();
+    elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9
+}
+
+impl<'a> JumpWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>) -> JumpWindow<'a> {
+
+        JumpWindow {
+            resources: resources,
+            window: window,
+            engine: engine,
+
+            selected: 0,
+            opened: false,
+
+            running: None,
+        }
+    }
+
+    fn get_height() -> f64 {
+        STEPS.len() as f64 * ROW_HEIGHT + 100.0
+    }
+
+    fn jump(&mut self, idx: usize, cur_state: &Cell<States>) -> PostAction {
+        if STEPS[idx] <= MAX_INSTANT_STEPS {
+            self.engine.borrow_mut().iterations(STEPS[idx]);
+            return PostAction::Pop;
+        }
+
+        // the simulation would step the board along with the jump
+        cur_state.set(States::Paused);
+        self.running = Some((Progress::new(STEPS[idx], 0.0), Instant::now()));
+
+        PostAction::Stop
+    }
+
+    fn run_chunk(&mut self) -> PostAction {
+        let (progress, started) = match self.running {
+            Some(ref mut running) => (&mut running.0, running.1),
+            None => return PostAction::Transfer
+        };
+
+        let chunk_start = get_secs(started);
+        let mut done = progress.get_done();
+        let mut steps = 1;
+
+        // a step takes about as long as the previous one, the number of
+        // generations per step grows until the chunk is filled
+        while done < progress.get_total() && get_secs(started) - chunk_start < CHUNK_TIME {
+            let steps_now = cmp::min(steps, progress.get_total() - done);
+            self.engine.borrow_mut().iterations(steps_now);
+
+            done += steps_now;
+            steps *= 2;
+        }
+
+        progress.update(done, get_secs(started));
+
+        if progress.is_finished() {
+            PostAction::Pop
+        } else {
+            PostAction::Transfer
+        }
+    }
+
+}
+
+impl<'a> WindowBase for JumpWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let height = JumpWindow::get_height();
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - height));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, height], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, height - 10.0], c.transform, g);
+
+        text(super::WHITE, 15, "Jump ahead",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        let list_y = y + 40.0;
+
+        for (row, steps) in STEPS.iter().enumerate() {
+            let row_y = list_y + row as f64 * ROW_HEIGHT;
+
+            if row == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, WIDTH - 20.0, ROW_HEIGHT], c.transform, g);
+            }
+
+            text(super::WHITE, 13, &format!("{}. {} generations", row + 1, steps),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 15.0).transform, g);
+        }
+
+        if let Some((ref progress, _)) = self.running {
+            text(super::GRAY, 12, &progress.to_string(),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, y + height - 35.0).transform, g);
+        }
+
+        let hint = if self.running.is_some() {
+            "J to stop, generations computed so far are kept"
+        } else {
+            "1-5 or Enter to jump, J to cancel"
+        };
+
+        text(super::GREEN, 12, hint,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + height - 15.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
+
+        match event {
+
+            &Event::Update(_) => return self.run_chunk(),
+
+            // the window sees the J press which opened it, so it is
+            // closed only by the next one
+            &Event::Input(Input::Press(Button::Keyboard(Key::J))) => {
+                if self.opened {
+                    return PostAction::Pop;
+                }
+                self.opened = true;
+            }
+
+            // keys choose a jump only until one is running
+            &Event::Input(_) if self.running.is_some() => {}
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                let selected = self.selected;
+                return self.jump(selected, cur_state);
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::D1))) => return self.jump(0, cur_state),
+            &Event::Input(Input::Press(Button::Keyboard(Key::D2))) => return self.jump(1, cur_state),
+            &Event::Input(Input::Press(Button::Keyboard(Key::D3))) => return self.jump(2, cur_state),
+            &Event::Input(Input::Press(Button::Keyboard(Key::D4))) => return self.jump(3, cur_state),
+            &Event::Input(Input::Press(Button::Keyboard(Key::D5))) => return self.jump(4, cur_state),
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    self.selected -= 1;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < STEPS.len() {
+                    self.selected += 1;
+                }
+            }
+
+            &Event::Input(_) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+    fn is_animated(&self) -> bool {
+        self.running.is_some()
+    }
+
+}
@@ -0,0 +1,269 @@
+// Form for pattern name, author and comments, saves the pattern as RLE
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+use super::super::log;
+use super::super::config::Config;
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::io::PatternMeta;
+use self::engine::io::compression;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+use std::path::PathBuf;
+
+
+const WIDTH: f64 = 500.0;
+const ROW_HEIGHT: f64 = 22.0;
+
+// fields before the comment lines
+const NAME: usize = 0;
+const AUTHOR: usize = 1;
+const FIRST_COMMENT: usize = 2;
+
+
+pub struct MetaEditorWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    config: Rc<RefCell<Config>>,
+    // only the selected cells are saved if there is a selection
+    selection: Option<Rect>,
+
+    modifiers: Modifiers,
+
+    // name, author and comment lines
+    fields: Vec<String>,
+    focused: usize,
+
+    error: Option<String>,
+    // existing file the user was told about, saved over on the next try
+    overwrite: Option<PathBuf>,
+}
+
+impl<'a> MetaEditorWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>, config: Rc<RefCell<Config>>,
+               selection: Option<Rect>) -> MetaEditorWindow<'a> {
+
+        let fields = {
+            let engine = engine.borrow();
+            let meta = engine.get_pattern_meta();
+
+            let mut fields = vec![meta.name.clone().unwrap_or(String::new()),
+                                  meta.author.clone().unwrap_or(String::new())];
+            fields.extend(meta.comments.iter().cloned());
+
+            // there is always a line to type a comment into
+            if fields.len() == FIRST_COMMENT {
+                fields.push(String::new());
+            }
+            fields
+        };
+
+        MetaEditorWindow {
+            engine: engine,
+            resources: resources,
+            window: window,
+            config: config,
+            selection: selection,
+
+            modifiers: Modifiers::new(),
+
+            fields: fields,
+            focused: NAME,
+
+            error: None,
+            overwrite: None,
+        }
+    }
+
+    fn get_meta(&self) -> PatternMeta {
+        let non_empty = |value: &String| if value.trim().is_empty() { None } else { Some(value.trim().to_string()) };
+
+        PatternMeta {
+            name: non_empty(&self.fields[NAME]),
+            author: non_empty(&self.fields[AUTHOR]),
+            comments: self.fields[FIRST_COMMENT..].iter().filter_map(non_empty).collect(),
+        }
+    }
+
+    fn get_path(&self, meta: &PatternMeta) -> PathBuf {
+        // file is named after the pattern
+        let name: String = meta.name.as_ref().map_or("pattern", |n| n.as_str()).to_lowercase().chars()
+            .map(|ch| if ch.is_alphanumeric() || ch == '-' { ch } else { '_' })
+            .collect();
+
+        let config = self.config.borrow();
+        let path = PathBuf::from(config.get("export.dir").unwrap_or(".")).join(format!("{}.rle", name));
+
+        if config.get_parsed::<u32>("save.compress").unwrap_or(0) != 0 {
+            compression::with_gz_extension(&path)
+        } else {
+            path
+        }
+    }
+
+    fn save(&mut self) -> bool {
+        let meta = self.get_meta();
+        let path = self.get_path(&meta);
+
+        // another pattern of the same name may be there already
+        if path.exists() && self.overwrite.as_ref() != Some(&path) {
+            self.error = Some(format!("{} exists, Ctrl+Enter again to replace it", path.display()));
+            self.overwrite = Some(path);
+            return false;
+        }
+
+        self.engine.borrow_mut().set_pattern_meta(meta);
+        let saved = self.engine.borrow().save_rle_region(self.selection.as_ref(), &path);
+
+        match saved {
+            Ok(_) => {
+                log::write(&format!("pattern saved to {}", path.display()));
+                true
+            }
+            Err(err) => {
+                self.error = Some(format!("unable to save {}: {}", path.display(), err));
+                false
+            }
+        }
+    }
+
+    fn get_label(&self, idx: usize) -> &'static str {
+        match idx {
+            NAME => "Name",
+            AUTHOR => "Author",
+            FIRST_COMMENT => "Comments",
+            _ => ""
+        }
+    }
+
+}
+
+impl<'a> WindowBase for MetaEditorWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let height = (self.fields.len() + 3) as f64 * ROW_HEIGHT + 20.0;
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - height));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, height], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, height - 10.0], c.transform, g);
+
+        let title = if self.selection.is_some() { "Save selection" } else { "Save whole pattern" };
+
+        text(super::WHITE, 15, title,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        for (idx, value) in self.fields.iter().enumerate() {
+            let row_y = y + 15.0 + (idx + 1) as f64 * ROW_HEIGHT;
+
+            if idx == self.focused {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 95.0, row_y + 4.0, WIDTH - 110.0, ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            text(super::GRAY, 13, self.get_label(idx),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 19.0).transform, g);
+
+            let cursor = if idx == self.focused { "_" } else { "" };
+            text(super::WHITE, 13, &format!("{}{}", value, cursor),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 100.0, row_y + 19.0).transform, g);
+        }
+
+        let status_y = y + height - 30.0;
+
+        if let Some(ref error) = self.error {
+            text(super::RED, 12, error,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, status_y).transform, g);
+        }
+
+        text(super::GREEN, 12, "Up/Down to move, Enter for a new comment line, Ctrl+Enter to save, Ctrl+E to cancel",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, status_y + 16.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) if self.modifiers.ctrl() => {
+                if self.save() {
+                    return PostAction::Pop;
+                }
+            }
+
+            // the window sees the Ctrl+E which opened it, but not the Ctrl press
+            // before it, so it is closed only by the next Ctrl+E
+            &Event::Input(Input::Press(Button::Keyboard(Key::E))) if self.modifiers.ctrl() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                // new comment line goes after the current one
+                if self.focused >= FIRST_COMMENT {
+                    self.fields.insert(self.focused + 1, String::new());
+                }
+                self.focused += 1;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                self.focused = self.focused.saturating_sub(1);
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) |
+            &Event::Input(Input::Press(Button::Keyboard(Key::Tab))) => {
+                self.focused = (self.focused + 1).min(self.fields.len() - 1);
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Backspace))) => {
+                let is_extra_comment = self.focused > FIRST_COMMENT ||
+                    (self.focused == FIRST_COMMENT && self.fields.len() > FIRST_COMMENT + 1);
+
+                // backspace on an empty comment line removes the line
+                if self.fields[self.focused].is_empty() && is_extra_comment {
+                    self.fields.remove(self.focused);
+                    self.focused = (self.focused - 1).max(FIRST_COMMENT).min(self.fields.len() - 1);
+                } else {
+                    self.fields[self.focused].pop();
+                }
+            }
+
+            // control keys come as text too
+            &Event::Input(Input::Text(ref s)) if !self.modifiers.ctrl() => {
+                self.fields[self.focused].extend(s.chars().filter(|ch| !ch.is_control()));
+            }
+
+            &Event::Input(_) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
@@ -1,14 +1,37 @@
+pub mod chrome;
 pub mod confirm;
 pub mod board;
 pub mod hud;
 pub mod info;
+pub mod stats;
+pub mod onboarding;
+pub mod demo;
+pub mod soup;
+pub mod toast;
+pub mod rules;
+pub mod meta;
+pub mod gif;
+pub mod browser;
+pub mod find;
+pub mod prompts;
+pub mod files;
+pub mod presets;
+pub mod jump;
+pub mod screensaver;
+pub mod explorer;
 
 use opengl_graphics::GlGraphics;
 use std::cell::Cell;
+use std::io;
+use std::io::Write;
 
-pub use piston_window::{Context, Event, Transformed, line, rectangle, text};
+pub use piston_window::{Context, Event, Input, Button, Key, Transformed, line, rectangle, text};
 use piston_window::character::CharacterCache;
 use super::Resources;
+use super::config::Config;
+use super::image::Canvas;
+use super::engine::io::container::Container;
+use super::engine::board::Rect;
 
 use std::rc::Rc;
 use std::cell::RefCell;
@@ -36,12 +59,180 @@ pub enum States {
     Help,
 }
 
+// how much of the simulation state is drawn over the board: everything,
+// only the generation, or nothing for a clean view
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HudMode {
+    Full,
+    Minimal,
+    Hidden,
+}
+
+impl HudMode {
+
+    pub fn next(&self) -> HudMode {
+        match *self {
+            HudMode::Full => HudMode::Minimal,
+            HudMode::Minimal => HudMode::Hidden,
+            HudMode::Hidden => HudMode::Full,
+        }
+    }
+
+}
+
 pub trait WindowBase {
 
     fn paint(&mut self, c: Context, g: &mut GlGraphics);
     fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction;
     fn is_modal(&self) -> bool { false }
 
+    // the board can't be closed, it is recreated after a failure
+    fn is_board(&self) -> bool { false }
+
+    // auxiliary windows can be focused and closed from the keyboard
+    fn is_focusable(&self) -> bool { false }
+    fn is_focused(&self) -> bool { false }
+    fn set_focused(&mut self, _focused: bool) {}
+    fn close(&mut self) -> PostAction { PostAction::Pop }
+
+    // focused windows reading the keyboard, like lists, keep the keys
+    // from the global shortcuts
+    fn takes_keys(&self) -> bool { false }
+
+    // windows having a name remember their placement between sessions
+    fn get_name(&self) -> Option<&'static str> { None }
+    fn get_layout(&self) -> Option<WindowLayout> { None }
+    fn set_layout(&mut self, _layout: WindowLayout) {}
+
+    // called after the main window has been resized
+    fn on_resize(&mut self, _width: f64, _height: f64) {}
+
+    // windows changing on their own need full frame rate even
+    // when the simulation is paused and the user is away
+    fn is_animated(&self) -> bool { false }
+
+    // windows having state worth keeping, like camera position,
+    // store it in saved sessions
+    fn save_session(&self, _container: &mut Container) {}
+    fn restore_session(&mut self, _container: &Container) {}
+
+    // called after cells from a file were put into the given region
+    fn on_pattern_loaded(&mut self, _rect: &Rect) {}
+
+    // zoom of the board view, 1 is the default cell size
+    fn set_zoom(&mut self, _scale: f64) {}
+
+    // generations run on every tick while the simulation goes on
+    fn set_speed(&mut self, _generations: u64) {}
+
+    // generations computed on the GPU when the board allows it
+    fn set_gpu(&mut self, _gpu: bool) {}
+
+    // brings the engine up to date with the generations the window computed
+    // on its own, e.g. on the GPU, before anything else reads or edits it
+    fn catch_up(&mut self) {}
+
+    // message for the user, shown as a toast
+    fn take_notice(&mut self) -> Option<String> { None }
+
+    // overlays hide themselves, or a part of what they show
+    fn set_hud_mode(&mut self, _mode: HudMode) {}
+
+    // board picture for screenshots, either what is on the screen
+    // or all the live cells
+    fn render_to_image(&self, _whole_board: bool) -> Option<Canvas> { None }
+
+    // vector picture of the selected cells or all of them
+    fn render_to_svg(&self, _writer: &mut Write) -> Option<io::Result<()>> { None }
+
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WindowLayout {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub open: bool,
+}
+
+impl WindowLayout {
+
+    pub fn new(x: f64, y: f64, width: f64, height: f64, open: bool) -> Self {
+        WindowLayout { x: x, y: y, width: width, height: height, open: open }
+    }
+
+    pub fn load(config: &Config, name: &str) -> Option<WindowLayout> {
+        // stored as "x,y,width,height,open"
+        let values: Vec<f64> = match config.get(&format!("window.{}", name)) {
+            Some(value) => value.split(',').filter_map(|v| v.trim().parse().ok()).collect(),
+            None => return None
+        };
+
+        // NaN or infinity typed into the config would be kept by the clamping of the size
+        if values.len() != 5 || values.iter().any(|v| !v.is_finite()) || values[2] <= 0.0 || values[3] <= 0.0 {
+            return None;
+        }
+
+        Some(WindowLayout::new(values[0], values[1], values[2], values[3], values[4] != 0.0))
+    }
+
+    pub fn save(&self, config: &mut Config, name: &str) {
+        config.set(&format!("window.{}", name),
+                   format!("{},{},{},{},{}", self.x, self.y, self.width, self.height,
+                           if self.open { 1 } else { 0 }));
+    }
+
+}
+
+#[derive(Default)]
+pub struct Modifiers {
+    ctrl: bool,
+    shift: bool,
+}
+
+impl Modifiers {
+
+    pub fn new() -> Self {
+        Modifiers::default()
+    }
+
+    pub fn handle(&mut self, event: &Event) {
+        // piston doesn't report modifiers along with key presses,
+        // so we have to keep track of them ourselves
+        match event {
+            &Event::Input(Input::Press(Button::Keyboard(Key::LCtrl))) |
+            &Event::Input(Input::Press(Button::Keyboard(Key::RCtrl))) => self.ctrl = true,
+
+            &Event::Input(Input::Release(Button::Keyboard(Key::LCtrl))) |
+            &Event::Input(Input::Release(Button::Keyboard(Key::RCtrl))) => self.ctrl = false,
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::LShift))) |
+            &Event::Input(Input::Press(Button::Keyboard(Key::RShift))) => self.shift = true,
+
+            &Event::Input(Input::Release(Button::Keyboard(Key::LShift))) |
+            &Event::Input(Input::Release(Button::Keyboard(Key::RShift))) => self.shift = false,
+
+            // releases are lost when the window loses focus
+            &Event::Input(Input::Focus(false)) => {
+                self.ctrl = false;
+                self.shift = false;
+            }
+
+            _ => {}
+        }
+    }
+
+    #[inline]
+    pub fn ctrl(&self) -> bool {
+        self.ctrl
+    }
+
+    #[inline]
+    pub fn shift(&self) -> bool {
+        self.shift
+    }
+
 }
 
 pub trait InfoWindowTrait: WindowBase {
@@ -55,7 +246,7 @@ pub trait InfoWindowTrait: WindowBase {
         let msg_width = resources.borrow_mut().font.width(font_size, msg);
         let prompt_width = resources.borrow_mut().font.width(font_size, prompt);
 
-        let prompt_outer_window_width = msg_width + 60.0;
+        let prompt_outer_window_width = msg_width.max(prompt_width) + 60.0;
         let prompt_outer_window_height = 60.0;
 
         let prompt_window_offset_x =  0.5 * (scr_width - prompt_outer_window_width);
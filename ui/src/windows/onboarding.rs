@@ -0,0 +1,157 @@
+// First run tutorial, a sequence of hints shown over the normal UI
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, line, rectangle, text};
+use piston_window::character::CharacterCache;
+
+use super::{WindowBase, PostAction, States};
+use super::Resources;
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const FONT_SIZE: u32 = 15;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Goal {
+    // population grows
+    Draw,
+    Run,
+    Pause,
+    Step,
+    // a pattern file is opened
+    Load,
+}
+
+struct Step {
+    hint: &'static str,
+    goal: Goal,
+    // area of the screen the hint is about
+    highlight: Option<[f64; 4]>,
+}
+
+const STEPS: [Step; 5] = [
+    Step { hint: "Click on the board to draw some cells", goal: Goal::Draw, highlight: None },
+    Step { hint: "Press P to start the simulation", goal: Goal::Run,
+           highlight: Some([5.0, 3.0, 140.0, 22.0]) },
+    Step { hint: "Press P again to pause it", goal: Goal::Pause,
+           highlight: Some([5.0, 3.0, 140.0, 22.0]) },
+    Step { hint: "Press S to advance one generation at a time", goal: Goal::Step,
+           highlight: Some([5.0, 3.0, 140.0, 22.0]) },
+    Step { hint: "Press Ctrl+Shift+O to open a pattern file, Ctrl+L lists the built-in ones",
+           goal: Goal::Load, highlight: None },
+];
+
+pub struct OnboardingWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+
+    step: usize,
+    // a pattern has been loaded since the current step was shown
+    loaded: bool,
+}
+
+impl<'a> OnboardingWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>) -> OnboardingWindow<'a> {
+
+        OnboardingWindow {
+            engine: engine,
+            resources: resources,
+            window: window,
+
+            step: 0,
+            loaded: false,
+        }
+
+    }
+
+    fn is_reached(&self, goal: Goal, event: &Event, cur_state: &Cell<States>) -> bool {
+        // keys are handled by the UI before windows see them,
+        // so state changes are already applied here
+        match (goal, event) {
+            (Goal::Draw, _) => self.engine.borrow().get_board().get_population() > 0,
+            (Goal::Run, &Event::Input(Input::Press(Button::Keyboard(Key::P)))) =>
+                cur_state.get() == States::Working,
+            (Goal::Pause, &Event::Input(Input::Press(Button::Keyboard(Key::P)))) =>
+                cur_state.get() == States::Paused,
+            (Goal::Step, &Event::Input(Input::Press(Button::Keyboard(Key::S)))) =>
+                cur_state.get() == States::StepByStep,
+            (Goal::Load, _) => self.loaded,
+            _ => false
+        }
+    }
+
+}
+
+impl<'a> WindowBase for OnboardingWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let step = &STEPS[self.step];
+
+        if let Some(area) = step.highlight {
+            let (left, top, right, bottom) = (area[0], area[1], area[0] + area[2], area[1] + area[3]);
+            for border in &[[left, top, right, top], [right, top, right, bottom],
+                            [right, bottom, left, bottom], [left, bottom, left, top]] {
+                line(YELLOW, 1.0, *border, c.transform, g);
+            }
+        }
+
+        let progress = format!("{}/{}, Escape to skip the tutorial", self.step + 1, STEPS.len());
+
+        let (hint_width, progress_width) = {
+            let font = &mut self.resources.borrow_mut().font;
+            (font.width(FONT_SIZE, step.hint), font.width(12, &progress))
+        };
+
+        let width = hint_width.max(progress_width) + 20.0;
+        let (x, y) = (0.5 * (self.window.get_width() - width), self.window.get_height() - 150.0);
+
+        rectangle([0.0, 0.0, 0.3, 0.8], [x, y, width, 50.0], c.transform, g);
+
+        text(YELLOW, FONT_SIZE, step.hint,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 10.0, y + 10.0 + FONT_SIZE as f64).transform, g);
+
+        text(super::GRAY, 12, &progress,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 10.0, y + 43.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
+
+        if let &Event::Input(Input::Press(Button::Keyboard(Key::Escape))) = event {
+            return PostAction::Pop;
+        }
+
+        if self.is_reached(STEPS[self.step].goal, event, cur_state) {
+            self.step += 1;
+            self.loaded = false;
+            if self.step == STEPS.len() {
+                return PostAction::Pop;
+            }
+        }
+
+        // the tutorial only watches, user interacts with the normal UI
+        PostAction::Transfer
+
+    }
+
+    fn on_pattern_loaded(&mut self, _rect: &Rect) {
+        self.loaded = true;
+    }
+
+}
@@ -0,0 +1,141 @@
+// Start menu, list of ready to run demos which replace the board
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+
+use self::engine::presets::PRESETS;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const WIDTH: f64 = 420.0;
+const ROW_HEIGHT: f64 = 20.0;
+
+
+pub struct PresetWindow {
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    // index of the chosen preset, it is loaded by the UI
+    preset_request: Rc<Cell<Option<usize>>>,
+
+    modifiers: Modifiers,
+
+    selected: usize,
+}
+
+impl PresetWindow {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               preset_request: Rc<Cell<Option<usize>>>) -> PresetWindow {
+
+        PresetWindow {
+            resources: resources,
+            window: window,
+            preset_request: preset_request,
+
+            modifiers: Modifiers::new(),
+
+            selected: 0,
+        }
+    }
+
+    fn get_height() -> f64 {
+        PRESETS.len() as f64 * ROW_HEIGHT + 80.0
+    }
+
+}
+
+impl WindowBase for PresetWindow {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let height = PresetWindow::get_height();
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - height));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, height], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, height - 10.0], c.transform, g);
+
+        text(super::WHITE, 15, "Start",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        let list_y = y + 40.0;
+
+        for (row, preset) in PRESETS.iter().enumerate() {
+            let row_y = list_y + row as f64 * ROW_HEIGHT;
+
+            if row == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, WIDTH - 20.0, ROW_HEIGHT], c.transform, g);
+            }
+
+            text(super::WHITE, 13, preset.name,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 15.0).transform, g);
+
+            text(super::GRAY, 13, preset.rule,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 230.0, row_y + 15.0).transform, g);
+
+            text(super::GRAY, 13, &format!("x{}", preset.speed),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 330.0, row_y + 15.0).transform, g);
+        }
+
+        text(super::GREEN, 12, "Up/Down to choose, Enter to start, Ctrl+P to cancel",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + height - 15.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                self.preset_request.set(Some(self.selected));
+                return PostAction::Pop;
+            }
+
+            // the menu sees the Ctrl+P which opened it, but not the Ctrl press
+            // before it, so it is closed only by the next Ctrl+P
+            &Event::Input(Input::Press(Button::Keyboard(Key::P))) if self.modifiers.ctrl() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    self.selected -= 1;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < PRESETS.len() {
+                    self.selected += 1;
+                }
+            }
+
+            &Event::Input(_) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
@@ -0,0 +1,145 @@
+// Settings of confirmation prompts, turns back on those
+// which were turned off with "don't ask again"
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States};
+use super::Resources;
+use super::super::config::Config;
+use super::super::prompts;
+use super::super::prompts::PROMPTS;
+
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const WIDTH: f64 = 420.0;
+const ROW_HEIGHT: f64 = 22.0;
+
+
+pub struct PromptSettingsWindow {
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+    config: Rc<RefCell<Config>>,
+
+    selected: usize,
+    error: Option<String>,
+}
+
+impl PromptSettingsWindow {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               config: Rc<RefCell<Config>>) -> PromptSettingsWindow {
+
+        PromptSettingsWindow {
+            resources: resources,
+            window: window,
+            config: config,
+
+            selected: 0,
+            error: None,
+        }
+    }
+
+    fn toggle(&mut self) {
+        let id = PROMPTS[self.selected].id;
+        let mut config = self.config.borrow_mut();
+
+        let enabled = prompts::is_enabled(&config, id);
+        prompts::set_enabled(&mut config, id, !enabled);
+
+        self.error = config.save().err().map(|err| format!("unable to save config: {}", err));
+    }
+
+    fn get_height() -> f64 {
+        PROMPTS.len() as f64 * ROW_HEIGHT + 85.0
+    }
+
+}
+
+impl WindowBase for PromptSettingsWindow {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let height = PromptSettingsWindow::get_height();
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - height));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, height], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, height - 10.0], c.transform, g);
+
+        text(super::WHITE, 15, "Ask before",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        for (idx, prompt) in PROMPTS.iter().enumerate() {
+            let row_y = y + 35.0 + idx as f64 * ROW_HEIGHT;
+
+            if idx == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, WIDTH - 20.0, ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            let check = if prompts::is_enabled(&self.config.borrow(), prompt.id) { "[x]" } else { "[ ]" };
+            text(super::WHITE, 13, &format!("{}  {}", check, prompt.description),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 16.0).transform, g);
+        }
+
+        if let Some(ref error) = self.error {
+            text(super::RED, 12, error,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, y + height - 30.0).transform, g);
+        }
+
+        text(super::GREEN, 12, "Up/Down to choose, Space to switch, Enter to close",
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + height - 15.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        match event {
+
+            // F2 which opened the window comes here too, so it can't close it
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Space))) => {
+                self.toggle();
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    self.selected -= 1;
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < PROMPTS.len() {
+                    self.selected += 1;
+                }
+            }
+
+            &Event::Input(_) => {}
+
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+}
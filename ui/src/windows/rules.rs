@@ -0,0 +1,397 @@
+// Rule picker, searchable list of known rules and rule tables with a live
+// preview, the rule chosen is applied to the board or to a zone of it
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, rectangle, text};
+
+use super::{WindowBase, PostAction, States, Modifiers};
+use super::Resources;
+use super::super::rules::{RuleRegistry, Palette};
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::rule::Rule;
+use self::engine::io::ruletable::RuleTable;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const WIDTH: f64 = 480.0;
+const HEIGHT: f64 = 300.0;
+
+const LIST_ROWS: usize = 10;
+const ROW_HEIGHT: f64 = 20.0;
+
+// preview is a soup on a small torus
+const PREVIEW_CELLS: usize = 48;
+const PREVIEW_SIZE: f64 = 144.0;
+const PREVIEW_DENSITY: f64 = 0.35;
+// seconds per generation and generations before the soup is refilled
+const PREVIEW_STEP_TIME: f64 = 0.08;
+const PREVIEW_GENERATIONS: usize = 300;
+
+
+#[derive(Clone)]
+enum Kind {
+    Rule(Rule),
+    Table(RuleTable),
+}
+
+// entry of the list, a B/S rule or a rule table
+struct Choice {
+    kind: Kind,
+    name: String,
+    description: String,
+    palette: Palette,
+}
+
+impl Choice {
+
+    fn get_notation(&self) -> String {
+        match self.kind {
+            Kind::Rule(rule) => rule.to_string(),
+            Kind::Table(_) => "rule table".to_string(),
+        }
+    }
+
+}
+
+// known rules followed by the tables, zones take only B/S rules
+fn get_choices(rules: &RuleRegistry, engine: &Engine, zone: bool) -> Vec<Choice> {
+    let mut choices: Vec<Choice> = rules.get_rules().iter()
+        .map(|info| Choice {
+            kind: Kind::Rule(info.rule),
+            name: info.name.clone(),
+            description: info.description.clone(),
+            palette: info.palette,
+        })
+        .collect();
+
+    if zone {
+        return choices;
+    }
+
+    choices.extend(rules.get_tables().iter().map(|info| Choice {
+        kind: Kind::Table(info.table.clone()),
+        name: info.name.clone(),
+        description: info.description.clone(),
+        palette: info.palette,
+    }));
+
+    // the table of the board may come with a session
+    if let Some(table) = engine.get_rule_table() {
+        if !rules.get_tables().iter().any(|info| info.table.get_source() == table.get_source()) {
+            choices.push(Choice {
+                kind: Kind::Table(table.clone()),
+                name: engine.get_rule_name(),
+                description: "Rule of the board".to_string(),
+                palette: Palette::default(),
+            });
+        }
+    }
+
+    choices
+}
+
+pub struct RulePickerWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+    window: Rc<GraphicsWindow>,
+
+    modifiers: Modifiers,
+
+    // region which gets the rule instead of the whole board
+    zone: Option<Rect>,
+
+    choices: Vec<Choice>,
+    search: String,
+    // indices of choices matching the search
+    matches: Vec<usize>,
+    // position in matches of the highlighted rule
+    selected: usize,
+
+    preview: Engine<'a>,
+    preview_time: f64,
+}
+
+impl<'a> RulePickerWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>,
+               rules: Rc<RefCell<RuleRegistry>>) -> RulePickerWindow<'a> {
+
+        RulePickerWindow::with_zone(window, resources, engine, rules, None)
+    }
+
+    /// Picker giving the region a rule of its own
+    pub fn for_zone(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+                    engine: Rc<RefCell<Engine<'a>>>,
+                    rules: Rc<RefCell<RuleRegistry>>, zone: Rect) -> RulePickerWindow<'a> {
+
+        RulePickerWindow::with_zone(window, resources, engine, rules, Some(zone))
+    }
+
+    fn with_zone(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+                 engine: Rc<RefCell<Engine<'a>>>,
+                 rules: Rc<RefCell<RuleRegistry>>, zone: Option<Rect>) -> RulePickerWindow<'a> {
+
+        let choices = get_choices(&rules.borrow(), &engine.borrow(), zone.is_some());
+
+        let mut picker = RulePickerWindow {
+            engine: engine,
+            resources: resources,
+            window: window,
+
+            modifiers: Modifiers::new(),
+
+            zone: zone,
+
+            choices: choices,
+            search: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+
+            preview: Engine::new(Some(PREVIEW_CELLS), Some(PREVIEW_CELLS)),
+            preview_time: 0.0,
+        };
+
+        picker.update_matches();
+
+        // start with the rule currently in use
+        let current = picker.choices.iter().position(|choice| picker.is_current(choice));
+        if let Some(pos) = current.and_then(|idx| picker.matches.iter().position(|&m| m == idx)) {
+            picker.select(pos);
+        }
+
+        picker
+    }
+
+    fn is_current(&self, choice: &Choice) -> bool {
+        let engine = self.engine.borrow();
+        match (&choice.kind, engine.get_rule_table()) {
+            (&Kind::Table(ref table), Some(current)) => table.get_source() == current.get_source(),
+            (&Kind::Rule(rule), None) => rule == engine.get_rule(),
+            _ => false
+        }
+    }
+
+    fn update_matches(&mut self) {
+        let search = self.search.to_lowercase();
+
+        self.matches = self.choices.iter().enumerate()
+            .filter(|&(_, choice)| {
+                choice.name.to_lowercase().contains(&search) ||
+                choice.description.to_lowercase().contains(&search) ||
+                choice.get_notation().to_lowercase().contains(&search)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+
+        self.select(0);
+    }
+
+    fn select(&mut self, pos: usize) {
+        self.selected = pos;
+        self.restart_preview();
+    }
+
+    fn restart_preview(&mut self) {
+        if let Some(&idx) = self.matches.get(self.selected) {
+            let bounds = self.preview.get_board().get_bounds();
+            let board = self.preview.create_random(PREVIEW_DENSITY, &bounds);
+
+            self.preview.reset();
+            match self.choices[idx].kind.clone() {
+                Kind::Rule(rule) => self.preview.set_rule(rule),
+                Kind::Table(table) => self.preview.set_rule_table(table),
+            }
+            self.preview.set_board(board);
+            self.preview_time = 0.0;
+        }
+    }
+
+    fn update_preview(&mut self, dt: f64) {
+        if self.matches.is_empty() {
+            return;
+        }
+
+        self.preview_time += dt;
+
+        while self.preview_time >= PREVIEW_STEP_TIME {
+            self.preview_time -= PREVIEW_STEP_TIME;
+            self.preview.one_iteration();
+        }
+
+        if self.preview.cur_iteration() >= PREVIEW_GENERATIONS ||
+            self.preview.get_board().get_population() == 0 {
+            self.restart_preview();
+        }
+    }
+
+    fn apply(&mut self) {
+        if let Some(&idx) = self.matches.get(self.selected) {
+            let mut engine = self.engine.borrow_mut();
+            match (self.choices[idx].kind.clone(), self.zone) {
+                (Kind::Rule(rule), Some(rect)) => engine.add_zone(rect, rule),
+                (Kind::Rule(rule), None) => engine.set_rule(rule),
+                // zones are given B/S rules only, see get_choices
+                (Kind::Table(table), _) => engine.set_rule_table(table),
+            }
+        }
+    }
+
+    fn paint_preview(&self, c: &Context, g: &mut GlGraphics, x: f64, y: f64) {
+        let idx = match self.matches.get(self.selected) {
+            Some(&idx) => idx,
+            None => return
+        };
+
+        let palette = self.choices[idx].palette;
+        rectangle(palette.background, [x, y, PREVIEW_SIZE, PREVIEW_SIZE], c.transform, g);
+
+        let board = self.preview.get_board();
+        let bounds = board.get_bounds();
+        let cell_size = PREVIEW_SIZE / PREVIEW_CELLS as f64;
+
+        for cell in board.into_iter().filter(|cell| cell.is_alive) {
+            rectangle(palette.get_cell_color(cell.gen),
+                      [x + (cell.coord.col - bounds.left) as f64 * cell_size,
+                       y + (cell.coord.row - bounds.top) as f64 * cell_size,
+                       cell_size, cell_size],
+                      c.transform, g);
+        }
+    }
+
+}
+
+impl<'a> WindowBase for RulePickerWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let (x, y) = (0.5 * (self.window.get_width() - WIDTH), 0.5 * (self.window.get_height() - HEIGHT));
+
+        rectangle([0.4, 0.4, 0.0, 1.0], [x, y, WIDTH, HEIGHT], c.transform, g);
+        rectangle([0.0, 0.0, 0.3, 1.0], [x + 5.0, y + 5.0, WIDTH - 10.0, HEIGHT - 10.0], c.transform, g);
+
+        let title = if self.zone.is_some() { "Zone rule" } else { "Rule" };
+        text(super::WHITE, 15, &format!("{}: {}_", title, self.search),
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + 25.0).transform, g);
+
+        // list is scrolled to keep the highlighted rule visible
+        let first = if self.selected >= LIST_ROWS { self.selected + 1 - LIST_ROWS } else { 0 };
+        let list_y = y + 45.0;
+
+        for (row, &idx) in self.matches.iter().enumerate().skip(first).take(LIST_ROWS) {
+            let choice = &self.choices[idx];
+            let row_y = list_y + (row - first) as f64 * ROW_HEIGHT;
+
+            if row == self.selected {
+                rectangle([0.2, 0.2, 0.6, 1.0], [x + 10.0, row_y, WIDTH - PREVIEW_SIZE - 35.0, ROW_HEIGHT],
+                          c.transform, g);
+            }
+
+            text(super::WHITE, 13, &choice.name,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, row_y + 15.0).transform, g);
+
+            text(super::GRAY, 13, &choice.get_notation(),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 175.0, row_y + 15.0).transform, g);
+        }
+
+        if let Some(&idx) = self.matches.get(self.selected) {
+            text(super::GRAY, 12, &self.choices[idx].description,
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, y + HEIGHT - 35.0).transform, g);
+        } else {
+            text(super::GRAY, 13, "no matching rules",
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(x + 15.0, list_y + 15.0).transform, g);
+        }
+
+        let hint = if self.zone.is_some() {
+            "type to search, Up/Down to choose, Enter to apply to the selection, Ctrl+R to cancel"
+        } else {
+            "type to search, Up/Down to choose, Enter to apply, Ctrl+R to cancel"
+        };
+        text(super::GREEN, 12, hint,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x + 15.0, y + HEIGHT - 15.0).transform, g);
+
+        self.paint_preview(&c, g, x + WIDTH - PREVIEW_SIZE - 15.0, list_y);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        self.modifiers.handle(event);
+
+        match event {
+
+            &Event::Update(args) => {
+                self.update_preview(args.dt);
+                return PostAction::Transfer;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Return))) => {
+                self.apply();
+                return PostAction::Pop;
+            }
+
+            // the picker sees the Ctrl+R which opened it, but not the Ctrl press
+            // before it, so it is closed only by the next Ctrl+R
+            &Event::Input(Input::Press(Button::Keyboard(Key::R))) if self.modifiers.ctrl() => {
+                return PostAction::Pop;
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Up))) => {
+                if self.selected > 0 {
+                    let pos = self.selected - 1;
+                    self.select(pos);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Down))) => {
+                if self.selected + 1 < self.matches.len() {
+                    let pos = self.selected + 1;
+                    self.select(pos);
+                }
+            }
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::Backspace))) => {
+                self.search.pop();
+                self.update_matches();
+            }
+
+            // control keys come as text too
+            &Event::Input(Input::Text(ref s)) if !self.modifiers.ctrl() => {
+                self.search.extend(s.chars().filter(|ch| !ch.is_control()));
+                self.update_matches();
+            }
+
+            &Event::Input(_) => {}
+
+            // the simulation goes on behind the picker
+            _ => return PostAction::Transfer
+
+        }
+
+        PostAction::Stop
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+    fn is_animated(&self) -> bool {
+        true
+    }
+
+}
@@ -0,0 +1,175 @@
+// Screensaver, random soups of the known rules in random colors with
+// the camera slowly drifting over them, any input closes the program
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Event, Input, Window};
+
+use super::{WindowBase, PostAction, States};
+use super::super::rules::{RuleRegistry, RuleInfo};
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::soup::SoupSearch;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+use std::f64::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+// seconds each soup is shown for, unless it dies out earlier
+const SCENE_TIME: f64 = 40.0;
+
+const SOUP_SIZE: i64 = 48;
+const SOUP_DENSITY: f64 = 0.35;
+
+// camera circles around the soup at that distance in cells,
+// taking that many seconds for a full turn
+const DRIFT_RADIUS: f64 = 12.0;
+const DRIFT_PERIOD: f64 = 120.0;
+
+// input right after the start, e.g. the cursor found in the middle of
+// the window going fullscreen, doesn't count
+const GRACE_TIME: f64 = 1.0;
+
+pub struct ScreensaverWindow<'a> {
+    window: Rc<GraphicsWindow>,
+    engine: Rc<RefCell<Engine<'a>>>,
+    rules: Rc<RefCell<RuleRegistry>>,
+    look_at: Rc<Cell<Option<Rect>>>,
+
+    // state of the generator picking seeds, rules and colors
+    random: u64,
+
+    // rule shown now, before its palette was replaced
+    recolored: Option<RuleInfo>,
+
+    elapsed: f64,
+    scene_elapsed: f64,
+    started: bool,
+    // direction of the camera drift in the current scene
+    angle: f64,
+}
+
+impl<'a> ScreensaverWindow<'a> {
+
+    pub fn new(window: Rc<GraphicsWindow>, engine: Rc<RefCell<Engine<'a>>>,
+               rules: Rc<RefCell<RuleRegistry>>, look_at: Rc<Cell<Option<Rect>>>) -> ScreensaverWindow<'a> {
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs() ^ t.subsec_nanos() as u64);
+
+        ScreensaverWindow {
+            window: window,
+            engine: engine,
+            rules: rules,
+            look_at: look_at,
+
+            random: now.unwrap_or(1),
+
+            recolored: None,
+
+            elapsed: 0.0,
+            scene_elapsed: 0.0,
+            started: false,
+            angle: 0.0,
+        }
+
+    }
+
+    fn next_random(&mut self, bound: usize) -> usize {
+        // 64-bit LCG, the high bits are random enough for choosing scenes
+        self.random = self.random.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.random >> 33) % bound as u64) as usize
+    }
+
+    fn show_next(&mut self) {
+        if let Some(info) = self.recolored.take() {
+            self.rules.borrow_mut().add(info);
+        }
+
+        let count = self.rules.borrow().get_rules().len();
+        let (rule_idx, palette_idx) = (self.next_random(count), self.next_random(count));
+
+        let (info, palette) = {
+            let rules = self.rules.borrow();
+            (rules.get_rules()[rule_idx].clone(), rules.get_rules()[palette_idx].palette)
+        };
+
+        // colors of another rule are lent to this one for the scene
+        self.rules.borrow_mut().add(RuleInfo { palette: palette, ..info.clone() });
+        self.engine.borrow_mut().set_rule(info.rule);
+        self.recolored = Some(info);
+
+        let seed = self.next_random(1 << 30) as u64;
+        let rect = Rect::new(-SOUP_SIZE / 2, -SOUP_SIZE / 2, SOUP_SIZE / 2 - 1, SOUP_SIZE / 2 - 1);
+        SoupSearch::new(seed, rect, SOUP_DENSITY).seed_board(&mut self.engine.borrow_mut());
+
+        self.angle = self.next_random(360) as f64 * PI / 180.0;
+        self.scene_elapsed = 0.0;
+        self.started = true;
+    }
+
+    fn drift(&mut self) {
+        let angle = self.angle + 2.0 * PI * self.scene_elapsed / DRIFT_PERIOD;
+        let (col, row) = ((DRIFT_RADIUS * angle.cos()).round() as i64, (DRIFT_RADIUS * angle.sin()).round() as i64);
+
+        // the board camera follows the target smoothly
+        self.look_at.set(Some(Rect::new(col, row, col, row)));
+    }
+
+}
+
+impl<'a> WindowBase for ScreensaverWindow<'a> {
+
+    fn paint(&mut self, _c: Context, _g: &mut GlGraphics) {
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
+
+        match event {
+
+            &Event::Update(args) => {
+                self.elapsed += args.dt;
+                self.scene_elapsed += args.dt;
+
+                let extinct = self.engine.borrow().get_board().get_population() == 0;
+
+                if !self.started || self.scene_elapsed >= SCENE_TIME || extinct {
+                    self.show_next();
+                }
+
+                self.drift();
+                cur_state.set(States::Working);
+            }
+
+            &Event::Input(Input::Press(_)) |
+            &Event::Input(Input::Move(_)) if self.elapsed >= GRACE_TIME => {
+                if let Some(window) = self.window.get_window() {
+                    window.borrow_mut().set_should_close(true);
+                }
+                return PostAction::Stop;
+            }
+
+            &Event::Input(_) => return PostAction::Stop,
+
+            _ => {}
+
+        }
+
+        PostAction::Transfer
+
+    }
+
+    fn is_modal(&self) -> bool {
+        true
+    }
+
+    fn is_animated(&self) -> bool {
+        true
+    }
+
+}
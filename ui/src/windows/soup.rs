@@ -0,0 +1,131 @@
+// Continuous soup search, toggled with A
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, text};
+
+use super::{WindowBase, PostAction, States, HudMode};
+use super::Resources;
+use super::super::log;
+use super::super::config::Config;
+
+use self::engine::engine::Engine;
+use self::engine::board::Rect;
+use self::engine::soup::SoupSearch;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const SOUP_SIZE: i64 = 16;
+const SOUP_DENSITY: f64 = 0.5;
+
+pub struct SoupWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+    config: Rc<RefCell<Config>>,
+
+    search: Option<SoupSearch>,
+    // progress is shown only with the full HUD
+    shown: bool,
+}
+
+impl<'a> SoupWindow<'a> {
+
+    pub fn new(resources: Rc<RefCell<Resources>>, engine: Rc<RefCell<Engine<'a>>>,
+               config: Rc<RefCell<Config>>) -> SoupWindow<'a> {
+
+        SoupWindow {
+            engine: engine,
+            resources: resources,
+            config: config,
+
+            search: None,
+            shown: true,
+        }
+
+    }
+
+    fn start(&mut self, cur_state: &Cell<States>) {
+        // seeds continue where the previous session stopped
+        let seed = self.config.borrow().get_parsed("soup.next_seed").unwrap_or(1);
+        let rect = Rect::new(-SOUP_SIZE / 2, -SOUP_SIZE / 2, SOUP_SIZE / 2 - 1, SOUP_SIZE / 2 - 1);
+
+        let mut search = SoupSearch::new(seed, rect, SOUP_DENSITY);
+        search.seed_board(&mut self.engine.borrow_mut());
+
+        self.search = Some(search);
+        cur_state.set(States::Working);
+    }
+
+    fn stop(&mut self, cur_state: &Cell<States>) {
+        if let Some(search) = self.search.take() {
+            self.config.borrow_mut().set("soup.next_seed", search.get_seed());
+        }
+        cur_state.set(States::Paused);
+    }
+
+}
+
+impl<'a> WindowBase for SoupWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        if !self.shown {
+            return;
+        }
+
+        if let Some(ref search) = self.search {
+            text(super::GREEN, 15,
+                 &format!("soup search: seed {}, {} soups finished",
+                          search.get_seed(), search.get_censuses().len()),
+                 &mut self.resources.borrow_mut().font,
+                 c.trans(10.0, 60.0).transform, g);
+        }
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, cur_state: &Cell<States>) -> PostAction {
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::A))) => {
+                if self.search.is_some() {
+                    self.stop(cur_state);
+                } else {
+                    self.start(cur_state);
+                }
+            }
+
+            &Event::Update(_) => {
+                if let Some(ref mut search) = self.search {
+                    if let Some(census) = search.update(&mut self.engine.borrow_mut()) {
+                        // config is saved on exit, so progress survives quitting mid search
+                        self.config.borrow_mut().set("soup.next_seed", search.get_seed());
+
+                        if census.period == 0 {
+                            log::write(&format!("soup {}: no stabilization after {} generations, population {}",
+                                                census.seed, census.generations, census.population));
+                        } else {
+                            log::write(&format!("soup {}: stabilized after {} generations, period {}, population {}",
+                                                census.seed, census.generations, census.period, census.population));
+                        }
+                    }
+                }
+            }
+
+            _ => {}
+
+        }
+
+        PostAction::Transfer
+
+    }
+
+    fn set_hud_mode(&mut self, mode: HudMode) {
+        self.shown = mode == HudMode::Full;
+    }
+
+}
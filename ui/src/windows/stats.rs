@@ -0,0 +1,215 @@
+// Population graph window
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, Input, Button, Key, line, text};
+
+use super::{WindowBase, WindowLayout, PostAction, States, HudMode};
+use super::chrome::Chrome;
+use super::Resources;
+
+use self::engine::engine::Engine;
+use self::engine::history::EventKind;
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+const GRAPH_WIDTH: f64 = 400.0;
+const GRAPH_HEIGHT: f64 = 120.0;
+const MARGIN: f64 = 10.0;
+
+const YELLOW: [f32; 4] = [1.0, 0.9, 0.0, 1.0];
+const PURPLE: [f32; 4] = [0.8, 0.3, 1.0, 1.0];
+
+// board replaced in red, rule in yellow, speed in gray, edges and
+// kind of the board in purple
+fn get_event_color(kind: &EventKind) -> [f32; 4] {
+    match *kind {
+        EventKind::BoardReplaced => super::RED,
+        EventKind::RuleChanged => YELLOW,
+        EventKind::SpeedChanged => super::GRAY,
+        EventKind::TopologyChanged | EventKind::BackendChanged => PURPLE,
+    }
+}
+
+pub struct StatsWindow<'a> {
+    engine: Rc<RefCell<Engine<'a>>>,
+    resources: Rc<RefCell<Resources>>,
+
+    chrome: Chrome,
+    // the graph is shown only with the full HUD, the open flag is kept
+    shown: bool,
+}
+
+impl<'a> StatsWindow<'a> {
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>,
+               engine: Rc<RefCell<Engine<'a>>>) -> StatsWindow<'a> {
+
+        StatsWindow {
+            engine: engine,
+            resources: resources,
+
+            chrome: Chrome::new("population",
+                                WindowLayout::new(MARGIN, window.get_height() - GRAPH_HEIGHT - MARGIN,
+                                                  GRAPH_WIDTH, GRAPH_HEIGHT, false)),
+            shown: true,
+        }
+
+    }
+}
+
+impl<'a> WindowBase for StatsWindow<'a> {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        if !self.is_focusable() {
+            return;
+        }
+
+        self.chrome.paint(&c, g, &self.resources);
+
+        // there is always at least one column to draw into
+        let rect = self.chrome.get_content_rect();
+        let (left, top, width, height) = (rect[0], rect[1], rect[2].max(1.0), rect[3]);
+
+        let engine = self.engine.borrow();
+        let history = engine.get_history();
+
+        let buckets = history.get_buckets();
+
+        if buckets.len() < 2 {
+            return;
+        }
+
+        let first_gen = buckets[0].first_gen;
+        let last_gen = buckets[buckets.len() - 1].last_gen;
+        let gens = (last_gen - first_gen).max(1) as f64;
+
+        // activity is drawn on the same scale, it can't be more than
+        // twice the population
+        let max_population = buckets.iter().map(|b| b.max.max(b.changed.ceil() as usize))
+            .max().unwrap_or(0).max(1) as f64;
+
+        let to_x = |gen: usize| left + (gen - first_gen) as f64 / gens * (width - 1.0);
+        let to_y = |population: f64| top + height - population / max_population * height;
+
+        // several buckets can fall into the same pixel column, so aggregate them first
+        // to draw exactly one vertical min-max line per column
+        let mut columns: Vec<Option<(usize, usize)>> = vec![None; width as usize];
+        // mean number of changed cells of the column, with the number of samples
+        let mut activity: Vec<(f64, usize)> = vec![(0.0, 0); width as usize];
+
+        for bucket in &buckets {
+            let col = (to_x(bucket.first_gen) - left) as usize;
+            columns[col] = match columns[col] {
+                Some((min, max)) => Some((min.min(bucket.min), max.max(bucket.max))),
+                None => Some((bucket.min, bucket.max))
+            };
+
+            let (changed, samples) = activity[col];
+            activity[col] = (changed + bucket.changed * bucket.samples as f64, samples + bucket.samples);
+        }
+
+        for (col, range) in columns.iter().enumerate() {
+            if let Some((min, max)) = *range {
+                let x = left + col as f64;
+                line(super::GREEN, 0.5, [x, to_y(min as f64), x, to_y(max as f64) - 1.0],
+                     c.transform, g);
+            }
+        }
+
+        let points: Vec<(f64, f64)> = activity.iter().enumerate()
+            .filter(|&(_, &(_, samples))| samples > 0)
+            .map(|(col, &(changed, samples))| (left + col as f64, to_y(changed / samples as f64)))
+            .collect();
+
+        for pair in points.windows(2) {
+            line(super::BLUE, 0.5, [pair[0].0, pair[0].1, pair[1].0, pair[1].1], c.transform, g);
+        }
+
+        // mark events, so it is clear why the graph changes its behaviour
+        for event in history.get_events().iter().filter(|e| e.gen >= first_gen && e.gen <= last_gen) {
+            let x = to_x(event.gen);
+            line(get_event_color(&event.kind), 0.5, [x, top, x, top + height], c.transform, g);
+        }
+
+        text(super::WHITE, 12,
+             &format!("max {}, changed cells in blue", max_population),
+             &mut self.resources.borrow_mut().font,
+             c.trans(left + 5.0, top + 15.0).transform, g);
+
+        text(super::WHITE, 12,
+             &format!("generations {}..{}", first_gen, last_gen),
+             &mut self.resources.borrow_mut().font,
+             c.trans(left + 5.0, top + height - 5.0).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        if self.shown && self.chrome.event_dispatcher(event) {
+            return PostAction::Stop;
+        }
+
+        match event {
+
+            &Event::Input(Input::Press(Button::Keyboard(Key::T))) => {
+                // show/hide population graph
+                let open = self.chrome.is_open();
+                self.chrome.set_open(!open);
+            }
+
+            _ => {}
+
+        }
+
+        PostAction::Transfer
+
+    }
+
+    fn is_focusable(&self) -> bool {
+        self.shown && self.chrome.is_open()
+    }
+
+    fn is_focused(&self) -> bool {
+        self.chrome.is_focused()
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.chrome.set_focused(focused);
+    }
+
+    fn close(&mut self) -> PostAction {
+        // the window lives in the stack for the whole session, just hide it
+        self.chrome.set_open(false);
+        PostAction::Transfer
+    }
+
+    fn get_name(&self) -> Option<&'static str> {
+        Some("stats")
+    }
+
+    fn get_layout(&self) -> Option<WindowLayout> {
+        Some(self.chrome.get_layout())
+    }
+
+    fn set_layout(&mut self, layout: WindowLayout) {
+        self.chrome.set_layout(layout);
+    }
+
+    fn set_hud_mode(&mut self, mode: HudMode) {
+        self.shown = mode == HudMode::Full;
+        if !self.shown {
+            self.chrome.set_focused(false);
+        }
+    }
+
+    fn on_resize(&mut self, width: f64, height: f64) {
+        self.chrome.fit_into(width, height);
+    }
+
+}
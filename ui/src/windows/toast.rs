@@ -0,0 +1,81 @@
+// Short message at the bottom of the screen which disappears by itself
+extern crate piston_window;
+extern crate engine;
+
+use piston_window::{Context, Transformed, Event, rectangle, text};
+use piston_window::character::CharacterCache;
+
+use super::{WindowBase, PostAction, States};
+use super::Resources;
+
+use self::engine::structs::GraphicsWindow;
+
+use opengl_graphics::GlGraphics;
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+
+// seconds the message stays on the screen
+const TOAST_TIME: f64 = 4.0;
+
+const FONT_SIZE: u32 = 14;
+
+
+pub struct ToastWindow {
+    msg: String,
+    time_left: f64,
+
+    window: Rc<GraphicsWindow>,
+    resources: Rc<RefCell<Resources>>,
+}
+
+impl ToastWindow {
+
+    pub fn new(window: Rc<GraphicsWindow>, resources: Rc<RefCell<Resources>>, msg: String) -> Self {
+        ToastWindow {
+            msg: msg,
+            time_left: TOAST_TIME,
+
+            window: window,
+            resources: resources,
+        }
+    }
+
+}
+
+impl WindowBase for ToastWindow {
+
+    fn paint(&mut self, c: Context, g: &mut GlGraphics) {
+
+        let msg_width = self.resources.borrow_mut().font.width(FONT_SIZE, &self.msg);
+
+        let x = 0.5 * (self.window.get_width() - msg_width);
+        let y = self.window.get_height() - 40.0;
+
+        rectangle([0.0, 0.0, 0.0, 0.7],
+                  [x - 10.0, y - FONT_SIZE as f64 - 6.0, msg_width + 20.0, FONT_SIZE as f64 + 14.0],
+                  c.transform, g);
+
+        text(super::WHITE, FONT_SIZE, &self.msg,
+             &mut self.resources.borrow_mut().font,
+             c.trans(x, y).transform, g);
+
+    }
+
+    fn event_dispatcher(&mut self, event: &Event, _cur_state: &Cell<States>) -> PostAction {
+
+        if let &Event::Update(args) = event {
+            self.time_left -= args.dt;
+
+            if self.time_left <= 0.0 {
+                return PostAction::Pop;
+            }
+        }
+
+        // the message doesn't take part in user interaction
+        PostAction::Transfer
+
+    }
+
+}